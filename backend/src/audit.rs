@@ -0,0 +1,192 @@
+use std::future::{ready, Ready};
+
+use actix_web::dev::Payload;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest};
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection, OptionalExtension, ToSql};
+use serde_json::{Map, Value};
+
+use crate::auth::AuthenticatedUser;
+use crate::request_id::RequestId;
+
+// Columns left out of audit snapshots
+const REDACTED_COLUMNS: &[&str] = &["password_hash", "token_hash"];
+
+#[derive(Clone, Copy, Debug)]
+pub enum Action {
+    Insert,
+    Update,
+    Delete,
+    // A trade created through the bulk endpoint, which is how imports come in
+    Import,
+}
+
+impl Action {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Action::Insert => "insert",
+            Action::Update => "update",
+            Action::Delete => "delete",
+            Action::Import => "import",
+        }
+    }
+}
+
+// Who made a change and in which request. Changes made outside of a request, like the trash purge
+// job, are attributed to "system".
+#[derive(Clone, Debug)]
+pub struct Audit {
+    user_id: Option<i64>,
+    actor: String,
+    request_id: Option<String>,
+}
+
+impl Audit {
+    pub fn system() -> Self {
+        Audit {
+            user_id: None,
+            actor: "system".to_string(),
+            request_id: None,
+        }
+    }
+
+    // Attribute changes to a user that isn't logged in yet, e.g. while registering
+    pub fn for_user(self, user_id: i64, username: &str) -> Self {
+        Audit {
+            user_id: Some(user_id),
+            actor: username.to_string(),
+            ..self
+        }
+    }
+
+    // Record a change to a row. The row's state after the change is read from the database, so
+    // this must be called after making the change. `before` is the snapshot taken before it.
+    pub fn record(
+        &self,
+        conn: &Connection,
+        action: Action,
+        table_name: &str,
+        record_id: i64,
+        before: Option<Value>,
+    ) -> rusqlite::Result<()> {
+        let after = snapshot(conn, table_name, record_id)?;
+
+        conn.execute(
+            "INSERT INTO audit_log (user_id, actor, timestamp, request_id, action, table_name, record_id, before, after)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                self.user_id,
+                self.actor,
+                chrono::Utc::now().timestamp(),
+                self.request_id,
+                action.as_str(),
+                table_name,
+                record_id,
+                before.map(|before| before.to_string()),
+                after.map(|after| after.to_string()),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    // Delete the rows of a table matching a condition, recording each deleted row.
+    // Returns the number of rows deleted.
+    pub fn delete_rows(
+        &self,
+        conn: &Connection,
+        table_name: &str,
+        condition: &str,
+        params: &[&dyn ToSql],
+    ) -> rusqlite::Result<usize> {
+        let mut before = Vec::new();
+        {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT rowid FROM {} WHERE {}",
+                table_name, condition
+            ))?;
+            let rowids = stmt
+                .query_map(params, |row| row.get::<_, i64>(0))?
+                .collect::<rusqlite::Result<Vec<i64>>>()?;
+
+            for rowid in rowids {
+                before.push((rowid, snapshot(conn, table_name, rowid)?));
+            }
+        }
+
+        conn.execute(
+            &format!("DELETE FROM {} WHERE {}", table_name, condition),
+            params,
+        )?;
+
+        for (rowid, row) in &before {
+            self.record(conn, Action::Delete, table_name, *rowid, row.clone())?;
+        }
+
+        Ok(before.len())
+    }
+}
+
+impl FromRequest for Audit {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let extensions = req.extensions();
+        let user = extensions.get::<AuthenticatedUser>();
+
+        let actor = match user {
+            Some(AuthenticatedUser {
+                username,
+                api_token: Some(api_token),
+                ..
+            }) => format!("{} (token {})", username, api_token),
+            Some(user) => user.username.clone(),
+            None => "anonymous".to_string(),
+        };
+
+        let request_id = extensions
+            .get::<RequestId>()
+            .cloned()
+            .unwrap_or_else(RequestId::new);
+
+        ready(Ok(Audit {
+            user_id: user.map(|user| user.id),
+            actor,
+            request_id: Some(request_id.0),
+        }))
+    }
+}
+
+// Read a row as a JSON object of its columns, or None if it doesn't exist
+pub fn snapshot(conn: &Connection, table_name: &str, id: i64) -> rusqlite::Result<Option<Value>> {
+    // Look rows up by rowid, which tables like flip_trades have even without an id column
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {} WHERE rowid = ?1", table_name))?;
+    let columns: Vec<String> = stmt
+        .column_names()
+        .into_iter()
+        .map(|column| column.to_string())
+        .collect();
+
+    stmt.query_row([id], |row| {
+        let mut object = Map::new();
+
+        for (index, column) in columns.iter().enumerate() {
+            if REDACTED_COLUMNS.contains(&column.as_str()) {
+                continue;
+            }
+
+            let value = match row.get_ref(index)? {
+                ValueRef::Null => Value::Null,
+                ValueRef::Integer(integer) => Value::from(integer),
+                ValueRef::Real(real) => Value::from(real),
+                ValueRef::Text(text) => Value::from(String::from_utf8_lossy(text).into_owned()),
+                ValueRef::Blob(_) => Value::Null,
+            };
+            object.insert(column.clone(), value);
+        }
+
+        Ok(Value::Object(object))
+    })
+    .optional()
+}
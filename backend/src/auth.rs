@@ -0,0 +1,290 @@
+use std::future::{ready, Ready};
+use std::sync::LazyLock;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::error::{ErrorInternalServerError, ErrorUnauthorized};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, FromRequest, HttpMessage, HttpRequest, HttpResponse};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use rand_core::{OsRng, RngCore};
+use rusqlite::OptionalExtension;
+use sha2::{Digest, Sha256};
+use tracing::error;
+
+use crate::db::Database;
+use crate::frontend;
+use crate::models::Scope;
+
+// Name of the HttpOnly cookie holding the session token
+pub const SESSION_COOKIE: &str = "ardy_session";
+
+// How long a login stays valid
+pub const SESSION_DURATION_DAYS: i64 = 30;
+
+// API tokens are prefixed so they can be told apart from session tokens
+pub const API_TOKEN_PREFIX: &str = "ardy_";
+
+// Routes under these paths manage API tokens or the server and need the admin scope
+const TOKENS_PATH: &str = "/api/v1/tokens";
+const ADMIN_PATH: &str = "/api/v1/admin";
+
+// Routes that can be used without logging in
+const PUBLIC_PATHS: &[&str] = &[
+    "/api/v1/auth/register",
+    "/api/v1/auth/login",
+    "/api/v1/openapi.json",
+    "/api/v1/docs",
+    "/healthz",
+    "/readyz",
+    // Only served with the metrics feature
+    "/metrics",
+];
+
+// The user a request was authenticated as, inserted into the request extensions by `require_auth`.
+// Browser sessions have every scope, API tokens only the ones they were created with.
+#[derive(Clone, Debug)]
+pub struct AuthenticatedUser {
+    pub id: i64,
+    pub username: String,
+    pub scopes: Vec<Scope>,
+    // Name of the API token used, if the request didn't come from a browser session
+    pub api_token: Option<String>,
+}
+
+impl AuthenticatedUser {
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.iter().any(|granted| *granted >= scope)
+    }
+
+    // Whether the user may manage the server, like taking backups. Admins are listed by username
+    // in `ARDY_ADMINS`, separated by commas. Without it nobody is an admin.
+    pub fn is_admin(&self) -> bool {
+        std::env::var("ARDY_ADMINS")
+            .is_ok_and(|admins| admins.split(',').any(|admin| admin.trim() == self.username))
+    }
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<AuthenticatedUser>()
+                .cloned()
+                .ok_or_else(|| ErrorUnauthorized("Not logged in")),
+        )
+    }
+}
+
+pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default()
+        .hash_password(password.as_bytes(), &salt)?
+        .to_string())
+}
+
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .map(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+// Hash of a random password nobody knows. Logging in as a username that doesn't exist checks the
+// password against it, so it takes as long as a wrong password and doesn't reveal who has signed up.
+static DUMMY_PASSWORD_HASH: LazyLock<String> =
+    LazyLock::new(|| hash_password(&generate_token()).expect("Hashing a random password succeeds"));
+
+// Check a password against a user's hash, or against the dummy hash if there's no such user
+pub fn verify_login(password: &str, password_hash: Option<&str>) -> bool {
+    match password_hash {
+        Some(password_hash) => verify_password(password, password_hash),
+        None => {
+            verify_password(password, &DUMMY_PASSWORD_HASH);
+            false
+        }
+    }
+}
+
+// Generate a random token for a session, returned to the client once and only stored hashed
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn generate_api_token() -> String {
+    format!("{}{}", API_TOKEN_PREFIX, generate_token())
+}
+
+// Tokens are long and random, so a fast unsalted hash is enough to keep them useless if leaked
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+// Get the session token from the `Authorization: Bearer` header or the session cookie
+pub fn request_token(req: &HttpRequest) -> Option<String> {
+    let bearer = req
+        .headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string());
+
+    bearer.or_else(|| {
+        req.cookie(SESSION_COOKIE)
+            .map(|cookie| cookie.value().to_string())
+    })
+}
+
+// Look up the user owning an unexpired session token
+fn find_session_user(db: &Database, token: &str) -> rusqlite::Result<Option<AuthenticatedUser>> {
+    let conn = db.open()?;
+
+    conn.query_row(
+        "SELECT users.id, users.username FROM sessions
+            INNER JOIN users ON sessions.user_id = users.id
+            WHERE sessions.token_hash = ?1 AND sessions.expires_at > ?2",
+        rusqlite::params![hash_token(token), chrono::Utc::now().timestamp()],
+        |row| {
+            Ok(AuthenticatedUser {
+                id: row.get(0)?,
+                username: row.get(1)?,
+                scopes: vec![Scope::Read, Scope::Write, Scope::Admin],
+                api_token: None,
+            })
+        },
+    )
+    .optional()
+}
+
+// Look up a user by name for tools that work on the database directly instead of going through
+// the HTTP API, like the CLI. Whoever can open the database file can read everything anyway, so
+// they act with every scope like a browser session.
+pub fn find_local_user(
+    db: &Database,
+    username: &str,
+) -> rusqlite::Result<Option<AuthenticatedUser>> {
+    let conn = db.open()?;
+
+    conn.query_row(
+        "SELECT id, username FROM users WHERE username = ?1",
+        [username],
+        |row| {
+            Ok(AuthenticatedUser {
+                id: row.get(0)?,
+                username: row.get(1)?,
+                scopes: vec![Scope::Read, Scope::Write, Scope::Admin],
+                api_token: None,
+            })
+        },
+    )
+    .optional()
+}
+
+// Look up the user owning an unexpired API token, recording that the token was used
+fn find_api_token_user(db: &Database, token: &str) -> rusqlite::Result<Option<AuthenticatedUser>> {
+    let conn = db.open()?;
+    let now = chrono::Utc::now().timestamp();
+
+    let found = conn
+        .query_row(
+            "SELECT api_tokens.id, users.id, users.username, api_tokens.scopes, api_tokens.name FROM api_tokens
+                INNER JOIN users ON api_tokens.user_id = users.id
+                WHERE api_tokens.token_hash = ?1 AND (api_tokens.expires_at IS NULL OR api_tokens.expires_at > ?2)",
+            rusqlite::params![hash_token(token), now],
+            |row| {
+                let scopes: String = row.get(3)?;
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    AuthenticatedUser {
+                        id: row.get(1)?,
+                        username: row.get(2)?,
+                        scopes: scopes.split(',').filter_map(Scope::parse).collect(),
+                        api_token: Some(row.get(4)?),
+                    },
+                ))
+            },
+        )
+        .optional()?;
+
+    let Some((token_id, user)) = found else {
+        return Ok(None);
+    };
+
+    conn.execute(
+        "UPDATE api_tokens SET last_used_at = ?1 WHERE id = ?2",
+        rusqlite::params![now, token_id],
+    )?;
+
+    Ok(Some(user))
+}
+
+// Reads only need the read scope, anything that changes data needs write
+fn required_scope(req: &ServiceRequest) -> Scope {
+    if req.path().starts_with(TOKENS_PATH) || req.path().starts_with(ADMIN_PATH) {
+        Scope::Admin
+    } else if req.method() == Method::GET || req.method() == Method::HEAD {
+        Scope::Read
+    } else {
+        Scope::Write
+    }
+}
+
+// Middleware rejecting requests to non-public routes that don't carry a valid session or API token,
+// or whose token lacks the scope the route needs.
+// Authenticated requests get an `AuthenticatedUser` that handlers use to scope their queries.
+pub async fn require_auth(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    if PUBLIC_PATHS.contains(&req.path()) || frontend::is_frontend_path(req.path()) {
+        return next.call(req).await.map(|res| res.map_into_left_body());
+    }
+
+    let Some(db) = req.app_data::<web::Data<Database>>().cloned() else {
+        return Err(ErrorInternalServerError("No database configured"));
+    };
+
+    let user = match request_token(req.request()) {
+        Some(token) => {
+            let user = if token.starts_with(API_TOKEN_PREFIX) {
+                find_api_token_user(&db, &token)
+            } else {
+                find_session_user(&db, &token)
+            };
+
+            user.unwrap_or_else(|e| {
+                error!(error = %e, "Failed to look up session");
+                None
+            })
+        }
+        None => None,
+    };
+
+    let scope = required_scope(&req);
+
+    match user {
+        Some(user) if !user.has_scope(scope) => Ok(req
+            .into_response(
+                HttpResponse::Forbidden().body(format!("Token lacks the {} scope", scope.as_str())),
+            )
+            .map_into_right_body()),
+        Some(user) => {
+            req.extensions_mut().insert(user);
+            next.call(req).await.map(|res| res.map_into_left_body())
+        }
+        // Respond here instead of returning an error so the CORS middleware still adds its headers
+        None => Ok(req
+            .into_response(HttpResponse::Unauthorized().body("Not logged in"))
+            .map_into_right_body()),
+    }
+}
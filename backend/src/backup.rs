@@ -0,0 +1,393 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+use rusqlite::backup::{Backup, StepResult};
+use rusqlite::{Connection, OpenFlags};
+
+use crate::db::{self, Database};
+
+// Backups are kept in this directory next to the database
+const BACKUP_DIR: &str = "backups";
+
+// Backup file names are the time they were made, so sorting them by name sorts them by age
+const BACKUP_PREFIX: &str = "ardy-";
+const BACKUP_EXTENSION: &str = "db";
+const BACKUP_TIME_FORMAT: &str = "%Y%m%d-%H%M%S-%3f";
+
+// How many scheduled or requested backups are kept unless `ARDY_BACKUP_KEEP` says otherwise
+const DEFAULT_KEEP: usize = 7;
+
+#[derive(Debug)]
+pub enum BackupError {
+    // The file isn't an ardy database or is damaged
+    Invalid(String),
+    // The backup was made by a newer version with migrations this one doesn't know
+    NewerSchema { version: usize, supported: usize },
+    Database(rusqlite::Error),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupError::Invalid(reason) => write!(f, "Not a valid backup: {}", reason),
+            BackupError::NewerSchema { version, supported } => write!(
+                f,
+                "Backup has schema version {}, but this version only supports up to {}",
+                version, supported
+            ),
+            BackupError::Database(e) => write!(f, "{}", e),
+            BackupError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for BackupError {
+    fn from(e: rusqlite::Error) -> Self {
+        BackupError::Database(e)
+    }
+}
+
+impl From<std::io::Error> for BackupError {
+    fn from(e: std::io::Error) -> Self {
+        BackupError::Io(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, BackupError>;
+
+// A backup file in the backup directory
+#[derive(Clone, Debug)]
+pub struct BackupFile {
+    pub path: PathBuf,
+    pub created_at: NaiveDateTime,
+    pub size_bytes: u64,
+}
+
+impl BackupFile {
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+// What `restore` did
+#[derive(Debug)]
+pub struct Restored {
+    // Schema version of the restored backup. Missing migrations are applied on the next start.
+    pub schema_version: usize,
+    // Backup of the database that was replaced, if there was one
+    pub previous: Option<PathBuf>,
+}
+
+// Number of backups to keep, from `ARDY_BACKUP_KEEP`. At least the newest one is always kept.
+pub fn keep() -> usize {
+    std::env::var("ARDY_BACKUP_KEEP")
+        .ok()
+        .and_then(|keep| keep.parse().ok())
+        .unwrap_or(DEFAULT_KEEP)
+        .max(1)
+}
+
+pub fn backup_dir(db: &Database) -> PathBuf {
+    db.path()
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(BACKUP_DIR)
+}
+
+// Copy the database to a file with SQLite's online backup API. The copy is consistent even while
+// requests keep writing, since it's made from a single read transaction.
+fn copy_database(db: &Database, destination: &Path) -> Result<()> {
+    let source = db.open()?;
+    let mut destination = Connection::open(destination)?;
+
+    // Copy all pages in one step. With the write-ahead log, writers aren't blocked meanwhile.
+    let backup = Backup::new(&source, &mut destination)?;
+    loop {
+        match backup.step(-1)? {
+            StepResult::Done => break,
+            // Another connection holds a lock, so try again shortly
+            _ => std::thread::sleep(Duration::from_millis(50)),
+        }
+    }
+    drop(backup);
+
+    // The copy takes over the write-ahead log setting. Turn it off so the backup is a single file,
+    // which opening it read-only doesn't leave a log next to. It's turned on again on restore.
+    destination.pragma_update_and_check(None, "journal_mode", "DELETE", |row| {
+        row.get::<_, String>(0)
+    })?;
+
+    Ok(())
+}
+
+// Back up the database into the backup directory, returning the new backup
+pub fn create_backup(db: &Database) -> Result<BackupFile> {
+    let dir = backup_dir(db);
+    fs::create_dir_all(&dir)?;
+
+    let created_at = Utc::now().naive_utc();
+    let path = dir.join(format!(
+        "{}{}.{}",
+        BACKUP_PREFIX,
+        created_at.format(BACKUP_TIME_FORMAT),
+        BACKUP_EXTENSION
+    ));
+    copy_database(db, &path)?;
+
+    Ok(BackupFile {
+        size_bytes: fs::metadata(&path)?.len(),
+        path,
+        created_at,
+    })
+}
+
+// Make a consistent copy of the database and read it into memory, without keeping it as a backup
+pub fn snapshot(db: &Database) -> Result<Vec<u8>> {
+    let dir = backup_dir(db);
+    fs::create_dir_all(&dir)?;
+
+    // Not named like a backup, so it's never listed or pruned
+    let path = dir.join(format!(".snapshot-{}.db", uuid::Uuid::new_v4()));
+    let snapshot = copy_database(db, &path).and_then(|()| Ok(fs::read(&path)?));
+    let _ = fs::remove_file(&path);
+
+    snapshot
+}
+
+// List the backups in the backup directory, oldest first
+pub fn list_backups(db: &Database) -> Result<Vec<BackupFile>> {
+    let dir = backup_dir(db);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_none_or(|extension| extension != BACKUP_EXTENSION)
+        {
+            continue;
+        }
+        let Some(created_at) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.strip_prefix(BACKUP_PREFIX))
+            .and_then(|time| NaiveDateTime::parse_from_str(time, BACKUP_TIME_FORMAT).ok())
+        else {
+            continue;
+        };
+
+        backups.push(BackupFile {
+            size_bytes: fs::metadata(&path)?.len(),
+            path,
+            created_at,
+        });
+    }
+
+    backups.sort_by_key(|backup| backup.created_at);
+    Ok(backups)
+}
+
+// Delete the oldest backups beyond the newest `keep`, returning the deleted ones
+pub fn prune_backups(db: &Database, keep: usize) -> Result<Vec<BackupFile>> {
+    let backups = list_backups(db)?;
+    let excess = backups.len().saturating_sub(keep);
+
+    let pruned: Vec<BackupFile> = backups.into_iter().take(excess).collect();
+    for backup in &pruned {
+        fs::remove_file(&backup.path)?;
+    }
+
+    Ok(pruned)
+}
+
+// Check that a file is an intact ardy database this version can open, returning its schema version
+pub fn validate(path: &Path) -> Result<usize> {
+    if !path.is_file() {
+        return Err(BackupError::Invalid(format!(
+            "{} is not a file",
+            path.display()
+        )));
+    }
+
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+    let integrity: String = conn
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|e| BackupError::Invalid(e.to_string()))?;
+    if integrity != "ok" {
+        return Err(BackupError::Invalid(integrity));
+    }
+
+    let has_tables: bool = conn.query_row(
+        "SELECT COUNT(*) = 2 FROM sqlite_master WHERE type = 'table' AND name IN ('trades', 'users')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_tables {
+        return Err(BackupError::Invalid(
+            "it has no trades or users table".to_string(),
+        ));
+    }
+
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > db::SCHEMA_VERSION {
+        return Err(BackupError::NewerSchema {
+            version,
+            supported: db::SCHEMA_VERSION,
+        });
+    }
+
+    Ok(version)
+}
+
+// Replace the database with a backup after validating it. The database being replaced is backed
+// up first. The server must not be running, since it would keep using the replaced file.
+pub fn restore(db: &Database, backup: &Path) -> Result<Restored> {
+    let schema_version = validate(backup)?;
+
+    let previous = if db.path().exists() {
+        Some(create_backup(db)?.path)
+    } else {
+        None
+    };
+
+    // Copy next to the database first, so the swap itself is a rename that can't be left half done
+    let mut restoring = db.path().as_os_str().to_owned();
+    restoring.push(".restoring");
+    let restoring = PathBuf::from(restoring);
+    if let Some(parent) = db.path().parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(backup, &restoring)?;
+
+    // The replaced database's write-ahead log would otherwise be applied to the backup
+    for suffix in ["-wal", "-shm"] {
+        let mut path = db.path().as_os_str().to_owned();
+        path.push(suffix);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    fs::rename(&restoring, db.path())?;
+
+    Ok(Restored {
+        schema_version,
+        previous,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_db() -> (tempfile::TempDir, Database) {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::new(dir.path().join("ardy.db"));
+        db::init_db(&db).unwrap();
+        (dir, db)
+    }
+
+    fn user_count(db: &Database) -> i64 {
+        db.open()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    fn add_user(db: &Database, username: &str) {
+        db.open()
+            .unwrap()
+            .execute(
+                "INSERT INTO users (username, password_hash) VALUES (?1, '')",
+                [username],
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn backups_are_listed_oldest_first_and_pruned() {
+        let (_dir, db) = test_db();
+
+        let created: Vec<PathBuf> = (0..4)
+            .map(|_| {
+                // Names have millisecond precision
+                std::thread::sleep(Duration::from_millis(2));
+                create_backup(&db).unwrap().path
+            })
+            .collect();
+
+        let listed: Vec<PathBuf> = list_backups(&db)
+            .unwrap()
+            .into_iter()
+            .map(|backup| backup.path)
+            .collect();
+        assert_eq!(listed, created);
+
+        let pruned: Vec<PathBuf> = prune_backups(&db, 3)
+            .unwrap()
+            .into_iter()
+            .map(|backup| backup.path)
+            .collect();
+        assert_eq!(pruned, created[..1]);
+        assert_eq!(list_backups(&db).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn restore_replaces_the_database_and_keeps_the_old_one() {
+        let (_dir, db) = test_db();
+        add_user(&db, "alice");
+        let backup = create_backup(&db).unwrap();
+
+        add_user(&db, "bob");
+        assert_eq!(user_count(&db), 2);
+
+        let restored = restore(&db, &backup.path).unwrap();
+        assert_eq!(restored.schema_version, db::SCHEMA_VERSION);
+        assert_eq!(user_count(&db), 1);
+
+        // The replaced database can be restored in turn
+        restore(&db, &restored.previous.unwrap()).unwrap();
+        assert_eq!(user_count(&db), 2);
+    }
+
+    #[test]
+    fn restore_rejects_newer_schemas_and_other_files() {
+        let (dir, db) = test_db();
+        add_user(&db, "alice");
+
+        let newer = create_backup(&db).unwrap().path;
+        Connection::open(&newer)
+            .unwrap()
+            .pragma_update(None, "user_version", db::SCHEMA_VERSION + 1)
+            .unwrap();
+        assert!(matches!(
+            restore(&db, &newer),
+            Err(BackupError::NewerSchema { .. })
+        ));
+
+        let text = dir.path().join("notes.txt");
+        fs::write(&text, "not a database").unwrap();
+        assert!(matches!(restore(&db, &text), Err(BackupError::Invalid(_))));
+
+        let empty = dir.path().join("empty.db");
+        Connection::open(&empty)
+            .unwrap()
+            .execute("CREATE TABLE things (id INTEGER)", [])
+            .unwrap();
+        assert!(matches!(restore(&db, &empty), Err(BackupError::Invalid(_))));
+
+        // Nothing was replaced
+        assert_eq!(user_count(&db), 1);
+    }
+}
@@ -1,9 +1,100 @@
-use rusqlite::{Connection, Result};
+use std::path::{Path, PathBuf};
 
-pub fn init_db() -> Result<()> {
-    let conn = Connection::open("db/ardy.db")?;
+use chrono::{DateTime, NaiveDateTime};
+use rusqlite::{types::Type, Connection, OptionalExtension, Result, Row};
+use tracing::info;
 
-    println!("Creating tables");
+use crate::audit::{Action, Audit};
+use crate::models::ItemData;
+
+// Schema changes to existing tables, applied in order after the tables are created.
+// PRAGMA user_version holds the number of migrations already applied to the database.
+const MIGRATIONS: &[&str] = &[
+    // 1: Scope trades and offers to accounts, moving existing data to a "Main" account
+    "ALTER TABLE trades ADD COLUMN account_id INTEGER REFERENCES accounts (id);
+    ALTER TABLE offers ADD COLUMN account_id INTEGER REFERENCES accounts (id);
+    INSERT INTO accounts (name) SELECT 'Main' WHERE EXISTS (SELECT 1 FROM trades UNION SELECT 1 FROM offers);
+    UPDATE trades SET account_id = (SELECT id FROM accounts WHERE name = 'Main');
+    UPDATE offers SET account_id = (SELECT id FROM accounts WHERE name = 'Main');
+    DROP INDEX IF EXISTS offers_open_slot;
+    CREATE UNIQUE INDEX offers_open_slot ON offers (IFNULL(account_id, 0), slot) WHERE status = 'open';",
+    // 2: Scope data to users. Existing rows are claimed by the first user to register.
+    // Account names only need to be unique per user, which requires rebuilding the table.
+    "ALTER TABLE trades ADD COLUMN user_id INTEGER REFERENCES users (id);
+    ALTER TABLE offers ADD COLUMN user_id INTEGER REFERENCES users (id);
+    ALTER TABLE flips ADD COLUMN user_id INTEGER REFERENCES users (id);
+    CREATE TABLE accounts_new (
+        id INTEGER PRIMARY KEY NOT NULL,
+        user_id INTEGER REFERENCES users (id),
+        name TEXT NOT NULL,
+        UNIQUE (user_id, name)
+    );
+    INSERT INTO accounts_new (id, name) SELECT id, name FROM accounts;
+    DROP TABLE accounts;
+    ALTER TABLE accounts_new RENAME TO accounts;
+    DROP INDEX offers_open_slot;
+    CREATE UNIQUE INDEX offers_open_slot ON offers (user_id, IFNULL(account_id, 0), slot) WHERE status = 'open';",
+    // 3: Soft delete trades, moving them to the trash until they are restored or purged
+    "ALTER TABLE trades ADD COLUMN deleted_at INTEGER;",
+    // 4: Free-text notes on trades
+    "ALTER TABLE trades ADD COLUMN note TEXT;",
+    // 5: Trades added during a trading session belong to it
+    "ALTER TABLE trades ADD COLUMN session_id INTEGER REFERENCES trading_sessions (id);",
+];
+
+// The user_version of a fully migrated database
+pub const SCHEMA_VERSION: usize = MIGRATIONS.len();
+
+// Tables whose rows belong to a user
+pub const USER_TABLES: &[&str] = &[
+    "accounts",
+    "trades",
+    "offers",
+    "flips",
+    "tags",
+    "trading_sessions",
+    "goals",
+];
+
+// Selects the names of a trade's tags as a JSON array, read with `tags_from_row`
+pub const TAGS_COLUMN: &str = "(SELECT json_group_array(tags.name) FROM trade_tags
+    INNER JOIN tags ON trade_tags.tag_id = tags.id
+    WHERE trade_tags.trade_id = trades.id)";
+
+// Where the database lives unless configured otherwise
+pub const DEFAULT_PATH: &str = "db/ardy.db";
+
+// The SQLite database the app stores its data in. It's shared with handlers as app data, and each
+// request opens its own connection to it.
+#[derive(Clone, Debug)]
+pub struct Database {
+    path: PathBuf,
+}
+
+impl Database {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Database { path: path.into() }
+    }
+
+    pub fn open(&self) -> Result<Connection> {
+        let conn = Connection::open(&self.path)?;
+        #[cfg(feature = "metrics")]
+        let conn = crate::metrics::time_queries(conn);
+        Ok(conn)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+pub fn init_db(db: &Database) -> Result<()> {
+    let mut conn = db.open()?;
+
+    // Readers don't block the writer and the other way round. Stays set in the database file.
+    conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+
+    info!("Creating tables");
 
     conn.execute(
         "CREATE TABLE IF NOT EXISTS items (
@@ -13,6 +104,14 @@ pub fn init_db() -> Result<()> {
         [],
     )?;
 
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS accounts (
+            id INTEGER PRIMARY KEY NOT NULL,
+            name TEXT UNIQUE NOT NULL
+        )",
+        [],
+    )?;
+
     conn.execute(
         "CREATE TABLE IF NOT EXISTS trades (
             id INTEGER PRIMARY KEY NOT NULL,
@@ -26,5 +125,287 @@ pub fn init_db() -> Result<()> {
         [],
     )?;
 
+    // Open GE offers, one per slot (1-8) while open
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS offers (
+            id INTEGER PRIMARY KEY NOT NULL,
+            item_id INTEGER NOT NULL,
+            slot INTEGER NOT NULL,
+            is_purchase BOOLEAN NOT NULL,
+            price INTEGER NOT NULL,
+            quantity INTEGER NOT NULL,
+            status TEXT NOT NULL,
+            timestamp TEXT NOT NULL,
+            FOREIGN KEY (item_id) REFERENCES items (id)
+        )",
+        [],
+    )?;
+
+    // Enforce one open offer per slot, even if two offers for the same slot race each other.
+    // Migrations replace it with the slots of each account as offers are scoped further.
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS offers_open_slot ON offers (slot) WHERE status = 'open'",
+        [],
+    )?;
+
+    // Links each fill of an offer to the trade it created
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS offer_fills (
+            id INTEGER PRIMARY KEY NOT NULL,
+            offer_id INTEGER NOT NULL,
+            trade_id INTEGER NOT NULL,
+            FOREIGN KEY (offer_id) REFERENCES offers (id),
+            FOREIGN KEY (trade_id) REFERENCES trades (id)
+        )",
+        [],
+    )?;
+
+    // Flips group the buy and sell trades of one item into a single record
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS flips (
+            id INTEGER PRIMARY KEY NOT NULL,
+            item_id INTEGER NOT NULL,
+            FOREIGN KEY (item_id) REFERENCES items (id)
+        )",
+        [],
+    )?;
+
+    // A trade can belong to at most one flip
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS flip_trades (
+            flip_id INTEGER NOT NULL,
+            trade_id INTEGER UNIQUE NOT NULL,
+            FOREIGN KEY (flip_id) REFERENCES flips (id),
+            FOREIGN KEY (trade_id) REFERENCES trades (id)
+        )",
+        [],
+    )?;
+
+    // Labels like "clan flip" that users put on their trades
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS tags (
+            id INTEGER PRIMARY KEY NOT NULL,
+            user_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            UNIQUE (user_id, name),
+            FOREIGN KEY (user_id) REFERENCES users (id)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS trade_tags (
+            trade_id INTEGER NOT NULL,
+            tag_id INTEGER NOT NULL,
+            PRIMARY KEY (trade_id, tag_id),
+            FOREIGN KEY (trade_id) REFERENCES trades (id),
+            FOREIGN KEY (tag_id) REFERENCES tags (id)
+        )",
+        [],
+    )?;
+
+    // Stretches of play started and stopped by the user, at most one in progress (no ended_at) at a
+    // time. Named apart from the login sessions below. Times are in the same local time as trade
+    // timestamps, stored as unix seconds.
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS trading_sessions (
+            id INTEGER PRIMARY KEY NOT NULL,
+            user_id INTEGER NOT NULL,
+            started_at INTEGER NOT NULL,
+            ended_at INTEGER,
+            FOREIGN KEY (user_id) REFERENCES users (id)
+        );
+        CREATE UNIQUE INDEX IF NOT EXISTS trading_sessions_in_progress
+            ON trading_sessions (user_id) WHERE ended_at IS NULL;",
+    )?;
+
+    // Amounts of gp to make by a deadline, counting the profit realized from the start on. Scoped to
+    // an item and/or account if they're set. Times are stored like those of trading sessions.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS goals (
+            id INTEGER PRIMARY KEY NOT NULL,
+            user_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            target INTEGER NOT NULL,
+            started_at INTEGER NOT NULL,
+            deadline INTEGER NOT NULL,
+            item_id INTEGER,
+            account_id INTEGER,
+            FOREIGN KEY (user_id) REFERENCES users (id),
+            FOREIGN KEY (item_id) REFERENCES items (id),
+            FOREIGN KEY (account_id) REFERENCES accounts (id)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS users (
+            id INTEGER PRIMARY KEY NOT NULL,
+            username TEXT UNIQUE NOT NULL,
+            password_hash TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Logins, identified by the SHA-256 hash of the token handed to the client
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sessions (
+            id INTEGER PRIMARY KEY NOT NULL,
+            user_id INTEGER NOT NULL,
+            token_hash TEXT UNIQUE NOT NULL,
+            expires_at INTEGER NOT NULL,
+            FOREIGN KEY (user_id) REFERENCES users (id)
+        )",
+        [],
+    )?;
+
+    // Personal API tokens, identified by the SHA-256 hash of the token like sessions.
+    // Scopes are stored as a comma separated list, timestamps as unix seconds.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS api_tokens (
+            id INTEGER PRIMARY KEY NOT NULL,
+            user_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            token_hash TEXT UNIQUE NOT NULL,
+            scopes TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            expires_at INTEGER,
+            last_used_at INTEGER,
+            FOREIGN KEY (user_id) REFERENCES users (id)
+        )",
+        [],
+    )?;
+
+    // Append-only record of every change to the data, with the row before and after as JSON
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY NOT NULL,
+            user_id INTEGER,
+            actor TEXT NOT NULL,
+            timestamp INTEGER NOT NULL,
+            request_id TEXT,
+            action TEXT NOT NULL,
+            table_name TEXT NOT NULL,
+            record_id INTEGER NOT NULL,
+            before TEXT,
+            after TEXT,
+            FOREIGN KEY (user_id) REFERENCES users (id)
+        );
+        CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log
+            BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END;
+        CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log
+            BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END;",
+    )?;
+
+    run_migrations(&mut conn)?;
+
     Ok(())
 }
+
+fn run_migrations(conn: &mut Connection) -> Result<()> {
+    let applied: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    // Rebuilding a table drops one that others reference, so foreign keys are off while migrating.
+    // This can't be changed inside a transaction.
+    conn.pragma_update(None, "foreign_keys", false)?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        info!(migration = index + 1, "Applying migration");
+
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+        tx.commit()?;
+    }
+
+    conn.pragma_update(None, "foreign_keys", true)?;
+
+    Ok(())
+}
+
+// Number of migrations not yet applied, which is 0 once `init_db` has run
+pub fn pending_migrations(conn: &Connection) -> Result<usize> {
+    let applied: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    Ok(SCHEMA_VERSION.saturating_sub(applied))
+}
+
+// Move everything in the write-ahead log into the database file and empty the log, so the file
+// is complete on its own. Returns false if a reader kept part of the log from being moved.
+pub fn checkpoint(db: &Database) -> Result<bool> {
+    let conn = db.open()?;
+    let busy: bool = conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))?;
+    Ok(!busy)
+}
+
+// Get the id of an item by name, inserting it into the items table if it doesn't exist yet.
+// Names are stored trimmed, so "Dragon bones " is the same item as "Dragon bones".
+pub fn get_or_insert_item_id(conn: &Connection, audit: &Audit, item_name: &str) -> Result<i64> {
+    let item_name = item_name.trim();
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO items (name) VALUES (?1)",
+        [item_name],
+    )?;
+    if inserted > 0 {
+        audit.record(
+            conn,
+            Action::Insert,
+            "items",
+            conn.last_insert_rowid(),
+            None,
+        )?;
+    }
+
+    conn.query_row("SELECT id FROM items WHERE name = ?1", [item_name], |row| {
+        row.get(0)
+    })
+}
+
+// Timestamps are stored as unix seconds in a TEXT column
+pub fn timestamp_from_row(row: &Row, idx: usize) -> Result<NaiveDateTime> {
+    let timestamp: String = row.get(idx)?;
+    timestamp
+        .parse::<i64>()
+        .ok()
+        .and_then(datetime_from_unix)
+        .ok_or_else(|| rusqlite::Error::InvalidColumnType(idx, "timestamp".to_string(), Type::Text))
+}
+
+// Convert unix seconds, as newer tables store them in INTEGER columns, to a datetime
+pub fn datetime_from_unix(timestamp: i64) -> Option<NaiveDateTime> {
+    DateTime::from_timestamp(timestamp, 0).map(|datetime| datetime.naive_utc())
+}
+
+// Read the JSON array of tag names selected by `TAGS_COLUMN`
+pub fn tags_from_row(row: &Row, idx: usize) -> Result<Vec<String>> {
+    let tags: String = row.get(idx)?;
+    serde_json::from_str(&tags)
+        .map_err(|_| rusqlite::Error::InvalidColumnType(idx, "tags".to_string(), Type::Text))
+}
+
+// Get a trade by id, including trades in the trash, or None if it doesn't exist
+pub fn query_trade(conn: &Connection, id: i64) -> Result<Option<ItemData>> {
+    conn.query_row(
+        &format!(
+            "SELECT trades.id, items.name, trades.quantity, trades.total_price, trades.is_purchase, trades.timestamp, trades.account_id, trades.note, trades.session_id, {}
+            FROM trades INNER JOIN items ON trades.item_id = items.id
+            WHERE trades.id = ?1",
+            TAGS_COLUMN
+        ),
+        [id],
+        |row| {
+            Ok(ItemData {
+                id: row.get(0)?,
+                item_name: row.get(1)?,
+                quantity: row.get(2)?,
+                total_price: row.get(3)?,
+                is_purchase: row.get(4)?,
+                timestamp: timestamp_from_row(row, 5)?,
+                account_id: row.get(6)?,
+                note: row.get(7)?,
+                session_id: row.get(8)?,
+                tags: tags_from_row(row, 9)?,
+            })
+        },
+    )
+    .optional()
+}
@@ -0,0 +1,72 @@
+use actix_web::web::Bytes;
+use actix_web::Error;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use utoipa::ToSchema;
+
+use crate::models::ItemData;
+
+// How many events a slow client can fall behind before it misses some and is told to resync
+const CHANNEL_CAPACITY: usize = 256;
+
+// A change to a trade, pushed to every open event stream of the trade's user.
+// Updates and deletes carry the trade as it was before, so clients can undo its old contribution
+// to totals like profit/loss. Restoring a trade from the trash counts as creating it.
+#[derive(Serialize, Clone, Debug, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TradeEvent {
+    TradeCreated { trade: ItemData },
+    TradeUpdated { before: ItemData, trade: ItemData },
+    TradeDeleted { trade: ItemData },
+    // Events were missed, so the client should reload its data
+    Resync,
+}
+
+// Broadcasts trade events from the handlers making changes to the event streams of clients
+#[derive(Clone)]
+pub struct Events {
+    sender: broadcast::Sender<(i64, TradeEvent)>,
+}
+
+impl Events {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Events { sender }
+    }
+
+    pub fn send(&self, user_id: i64, event: TradeEvent) {
+        // Sending only fails when no client is listening, in which case there is no one to tell
+        let _ = self.sender.send((user_id, event));
+    }
+
+    // Stream a user's events in the server-sent events format
+    pub fn subscribe(&self, user_id: i64) -> impl Stream<Item = Result<Bytes, Error>> {
+        let events =
+            BroadcastStream::new(self.sender.subscribe()).filter_map(
+                move |message| match message {
+                    Ok((event_user_id, event)) if event_user_id == user_id => Some(event),
+                    Ok(_) => None,
+                    Err(BroadcastStreamRecvError::Lagged(_)) => Some(TradeEvent::Resync),
+                },
+            );
+
+        // Start with a comment so the client sees the stream open before the first event
+        tokio_stream::once(Bytes::from_static(b": connected\n\n"))
+            .chain(events.map(|event| {
+                Bytes::from(format!(
+                    "data: {}\n\n",
+                    serde_json::to_string(&event).unwrap_or_default()
+                ))
+            }))
+            .map(Ok)
+    }
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Self::new()
+    }
+}
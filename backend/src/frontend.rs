@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+
+use actix_files::{Files, NamedFile};
+use actix_web::dev::{fn_service, ServiceRequest, ServiceResponse};
+use actix_web::HttpResponse;
+
+// Where the frontend is built to by `trunk build`, relative to the repository root
+pub const DEFAULT_DIR: &str = "frontend/dist";
+
+const INDEX_FILE: &str = "index.html";
+
+// Paths the frontend doesn't handle, so unknown routes under them are a 404 instead of the app
+const API_PREFIX: &str = "/api/";
+
+// The built frontend, from `ARDY_FRONTEND_DIR` or the default. None if it hasn't been built, in
+// which case only the API is served.
+pub fn dir() -> Option<PathBuf> {
+    let dir = std::env::var("ARDY_FRONTEND_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(DEFAULT_DIR));
+
+    dir.join(INDEX_FILE).is_file().then_some(dir)
+}
+
+// Serve the files of the built frontend. The frontend routes in the browser, so any other path
+// gets index.html and the app shows the page for it, which lets pages be linked and reloaded.
+pub fn service(dir: PathBuf) -> Files {
+    let index = dir.join(INDEX_FILE);
+
+    Files::new("/", dir)
+        .index_file(INDEX_FILE)
+        .default_handler(fn_service(move |req: ServiceRequest| {
+            let index = index.clone();
+            async move {
+                let (req, _) = req.into_parts();
+                if req.path().starts_with(API_PREFIX) {
+                    return Ok(ServiceResponse::new(req, HttpResponse::NotFound().finish()));
+                }
+
+                let response = NamedFile::open_async(index).await?.into_response(&req);
+                Ok(ServiceResponse::new(req, response))
+            }
+        }))
+}
+
+// Requests for the frontend don't need a session, since the login page is part of it
+pub fn is_frontend_path(path: &str) -> bool {
+    !path.starts_with(API_PREFIX)
+}
@@ -0,0 +1,547 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{Duration, NaiveDateTime};
+
+use crate::models::{Holding, ItemData};
+
+// Trades further apart than this are taken to be from different sessions of play
+pub const SESSION_GAP: Duration = Duration::hours(1);
+
+// The Grand Exchange limits how many of an item each character can buy in a window starting at the
+// first purchase, and the limit resets once the window is over
+pub const BUY_LIMIT_WINDOW: Duration = Duration::hours(4);
+
+// Profit made by selling items, at the price they sold for minus what they cost on average
+#[derive(Debug, Clone, PartialEq)]
+pub struct RealizedSale {
+    pub trade_id: i64,
+    pub item_name: String,
+    pub account_id: Option<i64>,
+    pub timestamp: NaiveDateTime,
+    pub profit: i64,
+}
+
+// Holdings and realized profit of a user's trades, valuing items at the average price paid for the
+// ones held when they were sold. Each account holds its own items, like flips are per account, so
+// a sale only takes its cost from what the same account bought. Sales of items that weren't
+// bought first have no cost to go on, so they realize nothing and hold nothing.
+#[derive(Debug, Default)]
+pub struct Ledger {
+    // In item name order, leaving out items sold down to nothing. Items held on several accounts
+    // are added up.
+    pub holdings: Vec<Holding>,
+    // In the order they were made
+    pub sales: Vec<RealizedSale>,
+}
+
+// Go through the trades in the order they were made
+fn in_order(trades: &[ItemData]) -> Vec<&ItemData> {
+    let mut trades: Vec<&ItemData> = trades.iter().collect();
+    trades.sort_by_key(|trade| (trade.timestamp, trade.id));
+    trades
+}
+
+pub fn ledger(trades: &[ItemData]) -> Ledger {
+    let mut sales = Vec::new();
+
+    // (quantity, cost) of each item on each account
+    let mut held: BTreeMap<(Option<i64>, &str), (i64, i64)> = BTreeMap::new();
+    for trade in in_order(trades) {
+        let (quantity, cost) = held
+            .entry((trade.account_id, &trade.item_name))
+            .or_default();
+        if trade.is_purchase {
+            *quantity += trade.quantity;
+            *cost += trade.quantity * trade.total_price;
+        } else if *quantity > 0 {
+            let sold = trade.quantity.min(*quantity);
+            let cost_of_sold = *cost * sold / *quantity;
+            *cost -= cost_of_sold;
+            *quantity -= sold;
+
+            sales.push(RealizedSale {
+                trade_id: trade.id,
+                item_name: trade.item_name.clone(),
+                account_id: trade.account_id,
+                timestamp: trade.timestamp,
+                profit: sold * trade.total_price - cost_of_sold,
+            });
+        }
+    }
+
+    let mut held_items: BTreeMap<&str, (i64, i64)> = BTreeMap::new();
+    for ((_, item_name), (quantity, cost)) in held {
+        let (total_quantity, total_cost) = held_items.entry(item_name).or_default();
+        *total_quantity += quantity;
+        *total_cost += cost;
+    }
+
+    let holdings = held_items
+        .into_iter()
+        .filter(|(_, (quantity, _))| *quantity > 0)
+        .map(|(item_name, (quantity, cost))| Holding {
+            item_name: item_name.to_string(),
+            quantity,
+            average_price: cost / quantity,
+            cost,
+        })
+        .collect();
+
+    Ledger { holdings, sales }
+}
+
+// A stretch of trades with no gap between them longer than `SESSION_GAP`
+#[derive(Debug, Clone, PartialEq)]
+pub struct InferredSession {
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub trade_count: i64,
+}
+
+// Split trades into sessions of play, oldest first
+pub fn infer_sessions(trades: &[ItemData]) -> Vec<InferredSession> {
+    let mut sessions: Vec<InferredSession> = Vec::new();
+
+    for trade in in_order(trades) {
+        match sessions.last_mut() {
+            Some(session) if trade.timestamp - session.end <= SESSION_GAP => {
+                session.end = trade.timestamp;
+                session.trade_count += 1;
+            }
+            _ => sessions.push(InferredSession {
+                start: trade.timestamp,
+                end: trade.timestamp,
+                trade_count: 1,
+            }),
+        }
+    }
+
+    sessions
+}
+
+// What the trades made during a session of play came to
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SessionTotals {
+    pub trade_count: i64,
+    // Realized by the sales made during the session
+    pub profit: i64,
+    // Spent on the purchases made during the session
+    pub capital_used: i64,
+}
+
+// Total up the trades a session is made of, with the profit their sales realized in the ledger
+pub fn session_totals<'a>(
+    trades: impl IntoIterator<Item = &'a ItemData>,
+    ledger: &Ledger,
+) -> SessionTotals {
+    let mut totals = SessionTotals::default();
+    let mut trade_ids = HashSet::new();
+    for trade in trades {
+        totals.trade_count += 1;
+        if trade.is_purchase {
+            totals.capital_used += trade.quantity * trade.total_price;
+        }
+        trade_ids.insert(trade.id);
+    }
+
+    totals.profit = ledger
+        .sales
+        .iter()
+        .filter(|sale| trade_ids.contains(&sale.trade_id))
+        .map(|sale| sale.profit)
+        .sum();
+
+    totals
+}
+
+// Profit per hour over a stretch of time, or None if it took no time
+pub fn gp_per_hour(profit: i64, duration: Duration) -> Option<i64> {
+    let seconds = duration.num_seconds();
+    (seconds > 0).then(|| profit * 3600 / seconds)
+}
+
+// Progress towards a goal is projected at the rate profit was made over this long before now
+pub const GOAL_RATE_WINDOW: Duration = Duration::days(7);
+
+// Profit realized towards a goal, and when it's projected to be reached
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoalProgress {
+    pub progress: i64,
+    // None once the goal is reached, if no profit was made recently to project from, or if it's
+    // too far off to put a date on
+    pub projected_completion: Option<NaiveDateTime>,
+}
+
+// Sum up the profit the sales picked out by `counts` realized towards a target since `since`, and
+// project when the rest will be made at the rate of the last `GOAL_RATE_WINDOW`. Goals started
+// more recently than that are projected from their start.
+pub fn goal_progress(
+    sales: &[RealizedSale],
+    target: i64,
+    since: NaiveDateTime,
+    now: NaiveDateTime,
+    counts: impl Fn(&RealizedSale) -> bool,
+) -> GoalProgress {
+    let profit_since = |start: NaiveDateTime| -> i64 {
+        sales
+            .iter()
+            .filter(|sale| start <= sale.timestamp && sale.timestamp <= now && counts(sale))
+            .map(|sale| sale.profit)
+            .sum()
+    };
+
+    let progress = profit_since(since);
+    let rate_start = since.max(now - GOAL_RATE_WINDOW);
+    let recent_profit = profit_since(rate_start);
+    let seconds = (now - rate_start).num_seconds();
+
+    let projected_completion = (progress < target && recent_profit > 0 && seconds > 0)
+        .then(|| {
+            let seconds_left = target.checked_sub(progress)?.checked_mul(seconds)? / recent_profit;
+            now.checked_add_signed(Duration::try_seconds(seconds_left)?)
+        })
+        .flatten();
+
+    GoalProgress {
+        progress,
+        projected_completion,
+    }
+}
+
+// Purchases of an item by one account in a buy limit window that hasn't reset yet
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuyLimitWindow {
+    pub item_name: String,
+    pub account_id: Option<i64>,
+    pub quantity_bought: i64,
+    pub resets_at: NaiveDateTime,
+}
+
+// The buy limit windows still running at `now`, soonest to reset first
+pub fn buy_limit_windows(trades: &[ItemData], now: NaiveDateTime) -> Vec<BuyLimitWindow> {
+    let mut windows: HashMap<(Option<i64>, &str), BuyLimitWindow> = HashMap::new();
+
+    for trade in in_order(trades) {
+        if !trade.is_purchase || trade.timestamp > now {
+            continue;
+        }
+
+        let window = windows
+            .entry((trade.account_id, &trade.item_name))
+            .or_insert_with(|| BuyLimitWindow {
+                item_name: trade.item_name.clone(),
+                account_id: trade.account_id,
+                quantity_bought: 0,
+                resets_at: trade.timestamp,
+            });
+
+        // A purchase after the window reset starts the next one
+        if trade.timestamp >= window.resets_at {
+            window.quantity_bought = 0;
+            window.resets_at = trade.timestamp + BUY_LIMIT_WINDOW;
+        }
+        window.quantity_bought += trade.quantity;
+    }
+
+    let mut windows: Vec<BuyLimitWindow> = windows
+        .into_values()
+        .filter(|window| window.resets_at > now)
+        .collect();
+    windows.sort_by(|a, b| {
+        (a.resets_at, &a.item_name, a.account_id).cmp(&(b.resets_at, &b.item_name, b.account_id))
+    });
+
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn at(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, 31)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn trade(
+        item_name: &str,
+        quantity: i64,
+        price_each: i64,
+        is_purchase: bool,
+        timestamp: NaiveDateTime,
+    ) -> ItemData {
+        ItemData {
+            id: 0,
+            item_name: item_name.to_string(),
+            quantity,
+            total_price: price_each,
+            is_purchase,
+            timestamp,
+            account_id: None,
+            note: None,
+            session_id: None,
+            tags: Vec::new(),
+        }
+    }
+
+    // Give the trades ids in the order they're listed, starting at 1
+    fn numbered(mut trades: Vec<ItemData>) -> Vec<ItemData> {
+        for (index, trade) in trades.iter_mut().enumerate() {
+            trade.id = index as i64 + 1;
+        }
+        trades
+    }
+
+    #[test]
+    fn sales_realize_profit_on_the_average_buy_price() {
+        // Listed out of order, since only the timestamps say what happened first
+        let ledger = ledger(&numbered(vec![
+            trade("Dragon bones", 50, 2_500, false, at(12, 0)),
+            trade("Dragon bones", 100, 2_000, true, at(10, 0)),
+            trade("Dragon bones", 100, 2_300, true, at(11, 0)),
+            trade("Dragon bones", 200, 2_100, false, at(13, 0)),
+            trade("Coal", 500, 150, false, at(13, 0)),
+        ]));
+
+        assert_eq!(
+            ledger.sales,
+            vec![
+                RealizedSale {
+                    trade_id: 1,
+                    item_name: "Dragon bones".to_string(),
+                    account_id: None,
+                    timestamp: at(12, 0),
+                    profit: 50 * 2_500 - 107_500,
+                },
+                // Only the 150 left are sold
+                RealizedSale {
+                    trade_id: 4,
+                    item_name: "Dragon bones".to_string(),
+                    account_id: None,
+                    timestamp: at(13, 0),
+                    profit: 150 * 2_100 - 322_500,
+                },
+            ]
+        );
+        assert!(ledger.holdings.is_empty());
+    }
+
+    #[test]
+    fn accounts_hold_their_own_items() {
+        let on_account = |account_id: i64, trade: ItemData| ItemData {
+            account_id: Some(account_id),
+            ..trade
+        };
+        let ledger = ledger(&numbered(vec![
+            on_account(1, trade("Dragon bones", 100, 2_000, true, at(10, 0))),
+            on_account(2, trade("Dragon bones", 100, 2_400, true, at(10, 0))),
+            // Sold on the first account, so only its 2,000 buy counts
+            on_account(1, trade("Dragon bones", 50, 2_300, false, at(11, 0))),
+            // The second account has no coal to sell
+            on_account(1, trade("Coal", 500, 150, true, at(11, 0))),
+            on_account(2, trade("Coal", 500, 160, false, at(12, 0))),
+        ]));
+
+        assert_eq!(
+            ledger.sales,
+            vec![RealizedSale {
+                trade_id: 3,
+                item_name: "Dragon bones".to_string(),
+                account_id: Some(1),
+                timestamp: at(11, 0),
+                profit: 50 * 2_300 - 100_000,
+            }]
+        );
+        // What's held on both accounts is added up
+        assert_eq!(
+            ledger.holdings,
+            vec![
+                Holding {
+                    item_name: "Coal".to_string(),
+                    quantity: 500,
+                    average_price: 150,
+                    cost: 75_000,
+                },
+                Holding {
+                    item_name: "Dragon bones".to_string(),
+                    quantity: 150,
+                    average_price: 2_266,
+                    cost: 340_000,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn session_totals_count_the_trades_in_the_session() {
+        let trades = numbered(vec![
+            trade("Dragon bones", 100, 2_000, true, at(10, 0)),
+            trade("Dragon bones", 50, 2_400, false, at(10, 30)),
+            trade("Coal", 1_000, 150, true, at(11, 0)),
+            trade("Dragon bones", 50, 2_500, false, at(14, 0)),
+        ]);
+        let ledger = ledger(&trades);
+
+        assert_eq!(
+            session_totals(&trades[1..3], &ledger),
+            SessionTotals {
+                trade_count: 2,
+                profit: 50 * 2_400 - 100_000,
+                capital_used: 150_000,
+            }
+        );
+        assert_eq!(session_totals([], &ledger), SessionTotals::default());
+        assert_eq!(gp_per_hour(20_000, Duration::minutes(30)), Some(40_000));
+        assert_eq!(gp_per_hour(20_000, Duration::zero()), None);
+    }
+
+    #[test]
+    fn goal_progress_projects_from_recent_profit() {
+        let date = |month: u32, day: u32, hour: u32| {
+            NaiveDate::from_ymd_opt(2024, month, day)
+                .unwrap()
+                .and_hms_opt(hour, 0, 0)
+                .unwrap()
+        };
+        let sales: Vec<RealizedSale> = [(29, 1_000_000), (30, 2_000_000), (31, -500_000)]
+            .into_iter()
+            .map(|(day, profit)| RealizedSale {
+                trade_id: 0,
+                item_name: "Dragon bones".to_string(),
+                account_id: None,
+                timestamp: date(1, day, 12),
+                profit,
+            })
+            .collect();
+        let progress = |target: i64, since: NaiveDateTime, now: NaiveDateTime| {
+            goal_progress(&sales, target, since, now, |_| true)
+        };
+
+        // 1.5m in the 2 days since the goal started, so the other 8.5m take 11 days and 8 hours
+        assert_eq!(
+            progress(10_000_000, date(1, 30, 0), date(2, 1, 0)),
+            GoalProgress {
+                progress: 1_500_000,
+                projected_completion: Some(date(2, 12, 8)),
+            }
+        );
+        // 2.5m in the last week, so the other 7.5m take 3 weeks
+        assert_eq!(
+            progress(10_000_000, date(1, 1, 0), date(2, 1, 0)),
+            GoalProgress {
+                progress: 2_500_000,
+                projected_completion: Some(date(2, 22, 0)),
+            }
+        );
+        // Reached already
+        assert_eq!(
+            progress(2_000_000, date(1, 1, 0), date(2, 1, 0)).projected_completion,
+            None
+        );
+        // Nothing made in the last week to go on
+        assert_eq!(
+            progress(10_000_000, date(1, 1, 0), date(3, 1, 0)),
+            GoalProgress {
+                progress: 2_500_000,
+                projected_completion: None,
+            }
+        );
+        // Too far off at this rate to put a date on
+        assert_eq!(
+            progress(i64::MAX, date(1, 1, 0), date(2, 1, 0)).projected_completion,
+            None
+        );
+        let tiny_profit = [RealizedSale {
+            profit: 1,
+            ..sales[0].clone()
+        }];
+        assert_eq!(
+            goal_progress(
+                &tiny_profit,
+                100_000_000,
+                date(1, 28, 0),
+                date(2, 1, 0),
+                |_| true
+            )
+            .projected_completion,
+            None
+        );
+        // Only sales of other items
+        assert_eq!(
+            goal_progress(&sales, 10_000_000, date(1, 1, 0), date(2, 1, 0), |sale| {
+                sale.item_name == "Coal"
+            })
+            .progress,
+            0
+        );
+    }
+
+    #[test]
+    fn sessions_split_at_long_gaps() {
+        let sessions = infer_sessions(&[
+            trade("Dragon bones", 1, 1, true, at(10, 0)),
+            trade("Dragon bones", 1, 1, false, at(10, 30)),
+            trade("Dragon bones", 1, 1, true, at(11, 30)),
+            trade("Dragon bones", 1, 1, false, at(12, 31)),
+        ]);
+
+        assert_eq!(
+            sessions,
+            vec![
+                InferredSession {
+                    start: at(10, 0),
+                    end: at(11, 30),
+                    trade_count: 3,
+                },
+                InferredSession {
+                    start: at(12, 31),
+                    end: at(12, 31),
+                    trade_count: 1,
+                },
+            ]
+        );
+        assert!(infer_sessions(&[]).is_empty());
+    }
+
+    #[test]
+    fn buy_limit_windows_start_at_the_first_purchase() {
+        let mut other_account = trade("Dragon bones", 300, 2_000, true, at(9, 30));
+        other_account.account_id = Some(2);
+
+        let windows = buy_limit_windows(
+            &[
+                // Reset at 9:00, so the purchase at 10:00 starts a new window
+                trade("Dragon bones", 500, 2_000, true, at(5, 0)),
+                trade("Dragon bones", 100, 2_000, true, at(10, 0)),
+                trade("Dragon bones", 200, 2_000, true, at(11, 0)),
+                trade("Dragon bones", 100, 2_500, false, at(11, 30)),
+                other_account,
+                // Reset already
+                trade("Coal", 1_000, 150, true, at(7, 0)),
+            ],
+            at(12, 0),
+        );
+
+        assert_eq!(
+            windows,
+            vec![
+                BuyLimitWindow {
+                    item_name: "Dragon bones".to_string(),
+                    account_id: Some(2),
+                    quantity_bought: 300,
+                    resets_at: at(13, 30),
+                },
+                BuyLimitWindow {
+                    item_name: "Dragon bones".to_string(),
+                    account_id: None,
+                    quantity_bought: 300,
+                    resets_at: at(14, 0),
+                },
+            ]
+        );
+    }
+}
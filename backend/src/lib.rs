@@ -0,0 +1,127 @@
+pub mod audit;
+pub mod auth;
+pub mod backup;
+pub mod db;
+pub mod events;
+pub mod frontend;
+pub mod ledger;
+pub mod logging;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod models;
+pub mod openapi;
+pub mod repository;
+pub mod request_id;
+pub mod routes;
+
+use actix_cors::Cors;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::middleware::from_fn;
+use actix_web::{web, App, Error};
+
+use db::Database;
+use events::Events;
+use repository::TradeRepository;
+use routes::*;
+
+// Build the app with every route and middleware, storing its data in the given database.
+// Trade handlers go through `trades` instead, which is usually backed by the same database.
+// The server builds one per worker, and tests build one around a temporary database.
+pub fn app(
+    db: web::Data<Database>,
+    trades: web::Data<dyn TradeRepository>,
+    events: web::Data<Events>,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    // Session cookies are sent cross-origin, so only the frontend's origin may make requests
+    let allowed_origin = std::env::var("ARDY_ALLOWED_ORIGIN")
+        .unwrap_or_else(|_| "http://localhost:8080".to_string());
+
+    let cors = Cors::default()
+        .allowed_origin(&allowed_origin)
+        .allow_any_method()
+        .allow_any_header()
+        .supports_credentials()
+        .expose_headers([request_id::REQUEST_ID_HEADER])
+        .max_age(3600);
+
+    let app = App::new()
+        .wrap(from_fn(auth::require_auth))
+        .wrap(cors)
+        .wrap(from_fn(request_id::assign_request_id))
+        .app_data(db)
+        .app_data(trades)
+        .app_data(events)
+        .service(trade_post)
+        .service(trade_get)
+        .service(trade_delete)
+        .service(bulk::trades_bulk_post)
+        .service(tags::tags_get)
+        .service(tags::tags_post)
+        .service(tags::tags_put)
+        .service(tags::tags_delete)
+        .service(tags::trade_tags_put)
+        .service(tags::profit_loss_tags_get)
+        .service(trash::trash_get)
+        .service(trash::trade_restore_post)
+        .service(profit_loss_get)
+        .service(dashboard::dashboard_get)
+        .service(offers::offer_get)
+        .service(offers::offer_post)
+        .service(offers::offer_fill_post)
+        .service(offers::offer_cancel_post)
+        .service(offers::offer_complete_post)
+        .service(flips::flips_get)
+        .service(flips::flips_post)
+        .service(flips::flips_auto_post)
+        .service(flips::flips_delete)
+        .service(holdings::holdings_get)
+        .service(trading_sessions::trading_sessions_get)
+        .service(trading_sessions::trading_sessions_inferred_get)
+        .service(trading_sessions::trading_sessions_post)
+        .service(trading_sessions::trading_session_stop_post)
+        .service(goals::goals_get)
+        .service(goals::goals_post)
+        .service(goals::goals_delete)
+        .service(accounts::account_get)
+        .service(accounts::account_post)
+        .service(accounts::account_put)
+        .service(accounts::account_delete)
+        .service(routes::auth::register_post)
+        .service(routes::auth::login_post)
+        .service(routes::auth::logout_post)
+        .service(routes::auth::me_get)
+        .service(tokens::tokens_get)
+        .service(tokens::tokens_post)
+        .service(tokens::tokens_delete)
+        .service(audit_log::audit_get)
+        .service(event_stream::events_get)
+        .service(api_docs::openapi_get)
+        .service(api_docs::docs_get)
+        .service(health::healthz_get)
+        .service(health::readyz_get)
+        .service(backups::backups_get)
+        .service(backups::backups_post)
+        .service(backups::snapshot_get);
+
+    // Outermost, so requests rejected by the other middleware are counted too
+    #[cfg(feature = "metrics")]
+    let app = app
+        .wrap(from_fn(metrics::track_requests))
+        .app_data(web::Data::new(metrics::RealizedProfitCache::default()))
+        .route(metrics::METRICS_PATH, web::get().to(metrics::metrics_get));
+
+    // Last, since it serves every path no other route matched
+    match frontend::dir() {
+        Some(dir) => app.service(frontend::service(dir)),
+        None => app,
+    }
+}
@@ -0,0 +1,19 @@
+use tracing_subscriber::EnvFilter;
+
+// Used when RUST_LOG isn't set
+const DEFAULT_FILTER: &str = "info";
+
+// Log to stdout at the levels set by RUST_LOG, e.g. `RUST_LOG=backend=debug,info`.
+// Setting ARDY_LOG_FORMAT=json writes one JSON object per line, for log collectors.
+pub fn init() {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+
+    let json = std::env::var("ARDY_LOG_FORMAT").is_ok_and(|format| format == "json");
+    if json {
+        builder.json().with_current_span(true).init();
+    } else {
+        builder.init();
+    }
+}
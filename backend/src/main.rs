@@ -1,34 +1,150 @@
-mod db;
-mod models;
-mod routes;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 
-use actix_cors::Cors;
-use actix_web::{App, HttpServer};
-use routes::*;
+use actix_web::{web, HttpServer};
+use backend::backup;
+use backend::db::{self, Database};
+use backend::events::Events;
+use backend::frontend;
+use backend::logging;
+use backend::repository::{SqliteTradeRepository, TradeRepository};
+use backend::routes::trash;
+use tracing::{error, info, warn};
+
+// How long to wait for requests in flight when shutting down
+const SHUTDOWN_TIMEOUT_SECS: u64 = 10;
+
+// Hours between scheduled backups unless `ARDY_BACKUP_INTERVAL_HOURS` says otherwise
+const DEFAULT_BACKUP_INTERVAL_HOURS: u64 = 24;
+
+const USAGE: &str = "Usage: backend [restore <backup file>]";
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    logging::init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.as_slice() {
+        [] => serve().await,
+        [command, backup] if command == "restore" => {
+            restore(Path::new(backup));
+            Ok(())
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+// Replace the database with a backup. Run it while the server is stopped.
+fn restore(backup: &Path) {
+    let db = Database::new(db::DEFAULT_PATH);
+
+    let restored = match backup::restore(&db, backup) {
+        Ok(restored) => restored,
+        Err(e) => {
+            error!(error = %e, backup = %backup.display(), "Failed to restore backup");
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(previous) = restored.previous {
+        info!(backup = %previous.display(), "Backed up the replaced database");
+    }
+    info!(
+        backup = %backup.display(),
+        schema_version = restored.schema_version,
+        "Restored database, missing migrations are applied on the next start"
+    );
+}
+
+async fn serve() -> std::io::Result<()> {
+    let db = web::Data::new(Database::new(db::DEFAULT_PATH));
+
     // Initialize database
-    db::init_db().expect("Failed to initialize database");
-
-    HttpServer::new(|| {
-        // Enable CORS *UNSAFE FOR PRODUCTION*
-        let cors = Cors::default()
-            .allow_any_origin()
-            .allow_any_method()
-            .allow_any_header()
-            .max_age(3600);
-
-        App::new()
-            .wrap(cors)
-            .service(hello_get)
-            .service(hello_post)
-            .service(trade_post)
-            .service(trade_get)
-            .service(trade_delete)
-            .service(profit_loss_get)
-    })
-    .bind("127.0.0.1:43211")?
-    .run()
-    .await
+    db::init_db(&db).expect("Failed to initialize database");
+
+    // Purge old trades from the trash at startup and then once a day
+    let purge_db = db.clone();
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(Duration::from_secs(24 * 60 * 60));
+        loop {
+            interval.tick().await;
+            match trash::purge_deleted_trades(&purge_db) {
+                Ok(purged) => info!(purged, "Purged trades from the trash"),
+                Err(e) => error!(error = %e, "Failed to purge trash"),
+            }
+        }
+    });
+
+    // Back up the database at startup and then every interval, keeping the newest backups
+    let backup_interval_hours = std::env::var("ARDY_BACKUP_INTERVAL_HOURS")
+        .ok()
+        .and_then(|hours| hours.parse().ok())
+        .filter(|hours| *hours > 0)
+        .unwrap_or(DEFAULT_BACKUP_INTERVAL_HOURS);
+    let backup_db = db.clone();
+    actix_web::rt::spawn(async move {
+        let mut interval =
+            actix_web::rt::time::interval(Duration::from_secs(backup_interval_hours * 60 * 60));
+        loop {
+            interval.tick().await;
+            // Copying the database is blocking file I/O, so keep it off the runtime
+            let db = backup_db.clone();
+            let backed_up = web::block(move || {
+                let created = backup::create_backup(&db)?;
+                Ok::<_, backup::BackupError>((created, backup::prune_backups(&db, backup::keep())))
+            })
+            .await;
+            match backed_up {
+                Ok(Ok((created, pruned))) => {
+                    info!(backup = %created.path.display(), "Backed up database");
+                    match pruned {
+                        Ok(pruned) => {
+                            for backup in pruned {
+                                info!(backup = %backup.path.display(), "Deleted old backup");
+                            }
+                        }
+                        Err(e) => error!(error = %e, "Failed to delete old backups"),
+                    }
+                }
+                Ok(Err(e)) => error!(error = %e, "Failed to back up database"),
+                Err(e) => error!(error = %e, "Failed to back up database"),
+            }
+        }
+    });
+
+    // Trades are kept in the same database
+    let trades: Arc<dyn TradeRepository> =
+        Arc::new(SqliteTradeRepository::new(db.get_ref().clone()));
+    let trades = web::Data::from(trades);
+
+    // Shared by all workers so events reach clients connected to any of them
+    let events = web::Data::new(Events::new());
+
+    match frontend::dir() {
+        Some(dir) => info!(dir = %dir.display(), "Serving the frontend"),
+        None => info!("No built frontend found, serving only the API"),
+    }
+
+    // On SIGTERM or Ctrl-C the server stops accepting connections and waits for requests in
+    // flight to finish. Event streams never finish by themselves, so they are cut off after the
+    // timeout.
+    let server_db = db.clone();
+    HttpServer::new(move || backend::app(server_db.clone(), trades.clone(), events.clone()))
+        .bind("127.0.0.1:43211")?
+        .shutdown_timeout(SHUTDOWN_TIMEOUT_SECS)
+        .run()
+        .await?;
+
+    // Leave a database file that is complete without its write-ahead log
+    match db::checkpoint(&db) {
+        Ok(true) => info!("Checkpointed database after shutdown"),
+        Ok(false) => warn!("Database was busy, so its write-ahead log wasn't fully checkpointed"),
+        Err(e) => error!(error = %e, "Failed to checkpoint database"),
+    }
+
+    Ok(())
 }
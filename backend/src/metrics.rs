@@ -0,0 +1,264 @@
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::ErrorInternalServerError;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpResponse};
+use prometheus::{
+    exponential_buckets, Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts,
+    Registry, TextEncoder,
+};
+use rusqlite::Connection;
+use tracing::debug;
+
+use crate::auth::AuthenticatedUser;
+use crate::db::Database;
+use crate::ledger;
+use crate::repository::{TradeFilter, TradeRepository};
+use crate::routes::{internal_error, repository_error};
+
+pub const METRICS_PATH: &str = "/metrics";
+
+// How long the realized profit gauge is reused before it's worked out again. It goes through
+// every user's trades, which is too much to do on each scrape.
+const REALIZED_PROFIT_TTL: Duration = Duration::from_secs(60);
+
+// Every metric is named ardy_*
+const NAMESPACE: &str = "ardy";
+
+struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    db_query_duration: HistogramVec,
+    // Gauges are read from the database when scraped
+    db_size: IntGauge,
+    trades: IntGauge,
+    users: IntGauge,
+    realized_profit: IntGauge,
+}
+
+// SQLite reports query timings to a plain function, so the metrics live in a static
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some(NAMESPACE.to_string()), None)
+            .expect("Metrics namespace is valid");
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests handled"),
+            &["method", "route", "status"],
+        )
+        .expect("Metric is valid");
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time until the response headers were ready",
+            ),
+            &["method", "route", "status"],
+        )
+        .expect("Metric is valid");
+        let db_query_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "db_query_duration_seconds",
+                "Time SQLite took per statement",
+            )
+            .buckets(exponential_buckets(0.0001, 4.0, 8).expect("Buckets are valid")),
+            &["statement"],
+        )
+        .expect("Metric is valid");
+        let db_size =
+            IntGauge::new("db_size_bytes", "Size of the database file").expect("Metric is valid");
+        let trades = IntGauge::new("trades", "Trades outside the trash, of all users")
+            .expect("Metric is valid");
+        let users = IntGauge::new("users", "Registered users").expect("Metric is valid");
+        let realized_profit = IntGauge::new(
+            "realized_profit_gp",
+            "Lifetime realized profit of all users' trades, as the dashboard counts it",
+        )
+        .expect("Metric is valid");
+
+        for collector in [
+            Box::new(http_requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(http_request_duration.clone()),
+            Box::new(db_query_duration.clone()),
+            Box::new(db_size.clone()),
+            Box::new(trades.clone()),
+            Box::new(users.clone()),
+            Box::new(realized_profit.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("Metrics are only registered once");
+        }
+
+        Metrics {
+            registry,
+            http_requests,
+            http_request_duration,
+            db_query_duration,
+            db_size,
+            trades,
+            users,
+            realized_profit,
+        }
+    }
+}
+
+// Record how long each statement run on the connection takes
+pub fn time_queries(mut conn: Connection) -> Connection {
+    conn.profile(Some(record_query));
+    conn
+}
+
+fn record_query(sql: &str, duration: Duration) {
+    METRICS
+        .db_query_duration
+        .with_label_values(&[statement_kind(sql)])
+        .observe(duration.as_secs_f64());
+}
+
+// Label statements by their kind, since labelling by SQL would create a series per query
+fn statement_kind(sql: &str) -> &'static str {
+    let keyword = sql.split_whitespace().next().unwrap_or_default();
+    ["select", "insert", "update", "delete"]
+        .into_iter()
+        .find(|kind| keyword.eq_ignore_ascii_case(kind))
+        .unwrap_or("other")
+}
+
+// Middleware counting requests and timing them, per route and status
+pub async fn track_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let method = req.method().to_string();
+    // Label by route pattern like /api/v1/offer/{id}/fill, so ids don't create a series each
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+    let started = Instant::now();
+
+    let result = next.call(req).await;
+
+    let status = match &result {
+        Ok(res) => res.status(),
+        Err(e) => e.as_response_error().status_code(),
+    };
+    let labels = [method.as_str(), route.as_str(), status.as_str()];
+    METRICS.http_requests.with_label_values(&labels).inc();
+    METRICS
+        .http_request_duration
+        .with_label_values(&labels)
+        .observe(started.elapsed().as_secs_f64());
+
+    result
+}
+
+// The last realized profit worked out and when, shared by the scrapes of one app
+#[derive(Default)]
+pub struct RealizedProfitCache(Mutex<Option<(Instant, i64)>>);
+
+fn update_gauges(
+    db: &Database,
+    repository: &dyn TradeRepository,
+    cache: &RealizedProfitCache,
+) -> Result<(), Error> {
+    // The file doesn't exist until the first connection is opened
+    let db_size = std::fs::metadata(db.path()).map_or(0, |metadata| metadata.len());
+    METRICS.db_size.set(db_size as i64);
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let trades: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM trades WHERE deleted_at IS NULL",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(internal_error("Failed to count trades"))?;
+    METRICS.trades.set(trades);
+
+    let users: i64 = conn
+        .query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))
+        .map_err(internal_error("Failed to count users"))?;
+    METRICS.users.set(users);
+
+    let mut cached = cache.0.lock().unwrap_or_else(|e| e.into_inner());
+    let realized_profit = match *cached {
+        Some((updated, realized_profit)) if updated.elapsed() < REALIZED_PROFIT_TTL => {
+            realized_profit
+        }
+        _ => {
+            let realized_profit = total_realized_profit(&conn, repository)?;
+            *cached = Some((Instant::now(), realized_profit));
+            realized_profit
+        }
+    };
+    METRICS.realized_profit.set(realized_profit);
+
+    Ok(())
+}
+
+// Realized profit of every user's trades together. Each user's is worked out from their own
+// ledger, the same way the dashboard does, so their cost bases don't mix.
+fn total_realized_profit(conn: &Connection, trades: &dyn TradeRepository) -> Result<i64, Error> {
+    let users = conn
+        .prepare("SELECT id, username FROM users")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| {
+                Ok(AuthenticatedUser {
+                    id: row.get(0)?,
+                    username: row.get(1)?,
+                    scopes: Vec::new(),
+                    api_token: None,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<AuthenticatedUser>>>()
+        })
+        .map_err(internal_error("Failed to get users"))?;
+
+    let mut total = 0;
+    for user in users {
+        let trades = trades
+            .list(&user, &TradeFilter::default())
+            .map_err(repository_error("Failed to list trades"))?;
+        total += ledger::ledger(&trades)
+            .sales
+            .iter()
+            .map(|sale| sale.profit)
+            .sum::<i64>();
+    }
+
+    Ok(total)
+}
+
+// Handle GET request for metrics in the Prometheus text format. It's public so Prometheus can
+// scrape it without a token, and only shows totals across all users.
+pub async fn metrics_get(
+    db: web::Data<Database>,
+    trades: web::Data<dyn TradeRepository>,
+    cache: web::Data<RealizedProfitCache>,
+) -> Result<HttpResponse, Error> {
+    debug!("Gathering metrics");
+
+    update_gauges(&db, trades.as_ref(), &cache)?;
+
+    let encoder = TextEncoder::new();
+    let mut body = Vec::new();
+    encoder
+        .encode(&METRICS.registry.gather(), &mut body)
+        .map_err(|e| {
+            tracing::error!(error = %e, "Failed to encode metrics");
+            ErrorInternalServerError("Failed to encode metrics")
+        })?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(encoder.format_type())
+        .body(body))
+}
@@ -1,35 +1,416 @@
 use chrono::NaiveDateTime;
-use serde::{Deserialize, Deserializer, Serialize};
+use serde::{Deserialize, Serialize};
+use shared::models::{
+    deserialize_datetime, deserialize_optional_datetime, serialize_datetime,
+    serialize_optional_datetime,
+};
+use utoipa::ToSchema;
 
-#[derive(Deserialize)]
-pub struct Hello {
-    pub hello: String,
+pub use shared::models::{
+    BulkOperationResult, BulkTradeRequest, BulkTradeResponse, InvalidTrade, ItemData, TradeChanges,
+    TradeOperation,
+};
+
+// Outcome of one of the checks made by /readyz
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ReadinessCheck {
+    pub name: String,
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Readiness {
+    pub ready: bool,
+    pub checks: Vec<ReadinessCheck>,
+}
+
+// A backup of the database in the backup directory
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Backup {
+    pub name: String,
+    #[serde(serialize_with = "serialize_datetime")]
+    #[schema(value_type = String, example = "2024-01-31T13:45")]
+    pub created_at: NaiveDateTime,
+    pub size_bytes: u64,
 }
 
-fn deserialize_datetime<'de, D>(deserializer: D) -> Result<NaiveDateTime, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let s = String::deserialize(deserializer)?;
-    NaiveDateTime::parse_from_str(&s, "%Y-%m-%dT%H:%M").map_err(serde::de::Error::custom)
+// A trade in the trash, along with when it was deleted
+#[derive(Serialize, Debug, ToSchema)]
+pub struct DeletedTrade {
+    #[serde(flatten)]
+    pub trade: ItemData,
+    #[serde(serialize_with = "serialize_datetime")]
+    #[schema(value_type = String, example = "2024-01-31T13:45")]
+    pub deleted_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OfferStatus {
+    Open,
+    Completed,
+    Cancelled,
 }
 
-fn serialize_datetime<S>(datetime: &NaiveDateTime, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: serde::Serializer,
-{
-    let s = format!("{}", datetime.format("%Y-%m-%dT%H:%M"));
-    serializer.serialize_str(&s)
+impl OfferStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OfferStatus::Open => "open",
+            OfferStatus::Completed => "completed",
+            OfferStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<OfferStatus> {
+        match s {
+            "open" => Some(OfferStatus::Open),
+            "completed" => Some(OfferStatus::Completed),
+            "cancelled" => Some(OfferStatus::Cancelled),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct ItemData {
+// A Grand Exchange offer occupying one of the 8 slots.
+// `price` is the limit price per item, `quantity_filled` is derived from the linked trades.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Offer {
     pub id: i64,
     pub item_name: String,
+    pub slot: i64,
+    pub is_purchase: bool,
+    pub price: i64,
     pub quantity: i64,
-    pub total_price: i64,
+    pub quantity_filled: i64,
+    pub status: OfferStatus,
+    #[serde(serialize_with = "serialize_datetime")]
+    #[schema(value_type = String, example = "2024-01-31T13:45")]
+    pub timestamp: NaiveDateTime,
+    pub account_id: Option<i64>,
+}
+
+// Timestamp defaults to the time the offer is placed
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct NewOffer {
+    pub item_name: String,
+    pub slot: i64,
     pub is_purchase: bool,
+    pub price: i64,
+    pub quantity: i64,
+    #[serde(default, deserialize_with = "deserialize_optional_datetime")]
+    #[schema(value_type = Option<String>, example = "2024-01-31T13:45")]
+    pub timestamp: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub account_id: Option<i64>,
+}
+
+// A partial (or full) fill of an offer. Price defaults to the offer's limit price.
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct OfferFill {
+    pub quantity: i64,
+    pub price: Option<i64>,
+    #[serde(default, deserialize_with = "deserialize_optional_datetime")]
+    #[schema(value_type = Option<String>, example = "2024-01-31T13:45")]
+    pub timestamp: Option<NaiveDateTime>,
+}
+
+// An OSRS account (main, ironman, alt, ...) that trades and offers belong to
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Account {
+    pub id: i64,
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct NewAccount {
+    pub name: String,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FlipStatus {
+    Open,
+    PartiallyClosed,
+    Closed,
+}
+
+impl FlipStatus {
+    pub fn parse(s: &str) -> Option<FlipStatus> {
+        match s {
+            "open" => Some(FlipStatus::Open),
+            "partially_closed" => Some(FlipStatus::PartiallyClosed),
+            "closed" => Some(FlipStatus::Closed),
+            _ => None,
+        }
+    }
+}
+
+// A buy and its sells grouped together. Profit is realized profit on the quantity sold so far,
+// using the average buy price, and ROI is that profit relative to the cost of the sold items.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Flip {
+    pub id: i64,
+    pub item_name: String,
+    pub status: FlipStatus,
+    pub quantity_bought: i64,
+    pub quantity_sold: i64,
+    pub cost: i64,
+    pub revenue: i64,
+    pub profit: i64,
+    pub roi: f64,
+    pub duration_seconds: i64,
+    pub trades: Vec<ItemData>,
+}
+
+// Items still held after the sales so far, valued at the average buy price. Sales take their
+// share of the cost at the average price of the items held when they were sold.
+#[derive(Serialize, Debug, PartialEq, ToSchema)]
+pub struct Holding {
+    pub item_name: String,
+    pub quantity: i64,
+    pub average_price: i64,
+    pub cost: i64,
+}
+
+// Realized profit from the sales of one item
+#[derive(Serialize, Debug, PartialEq, ToSchema)]
+pub struct ItemProfit {
+    pub item_name: String,
+    pub profit: i64,
+}
+
+// A session of play inferred from the gaps between trades, with the profit realized by the sales
+// and the capital used by the purchases made during it. GP/hour is None for sessions of a single
+// trade, which take no time.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct RecentSession {
+    #[serde(serialize_with = "serialize_datetime")]
+    #[schema(value_type = String, example = "2024-01-31T13:45")]
+    pub start: NaiveDateTime,
+    #[serde(serialize_with = "serialize_datetime")]
+    #[schema(value_type = String, example = "2024-01-31T15:10")]
+    pub end: NaiveDateTime,
+    pub trade_count: i64,
+    pub profit: i64,
+    pub capital_used: i64,
+    pub gp_per_hour: Option<i64>,
+}
+
+// A session of play started and stopped by the user, with the trades added while it was in
+// progress. The profit is realized by the sales among them, and capital used is spent on the
+// purchases. GP/hour is None for sessions that took no time.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct TradingSession {
+    pub id: i64,
+    #[serde(serialize_with = "serialize_datetime")]
+    #[schema(value_type = String, example = "2024-01-31T13:45")]
+    pub started_at: NaiveDateTime,
+    // None while the session is in progress
+    #[serde(serialize_with = "serialize_optional_datetime")]
+    #[schema(value_type = Option<String>, example = "2024-01-31T15:10")]
+    pub ended_at: Option<NaiveDateTime>,
+    pub trade_count: i64,
+    pub profit: i64,
+    pub capital_used: i64,
+    pub gp_per_hour: Option<i64>,
+}
+
+// Purchases of an item by one account since its buy limit window started
+#[derive(Serialize, Debug, ToSchema)]
+pub struct BuyLimitReset {
+    pub item_name: String,
+    pub account_id: Option<i64>,
+    pub quantity_bought: i64,
+    #[serde(serialize_with = "serialize_datetime")]
+    #[schema(value_type = String, example = "2024-01-31T17:45")]
+    pub resets_at: NaiveDateTime,
+}
+
+// The numbers shown on the dashboard, all in one response. Profit is realized profit, as in
+// holdings, counted on the day and week of the sale.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Dashboard {
+    pub profit_today: i64,
+    pub profit_this_week: i64,
+    pub profit_all_time: i64,
+    // Over the recent sessions together
+    pub gp_per_hour: Option<i64>,
+    // Most recent first
+    pub recent_sessions: Vec<RecentSession>,
+    // The most profitable items, most profitable first
+    pub top_items: Vec<ItemProfit>,
+    // The items that lost the most, biggest loss first
+    pub worst_items: Vec<ItemProfit>,
+    // What the items still held cost
+    pub capital_tied_up: i64,
+    pub open_flips: i64,
+    // Soonest to reset first
+    pub buy_limit_resets: Vec<BuyLimitReset>,
+    // Every goal, with progress in its own scope rather than the selected account's, soonest
+    // deadline first
+    pub goals: Vec<Goal>,
+}
+
+// An amount of gp to make by a deadline, counting the profit realized by sales from the start on.
+// Only sales of the item or by the account count if they're set.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Goal {
+    pub id: i64,
+    pub name: String,
+    pub target: i64,
+    #[serde(serialize_with = "serialize_datetime")]
+    #[schema(value_type = String, example = "2024-01-31T13:45")]
+    pub started_at: NaiveDateTime,
+    #[serde(serialize_with = "serialize_datetime")]
+    #[schema(value_type = String, example = "2024-02-29T23:59")]
+    pub deadline: NaiveDateTime,
+    pub item_name: Option<String>,
+    pub account_id: Option<i64>,
+    pub progress: i64,
+    // When the target will be reached at the recent rate of profit. None once it's reached, or
+    // without recent profit to go on.
+    #[serde(serialize_with = "serialize_optional_datetime")]
+    #[schema(value_type = Option<String>, example = "2024-02-21T09:30")]
+    pub projected_completion: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct NewGoal {
+    pub name: String,
+    pub target: i64,
     #[serde(deserialize_with = "deserialize_datetime")]
+    #[schema(value_type = String, example = "2024-02-29T23:59")]
+    pub deadline: NaiveDateTime,
+    // Defaults to the backend's local time
+    #[serde(default, deserialize_with = "deserialize_optional_datetime")]
+    #[schema(value_type = Option<String>, example = "2024-01-31T13:45")]
+    pub started_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub item_name: Option<String>,
+    #[serde(default)]
+    pub account_id: Option<i64>,
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct NewFlip {
+    pub trade_ids: Vec<i64>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct User {
+    pub id: i64,
+    pub username: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+// Returned on login. The token is also set as an HttpOnly cookie.
+#[derive(Serialize, ToSchema)]
+pub struct LoginResponse {
+    pub token: String,
+    pub user: User,
+}
+
+// What an API token may do. Each scope includes the ones before it: read < write < admin.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Read,
+    Write,
+    Admin,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Write => "write",
+            Scope::Admin => "admin",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Scope> {
+        match s {
+            "read" => Some(Scope::Read),
+            "write" => Some(Scope::Write),
+            "admin" => Some(Scope::Admin),
+            _ => None,
+        }
+    }
+}
+
+// A personal API token for scripts and bots. The token itself is only shown once, on creation.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ApiToken {
+    pub id: i64,
+    pub name: String,
+    pub scopes: Vec<Scope>,
     #[serde(serialize_with = "serialize_datetime")]
+    #[schema(value_type = String, example = "2024-01-31T13:45")]
+    pub created_at: NaiveDateTime,
+    #[serde(serialize_with = "serialize_optional_datetime")]
+    #[schema(value_type = Option<String>, example = "2024-01-31T13:45")]
+    pub expires_at: Option<NaiveDateTime>,
+    #[serde(serialize_with = "serialize_optional_datetime")]
+    #[schema(value_type = Option<String>, example = "2024-01-31T13:45")]
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+// Tokens without an expiry stay valid until revoked
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct NewApiToken {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CreatedApiToken {
+    pub token: String,
+    #[serde(flatten)]
+    pub api_token: ApiToken,
+}
+
+// A change recorded in the audit log, with the changed row before and after as JSON.
+// `before` is null for inserts and `after` is null for deletes.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub actor: String,
+    #[serde(serialize_with = "serialize_datetime")]
+    #[schema(value_type = String, example = "2024-01-31T13:45")]
     pub timestamp: NaiveDateTime,
+    pub request_id: Option<String>,
+    pub action: String,
+    pub table_name: String,
+    pub record_id: i64,
+    #[schema(value_type = Option<Object>)]
+    pub before: Option<serde_json::Value>,
+    #[schema(value_type = Option<Object>)]
+    pub after: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+    pub trade_count: i64,
+}
+
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct NewTag {
+    pub name: String,
+}
+
+// Profit of the trades with a tag. Untagged trades are grouped under a null tag, and trades with
+// several tags count towards each of them.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct TagProfitLoss {
+    pub tag: Option<String>,
+    pub profit_loss: i64,
 }
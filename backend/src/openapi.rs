@@ -0,0 +1,188 @@
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use crate::auth::SESSION_COOKIE;
+use crate::routes::{self, *};
+
+// The OpenAPI document describing the HTTP API, served at /api/v1/openapi.json.
+// Every handler registered in lib.rs must be listed under `paths`, which the drift test checks.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "ardy",
+        description = "Track Grand Exchange trades, offers and flips. Requests are made with the session cookie set on login, or an API token as a bearer token."
+    ),
+    paths(
+        routes::trade_post,
+        routes::trade_get,
+        routes::trade_delete,
+        routes::profit_loss_get,
+        dashboard::dashboard_get,
+        bulk::trades_bulk_post,
+        tags::tags_get,
+        tags::tags_post,
+        tags::tags_put,
+        tags::tags_delete,
+        tags::trade_tags_put,
+        tags::profit_loss_tags_get,
+        trash::trash_get,
+        trash::trade_restore_post,
+        offers::offer_get,
+        offers::offer_post,
+        offers::offer_fill_post,
+        offers::offer_cancel_post,
+        offers::offer_complete_post,
+        flips::flips_get,
+        flips::flips_post,
+        flips::flips_auto_post,
+        flips::flips_delete,
+        holdings::holdings_get,
+        trading_sessions::trading_sessions_get,
+        trading_sessions::trading_sessions_inferred_get,
+        trading_sessions::trading_sessions_post,
+        trading_sessions::trading_session_stop_post,
+        goals::goals_get,
+        goals::goals_post,
+        goals::goals_delete,
+        accounts::account_get,
+        accounts::account_post,
+        accounts::account_put,
+        accounts::account_delete,
+        routes::auth::register_post,
+        routes::auth::login_post,
+        routes::auth::logout_post,
+        routes::auth::me_get,
+        tokens::tokens_get,
+        tokens::tokens_post,
+        tokens::tokens_delete,
+        audit_log::audit_get,
+        event_stream::events_get,
+        api_docs::openapi_get,
+        api_docs::docs_get,
+        health::healthz_get,
+        health::readyz_get,
+        backups::backups_get,
+        backups::backups_post,
+        backups::snapshot_get,
+    ),
+    modifiers(&SecuritySchemes),
+    security(("session" = []), ("api_token" = [])),
+)]
+pub struct ApiDoc;
+
+// Adds the two ways of authenticating that `auth::require_auth` accepts
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(SESSION_COOKIE))),
+        );
+        components.add_security_scheme(
+            "api_token",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::fs;
+    use std::path::Path;
+
+    use utoipa::OpenApi;
+
+    use super::ApiDoc;
+
+    const METHODS: &[&str] = &["get", "post", "put", "delete", "patch"];
+
+    // (method, path, handler name)
+    type Route = (String, String, String);
+
+    // Collect the handlers declared with actix route macros in the source files under a directory
+    fn source_routes(dir: &Path, routes: &mut BTreeSet<Route>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                source_routes(&path, routes);
+                continue;
+            }
+
+            let source = fs::read_to_string(&path).unwrap();
+            let mut lines = source.lines().map(str::trim);
+            while let Some(line) = lines.next() {
+                let Some((method, route_path)) = METHODS.iter().find_map(|method| {
+                    let route_path = line.strip_prefix(&format!("#[{}(\"", method))?;
+                    Some((method, route_path.strip_suffix("\")]")?))
+                }) else {
+                    continue;
+                };
+
+                let name = lines
+                    .find_map(|line| line.strip_prefix("pub async fn "))
+                    .and_then(|line| line.split('(').next())
+                    .unwrap();
+
+                routes.insert((method.to_string(), route_path.to_string(), name.to_string()));
+            }
+        }
+    }
+
+    fn spec_routes() -> BTreeSet<Route> {
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+
+        let mut routes = BTreeSet::new();
+        for (path, item) in spec["paths"].as_object().unwrap() {
+            for method in METHODS {
+                if let Some(operation_id) = item[*method]["operationId"].as_str() {
+                    routes.insert((method.to_string(), path.clone(), operation_id.to_string()));
+                }
+            }
+        }
+        routes
+    }
+
+    #[test]
+    fn spec_documents_every_route() {
+        let mut routes = BTreeSet::new();
+        source_routes(
+            &Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+            &mut routes,
+        );
+        assert!(!routes.is_empty());
+
+        let spec_routes = spec_routes();
+        let undocumented: Vec<&Route> = routes.difference(&spec_routes).collect();
+        let unknown: Vec<&Route> = spec_routes.difference(&routes).collect();
+
+        assert!(
+            undocumented.is_empty() && unknown.is_empty(),
+            "OpenAPI spec is out of date.\nMissing from the spec (add #[utoipa::path] and list it in ApiDoc): {:?}\nIn the spec but not a route: {:?}",
+            undocumented,
+            unknown
+        );
+    }
+
+    #[test]
+    fn documented_routes_are_registered() {
+        let main =
+            fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("src/lib.rs")).unwrap();
+
+        let unregistered: Vec<String> = spec_routes()
+            .into_iter()
+            .map(|(_, _, name)| name)
+            .filter(|name| {
+                !main.contains(&format!("({})", name)) && !main.contains(&format!("::{})", name))
+            })
+            .collect();
+
+        assert!(
+            unregistered.is_empty(),
+            "Handlers in the OpenAPI spec but not registered in lib.rs: {:?}",
+            unregistered
+        );
+    }
+}
@@ -0,0 +1,548 @@
+pub mod memory;
+pub mod sqlite;
+
+use std::fmt;
+
+use chrono::Utc;
+use shared::trade::{FieldError, TradeFields, MAX_UTC_OFFSET};
+
+use crate::audit::Audit;
+use crate::auth::AuthenticatedUser;
+use crate::models::{DeletedTrade, ItemData, TradeChanges, TradeOperation};
+
+pub use memory::InMemoryTradeRepository;
+pub use shared::models::TradeFilter;
+pub use sqlite::SqliteTradeRepository;
+
+#[derive(Debug)]
+pub enum RepositoryError {
+    // The trade refers to an account that isn't the user's
+    UnknownAccount,
+    // The trade breaks the rules in `shared::trade`, with an error for each invalid field
+    Invalid(Vec<FieldError>),
+    Database(rusqlite::Error),
+}
+
+impl fmt::Display for RepositoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepositoryError::UnknownAccount => write!(f, "Unknown account"),
+            RepositoryError::Invalid(errors) => {
+                let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+                write!(f, "Invalid trade: {}", messages.join(", "))
+            }
+            RepositoryError::Database(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for RepositoryError {
+    fn from(e: rusqlite::Error) -> Self {
+        RepositoryError::Database(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, RepositoryError>;
+
+// What one of the operations given to `TradeRepository::apply` did
+#[derive(Debug)]
+pub enum AppliedOperation {
+    Created(ItemData),
+    Updated { before: ItemData, trade: ItemData },
+    Deleted(ItemData),
+}
+
+impl AppliedOperation {
+    // The trade the operation created, changed or deleted
+    pub fn id(&self) -> i64 {
+        match self {
+            AppliedOperation::Created(trade)
+            | AppliedOperation::Updated { trade, .. }
+            | AppliedOperation::Deleted(trade) => trade.id,
+        }
+    }
+}
+
+// What an operation did, or why it failed
+pub type OperationOutcome = std::result::Result<AppliedOperation, String>;
+
+// Why an update or delete in a batch failed when its trade doesn't exist
+fn trade_not_found(id: i64) -> String {
+    format!("Trade {} not found", id)
+}
+
+// Only database errors fail a whole batch. Other errors are the outcome of the operation.
+fn operation_outcome(result: Result<OperationOutcome>) -> Result<OperationOutcome> {
+    match result {
+        Err(RepositoryError::Database(e)) => Err(RepositoryError::Database(e)),
+        Err(e) => Ok(Err(e.to_string())),
+        Ok(outcome) => Ok(outcome),
+    }
+}
+
+// Tags are stored trimmed, without blanks, sorted and unique
+pub(crate) fn normalize_tags(names: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = names
+        .iter()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+// Check a trade before it's stored. Timestamps have no time zone, so a trade is only in the
+// future once it's later than the current time anywhere.
+pub fn validate(trade: &ItemData) -> Result<()> {
+    validate_fields(&TradeFields {
+        item_name: &trade.item_name,
+        quantity: trade.quantity,
+        price_each: trade.total_price,
+        timestamp: trade.timestamp,
+    })
+}
+
+// Check fields that become a trade later on, like those of an offer
+pub(crate) fn validate_fields(fields: &TradeFields) -> Result<()> {
+    let errors = fields.validate(Utc::now().naive_utc() + MAX_UTC_OFFSET);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(RepositoryError::Invalid(errors))
+    }
+}
+
+// Where trades are stored. Every method is scoped to the given user, so trades of other users
+// behave as if they don't exist. Changes are recorded with the given audit.
+pub trait TradeRepository: Send + Sync {
+    // Add a trade, returning it as stored with its new id
+    fn insert(&self, audit: &Audit, user: &AuthenticatedUser, trade: &ItemData)
+        -> Result<ItemData>;
+
+    // Get a trade outside the trash
+    fn get(&self, user: &AuthenticatedUser, id: i64) -> Result<Option<ItemData>>;
+
+    // List the trades matching a filter, in the order they were added
+    fn list(&self, user: &AuthenticatedUser, filter: &TradeFilter) -> Result<Vec<ItemData>>;
+
+    // Change a trade outside the trash, returning it before and after the change
+    fn update(
+        &self,
+        audit: &Audit,
+        user: &AuthenticatedUser,
+        id: i64,
+        changes: &TradeChanges,
+    ) -> Result<Option<(ItemData, ItemData)>>;
+
+    // Move a trade to the trash, returning it as it was
+    fn delete(&self, audit: &Audit, user: &AuthenticatedUser, id: i64) -> Result<Option<ItemData>>;
+
+    // Sales minus purchases of the trades matching a filter, in gp
+    fn profit_loss(&self, user: &AuthenticatedUser, filter: &TradeFilter) -> Result<i64>;
+
+    // Apply operations all together or not at all, returning the outcome of each. If any of them
+    // fails, none are kept.
+    fn apply(
+        &self,
+        audit: &Audit,
+        user: &AuthenticatedUser,
+        operations: &[TradeOperation],
+    ) -> Result<Vec<OperationOutcome>>;
+
+    // List the trades in the trash, optionally of one account, most recently deleted first
+    fn list_deleted(
+        &self,
+        user: &AuthenticatedUser,
+        account: Option<i64>,
+    ) -> Result<Vec<DeletedTrade>>;
+
+    // Take a trade out of the trash, returning it as restored
+    fn restore(&self, audit: &Audit, user: &AuthenticatedUser, id: i64)
+        -> Result<Option<ItemData>>;
+}
+
+// Both implementations must behave the same, so they run the same tests
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::db::{self, Database};
+    use crate::models::Scope;
+
+    fn user(id: i64) -> AuthenticatedUser {
+        AuthenticatedUser {
+            id,
+            username: format!("user{}", id),
+            scopes: vec![Scope::Read, Scope::Write, Scope::Admin],
+            api_token: None,
+        }
+    }
+
+    fn trade(item_name: &str, quantity: i64, total_price: i64, is_purchase: bool) -> ItemData {
+        ItemData {
+            id: 0,
+            item_name: item_name.to_string(),
+            quantity,
+            total_price,
+            is_purchase,
+            timestamp: NaiveDate::from_ymd_opt(2024, 1, 31)
+                .unwrap()
+                .and_hms_opt(13, 45, 0)
+                .unwrap(),
+            account_id: None,
+            note: None,
+            session_id: None,
+            tags: Vec::new(),
+        }
+    }
+
+    fn item_names(trades: &[ItemData]) -> Vec<&str> {
+        trades
+            .iter()
+            .map(|trade| trade.item_name.as_str())
+            .collect()
+    }
+
+    // Run a test against each implementation
+    fn for_each_repository(test: impl Fn(&dyn TradeRepository)) {
+        let memory = InMemoryTradeRepository::new();
+        memory.add_account(1, 1);
+        test(&memory);
+
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::new(dir.path().join("ardy.db"));
+        db::init_db(&db).unwrap();
+        let conn = db.open().unwrap();
+        for id in [1, 2] {
+            conn.execute(
+                "INSERT INTO users (id, username, password_hash) VALUES (?1, ?2, '')",
+                rusqlite::params![id, format!("user{}", id)],
+            )
+            .unwrap();
+        }
+        conn.execute(
+            "INSERT INTO accounts (id, user_id, name) VALUES (1, 1, 'Main')",
+            [],
+        )
+        .unwrap();
+        test(&SqliteTradeRepository::new(db));
+    }
+
+    #[test]
+    fn insert_returns_stored_trade() {
+        for_each_repository(|trades| {
+            let audit = Audit::system();
+            // Item names are stored trimmed
+            let mut new_trade = trade("Abyssal whip ", 2, 1_500_000, true);
+            new_trade.note = Some("Bought the dip".to_string());
+            new_trade.tags = vec!["whips".to_string(), " whips ".to_string(), "".to_string()];
+
+            let stored = trades.insert(&audit, &user(1), &new_trade).unwrap();
+            assert_ne!(stored.id, 0);
+            assert_eq!(stored.item_name, "Abyssal whip");
+            assert_eq!(stored.timestamp, new_trade.timestamp);
+            assert_eq!(stored.note.as_deref(), Some("Bought the dip"));
+            assert_eq!(stored.tags, vec!["whips"]);
+
+            let got = trades.get(&user(1), stored.id).unwrap().unwrap();
+            assert_eq!(got.id, stored.id);
+            assert_eq!(got.tags, vec!["whips"]);
+
+            assert!(trades.get(&user(2), stored.id).unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn insert_rejects_unknown_accounts() {
+        for_each_repository(|trades| {
+            let mut new_trade = trade("Abyssal whip", 1, 1_500_000, true);
+            new_trade.account_id = Some(1);
+            assert!(trades
+                .insert(&Audit::system(), &user(1), &new_trade)
+                .is_ok());
+
+            // Account 1 belongs to user 1
+            assert!(matches!(
+                trades.insert(&Audit::system(), &user(2), &new_trade),
+                Err(RepositoryError::UnknownAccount)
+            ));
+        });
+    }
+
+    #[test]
+    fn insert_and_update_reject_invalid_trades() {
+        for_each_repository(|trades| {
+            let audit = Audit::system();
+
+            let invalid = trade(" ", -5, 0, true);
+            match trades.insert(&audit, &user(1), &invalid) {
+                Err(RepositoryError::Invalid(errors)) => assert_eq!(errors.len(), 3),
+                other => panic!("invalid trade was stored: {:?}", other),
+            }
+            assert!(trades
+                .list(&user(1), &TradeFilter::default())
+                .unwrap()
+                .is_empty());
+
+            let stored = trades
+                .insert(&audit, &user(1), &trade("Dragon bones", 100, 2_000, true))
+                .unwrap();
+
+            // The change is checked together with the fields it leaves alone
+            let changes = TradeChanges {
+                total_price: Some(100_000_000),
+                ..TradeChanges::default()
+            };
+            assert!(matches!(
+                trades.update(&audit, &user(1), stored.id, &changes),
+                Err(RepositoryError::Invalid(_))
+            ));
+            let unchanged = trades.get(&user(1), stored.id).unwrap().unwrap();
+            assert_eq!(unchanged.total_price, 2_000);
+        });
+    }
+
+    #[test]
+    fn list_filters_trades() {
+        for_each_repository(|trades| {
+            let audit = Audit::system();
+            let mut whip = trade("Abyssal whip", 1, 1_500_000, true);
+            whip.tags = vec!["clan flip".to_string()];
+            let mut bones = trade("Dragon bones", 100, 2_000, true);
+            bones.account_id = Some(1);
+
+            trades.insert(&audit, &user(1), &whip).unwrap();
+            trades
+                .insert(&audit, &user(1), &trade("Abyssal dagger", 1, 900_000, true))
+                .unwrap();
+            trades.insert(&audit, &user(1), &bones).unwrap();
+            trades
+                .insert(&audit, &user(2), &trade("Twisted bow", 1, 1, true))
+                .unwrap();
+
+            let list = |filter: TradeFilter| trades.list(&user(1), &filter).unwrap();
+
+            assert_eq!(
+                item_names(&list(TradeFilter::default())),
+                vec!["Abyssal whip", "Abyssal dagger", "Dragon bones"]
+            );
+            assert_eq!(
+                item_names(&list(TradeFilter {
+                    item_name: Some("abyssal W".to_string()),
+                    ..TradeFilter::default()
+                })),
+                vec!["Abyssal whip"]
+            );
+            assert_eq!(
+                item_names(&list(TradeFilter {
+                    account: Some(1),
+                    ..TradeFilter::default()
+                })),
+                vec!["Dragon bones"]
+            );
+            assert_eq!(
+                item_names(&list(TradeFilter {
+                    tag: Some("clan flip".to_string()),
+                    ..TradeFilter::default()
+                })),
+                vec!["Abyssal whip"]
+            );
+        });
+    }
+
+    #[test]
+    fn update_changes_given_fields() {
+        for_each_repository(|trades| {
+            let audit = Audit::system();
+            let mut new_trade = trade("Abyssal whip", 1, 1_500_000, true);
+            new_trade.note = Some("Bought the dip".to_string());
+            let stored = trades.insert(&audit, &user(1), &new_trade).unwrap();
+
+            let changes = TradeChanges {
+                item_name: Some("Abyssal dagger".to_string()),
+                quantity: Some(3),
+                account_id: Some(Some(1)),
+                note: Some(None),
+                tags: Some(vec!["daggers".to_string()]),
+                ..TradeChanges::default()
+            };
+
+            assert!(trades
+                .update(&audit, &user(2), stored.id, &changes)
+                .unwrap()
+                .is_none());
+
+            let (before, after) = trades
+                .update(&audit, &user(1), stored.id, &changes)
+                .unwrap()
+                .unwrap();
+            assert_eq!(before.item_name, "Abyssal whip");
+            assert_eq!(after.item_name, "Abyssal dagger");
+            assert_eq!(after.quantity, 3);
+            assert_eq!(after.total_price, 1_500_000);
+            assert_eq!(after.account_id, Some(1));
+            assert_eq!(after.note, None);
+            assert_eq!(after.tags, vec!["daggers"]);
+
+            let moved_to_other_users_account = TradeChanges {
+                account_id: Some(Some(1)),
+                ..TradeChanges::default()
+            };
+            let theirs = trades
+                .insert(&audit, &user(2), &trade("Twisted bow", 1, 1, true))
+                .unwrap();
+            assert!(matches!(
+                trades.update(&audit, &user(2), theirs.id, &moved_to_other_users_account),
+                Err(RepositoryError::UnknownAccount)
+            ));
+        });
+    }
+
+    #[test]
+    fn delete_moves_trades_out_of_the_list() {
+        for_each_repository(|trades| {
+            let audit = Audit::system();
+            let whip = trades
+                .insert(&audit, &user(1), &trade("Abyssal whip", 1, 1_500_000, true))
+                .unwrap();
+            trades
+                .insert(&audit, &user(1), &trade("Dragon bones", 100, 2_000, false))
+                .unwrap();
+
+            assert!(trades.delete(&audit, &user(2), whip.id).unwrap().is_none());
+
+            let deleted = trades.delete(&audit, &user(1), whip.id).unwrap().unwrap();
+            assert_eq!(deleted.item_name, "Abyssal whip");
+
+            assert!(trades.delete(&audit, &user(1), whip.id).unwrap().is_none());
+            assert!(trades.get(&user(1), whip.id).unwrap().is_none());
+            assert_eq!(
+                item_names(&trades.list(&user(1), &TradeFilter::default()).unwrap()),
+                vec!["Dragon bones"]
+            );
+            assert!(trades
+                .update(&audit, &user(1), whip.id, &TradeChanges::default())
+                .unwrap()
+                .is_none());
+        });
+    }
+
+    #[test]
+    fn restore_puts_deleted_trades_back() {
+        for_each_repository(|trades| {
+            let audit = Audit::system();
+            let mut whip = trade("Abyssal whip", 1, 1_500_000, true);
+            whip.account_id = Some(1);
+            let whip = trades.insert(&audit, &user(1), &whip).unwrap();
+            let bones = trades
+                .insert(&audit, &user(1), &trade("Dragon bones", 100, 2_000, false))
+                .unwrap();
+            trades.delete(&audit, &user(1), whip.id).unwrap();
+            trades.delete(&audit, &user(1), bones.id).unwrap();
+
+            let deleted = trades.list_deleted(&user(1), None).unwrap();
+            assert_eq!(
+                deleted
+                    .iter()
+                    .map(|deleted| deleted.trade.id)
+                    .collect::<Vec<i64>>(),
+                vec![bones.id, whip.id]
+            );
+            assert_eq!(trades.list_deleted(&user(1), Some(1)).unwrap().len(), 1);
+            assert!(trades.list_deleted(&user(2), None).unwrap().is_empty());
+
+            assert!(trades.restore(&audit, &user(2), whip.id).unwrap().is_none());
+            let restored = trades.restore(&audit, &user(1), whip.id).unwrap().unwrap();
+            assert_eq!(restored.item_name, "Abyssal whip");
+            assert!(trades.restore(&audit, &user(1), whip.id).unwrap().is_none());
+
+            assert_eq!(
+                item_names(&trades.list(&user(1), &TradeFilter::default()).unwrap()),
+                vec!["Abyssal whip"]
+            );
+            assert_eq!(trades.list_deleted(&user(1), None).unwrap().len(), 1);
+        });
+    }
+
+    #[test]
+    fn apply_keeps_all_operations_or_none() {
+        for_each_repository(|trades| {
+            let audit = Audit::system();
+            let whip = trades
+                .insert(&audit, &user(1), &trade("Abyssal whip", 1, 1_500_000, true))
+                .unwrap();
+
+            let operations = [
+                TradeOperation::Create {
+                    trade: trade("Dragon bones", 100, 2_000, true),
+                },
+                TradeOperation::Delete { id: whip.id },
+                TradeOperation::Update {
+                    id: whip.id + 100,
+                    changes: TradeChanges::default(),
+                },
+            ];
+            let outcomes = trades.apply(&audit, &user(1), &operations).unwrap();
+            assert!(outcomes[0].is_ok());
+            assert!(outcomes[1].is_ok());
+            assert_eq!(
+                outcomes[2].as_ref().unwrap_err(),
+                &format!("Trade {} not found", whip.id + 100)
+            );
+            assert_eq!(
+                item_names(&trades.list(&user(1), &TradeFilter::default()).unwrap()),
+                vec!["Abyssal whip"]
+            );
+
+            let outcomes = trades.apply(&audit, &user(1), &operations[..2]).unwrap();
+            match &outcomes[..] {
+                [Ok(AppliedOperation::Created(created)), Ok(AppliedOperation::Deleted(deleted))] => {
+                    assert_eq!(created.item_name, "Dragon bones");
+                    assert_eq!(deleted.id, whip.id);
+                }
+                other => panic!("operations weren't applied: {:?}", other),
+            }
+            assert_eq!(
+                item_names(&trades.list(&user(1), &TradeFilter::default()).unwrap()),
+                vec!["Dragon bones"]
+            );
+        });
+    }
+
+    #[test]
+    fn profit_loss_sums_sales_minus_purchases() {
+        for_each_repository(|trades| {
+            let audit = Audit::system();
+            let total = |filter: TradeFilter| trades.profit_loss(&user(1), &filter).unwrap();
+
+            assert_eq!(total(TradeFilter::default()), 0);
+
+            let mut sale = trade("Dragon bones", 100, 2_300, false);
+            sale.account_id = Some(1);
+            trades
+                .insert(&audit, &user(1), &trade("Dragon bones", 100, 2_000, true))
+                .unwrap();
+            trades.insert(&audit, &user(1), &sale).unwrap();
+            let whip = trades
+                .insert(&audit, &user(1), &trade("Abyssal whip", 1, 1_500_000, true))
+                .unwrap();
+            trades
+                .insert(&audit, &user(2), &trade("Twisted bow", 1, 1, false))
+                .unwrap();
+
+            assert_eq!(total(TradeFilter::default()), 30_000 - 1_500_000);
+            assert_eq!(
+                total(TradeFilter {
+                    account: Some(1),
+                    ..TradeFilter::default()
+                }),
+                230_000
+            );
+
+            trades.delete(&audit, &user(1), whip.id).unwrap();
+            assert_eq!(total(TradeFilter::default()), 30_000);
+        });
+    }
+}
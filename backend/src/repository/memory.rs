@@ -0,0 +1,302 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard};
+
+use chrono::NaiveDateTime;
+
+use crate::audit::Audit;
+use crate::auth::AuthenticatedUser;
+use crate::models::{DeletedTrade, ItemData, TradeChanges, TradeOperation};
+use crate::repository::{
+    normalize_tags, operation_outcome, trade_not_found, validate, AppliedOperation,
+    OperationOutcome, RepositoryError, Result, TradeFilter, TradeRepository,
+};
+
+#[derive(Clone)]
+struct StoredTrade {
+    user_id: i64,
+    // When the trade was moved to the trash, if it was
+    deleted_at: Option<NaiveDateTime>,
+    trade: ItemData,
+}
+
+#[derive(Default)]
+struct Store {
+    // Keyed by id, so iterating lists trades in the order they were added
+    trades: BTreeMap<i64, StoredTrade>,
+    // Owner of each account trades may refer to
+    accounts: HashMap<i64, i64>,
+    next_id: i64,
+}
+
+// Trades kept in memory and lost when dropped, for tests that don't need a database.
+// Nothing is written to the audit log, and there are no trading sessions for trades to join.
+#[derive(Default)]
+pub struct InMemoryTradeRepository {
+    store: Mutex<Store>,
+}
+
+impl InMemoryTradeRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Let the user's trades refer to an account
+    pub fn add_account(&self, id: i64, user_id: i64) {
+        self.store().accounts.insert(id, user_id);
+    }
+
+    fn store(&self) -> MutexGuard<'_, Store> {
+        // A panic while holding the lock can't leave a trade half-written, so carry on
+        self.store.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Store {
+    fn check_account(&self, account_id: Option<i64>, user: &AuthenticatedUser) -> Result<()> {
+        match account_id {
+            Some(account_id) if self.accounts.get(&account_id) != Some(&user.id) => {
+                Err(RepositoryError::UnknownAccount)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn trade_mut(&mut self, user: &AuthenticatedUser, id: i64) -> Option<&mut ItemData> {
+        self.trades
+            .get_mut(&id)
+            .filter(|stored| stored.user_id == user.id && stored.deleted_at.is_none())
+            .map(|stored| &mut stored.trade)
+    }
+
+    fn user_trades<'a>(
+        &'a self,
+        user: &'a AuthenticatedUser,
+        filter: &'a TradeFilter,
+    ) -> impl Iterator<Item = &'a ItemData> {
+        self.trades
+            .values()
+            .filter(move |stored| stored.user_id == user.id && stored.deleted_at.is_none())
+            .map(|stored| &stored.trade)
+            .filter(move |trade| filter.matches(trade))
+    }
+
+    fn insert(&mut self, user: &AuthenticatedUser, trade: &ItemData) -> Result<ItemData> {
+        validate(trade)?;
+        self.check_account(trade.account_id, user)?;
+
+        self.next_id += 1;
+        let trade = ItemData {
+            id: self.next_id,
+            item_name: trade.item_name.trim().to_string(),
+            session_id: None,
+            tags: normalize_tags(&trade.tags),
+            ..trade.clone()
+        };
+
+        self.trades.insert(
+            trade.id,
+            StoredTrade {
+                user_id: user.id,
+                deleted_at: None,
+                trade: trade.clone(),
+            },
+        );
+
+        Ok(trade)
+    }
+
+    fn update(
+        &mut self,
+        user: &AuthenticatedUser,
+        id: i64,
+        changes: &TradeChanges,
+    ) -> Result<Option<(ItemData, ItemData)>> {
+        let Some(before) = self.trade_mut(user, id).map(|trade| trade.clone()) else {
+            return Ok(None);
+        };
+        if let Some(account_id) = changes.account_id {
+            self.check_account(account_id, user)?;
+        }
+
+        // Only stored once the changed trade is known to be valid
+        let mut trade = before.clone();
+
+        if let Some(item_name) = &changes.item_name {
+            trade.item_name = item_name.trim().to_string();
+        }
+        if let Some(quantity) = changes.quantity {
+            trade.quantity = quantity;
+        }
+        if let Some(total_price) = changes.total_price {
+            trade.total_price = total_price;
+        }
+        if let Some(is_purchase) = changes.is_purchase {
+            trade.is_purchase = is_purchase;
+        }
+        if let Some(timestamp) = changes.timestamp {
+            trade.timestamp = timestamp;
+        }
+        if let Some(account_id) = changes.account_id {
+            trade.account_id = account_id;
+        }
+        if let Some(note) = &changes.note {
+            trade.note = note.clone();
+        }
+        if let Some(tags) = &changes.tags {
+            trade.tags = normalize_tags(tags);
+        }
+
+        validate(&trade)?;
+        if let Some(stored) = self.trade_mut(user, id) {
+            *stored = trade.clone();
+        }
+
+        Ok(Some((before, trade)))
+    }
+
+    fn delete(&mut self, user: &AuthenticatedUser, id: i64) -> Option<ItemData> {
+        match self.trades.get_mut(&id) {
+            Some(stored) if stored.user_id == user.id && stored.deleted_at.is_none() => {
+                stored.deleted_at = Some(chrono::Utc::now().naive_utc());
+                Some(stored.trade.clone())
+            }
+            _ => None,
+        }
+    }
+
+    fn apply_operation(
+        &mut self,
+        user: &AuthenticatedUser,
+        operation: &TradeOperation,
+    ) -> Result<OperationOutcome> {
+        Ok(match operation {
+            TradeOperation::Create { trade } => {
+                Ok(AppliedOperation::Created(self.insert(user, trade)?))
+            }
+            TradeOperation::Update { id, changes } => match self.update(user, *id, changes)? {
+                Some((before, trade)) => Ok(AppliedOperation::Updated { before, trade }),
+                None => Err(trade_not_found(*id)),
+            },
+            TradeOperation::Delete { id } => match self.delete(user, *id) {
+                Some(trade) => Ok(AppliedOperation::Deleted(trade)),
+                None => Err(trade_not_found(*id)),
+            },
+        })
+    }
+}
+
+impl TradeRepository for InMemoryTradeRepository {
+    fn insert(
+        &self,
+        _audit: &Audit,
+        user: &AuthenticatedUser,
+        trade: &ItemData,
+    ) -> Result<ItemData> {
+        self.store().insert(user, trade)
+    }
+
+    fn get(&self, user: &AuthenticatedUser, id: i64) -> Result<Option<ItemData>> {
+        Ok(self.store().trade_mut(user, id).map(|trade| trade.clone()))
+    }
+
+    fn list(&self, user: &AuthenticatedUser, filter: &TradeFilter) -> Result<Vec<ItemData>> {
+        Ok(self.store().user_trades(user, filter).cloned().collect())
+    }
+
+    fn update(
+        &self,
+        _audit: &Audit,
+        user: &AuthenticatedUser,
+        id: i64,
+        changes: &TradeChanges,
+    ) -> Result<Option<(ItemData, ItemData)>> {
+        self.store().update(user, id, changes)
+    }
+
+    fn delete(
+        &self,
+        _audit: &Audit,
+        user: &AuthenticatedUser,
+        id: i64,
+    ) -> Result<Option<ItemData>> {
+        Ok(self.store().delete(user, id))
+    }
+
+    fn profit_loss(&self, user: &AuthenticatedUser, filter: &TradeFilter) -> Result<i64> {
+        Ok(self
+            .store()
+            .user_trades(user, filter)
+            .map(|trade| {
+                let price = trade.quantity * trade.total_price;
+                if trade.is_purchase {
+                    -price
+                } else {
+                    price
+                }
+            })
+            .sum())
+    }
+
+    fn apply(
+        &self,
+        _audit: &Audit,
+        user: &AuthenticatedUser,
+        operations: &[TradeOperation],
+    ) -> Result<Vec<OperationOutcome>> {
+        let mut store = self.store();
+        // Put the trades back as they were if any operation fails
+        let (trades, next_id) = (store.trades.clone(), store.next_id);
+
+        let mut outcomes = Vec::with_capacity(operations.len());
+        for operation in operations {
+            outcomes.push(operation_outcome(store.apply_operation(user, operation))?);
+        }
+
+        if outcomes.iter().any(|outcome| outcome.is_err()) {
+            store.trades = trades;
+            store.next_id = next_id;
+        }
+
+        Ok(outcomes)
+    }
+
+    fn list_deleted(
+        &self,
+        user: &AuthenticatedUser,
+        account: Option<i64>,
+    ) -> Result<Vec<DeletedTrade>> {
+        let mut deleted: Vec<DeletedTrade> = self
+            .store()
+            .trades
+            .values()
+            .filter(|stored| stored.user_id == user.id)
+            .filter(|stored| account.is_none() || stored.trade.account_id == account)
+            .filter_map(|stored| {
+                Some(DeletedTrade {
+                    trade: stored.trade.clone(),
+                    deleted_at: stored.deleted_at?,
+                })
+            })
+            .collect();
+        // Most recently deleted first, like the database
+        deleted.sort_by_key(|deleted| std::cmp::Reverse((deleted.deleted_at, deleted.trade.id)));
+        Ok(deleted)
+    }
+
+    fn restore(
+        &self,
+        _audit: &Audit,
+        user: &AuthenticatedUser,
+        id: i64,
+    ) -> Result<Option<ItemData>> {
+        let mut store = self.store();
+
+        match store.trades.get_mut(&id) {
+            Some(stored) if stored.user_id == user.id && stored.deleted_at.is_some() => {
+                stored.deleted_at = None;
+                Ok(Some(stored.trade.clone()))
+            }
+            _ => Ok(None),
+        }
+    }
+}
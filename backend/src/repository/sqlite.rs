@@ -0,0 +1,455 @@
+use rusqlite::{params, Connection};
+
+use crate::audit::{self, Action, Audit};
+use crate::auth::AuthenticatedUser;
+use crate::db::{self, Database};
+use crate::models::{DeletedTrade, ItemData, TradeChanges, TradeOperation};
+use crate::repository::{
+    operation_outcome, trade_not_found, validate, AppliedOperation, OperationOutcome,
+    RepositoryError, Result, TradeFilter, TradeRepository,
+};
+use crate::routes::{tags, trading_sessions};
+
+// Trades stored in the SQLite database. Each call opens its own connection.
+#[derive(Clone, Debug)]
+pub struct SqliteTradeRepository {
+    db: Database,
+}
+
+impl SqliteTradeRepository {
+    pub fn new(db: Database) -> Self {
+        SqliteTradeRepository { db }
+    }
+}
+
+impl TradeRepository for SqliteTradeRepository {
+    fn insert(
+        &self,
+        audit: &Audit,
+        user: &AuthenticatedUser,
+        trade: &ItemData,
+    ) -> Result<ItemData> {
+        let mut conn = self.db.open()?;
+        // Roll back the trade if its tags fail to save
+        let tx = conn.transaction()?;
+        let trade = insert_trade(&tx, audit, Action::Insert, user, trade)?;
+        tx.commit()?;
+        Ok(trade)
+    }
+
+    fn get(&self, user: &AuthenticatedUser, id: i64) -> Result<Option<ItemData>> {
+        let conn = self.db.open()?;
+        get_trade(&conn, user, id)
+    }
+
+    fn list(&self, user: &AuthenticatedUser, filter: &TradeFilter) -> Result<Vec<ItemData>> {
+        let conn = self.db.open()?;
+        query_trades(&conn, user, filter)
+    }
+
+    fn update(
+        &self,
+        audit: &Audit,
+        user: &AuthenticatedUser,
+        id: i64,
+        changes: &TradeChanges,
+    ) -> Result<Option<(ItemData, ItemData)>> {
+        let mut conn = self.db.open()?;
+        // Roll back a change to the trade if its tags fail to save
+        let tx = conn.transaction()?;
+        let updated = update_trade(&tx, audit, user, id, changes)?;
+        tx.commit()?;
+        Ok(updated)
+    }
+
+    fn delete(&self, audit: &Audit, user: &AuthenticatedUser, id: i64) -> Result<Option<ItemData>> {
+        let mut conn = self.db.open()?;
+        let tx = conn.transaction()?;
+        let trade = trash_trade(&tx, audit, user, id)?;
+        tx.commit()?;
+        Ok(trade)
+    }
+
+    fn profit_loss(&self, user: &AuthenticatedUser, filter: &TradeFilter) -> Result<i64> {
+        let conn = self.db.open()?;
+        profit_loss(&conn, user, filter)
+    }
+
+    fn apply(
+        &self,
+        audit: &Audit,
+        user: &AuthenticatedUser,
+        operations: &[TradeOperation],
+    ) -> Result<Vec<OperationOutcome>> {
+        let mut conn = self.db.open()?;
+        let tx = conn.transaction()?;
+
+        let mut outcomes = Vec::with_capacity(operations.len());
+        for operation in operations {
+            outcomes.push(operation_outcome(apply_operation(
+                &tx, audit, user, operation,
+            ))?);
+        }
+
+        // Dropping the transaction rolls back every operation if any of them failed
+        if outcomes.iter().all(|outcome| outcome.is_ok()) {
+            tx.commit()?;
+        }
+
+        Ok(outcomes)
+    }
+
+    fn list_deleted(
+        &self,
+        user: &AuthenticatedUser,
+        account: Option<i64>,
+    ) -> Result<Vec<DeletedTrade>> {
+        let conn = self.db.open()?;
+        list_deleted_trades(&conn, user, account)
+    }
+
+    fn restore(
+        &self,
+        audit: &Audit,
+        user: &AuthenticatedUser,
+        id: i64,
+    ) -> Result<Option<ItemData>> {
+        let mut conn = self.db.open()?;
+        let tx = conn.transaction()?;
+        let trade = restore_trade(&tx, audit, user, id)?;
+        tx.commit()?;
+        Ok(trade)
+    }
+}
+
+// The functions below take a connection so the caller decides which transaction they run in
+
+// Fail with UnknownAccount unless the account is one of the user's. No account is always fine.
+pub(crate) fn check_account(
+    conn: &Connection,
+    account_id: Option<i64>,
+    user: &AuthenticatedUser,
+) -> Result<()> {
+    let Some(account_id) = account_id else {
+        return Ok(());
+    };
+
+    let owned: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM accounts WHERE id = ?1 AND user_id = ?2)",
+        params![account_id, user.id],
+        |row| row.get(0),
+    )?;
+
+    if !owned {
+        return Err(RepositoryError::UnknownAccount);
+    }
+
+    Ok(())
+}
+
+fn is_owned(conn: &Connection, user: &AuthenticatedUser, id: i64) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM trades WHERE id = ?1 AND user_id = ?2 AND deleted_at IS NULL)",
+        params![id, user.id],
+        |row| row.get(0),
+    )
+}
+
+// Insert a trade for the user, recording it in the audit log with the given action.
+// Trades added as they're made join the user's trading session in progress, while imported ones
+// were made some other time. Returns the trade as stored.
+pub(crate) fn insert_trade(
+    conn: &Connection,
+    audit: &Audit,
+    action: Action,
+    user: &AuthenticatedUser,
+    item_data: &ItemData,
+) -> Result<ItemData> {
+    validate(item_data)?;
+    check_account(conn, item_data.account_id, user)?;
+
+    // Insert item_name into items table and get its id
+    let item_id = db::get_or_insert_item_id(conn, audit, &item_data.item_name)?;
+
+    // Convert boolean to i64 (0 or 1)
+    let is_purchase_i64 = if item_data.is_purchase { 1 } else { 0 };
+
+    let naive_datetime = item_data.timestamp.and_utc().timestamp();
+
+    let session_id = match action {
+        Action::Insert => trading_sessions::session_in_progress(conn, user)?,
+        _ => None,
+    };
+
+    // Insert the trade data into trades table
+    conn.execute(
+        "INSERT INTO trades (item_id, quantity, total_price, is_purchase, timestamp, account_id, user_id, note, session_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![item_id, item_data.quantity, item_data.total_price, is_purchase_i64, naive_datetime, item_data.account_id, user.id, item_data.note, session_id],
+    )?;
+
+    let id = conn.last_insert_rowid();
+    audit.record(conn, action, "trades", id, None)?;
+
+    tags::set_trade_tags(conn, audit, user, id, &item_data.tags)?;
+
+    db::query_trade(conn, id)?.ok_or(RepositoryError::Database(
+        rusqlite::Error::QueryReturnedNoRows,
+    ))
+}
+
+// Get one of the user's trades outside the trash
+pub(crate) fn get_trade(
+    conn: &Connection,
+    user: &AuthenticatedUser,
+    id: i64,
+) -> Result<Option<ItemData>> {
+    if !is_owned(conn, user, id)? {
+        return Ok(None);
+    }
+
+    Ok(db::query_trade(conn, id)?)
+}
+
+pub(crate) fn query_trades(
+    conn: &Connection,
+    user: &AuthenticatedUser,
+    filter: &TradeFilter,
+) -> Result<Vec<ItemData>> {
+    let sql_query = format!(
+        "SELECT trades.id, items.name, trades.quantity, trades.total_price, trades.is_purchase, trades.timestamp, trades.account_id, trades.note, trades.session_id, {}
+        FROM trades INNER JOIN items ON trades.item_id = items.id
+        WHERE trades.user_id = ?3 AND trades.deleted_at IS NULL AND (?1 IS NULL OR items.name LIKE '%' || ?1 || '%') AND (?2 IS NULL OR trades.account_id = ?2)
+            AND (?4 IS NULL OR EXISTS (
+                SELECT 1 FROM trade_tags INNER JOIN tags ON trade_tags.tag_id = tags.id
+                WHERE trade_tags.trade_id = trades.id AND tags.name = ?4
+            ))
+        ORDER BY trades.id",
+        db::TAGS_COLUMN
+    );
+
+    let mut stmt = conn.prepare(&sql_query)?;
+
+    let rows = stmt.query_map(
+        params![filter.item_name, filter.account, user.id, filter.tag],
+        |row| {
+            Ok(ItemData {
+                id: row.get(0)?,
+                item_name: row.get(1)?,
+                quantity: row.get(2)?,
+                total_price: row.get(3)?,
+                is_purchase: row.get(4)?,
+                timestamp: db::timestamp_from_row(row, 5)?,
+                account_id: row.get(6)?,
+                note: row.get(7)?,
+                session_id: row.get(8)?,
+                tags: db::tags_from_row(row, 9)?,
+            })
+        },
+    )?;
+
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+// Change one of the user's trades outside the trash. Returns the trade before and after the
+// change, or None if the user has no such trade.
+pub(crate) fn update_trade(
+    conn: &Connection,
+    audit: &Audit,
+    user: &AuthenticatedUser,
+    id: i64,
+    changes: &TradeChanges,
+) -> Result<Option<(ItemData, ItemData)>> {
+    let Some(before_trade) = get_trade(conn, user, id)? else {
+        return Ok(None);
+    };
+
+    validate(&ItemData {
+        item_name: changes
+            .item_name
+            .clone()
+            .unwrap_or_else(|| before_trade.item_name.clone()),
+        quantity: changes.quantity.unwrap_or(before_trade.quantity),
+        total_price: changes.total_price.unwrap_or(before_trade.total_price),
+        timestamp: changes.timestamp.unwrap_or(before_trade.timestamp),
+        ..before_trade.clone()
+    })?;
+
+    if let Some(account_id) = changes.account_id {
+        check_account(conn, account_id, user)?;
+    }
+
+    let item_id = match &changes.item_name {
+        Some(item_name) => Some(db::get_or_insert_item_id(conn, audit, item_name)?),
+        None => None,
+    };
+
+    let before = audit::snapshot(conn, "trades", id)?;
+
+    conn.execute(
+        "UPDATE trades SET
+            item_id = COALESCE(?1, item_id),
+            quantity = COALESCE(?2, quantity),
+            total_price = COALESCE(?3, total_price),
+            is_purchase = COALESCE(?4, is_purchase),
+            timestamp = COALESCE(?5, timestamp),
+            account_id = CASE WHEN ?6 THEN ?7 ELSE account_id END,
+            note = CASE WHEN ?8 THEN ?9 ELSE note END
+        WHERE id = ?10",
+        params![
+            item_id,
+            changes.quantity,
+            changes.total_price,
+            changes.is_purchase,
+            changes
+                .timestamp
+                .map(|timestamp| timestamp.and_utc().timestamp().to_string()),
+            changes.account_id.is_some(),
+            changes.account_id.flatten(),
+            changes.note.is_some(),
+            changes.note.clone().flatten(),
+            id,
+        ],
+    )?;
+
+    audit.record(conn, Action::Update, "trades", id, before)?;
+
+    if let Some(names) = &changes.tags {
+        tags::set_trade_tags(conn, audit, user, id, names)?;
+    }
+
+    Ok(db::query_trade(conn, id)?.map(|trade| (before_trade, trade)))
+}
+
+// Move one of the user's trades to the trash. It keeps its links to flips and offer fills so a
+// restore puts it back. Returns the trade as it was, or None if the user has no such trade
+// outside the trash.
+pub(crate) fn trash_trade(
+    conn: &Connection,
+    audit: &Audit,
+    user: &AuthenticatedUser,
+    id: i64,
+) -> Result<Option<ItemData>> {
+    let Some(trade) = get_trade(conn, user, id)? else {
+        return Ok(None);
+    };
+
+    let before = audit::snapshot(conn, "trades", id)?;
+
+    conn.execute(
+        "UPDATE trades SET deleted_at = ?1 WHERE id = ?2",
+        params![chrono::Utc::now().timestamp(), id],
+    )?;
+
+    audit.record(conn, Action::Update, "trades", id, before)?;
+
+    Ok(Some(trade))
+}
+
+pub(crate) fn profit_loss(
+    conn: &Connection,
+    user: &AuthenticatedUser,
+    filter: &TradeFilter,
+) -> Result<i64> {
+    // Sum the price of each trade (quantity * total_price)
+    // If is_purchase is 0, multiply by -1 to get the correct sign
+    let profit_loss = conn.query_row(
+        "SELECT COALESCE(SUM(trades.quantity * trades.total_price * (CASE WHEN trades.is_purchase = 0 THEN 1 ELSE -1 END)), 0)
+        FROM trades INNER JOIN items ON trades.item_id = items.id
+        WHERE trades.user_id = ?2 AND trades.deleted_at IS NULL AND (?1 IS NULL OR trades.account_id = ?1)
+            AND (?4 IS NULL OR items.name LIKE '%' || ?4 || '%')
+            AND (?3 IS NULL OR EXISTS (
+                SELECT 1 FROM trade_tags INNER JOIN tags ON trade_tags.tag_id = tags.id
+                WHERE trade_tags.trade_id = trades.id AND tags.name = ?3
+            ))",
+        params![filter.account, user.id, filter.tag, filter.item_name],
+        |row| row.get(0),
+    )?;
+
+    Ok(profit_loss)
+}
+
+fn apply_operation(
+    conn: &Connection,
+    audit: &Audit,
+    user: &AuthenticatedUser,
+    operation: &TradeOperation,
+) -> Result<OperationOutcome> {
+    Ok(match operation {
+        TradeOperation::Create { trade } => Ok(AppliedOperation::Created(insert_trade(
+            conn,
+            audit,
+            Action::Import,
+            user,
+            trade,
+        )?)),
+        TradeOperation::Update { id, changes } => {
+            match update_trade(conn, audit, user, *id, changes)? {
+                Some((before, trade)) => Ok(AppliedOperation::Updated { before, trade }),
+                None => Err(trade_not_found(*id)),
+            }
+        }
+        TradeOperation::Delete { id } => match trash_trade(conn, audit, user, *id)? {
+            Some(trade) => Ok(AppliedOperation::Deleted(trade)),
+            None => Err(trade_not_found(*id)),
+        },
+    })
+}
+
+pub(crate) fn list_deleted_trades(
+    conn: &Connection,
+    user: &AuthenticatedUser,
+    account: Option<i64>,
+) -> Result<Vec<DeletedTrade>> {
+    let sql_query = format!(
+        "SELECT trades.id, items.name, trades.quantity, trades.total_price, trades.is_purchase, trades.timestamp, trades.account_id, trades.note, trades.session_id, {}, trades.deleted_at
+        FROM trades INNER JOIN items ON trades.item_id = items.id
+        WHERE trades.user_id = ?1 AND trades.deleted_at IS NOT NULL AND (?2 IS NULL OR trades.account_id = ?2)
+        ORDER BY trades.deleted_at DESC, trades.id DESC",
+        db::TAGS_COLUMN
+    );
+
+    let mut stmt = conn.prepare(&sql_query)?;
+
+    let rows = stmt.query_map(params![user.id, account], |row| {
+        Ok(DeletedTrade {
+            trade: ItemData {
+                id: row.get(0)?,
+                item_name: row.get(1)?,
+                quantity: row.get(2)?,
+                total_price: row.get(3)?,
+                is_purchase: row.get(4)?,
+                timestamp: db::timestamp_from_row(row, 5)?,
+                account_id: row.get(6)?,
+                note: row.get(7)?,
+                session_id: row.get(8)?,
+                tags: db::tags_from_row(row, 9)?,
+            },
+            deleted_at: db::datetime_from_unix(row.get(10)?).unwrap_or_default(),
+        })
+    })?;
+
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+// Take one of the user's trades out of the trash. Returns the restored trade, or None if the user
+// has no such trade in the trash.
+pub(crate) fn restore_trade(
+    conn: &Connection,
+    audit: &Audit,
+    user: &AuthenticatedUser,
+    id: i64,
+) -> Result<Option<ItemData>> {
+    let before = audit::snapshot(conn, "trades", id)?;
+
+    let restored = conn.execute(
+        "UPDATE trades SET deleted_at = NULL WHERE id = ?1 AND user_id = ?2 AND deleted_at IS NOT NULL",
+        params![id, user.id],
+    )?;
+    if restored == 0 {
+        return Ok(None);
+    }
+
+    audit.record(conn, Action::Update, "trades", id, before)?;
+
+    Ok(db::query_trade(conn, id)?)
+}
@@ -0,0 +1,99 @@
+use std::future::{ready, Ready};
+use std::time::Instant;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest};
+use tracing::Instrument;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+// Longest request id accepted from a client, which fits a UUID with room to spare
+const MAX_LENGTH: usize = 64;
+
+// Unique id of a request, inserted into the request extensions by `assign_request_id`
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    pub fn new() -> Self {
+        RequestId(uuid::Uuid::new_v4().to_string())
+    }
+
+    // Use the id a client or proxy sent, so its logs can be matched with ours. Ids that could
+    // mangle the logs or the response header are replaced.
+    fn from_header(value: &HeaderValue) -> Option<Self> {
+        let id = value.to_str().ok()?;
+        let valid = !id.is_empty()
+            && id.len() <= MAX_LENGTH
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        valid.then(|| RequestId(id.to_string()))
+    }
+}
+
+impl Default for RequestId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FromRequest for RequestId {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Ok(req
+            .extensions()
+            .get::<RequestId>()
+            .cloned()
+            .unwrap_or_else(RequestId::new)))
+    }
+}
+
+// Middleware giving each request an id, taken from its X-Request-Id header or generated.
+// Everything logged while handling the request is in a span carrying the id, and the response
+// echoes it in an X-Request-Id header.
+pub async fn assign_request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(RequestId::from_header)
+        .unwrap_or_default();
+    req.extensions_mut().insert(request_id.clone());
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id.0,
+        method = %req.method(),
+        path = %req.path(),
+    );
+    let started = Instant::now();
+
+    let result = next.call(req).instrument(span.clone()).await;
+
+    let _entered = span.enter();
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    // Handlers turn their errors into responses, so only a misconfigured middleware ends up here
+    let mut res =
+        result.inspect_err(|e| tracing::error!(error = %e, elapsed_ms, "Request failed"))?;
+
+    let status = res.status().as_u16();
+    if res.status().is_server_error() {
+        tracing::error!(status, elapsed_ms, "Request failed");
+    } else {
+        tracing::info!(status, elapsed_ms, "Request handled");
+    }
+
+    if let Ok(value) = HeaderValue::from_str(&request_id.0) {
+        res.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    Ok(res)
+}
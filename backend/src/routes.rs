@@ -1,232 +1,210 @@
+pub mod accounts;
+pub mod api_docs;
+pub mod audit_log;
+pub mod auth;
+pub mod backups;
+pub mod bulk;
+pub mod dashboard;
+pub mod event_stream;
+pub mod flips;
+pub mod goals;
+pub mod health;
+pub mod holdings;
+pub mod offers;
+pub mod tags;
+pub mod tokens;
+pub mod trading_sessions;
+pub mod trash;
+
+use actix_web::error::{ErrorBadRequest, ErrorInternalServerError, ErrorNotFound, InternalError};
 use actix_web::{delete, get, post, web, Error, HttpResponse, Responder};
-use rusqlite::{Connection, Result};
-use uuid::Uuid;
-
-use crate::models::{Hello, ItemData};
+use chrono::{Local, NaiveDateTime};
+use shared::trade::parse_timestamp;
+use tracing::{debug, error, info};
+use utoipa::IntoParams;
+
+use crate::audit::Audit;
+use crate::auth::AuthenticatedUser;
+use crate::events::{Events, TradeEvent};
+use crate::models::{InvalidTrade, ItemData};
+use crate::repository::{RepositoryError, TradeFilter, TradeRepository};
+
+// Log a database error and turn it into a 500 response carrying the given context
+pub(crate) fn internal_error(context: &'static str) -> impl Fn(rusqlite::Error) -> Error {
+    move |e| {
+        error!(error = %e, "{}", context);
+        ErrorInternalServerError(context)
+    }
+}
 
-// Handle GET request
-#[get("/api/v1/hello")]
-pub async fn hello_get() -> impl Responder {
-    HttpResponse::Ok().body("Hello, World!")
+// Like `internal_error`, but a trade referring to someone else's account or breaking a validation
+// rule is the client's fault. Invalid trades get a JSON body listing the invalid fields.
+pub(crate) fn repository_error(context: &'static str) -> impl Fn(RepositoryError) -> Error {
+    move |e| match e {
+        RepositoryError::UnknownAccount => ErrorBadRequest("Unknown account"),
+        RepositoryError::Invalid(errors) => {
+            let response = HttpResponse::BadRequest().json(InvalidTrade { errors });
+            InternalError::from_response("Invalid trade", response).into()
+        }
+        RepositoryError::Database(e) => internal_error(context)(e),
+    }
 }
 
-// Handle POST request
-#[post("/api/v1/hello")]
-pub async fn hello_post(web::Json(hello_data): web::Json<Hello>) -> impl Responder {
-    if hello_data.hello == "actix" {
-        println!("Hello, actix!");
-        HttpResponse::Ok().body("Hello, actix!")
-    } else {
-        println!("Invalid JSON body");
-        HttpResponse::BadRequest().body("Invalid JSON body")
+// The time it is where the trades are made, given by the client since trades are written in
+// local time. Defaults to the backend's local time.
+pub(crate) fn local_now(now: Option<&str>) -> Result<NaiveDateTime, Error> {
+    match now {
+        Some(now) => parse_timestamp(now).map_err(ErrorBadRequest),
+        None => Ok(Local::now().naive_local()),
     }
 }
 
 // Handle Post request for adding trade data to database
+#[utoipa::path(
+    tag = "trades",
+    summary = "Add a trade",
+    request_body = ItemData,
+    responses(
+        (status = 200, description = "Trade saved", body = String, content_type = "text/plain"),
+        (status = 400, description = "Invalid trade, or a plain text \"Unknown account\"", body = InvalidTrade),
+    ),
+)]
 #[post("/api/v1/trade")]
 pub async fn trade_post(
+    user: AuthenticatedUser,
+    trades: web::Data<dyn TradeRepository>,
+    audit: Audit,
+    events: web::Data<Events>,
     web::Json(item_data): web::Json<ItemData>,
 ) -> Result<impl Responder, Error> {
-    println!("POST request received");
-
-    let conn = Connection::open("db/ardy.db").map_err(|e| {
-        println!("Failed to open database: {}", e);
-        HttpResponse::InternalServerError().body("Failed to open database")
-    });
-
-    // Insert item_name into items table and get its id
-    conn.as_ref()
-        .unwrap()
-        .execute(
-            "INSERT OR IGNORE INTO items (name) VALUES (?1)",
-            &[&item_data.item_name],
-        )
-        .map_err(|e| {
-            println!("Failed to insert item name into items table: {}", e);
-            HttpResponse::InternalServerError().body("Failed to insert item name into items table")
-        })
-        .unwrap();
-
-    let item_id: i64 = conn
-        .as_ref()
-        .unwrap()
-        .query_row(
-            "SELECT id FROM items WHERE name = ?1",
-            &[&item_data.item_name],
-            |row| row.get(0),
-        )
-        .map_err(|e| {
-            println!("Failed to get item id from items table: {}", e);
-            HttpResponse::InternalServerError().body("Failed to get item id from items table")
-        })
-        .unwrap();
-
-    // Convert boolean to i64 (0 or 1)
-    let is_purchase_i64 = if item_data.is_purchase { 1 } else { 0 };
-
-    let naive_datetime = &item_data.timestamp.timestamp();
-
-    println!("item_data: {:?}", item_data);
-
-    // Insert the trade data into trades table
-    conn.unwrap().execute(
-        "INSERT INTO trades (item_id, quantity, total_price, is_purchase, timestamp) VALUES (?1, ?2, ?3, ?4, ?5)",
-        &[&item_id, &item_data.quantity, &item_data.total_price, &is_purchase_i64, &naive_datetime],
-    ).map_err(|e| {
-        println!("Failed to insert trade data into trades table: {}", e);
-        HttpResponse::InternalServerError().body("Failed to insert trade data into trades table")
-    }).unwrap();
+    debug!(?item_data, "Adding trade");
+
+    let trade = trades
+        .insert(&audit, &user, &item_data)
+        .map_err(repository_error(
+            "Failed to insert trade data into trades table",
+        ))?;
+
+    events.send(user.id, TradeEvent::TradeCreated { trade });
 
     Ok(HttpResponse::Ok().body("Trade data successfully saved"))
 }
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
 pub struct QueryParams {
     item_name: Option<String>,
+    account: Option<i64>,
+    tag: Option<String>,
 }
 
 // Handle GET request for getting trade data from database
-// Takes optional query parameters: item_name
+// Takes optional query parameters: item_name, account, tag
+#[utoipa::path(
+    tag = "trades",
+    summary = "List trades",
+    params(QueryParams),
+    responses(
+        (status = 200, description = "Trades outside the trash", body = Vec<ItemData>),
+    ),
+)]
 #[get("/api/v1/trade")]
-pub async fn trade_get(query_params: web::Query<QueryParams>) -> Result<impl Responder, Error> {
-    let unique_request_id = uuid::Uuid::new_v4();
-
-    println!(
-        "GET request received... unique request id: {:?}",
-        unique_request_id
-    );
-
-    let conn = Connection::open("db/ardy.db").map_err(|e| {
-        println!("Failed to open database: {}", e);
-        HttpResponse::InternalServerError().body("Failed to open database")
-    });
-
-    let sql_query = if let Some(item_name) = &query_params.item_name {
-        format!("SELECT trades.id, items.name, trades.quantity, trades.total_price, trades.is_purchase, trades.timestamp FROM trades INNER JOIN items ON trades.item_id = items.id WHERE items.name LIKE '%{}%'", item_name)
-    } else {
-        "SELECT trades.id, items.name, trades.quantity, trades.total_price, trades.is_purchase, trades.timestamp FROM trades INNER JOIN items ON trades.item_id = items.id".to_string()
+pub async fn trade_get(
+    user: AuthenticatedUser,
+    trades: web::Data<dyn TradeRepository>,
+    web::Query(query_params): web::Query<QueryParams>,
+) -> Result<impl Responder, Error> {
+    debug!(?query_params, "Listing trades");
+
+    let filter = TradeFilter {
+        item_name: query_params.item_name,
+        account: query_params.account,
+        tag: query_params.tag,
     };
 
-    let mut stmt = conn
-        .as_ref()
-        .unwrap()
-        .prepare(&sql_query)
-        .map_err(|e| {
-            println!("Failed to prepare statement: {}", e);
-            HttpResponse::InternalServerError().body("Failed to prepare statement")
-        })
-        .unwrap();
-
-    let rows = stmt
-        .query_map([], |row| {
-            Ok(ItemData {
-                id: row.get(0)?,
-                item_name: row.get(1)?,
-                quantity: row.get(2)?,
-                total_price: row.get(3)?,
-                is_purchase: row.get(4)?,
-                timestamp: {
-                    let timestamp: String = row.get(5)?;
-                    let timestamp: i64 = timestamp.parse().unwrap();
-                    let naive_datetime =
-                        chrono::NaiveDateTime::from_timestamp_opt(timestamp, 0).unwrap();
-                    naive_datetime
-                },
-            })
-        })
-        .map_err(|e| {
-            println!("Failed to query map: {}", e);
-            HttpResponse::InternalServerError().body("Failed to query map")
-        })
-        .unwrap();
-
-    let mut item_data_vec = Vec::new();
-
-    for row in rows {
-        item_data_vec.push(row.unwrap());
-    }
+    let item_data_vec = trades
+        .list(&user, &filter)
+        .map_err(repository_error("Failed to get trades from trades table"))?;
 
-    println!(
-        "successfully fetched trade data for unique request id: {:?}",
-        unique_request_id
-    );
+    debug!(count = item_data_vec.len(), "Listed trades");
 
     Ok(HttpResponse::Ok().json(item_data_vec))
 }
 
-// Handle DELETE request for deleting trade data from database
-#[derive(serde::Deserialize)]
+// Handle DELETE request for moving a trade to the trash. It can be restored until it is purged.
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct DeleteQueryParams {
     id: i64,
 }
 
+#[utoipa::path(
+    tag = "trades",
+    summary = "Move a trade to the trash",
+    params(DeleteQueryParams),
+    responses(
+        (status = 200, description = "Trade moved to the trash", body = String, content_type = "text/plain"),
+        (status = 404, description = "Trade not found"),
+    ),
+)]
 #[delete("/api/v1/trade")]
 pub async fn trade_delete(
+    user: AuthenticatedUser,
+    trades: web::Data<dyn TradeRepository>,
+    audit: Audit,
+    events: web::Data<Events>,
     query_params: web::Query<DeleteQueryParams>,
 ) -> Result<impl Responder, Error> {
-    println!("DELETE request received for id: {:?}", query_params.id);
+    debug!(trade_id = query_params.id, "Moving trade to trash");
 
-    let conn = Connection::open("db/ardy.db").map_err(|e| {
-        println!("Failed to open database: {}", e);
-        HttpResponse::InternalServerError().body("Failed to open database")
-    });
+    let trade = trades
+        .delete(&audit, &user, query_params.id)
+        .map_err(repository_error("Failed to move trade to trash"))?
+        .ok_or_else(|| ErrorNotFound("Trade not found"))?;
 
-    conn.unwrap()
-        .execute("DELETE FROM trades WHERE id = ?1", &[&query_params.id])
-        .map_err(|e| {
-            println!("Failed to delete trade data from trades table: {}", e);
-            HttpResponse::InternalServerError()
-                .body("Failed to delete trade data from trades table")
-        })
-        .unwrap();
+    events.send(user.id, TradeEvent::TradeDeleted { trade });
 
-    println!("Trade data successfully deleted");
+    info!(trade_id = query_params.id, "Trade moved to trash");
 
-    Ok(HttpResponse::Ok().body("Trade data successfully deleted"))
+    Ok(HttpResponse::Ok().body("Trade successfully moved to trash"))
 }
 
-struct ProfitLossData {
-    profit_loss: i64,
+#[derive(serde::Deserialize, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct ProfitLossQueryParams {
+    account: Option<i64>,
+    tag: Option<String>,
 }
 
 // Handle GET request for profit/loss calculation
+// Takes optional query parameters: account, tag
+#[utoipa::path(
+    tag = "trades",
+    summary = "Get the total profit/loss",
+    params(ProfitLossQueryParams),
+    responses(
+        (status = 200, description = "Sales minus purchases in gp", body = i64),
+    ),
+)]
 #[get("/api/v1/profit_loss")]
-pub async fn profit_loss_get() -> Result<impl Responder, Error> {
-    println!("GET request received for profit/loss calculation");
-    // Open database conn
-    let conn = Connection::open("db/ardy.db").map_err(|e| {
-        println!("Failed to open database: {}", e);
-        HttpResponse::InternalServerError().body("Failed to open database")
-    });
-
-    // Get a list of all prices for each trade (quantity * total_price)
-    // If is_purchase is 0, multiply by -1 to get the correct sign
-    let mut stmt = conn.as_ref().unwrap().prepare(
-        "SELECT trades.quantity * trades.total_price * (CASE WHEN trades.is_purchase = 0 THEN 1 ELSE -1 END) FROM trades",
-    ).map_err(|e| {
-        println!("Failed to prepare statement: {}", e);
-        HttpResponse::InternalServerError().body("Failed to prepare statement")
-    }).unwrap();
-
-    let rows = stmt
-        .query_map([], |row| {
-            Ok(ProfitLossData {
-                profit_loss: row.get(0)?,
-            })
-        })
-        .map_err(|e| {
-            println!("Failed to query map: {}", e);
-            HttpResponse::InternalServerError().body("Failed to query map")
-        })
-        .unwrap();
-
-    let mut profit_loss_vec = Vec::new();
-
-    for row in rows {
-        profit_loss_vec.push(row.unwrap().profit_loss);
-    }
+pub async fn profit_loss_get(
+    user: AuthenticatedUser,
+    trades: web::Data<dyn TradeRepository>,
+    web::Query(query_params): web::Query<ProfitLossQueryParams>,
+) -> Result<impl Responder, Error> {
+    debug!(?query_params, "Calculating profit/loss");
+
+    let filter = TradeFilter {
+        account: query_params.account,
+        tag: query_params.tag,
+        ..TradeFilter::default()
+    };
+
+    let profit_loss = trades
+        .profit_loss(&user, &filter)
+        .map_err(repository_error("Failed to calculate profit/loss"))?;
+
+    debug!(profit_loss, "Calculated profit/loss");
 
-    println!("profit_loss: {:?}", profit_loss_vec.iter().sum::<i64>());
-    // Return Sum of all prices
-    Ok(HttpResponse::Ok().json(profit_loss_vec.iter().sum::<i64>()))
+    Ok(HttpResponse::Ok().json(profit_loss))
 }
@@ -0,0 +1,235 @@
+use actix_web::error::{ErrorBadRequest, ErrorConflict, ErrorNotFound};
+use actix_web::{delete, get, post, put, web, Error, HttpResponse, Responder};
+use rusqlite::{params, Connection, ErrorCode};
+use tracing::debug;
+
+use crate::audit::{self, Action, Audit};
+use crate::auth::AuthenticatedUser;
+use crate::db::Database;
+use crate::models::{Account, NewAccount};
+use crate::repository::sqlite;
+use crate::routes::trash::TRASH_RETENTION_DAYS;
+use crate::routes::{internal_error, repository_error};
+
+// Map a failed insert or update of an account name, reporting duplicate names as a conflict
+fn account_name_error(e: rusqlite::Error) -> Error {
+    match e {
+        rusqlite::Error::SqliteFailure(ref err, _)
+            if err.code == ErrorCode::ConstraintViolation =>
+        {
+            ErrorConflict("An account with that name already exists")
+        }
+        e => internal_error("Failed to save account")(e),
+    }
+}
+
+fn validate_account_name(name: &str) -> Result<String, Error> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ErrorBadRequest("Account name must not be empty"));
+    }
+    Ok(name.to_string())
+}
+
+// Reject an account id that doesn't belong to the user, so data can't be filed under someone else's account
+pub(crate) fn check_account_owner(
+    conn: &Connection,
+    account_id: Option<i64>,
+    user: &AuthenticatedUser,
+) -> Result<(), Error> {
+    sqlite::check_account(conn, account_id, user).map_err(repository_error(
+        "Failed to get account from accounts table",
+    ))
+}
+
+// Handle GET request for listing accounts
+#[utoipa::path(
+    tag = "accounts",
+    summary = "List accounts",
+    responses(
+        (status = 200, description = "The user's accounts", body = Vec<Account>),
+    ),
+)]
+#[get("/api/v1/account")]
+pub async fn account_get(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+) -> Result<impl Responder, Error> {
+    debug!("Listing accounts");
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let mut stmt = conn
+        .prepare("SELECT id, name FROM accounts WHERE user_id = ?1 ORDER BY id")
+        .map_err(internal_error("Failed to prepare statement"))?;
+
+    let accounts = stmt
+        .query_map([user.id], |row| {
+            Ok(Account {
+                id: row.get(0)?,
+                name: row.get(1)?,
+            })
+        })
+        .map_err(internal_error("Failed to query map"))?
+        .collect::<rusqlite::Result<Vec<Account>>>()
+        .map_err(internal_error("Failed to read account row"))?;
+
+    Ok(HttpResponse::Ok().json(accounts))
+}
+
+// Handle POST request for adding an account
+#[utoipa::path(
+    tag = "accounts",
+    summary = "Add an account",
+    request_body = NewAccount,
+    responses(
+        (status = 200, description = "The new account", body = Account),
+        (status = 400, description = "Empty name"),
+        (status = 409, description = "Name already taken"),
+    ),
+)]
+#[post("/api/v1/account")]
+pub async fn account_post(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    audit: Audit,
+    web::Json(new_account): web::Json<NewAccount>,
+) -> Result<impl Responder, Error> {
+    debug!(?new_account, "Adding account");
+
+    let name = validate_account_name(&new_account.name)?;
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    conn.execute(
+        "INSERT INTO accounts (name, user_id) VALUES (?1, ?2)",
+        params![name, user.id],
+    )
+    .map_err(account_name_error)?;
+    let id = conn.last_insert_rowid();
+
+    audit
+        .record(&conn, Action::Insert, "accounts", id, None)
+        .map_err(internal_error("Failed to write audit log"))?;
+
+    Ok(HttpResponse::Ok().json(Account { id, name }))
+}
+
+// Handle PUT request for renaming an account
+#[utoipa::path(
+    tag = "accounts",
+    summary = "Rename an account",
+    request_body = NewAccount,
+    responses(
+        (status = 200, description = "The renamed account", body = Account),
+        (status = 400, description = "Empty name"),
+        (status = 404, description = "Account not found"),
+        (status = 409, description = "Name already taken"),
+    ),
+)]
+#[put("/api/v1/account/{id}")]
+pub async fn account_put(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    audit: Audit,
+    path: web::Path<i64>,
+    web::Json(new_account): web::Json<NewAccount>,
+) -> Result<impl Responder, Error> {
+    let id = path.into_inner();
+    debug!(account_id = id, ?new_account, "Renaming account");
+
+    let name = validate_account_name(&new_account.name)?;
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let before = audit::snapshot(&conn, "accounts", id)
+        .map_err(internal_error("Failed to get account from accounts table"))?;
+
+    let updated = conn
+        .execute(
+            "UPDATE accounts SET name = ?1 WHERE id = ?2 AND user_id = ?3",
+            params![name, id, user.id],
+        )
+        .map_err(account_name_error)?;
+
+    if updated == 0 {
+        return Err(ErrorNotFound("Account not found"));
+    }
+
+    audit
+        .record(&conn, Action::Update, "accounts", id, before)
+        .map_err(internal_error("Failed to write audit log"))?;
+
+    Ok(HttpResponse::Ok().json(Account { id, name }))
+}
+
+// Handle DELETE request for removing an account
+// Accounts that still have trades, offers or goals can't be deleted, including trades in the trash
+#[utoipa::path(
+    tag = "accounts",
+    summary = "Delete an account",
+    responses(
+        (status = 200, description = "Account deleted", body = String, content_type = "text/plain"),
+        (status = 404, description = "Account not found"),
+        (status = 409, description = "Account still has trades, offers or goals, or trades in the trash"),
+    ),
+)]
+#[delete("/api/v1/account/{id}")]
+pub async fn account_delete(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    audit: Audit,
+    path: web::Path<i64>,
+) -> Result<impl Responder, Error> {
+    let id = path.into_inner();
+    debug!(account_id = id, "Deleting account");
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let (in_use, in_trash): (bool, bool) = conn
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM trades WHERE account_id = ?1 AND user_id = ?2 AND deleted_at IS NULL)
+                OR EXISTS (SELECT 1 FROM offers WHERE account_id = ?1 AND user_id = ?2)
+                OR EXISTS (SELECT 1 FROM goals WHERE account_id = ?1 AND user_id = ?2),
+                EXISTS (SELECT 1 FROM trades WHERE account_id = ?1 AND user_id = ?2 AND deleted_at IS NOT NULL)",
+            params![id, user.id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(internal_error("Failed to check account usage"))?;
+
+    if in_use {
+        return Err(ErrorConflict("Account still has trades, offers or goals"));
+    }
+    // Trades in the trash keep their account so they can be restored
+    if in_trash {
+        return Err(ErrorConflict(format!(
+            "Account still has trades in the trash, which are purged {} days after they were deleted",
+            TRASH_RETENTION_DAYS
+        )));
+    }
+
+    let deleted = audit
+        .delete_rows(
+            &conn,
+            "accounts",
+            "id = ?1 AND user_id = ?2",
+            &[&id, &user.id],
+        )
+        .map_err(internal_error(
+            "Failed to delete account from accounts table",
+        ))?;
+
+    if deleted == 0 {
+        return Err(ErrorNotFound("Account not found"));
+    }
+
+    Ok(HttpResponse::Ok().body("Account successfully deleted"))
+}
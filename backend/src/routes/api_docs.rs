@@ -0,0 +1,37 @@
+use actix_web::{get, HttpResponse, Responder};
+use utoipa::OpenApi;
+use utoipa_rapidoc::RapiDoc;
+
+use crate::openapi::ApiDoc;
+
+const OPENAPI_PATH: &str = "/api/v1/openapi.json";
+
+// Handle GET request for the OpenAPI document describing this API
+#[utoipa::path(
+    tag = "docs",
+    summary = "Get the OpenAPI document",
+    responses(
+        (status = 200, description = "OpenAPI 3 document", content_type = "application/json"),
+    ),
+    security(()),
+)]
+#[get("/api/v1/openapi.json")]
+pub async fn openapi_get() -> impl Responder {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+// Handle GET request for the interactive API docs, rendered from the OpenAPI document
+#[utoipa::path(
+    tag = "docs",
+    summary = "Browse the API docs",
+    responses(
+        (status = 200, description = "Docs page", body = String, content_type = "text/html"),
+    ),
+    security(()),
+)]
+#[get("/api/v1/docs")]
+pub async fn docs_get() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(RapiDoc::new(OPENAPI_PATH).to_html())
+}
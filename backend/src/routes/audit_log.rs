@@ -0,0 +1,102 @@
+use actix_web::{get, web, Error, HttpResponse, Responder};
+use rusqlite::params;
+use tracing::debug;
+use utoipa::IntoParams;
+
+use crate::auth::AuthenticatedUser;
+use crate::db::{self, Database};
+use crate::models::AuditEntry;
+use crate::routes::internal_error;
+
+// Number of entries returned when no limit is given
+const DEFAULT_LIMIT: i64 = 100;
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditQueryParams {
+    table_name: Option<String>,
+    record_id: Option<i64>,
+    action: Option<String>,
+    request_id: Option<String>,
+    item_name: Option<String>,
+    limit: Option<i64>,
+}
+
+fn parse_json(json: Option<String>) -> Option<serde_json::Value> {
+    json.and_then(|json| serde_json::from_str(&json).ok())
+}
+
+// Handle GET request for the audit log of the user's changes, newest first
+// Takes optional query parameters: table_name, record_id, action, request_id, item_name, limit.
+// item_name matches changes to the item itself and to any row referring to it, like its trades.
+#[utoipa::path(
+    tag = "audit",
+    summary = "List audit log entries",
+    params(AuditQueryParams),
+    responses(
+        (status = 200, description = "Changes, newest first", body = Vec<AuditEntry>),
+    ),
+)]
+#[get("/api/v1/audit")]
+pub async fn audit_get(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    query_params: web::Query<AuditQueryParams>,
+) -> Result<impl Responder, Error> {
+    debug!("Listing audit log");
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, actor, timestamp, request_id, action, table_name, record_id, before, after
+            FROM audit_log
+            WHERE user_id = ?1
+                AND (?2 IS NULL OR table_name = ?2)
+                AND (?3 IS NULL OR record_id = ?3)
+                AND (?4 IS NULL OR action = ?4)
+                AND (?5 IS NULL OR request_id = ?5)
+                AND (?6 IS NULL OR EXISTS (
+                    SELECT 1 FROM items WHERE items.name = ?6 AND (
+                        (table_name = 'items' AND record_id = items.id)
+                        OR json_extract(COALESCE(after, before), '$.item_id') = items.id
+                    )
+                ))
+            ORDER BY id DESC
+            LIMIT ?7",
+        )
+        .map_err(internal_error("Failed to prepare statement"))?;
+
+    let entries = stmt
+        .query_map(
+            params![
+                user.id,
+                query_params.table_name,
+                query_params.record_id,
+                query_params.action,
+                query_params.request_id,
+                query_params.item_name,
+                query_params.limit.unwrap_or(DEFAULT_LIMIT),
+            ],
+            |row| {
+                Ok(AuditEntry {
+                    id: row.get(0)?,
+                    actor: row.get(1)?,
+                    timestamp: db::datetime_from_unix(row.get(2)?).unwrap_or_default(),
+                    request_id: row.get(3)?,
+                    action: row.get(4)?,
+                    table_name: row.get(5)?,
+                    record_id: row.get(6)?,
+                    before: parse_json(row.get(7)?),
+                    after: parse_json(row.get(8)?),
+                })
+            },
+        )
+        .map_err(internal_error("Failed to query map"))?
+        .collect::<rusqlite::Result<Vec<AuditEntry>>>()
+        .map_err(internal_error("Failed to read audit log row"))?;
+
+    Ok(HttpResponse::Ok().json(entries))
+}
@@ -0,0 +1,249 @@
+use actix_web::cookie::{time, Cookie, SameSite};
+use actix_web::error::{
+    ErrorBadRequest, ErrorConflict, ErrorInternalServerError, ErrorUnauthorized,
+};
+use actix_web::{get, post, web, Error, HttpRequest, HttpResponse, Responder};
+use rusqlite::{params, ErrorCode, OptionalExtension};
+use tracing::{debug, error};
+
+use crate::audit::{self, Action, Audit};
+use crate::auth::{self, AuthenticatedUser, SESSION_COOKIE, SESSION_DURATION_DAYS};
+use crate::db::{self, Database};
+use crate::models::{Credentials, LoginResponse, User};
+use crate::routes::internal_error;
+
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+fn session_cookie(token: &str) -> Cookie<'static> {
+    Cookie::build(SESSION_COOKIE, token.to_string())
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::days(SESSION_DURATION_DAYS))
+        .finish()
+}
+
+// Handle POST request for registering a new user
+// The first user to register takes ownership of any data created before authentication existed
+#[utoipa::path(
+    tag = "auth",
+    summary = "Register a user",
+    request_body = Credentials,
+    responses(
+        (status = 200, description = "The new user", body = User),
+        (status = 400, description = "Empty username or short password"),
+        (status = 409, description = "Username already taken"),
+    ),
+    security(()),
+)]
+#[post("/api/v1/auth/register")]
+pub async fn register_post(
+    db: web::Data<Database>,
+    audit: Audit,
+    web::Json(credentials): web::Json<Credentials>,
+) -> Result<impl Responder, Error> {
+    let username = credentials.username.trim().to_string();
+    debug!(username, "Registering user");
+
+    if username.is_empty() {
+        return Err(ErrorBadRequest("Username must not be empty"));
+    }
+
+    if credentials.password.len() < MIN_PASSWORD_LENGTH {
+        return Err(ErrorBadRequest(format!(
+            "Password must be at least {} characters",
+            MIN_PASSWORD_LENGTH
+        )));
+    }
+
+    let password_hash = auth::hash_password(&credentials.password).map_err(|e| {
+        error!(error = %e, "Failed to hash password");
+        ErrorInternalServerError("Failed to hash password")
+    })?;
+
+    let mut conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let tx = conn
+        .transaction()
+        .map_err(internal_error("Failed to start transaction"))?;
+
+    tx.execute(
+        "INSERT INTO users (username, password_hash) VALUES (?1, ?2)",
+        params![username, password_hash],
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::SqliteFailure(ref err, _)
+            if err.code == ErrorCode::ConstraintViolation =>
+        {
+            ErrorConflict("Username is already taken")
+        }
+        e => internal_error("Failed to insert user into users table")(e),
+    })?;
+    let user_id = tx.last_insert_rowid();
+    let audit = audit.for_user(user_id, &username);
+
+    audit
+        .record(&tx, Action::Insert, "users", user_id, None)
+        .map_err(internal_error("Failed to write audit log"))?;
+
+    let user_count: i64 = tx
+        .query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))
+        .map_err(internal_error("Failed to count users"))?;
+
+    if user_count == 1 {
+        for table in db::USER_TABLES {
+            let ids = tx
+                .prepare(&format!("SELECT id FROM {} WHERE user_id IS NULL", table))
+                .and_then(|mut stmt| {
+                    stmt.query_map([], |row| row.get::<_, i64>(0))?
+                        .collect::<rusqlite::Result<Vec<i64>>>()
+                })
+                .map_err(internal_error("Failed to get existing data"))?;
+
+            for id in ids {
+                let before = audit::snapshot(&tx, table, id)
+                    .map_err(internal_error("Failed to get existing data"))?;
+
+                tx.execute(
+                    &format!("UPDATE {} SET user_id = ?1 WHERE id = ?2", table),
+                    params![user_id, id],
+                )
+                .map_err(internal_error("Failed to assign existing data to user"))?;
+
+                audit
+                    .record(&tx, Action::Update, table, id, before)
+                    .map_err(internal_error("Failed to write audit log"))?;
+            }
+        }
+    }
+
+    tx.commit()
+        .map_err(internal_error("Failed to commit transaction"))?;
+
+    Ok(HttpResponse::Ok().json(User {
+        id: user_id,
+        username,
+    }))
+}
+
+// Handle POST request for logging in
+// Sets an HttpOnly session cookie and also returns the token for use as a bearer token
+#[utoipa::path(
+    tag = "auth",
+    summary = "Log in",
+    request_body = Credentials,
+    responses(
+        (status = 200, description = "Session token, also set as a cookie", body = LoginResponse),
+        (status = 401, description = "Invalid username or password"),
+    ),
+    security(()),
+)]
+#[post("/api/v1/auth/login")]
+pub async fn login_post(
+    db: web::Data<Database>,
+    web::Json(credentials): web::Json<Credentials>,
+) -> Result<impl Responder, Error> {
+    let username = credentials.username.trim();
+    debug!(username, "Logging in");
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let user: Option<(i64, String)> = conn
+        .query_row(
+            "SELECT id, password_hash FROM users WHERE username = ?1",
+            [username],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(internal_error("Failed to get user from users table"))?;
+
+    // Unknown usernames are checked against a dummy hash, so they take as long as wrong passwords
+    let verified = auth::verify_login(
+        &credentials.password,
+        user.as_ref()
+            .map(|(_, password_hash)| password_hash.as_str()),
+    );
+    let user_id = match user {
+        Some((user_id, _)) if verified => user_id,
+        _ => return Err(ErrorUnauthorized("Invalid username or password")),
+    };
+
+    let token = auth::generate_token();
+    let expires_at = chrono::Utc::now() + chrono::Duration::days(SESSION_DURATION_DAYS);
+
+    conn.execute(
+        "INSERT INTO sessions (user_id, token_hash, expires_at) VALUES (?1, ?2, ?3)",
+        params![user_id, auth::hash_token(&token), expires_at.timestamp()],
+    )
+    .map_err(internal_error(
+        "Failed to insert session into sessions table",
+    ))?;
+
+    Ok(HttpResponse::Ok()
+        .cookie(session_cookie(&token))
+        .json(LoginResponse {
+            token,
+            user: User {
+                id: user_id,
+                username: username.to_string(),
+            },
+        }))
+}
+
+// Handle POST request for logging out, ending the session the request was made with
+#[utoipa::path(
+    tag = "auth",
+    summary = "Log out",
+    responses(
+        (status = 200, description = "Logged out", body = String, content_type = "text/plain"),
+    ),
+)]
+#[post("/api/v1/auth/logout")]
+pub async fn logout_post(
+    req: HttpRequest,
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+) -> Result<impl Responder, Error> {
+    debug!(username = user.username, "Logging out");
+
+    if let Some(token) = auth::request_token(&req) {
+        let conn = db
+            .open()
+            .map_err(internal_error("Failed to open database"))?;
+
+        conn.execute(
+            "DELETE FROM sessions WHERE token_hash = ?1",
+            [auth::hash_token(&token)],
+        )
+        .map_err(internal_error(
+            "Failed to delete session from sessions table",
+        ))?;
+    }
+
+    let mut cookie = session_cookie("");
+    cookie.make_removal();
+
+    Ok(HttpResponse::Ok()
+        .cookie(cookie)
+        .body("Successfully logged out"))
+}
+
+// Handle GET request for the currently logged in user
+#[utoipa::path(
+    tag = "auth",
+    summary = "Get the logged in user",
+    responses(
+        (status = 200, description = "The logged in user", body = User),
+    ),
+)]
+#[get("/api/v1/auth/me")]
+pub async fn me_get(user: AuthenticatedUser) -> impl Responder {
+    HttpResponse::Ok().json(User {
+        id: user.id,
+        username: user.username,
+    })
+}
@@ -0,0 +1,144 @@
+use actix_web::error::{ErrorForbidden, ErrorInternalServerError};
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{get, post, web, Error, HttpResponse, Responder};
+use tracing::{debug, error, info};
+
+use crate::auth::AuthenticatedUser;
+use crate::backup::{self, BackupError, BackupFile};
+use crate::db::Database;
+use crate::models::Backup;
+
+// Like `internal_error`, for failures while backing up
+fn backup_error(context: &'static str) -> impl Fn(BackupError) -> Error {
+    move |e| {
+        error!(error = %e, "{}", context);
+        ErrorInternalServerError(context)
+    }
+}
+
+// Run backup file I/O on the blocking thread pool, so copying a large database doesn't hold up
+// the other requests on this worker
+async fn block_on_backup<T: Send + 'static>(
+    context: &'static str,
+    f: impl FnOnce() -> Result<T, BackupError> + Send + 'static,
+) -> Result<T, Error> {
+    web::block(f).await?.map_err(backup_error(context))
+}
+
+// The backups hold every user's data, so only admins may touch them
+fn require_admin(user: &AuthenticatedUser) -> Result<(), Error> {
+    if user.is_admin() {
+        Ok(())
+    } else {
+        Err(ErrorForbidden("Only admins can manage backups"))
+    }
+}
+
+impl From<BackupFile> for Backup {
+    fn from(backup: BackupFile) -> Self {
+        Backup {
+            name: backup.name(),
+            created_at: backup.created_at,
+            size_bytes: backup.size_bytes,
+        }
+    }
+}
+
+// Handle GET request for listing the database backups, oldest first
+#[utoipa::path(
+    tag = "admin",
+    summary = "List database backups",
+    responses(
+        (status = 200, description = "The backups, oldest first", body = Vec<Backup>),
+        (status = 403, description = "The user isn't an admin"),
+    ),
+)]
+#[get("/api/v1/admin/backups")]
+pub async fn backups_get(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+) -> Result<impl Responder, Error> {
+    require_admin(&user)?;
+    debug!("Listing backups");
+
+    let db = db.get_ref().clone();
+    let backups: Vec<Backup> =
+        block_on_backup("Failed to list backups", move || backup::list_backups(&db))
+            .await?
+            .into_iter()
+            .map(Backup::from)
+            .collect();
+
+    Ok(HttpResponse::Ok().json(backups))
+}
+
+// Handle POST request for backing up the database now
+// The oldest backups beyond the retention count are deleted afterwards, like scheduled backups.
+#[utoipa::path(
+    tag = "admin",
+    summary = "Back up the database",
+    responses(
+        (status = 200, description = "The new backup", body = Backup),
+        (status = 403, description = "The user isn't an admin"),
+    ),
+)]
+#[post("/api/v1/admin/backups")]
+pub async fn backups_post(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+) -> Result<impl Responder, Error> {
+    require_admin(&user)?;
+
+    let db = db.get_ref().clone();
+    let created = block_on_backup("Failed to back up database", {
+        let db = db.clone();
+        move || backup::create_backup(&db)
+    })
+    .await?;
+    info!(backup = %created.path.display(), username = %user.username, "Backed up database");
+
+    let pruned = block_on_backup("Failed to delete old backups", move || {
+        backup::prune_backups(&db, backup::keep())
+    })
+    .await?;
+    for backup in pruned {
+        info!(backup = %backup.path.display(), "Deleted old backup");
+    }
+
+    Ok(HttpResponse::Ok().json(Backup::from(created)))
+}
+
+// Handle GET request for downloading a consistent copy of the database as it is now
+// It isn't kept as a backup.
+#[utoipa::path(
+    tag = "admin",
+    summary = "Download a snapshot of the database",
+    responses(
+        (status = 200, description = "The SQLite database file", body = Vec<u8>, content_type = "application/vnd.sqlite3"),
+        (status = 403, description = "The user isn't an admin"),
+    ),
+)]
+#[get("/api/v1/admin/snapshot")]
+pub async fn snapshot_get(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+) -> Result<impl Responder, Error> {
+    require_admin(&user)?;
+    info!(username = %user.username, "Downloading database snapshot");
+
+    let db = db.get_ref().clone();
+    let snapshot = block_on_backup("Failed to make database snapshot", move || {
+        backup::snapshot(&db)
+    })
+    .await?;
+
+    let filename = format!("ardy-{}.db", chrono::Utc::now().format("%Y%m%d-%H%M%S"));
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/vnd.sqlite3")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(filename)],
+        })
+        .body(snapshot))
+}
@@ -0,0 +1,97 @@
+use actix_web::{post, web, Error, HttpResponse, Responder};
+use tracing::debug;
+
+use crate::audit::Audit;
+use crate::auth::AuthenticatedUser;
+use crate::events::{Events, TradeEvent};
+use crate::models::{BulkOperationResult, BulkTradeRequest, BulkTradeResponse, TradeOperation};
+use crate::repository::{self, AppliedOperation, TradeRepository};
+use crate::routes::repository_error;
+
+// Handle POST request for applying a list of trade create, update and delete operations.
+// They run in one transaction: if any operation fails, none of them are applied and the
+// response says which ones failed.
+#[utoipa::path(
+    tag = "trades",
+    summary = "Apply trade operations atomically",
+    request_body = BulkTradeRequest,
+    responses(
+        (status = 200, description = "All operations applied", body = BulkTradeResponse),
+        (status = 400, description = "Nothing applied because some operations failed", body = BulkTradeResponse),
+    ),
+)]
+#[post("/api/v1/trades/bulk")]
+pub async fn trades_bulk_post(
+    user: AuthenticatedUser,
+    trades: web::Data<dyn TradeRepository>,
+    audit: Audit,
+    events: web::Data<Events>,
+    web::Json(request): web::Json<BulkTradeRequest>,
+) -> Result<impl Responder, Error> {
+    debug!(
+        operations = request.operations.len(),
+        "Applying bulk trade operations"
+    );
+
+    let (response, trade_events) =
+        apply_operations(trades.as_ref(), &audit, &user, &request.operations)
+            .map_err(repository_error("Failed to apply bulk trade operations"))?;
+
+    if response.applied {
+        for event in trade_events {
+            events.send(user.id, event);
+        }
+        Ok(HttpResponse::Ok().json(response))
+    } else {
+        Ok(HttpResponse::BadRequest().json(response))
+    }
+}
+
+// Apply operations all together or not at all. Returns the outcome of each operation and, if
+// they were applied, the events to send for them. The CLI uses this too when it works on the
+// database directly.
+pub fn apply_operations(
+    trades: &dyn TradeRepository,
+    audit: &Audit,
+    user: &AuthenticatedUser,
+    operations: &[TradeOperation],
+) -> repository::Result<(BulkTradeResponse, Vec<TradeEvent>)> {
+    let outcomes = trades.apply(audit, user, operations)?;
+
+    let results: Vec<BulkOperationResult> = outcomes
+        .iter()
+        .enumerate()
+        .map(|(index, outcome)| match outcome {
+            Ok(applied) => BulkOperationResult {
+                index,
+                ok: true,
+                id: Some(applied.id()),
+                error: None,
+            },
+            Err(error) => BulkOperationResult {
+                index,
+                ok: false,
+                id: None,
+                error: Some(error.clone()),
+            },
+        })
+        .collect();
+
+    let applied = results.iter().all(|result| result.ok);
+    let trade_events = if applied {
+        outcomes.into_iter().flatten().map(trade_event).collect()
+    } else {
+        Vec::new()
+    };
+
+    Ok((BulkTradeResponse { applied, results }, trade_events))
+}
+
+// The event to send for an operation once the whole batch is applied
+fn trade_event(applied: AppliedOperation) -> TradeEvent {
+    match applied {
+        AppliedOperation::Created(trade) => TradeEvent::TradeCreated { trade },
+        AppliedOperation::Updated { before, trade } => TradeEvent::TradeUpdated { before, trade },
+        AppliedOperation::Deleted(trade) => TradeEvent::TradeDeleted { trade },
+    }
+}
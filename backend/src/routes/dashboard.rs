@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use actix_web::{get, web, Error, HttpResponse, Responder};
+use chrono::{Datelike, Days, Duration, NaiveDate, NaiveDateTime};
+use tracing::debug;
+use utoipa::IntoParams;
+
+use crate::auth::AuthenticatedUser;
+use crate::db::Database;
+use crate::ledger::{self, InferredSession, Ledger, RealizedSale};
+use crate::models::{
+    BuyLimitReset, Dashboard, FlipStatus, Goal, ItemData, ItemProfit, RecentSession,
+};
+use crate::repository::{TradeFilter, TradeRepository};
+use crate::routes::flips::query_flips;
+use crate::routes::goals::query_goals;
+use crate::routes::{internal_error, local_now, repository_error};
+
+// Number of sessions GP/hour is worked out over
+const RECENT_SESSIONS: usize = 5;
+
+// Number of items listed as the best and the worst
+const TOP_ITEMS: usize = 5;
+
+// Total up an inferred session, which is made of the trades between its start and end
+pub(crate) fn recent_session(
+    session: &InferredSession,
+    trades: &[ItemData],
+    ledger: &Ledger,
+) -> RecentSession {
+    let totals = ledger::session_totals(
+        trades
+            .iter()
+            .filter(|trade| session.start <= trade.timestamp && trade.timestamp <= session.end),
+        ledger,
+    );
+
+    RecentSession {
+        start: session.start,
+        end: session.end,
+        trade_count: totals.trade_count,
+        profit: totals.profit,
+        capital_used: totals.capital_used,
+        gp_per_hour: ledger::gp_per_hour(totals.profit, session.end - session.start),
+    }
+}
+
+// Items that made a profit or a loss, the most of either first
+fn top_items(sales: &[RealizedSale]) -> (Vec<ItemProfit>, Vec<ItemProfit>) {
+    let mut profits: HashMap<&str, i64> = HashMap::new();
+    for sale in sales {
+        *profits.entry(&sale.item_name).or_default() += sale.profit;
+    }
+
+    let mut profits: Vec<ItemProfit> = profits
+        .into_iter()
+        .map(|(item_name, profit)| ItemProfit {
+            item_name: item_name.to_string(),
+            profit,
+        })
+        .collect();
+    profits.sort_by(|a, b| (b.profit, &a.item_name).cmp(&(a.profit, &b.item_name)));
+
+    let worst = profits
+        .iter()
+        .rev()
+        .filter(|item| item.profit < 0)
+        .take(TOP_ITEMS)
+        .map(|item| ItemProfit {
+            item_name: item.item_name.clone(),
+            profit: item.profit,
+        })
+        .collect();
+    profits.retain(|item| item.profit > 0);
+    profits.truncate(TOP_ITEMS);
+
+    (profits, worst)
+}
+
+// Work out the dashboard from a user's trades as of `now`
+fn summarize(
+    trades: &[ItemData],
+    open_flips: i64,
+    goals: Vec<Goal>,
+    now: NaiveDateTime,
+) -> Dashboard {
+    let ledger = ledger::ledger(trades);
+
+    let today = now.date();
+    let week_start = today - Days::new(u64::from(today.weekday().num_days_from_monday()));
+    let profit_since = |since: NaiveDate| -> i64 {
+        ledger
+            .sales
+            .iter()
+            .filter(|sale| sale.timestamp.date() >= since)
+            .map(|sale| sale.profit)
+            .sum()
+    };
+
+    let recent_sessions: Vec<RecentSession> = ledger::infer_sessions(trades)
+        .iter()
+        .rev()
+        .take(RECENT_SESSIONS)
+        .map(|session| recent_session(session, trades, &ledger))
+        .collect();
+
+    // Sessions of a single trade take no time, so they're left out of the rate
+    let (profit, duration) = recent_sessions
+        .iter()
+        .filter(|session| session.gp_per_hour.is_some())
+        .fold((0, Duration::zero()), |(profit, duration), session| {
+            (
+                profit + session.profit,
+                duration + (session.end - session.start),
+            )
+        });
+
+    let (top_items, worst_items) = top_items(&ledger.sales);
+
+    Dashboard {
+        profit_today: profit_since(today),
+        profit_this_week: profit_since(week_start),
+        profit_all_time: ledger.sales.iter().map(|sale| sale.profit).sum(),
+        gp_per_hour: ledger::gp_per_hour(profit, duration),
+        recent_sessions,
+        top_items,
+        worst_items,
+        capital_tied_up: ledger.holdings.iter().map(|holding| holding.cost).sum(),
+        open_flips,
+        buy_limit_resets: ledger::buy_limit_windows(trades, now)
+            .into_iter()
+            .map(|window| BuyLimitReset {
+                item_name: window.item_name,
+                account_id: window.account_id,
+                quantity_bought: window.quantity_bought,
+                resets_at: window.resets_at,
+            })
+            .collect(),
+        goals,
+    }
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DashboardQueryParams {
+    account: Option<i64>,
+    // The time it is where the trades were made, since they're written in local time.
+    // Defaults to the backend's local time.
+    #[param(example = "2024-01-31T13:45")]
+    now: Option<String>,
+}
+
+// Handle GET request for the numbers on the dashboard, in one round trip
+// Takes optional query parameters: account, now
+#[utoipa::path(
+    tag = "trades",
+    summary = "Summarize trading for the dashboard",
+    params(DashboardQueryParams),
+    responses(
+        (status = 200, description = "Profit, GP/hour, best and worst items, capital, open flips, buy limits and goals", body = Dashboard),
+        (status = 400, description = "Invalid time"),
+    ),
+)]
+#[get("/api/v1/dashboard")]
+pub async fn dashboard_get(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    trades: web::Data<dyn TradeRepository>,
+    web::Query(query_params): web::Query<DashboardQueryParams>,
+) -> Result<impl Responder, Error> {
+    debug!(now = ?query_params.now, "Summarizing dashboard");
+
+    let now = local_now(query_params.now.as_deref())?;
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+    let open_flips = query_flips(&conn, trades.as_ref(), &user, now)?
+        .iter()
+        .filter(|flip| flip.status != FlipStatus::Closed)
+        .filter(|flip| {
+            query_params.account.is_none_or(|account| {
+                flip.trades
+                    .first()
+                    .is_some_and(|trade| trade.account_id == Some(account))
+            })
+        })
+        .count();
+
+    // Goals keep their own scope whichever account is selected, so they show the same progress
+    // as on their own. They go by every trade, of which each goal only counts its account's.
+    let all_trades = trades
+        .list(&user, &TradeFilter::default())
+        .map_err(repository_error("Failed to list trades"))?;
+    let filter = TradeFilter {
+        account: query_params.account,
+        ..TradeFilter::default()
+    };
+    let trades: Vec<ItemData> = all_trades
+        .iter()
+        .filter(|trade| filter.matches(trade))
+        .cloned()
+        .collect();
+
+    let goals = query_goals(&conn, &user, &all_trades, now)?;
+
+    Ok(HttpResponse::Ok().json(summarize(&trades, open_flips as i64, goals, now)))
+}
@@ -0,0 +1,25 @@
+use actix_web::http::header;
+use actix_web::{get, web, HttpResponse, Responder};
+use tracing::debug;
+
+use crate::auth::AuthenticatedUser;
+use crate::events::{Events, TradeEvent};
+
+// Handle GET request for the stream of the user's trade events, as server-sent events.
+// Each event is a JSON object whose `type` is trade_created, trade_updated, trade_deleted or resync.
+#[utoipa::path(
+    tag = "events",
+    summary = "Stream trade events",
+    responses(
+        (status = 200, description = "Server-sent events, each with a TradeEvent as JSON data", body = TradeEvent, content_type = "text/event-stream"),
+    ),
+)]
+#[get("/api/v1/events")]
+pub async fn events_get(user: AuthenticatedUser, events: web::Data<Events>) -> impl Responder {
+    debug!("Opening event stream");
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(events.subscribe(user.id))
+}
@@ -0,0 +1,496 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use actix_web::error::{ErrorBadRequest, ErrorConflict, ErrorNotFound};
+use actix_web::{delete, get, post, web, Error, HttpResponse, Responder};
+use chrono::NaiveDateTime;
+use rusqlite::{params, Connection, TransactionBehavior};
+use tracing::{debug, info};
+use utoipa::IntoParams;
+
+use crate::audit::{Action, Audit};
+use crate::auth::AuthenticatedUser;
+use crate::db::{self, Database};
+use crate::models::{Flip, FlipStatus, ItemData, NewFlip};
+use crate::repository::{TradeFilter, TradeRepository};
+use crate::routes::{internal_error, local_now, repository_error};
+
+// Summarize a flip from its trades, which must be ordered by timestamp. Open flips run until `now`.
+fn summarize_flip(id: i64, trades: Vec<ItemData>, now: NaiveDateTime) -> Flip {
+    let (buys, sells): (Vec<&ItemData>, Vec<&ItemData>) =
+        trades.iter().partition(|trade| trade.is_purchase);
+
+    let quantity_bought: i64 = buys.iter().map(|trade| trade.quantity).sum();
+    let quantity_sold: i64 = sells.iter().map(|trade| trade.quantity).sum();
+    let cost: i64 = buys
+        .iter()
+        .map(|trade| trade.quantity * trade.total_price)
+        .sum();
+    let revenue: i64 = sells
+        .iter()
+        .map(|trade| trade.quantity * trade.total_price)
+        .sum();
+
+    // Only the items sold so far count towards realized profit, valued at the average buy price
+    let cost_of_sold = if quantity_bought > 0 {
+        cost * quantity_sold.min(quantity_bought) / quantity_bought
+    } else {
+        0
+    };
+    let profit = revenue - cost_of_sold;
+    let roi = if cost_of_sold > 0 {
+        profit as f64 / cost_of_sold as f64
+    } else {
+        0.0
+    };
+
+    let status = if quantity_sold == 0 {
+        FlipStatus::Open
+    } else if quantity_sold < quantity_bought {
+        FlipStatus::PartiallyClosed
+    } else {
+        FlipStatus::Closed
+    };
+
+    // Open flips are still running, so their duration counts up to now
+    let first_timestamp = trades.first().map(|trade| trade.timestamp);
+    let last_timestamp = match status {
+        FlipStatus::Closed => trades.last().map(|trade| trade.timestamp),
+        _ => Some(now),
+    };
+    let duration_seconds = match (first_timestamp, last_timestamp) {
+        (Some(first), Some(last)) => (last - first).num_seconds().max(0),
+        _ => 0,
+    };
+
+    Flip {
+        id,
+        item_name: trades
+            .first()
+            .map(|trade| trade.item_name.clone())
+            .unwrap_or_default(),
+        status,
+        quantity_bought,
+        quantity_sold,
+        cost,
+        revenue,
+        profit,
+        roi,
+        duration_seconds,
+        trades,
+    }
+}
+
+// Load every flip of a user with its trades, ordered by flip id. Open flips run until `now`.
+// Trades in the trash are left out, and so are flips with no other trades.
+pub(crate) fn query_flips(
+    conn: &Connection,
+    trades: &dyn TradeRepository,
+    user: &AuthenticatedUser,
+    now: NaiveDateTime,
+) -> Result<Vec<Flip>, Error> {
+    let mut user_trades: HashMap<i64, ItemData> = trades
+        .list(user, &TradeFilter::default())
+        .map_err(repository_error("Failed to get trades from trades table"))?
+        .into_iter()
+        .map(|trade| (trade.id, trade))
+        .collect();
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT flip_trades.flip_id, flip_trades.trade_id
+            FROM flip_trades INNER JOIN flips ON flip_trades.flip_id = flips.id
+            WHERE flips.user_id = ?1",
+        )
+        .map_err(internal_error("Failed to prepare statement"))?;
+
+    let rows = stmt
+        .query_map([user.id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+        })
+        .map_err(internal_error("Failed to query map"))?
+        .collect::<rusqlite::Result<Vec<(i64, i64)>>>()
+        .map_err(internal_error("Failed to read flip row"))?;
+
+    let mut flip_trades: BTreeMap<i64, Vec<ItemData>> = BTreeMap::new();
+    for (flip_id, trade_id) in rows {
+        if let Some(trade) = user_trades.remove(&trade_id) {
+            flip_trades.entry(flip_id).or_default().push(trade);
+        }
+    }
+
+    Ok(flip_trades
+        .into_iter()
+        .map(|(id, mut trades)| {
+            trades.sort_by_key(|trade| (trade.timestamp, trade.id));
+            summarize_flip(id, trades, now)
+        })
+        .collect())
+}
+
+// Get the ids of the trades that are already part of a flip
+fn grouped_trade_ids(conn: &Connection) -> Result<HashSet<i64>, Error> {
+    let mut stmt = conn
+        .prepare("SELECT trade_id FROM flip_trades")
+        .map_err(internal_error("Failed to prepare statement"))?;
+
+    let ids = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(internal_error("Failed to query map"))?
+        .collect::<rusqlite::Result<HashSet<i64>>>()
+        .map_err(internal_error("Failed to read flip row"))?;
+
+    Ok(ids)
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FlipQueryParams {
+    #[param(value_type = Option<FlipStatus>)]
+    status: Option<String>,
+    item_name: Option<String>,
+    account: Option<i64>,
+    // The time it is where the trades are made, which open flips run until.
+    // Defaults to the backend's local time.
+    #[param(example = "2024-01-31T13:45")]
+    now: Option<String>,
+}
+
+// Handle GET request for listing flips with their profit, ROI, duration and status
+// Takes optional query parameters: status (open, partially_closed or closed), item_name, account, now
+#[utoipa::path(
+    tag = "flips",
+    summary = "List flips",
+    params(FlipQueryParams),
+    responses(
+        (status = 200, description = "Flips with their trades", body = Vec<Flip>),
+        (status = 400, description = "Invalid status or time"),
+    ),
+)]
+#[get("/api/v1/flips")]
+pub async fn flips_get(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    trades: web::Data<dyn TradeRepository>,
+    query_params: web::Query<FlipQueryParams>,
+) -> Result<impl Responder, Error> {
+    debug!("Listing flips");
+
+    let status = match &query_params.status {
+        Some(status) => {
+            Some(FlipStatus::parse(status).ok_or_else(|| ErrorBadRequest("Invalid flip status"))?)
+        }
+        None => None,
+    };
+    let now = local_now(query_params.now.as_deref())?;
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let flips: Vec<Flip> = query_flips(&conn, trades.as_ref(), &user, now)?
+        .into_iter()
+        .filter(|flip| status.is_none_or(|status| flip.status == status))
+        .filter(|flip| {
+            query_params.account.is_none_or(|account| {
+                flip.trades
+                    .first()
+                    .is_some_and(|trade| trade.account_id == Some(account))
+            })
+        })
+        .filter(|flip| {
+            query_params.item_name.as_ref().is_none_or(|item_name| {
+                flip.item_name
+                    .to_lowercase()
+                    .contains(&item_name.to_lowercase())
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(flips))
+}
+
+// Handle POST request for manually grouping trades into a flip
+// All trades must be of the same item and account, and not already part of a flip
+#[utoipa::path(
+    tag = "flips",
+    summary = "Group trades into a flip",
+    request_body = NewFlip,
+    responses(
+        (status = 200, description = "The new flip", body = Flip),
+        (status = 400, description = "No trades, a trade listed twice, or trades of different items or accounts"),
+        (status = 404, description = "Trade not found"),
+        (status = 409, description = "Trade already part of a flip"),
+    ),
+)]
+#[post("/api/v1/flips")]
+pub async fn flips_post(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    trades: web::Data<dyn TradeRepository>,
+    audit: Audit,
+    web::Json(new_flip): web::Json<NewFlip>,
+) -> Result<impl Responder, Error> {
+    debug!(?new_flip, "Adding flip");
+
+    if new_flip.trade_ids.is_empty() {
+        return Err(ErrorBadRequest("A flip needs at least one trade"));
+    }
+
+    let mut trade_ids = new_flip.trade_ids.clone();
+    trade_ids.sort_unstable();
+    if let Some(pair) = trade_ids.windows(2).find(|pair| pair[0] == pair[1]) {
+        return Err(ErrorBadRequest(format!(
+            "Trade {} is listed more than once",
+            pair[0]
+        )));
+    }
+
+    let mut conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    // Check and group the trades in one write transaction, so no other flip can take them in between
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(internal_error("Failed to start transaction"))?;
+
+    let grouped = grouped_trade_ids(&tx)?;
+
+    let mut item_accounts = Vec::new();
+    for trade_id in &new_flip.trade_ids {
+        let trade = trades
+            .get(&user, *trade_id)
+            .map_err(repository_error("Failed to get trade from trades table"))?
+            .ok_or_else(|| ErrorNotFound(format!("Trade {} not found", trade_id)))?;
+
+        if grouped.contains(trade_id) {
+            return Err(ErrorConflict(format!(
+                "Trade {} is already part of a flip",
+                trade_id
+            )));
+        }
+
+        item_accounts.push((trade.item_name, trade.account_id));
+    }
+
+    item_accounts.sort_unstable();
+    item_accounts.dedup();
+    if item_accounts.len() > 1 {
+        return Err(ErrorBadRequest(
+            "All trades in a flip must be of the same item and account",
+        ));
+    }
+
+    let item_id = db::get_or_insert_item_id(&tx, &audit, &item_accounts[0].0)
+        .map_err(internal_error("Failed to get item from items table"))?;
+
+    tx.execute(
+        "INSERT INTO flips (item_id, user_id) VALUES (?1, ?2)",
+        params![item_id, user.id],
+    )
+    .map_err(internal_error("Failed to insert flip into flips table"))?;
+    let flip_id = tx.last_insert_rowid();
+
+    audit
+        .record(&tx, Action::Insert, "flips", flip_id, None)
+        .map_err(internal_error("Failed to write audit log"))?;
+
+    for trade_id in &new_flip.trade_ids {
+        tx.execute(
+            "INSERT INTO flip_trades (flip_id, trade_id) VALUES (?1, ?2)",
+            params![flip_id, trade_id],
+        )
+        .map_err(internal_error(
+            "Failed to insert trade into flip_trades table",
+        ))?;
+
+        audit
+            .record(
+                &tx,
+                Action::Insert,
+                "flip_trades",
+                tx.last_insert_rowid(),
+                None,
+            )
+            .map_err(internal_error("Failed to write audit log"))?;
+    }
+
+    tx.commit()
+        .map_err(internal_error("Failed to commit transaction"))?;
+
+    let flip = query_flips(&conn, trades.as_ref(), &user, local_now(None)?)?
+        .into_iter()
+        .find(|flip| flip.id == flip_id)
+        .ok_or_else(|| ErrorNotFound("Flip not found"))?;
+
+    Ok(HttpResponse::Ok().json(flip))
+}
+
+// Handle POST request for automatically grouping all ungrouped trades into flips
+// Trades are walked in time order per item and account: a buy joins the unclosed flip or starts a
+// new one, and a sell joins the unclosed flip. Sells without a preceding buy are left ungrouped.
+#[utoipa::path(
+    tag = "flips",
+    summary = "Group ungrouped trades into flips",
+    responses(
+        (status = 200, description = "Number of trades grouped", body = i64),
+    ),
+)]
+#[post("/api/v1/flips/auto")]
+pub async fn flips_auto_post(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    trades: web::Data<dyn TradeRepository>,
+    audit: Audit,
+) -> Result<impl Responder, Error> {
+    debug!("Grouping trades into flips");
+
+    let mut conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    // Read the unclosed flips and ungrouped trades inside the write transaction, so trades grouped
+    // concurrently aren't grouped twice
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(internal_error("Failed to start transaction"))?;
+
+    // Quantities bought and sold of the unclosed flip of each item and account
+    let mut unclosed: HashMap<(String, Option<i64>), (i64, i64, i64)> = HashMap::new();
+    for flip in query_flips(&tx, trades.as_ref(), &user, local_now(None)?)? {
+        if flip.status != FlipStatus::Closed {
+            let account_id = flip.trades.first().and_then(|trade| trade.account_id);
+            unclosed.insert(
+                (flip.item_name.clone(), account_id),
+                (flip.id, flip.quantity_bought, flip.quantity_sold),
+            );
+        }
+    }
+
+    let grouped = grouped_trade_ids(&tx)?;
+    let mut ungrouped: Vec<ItemData> = trades
+        .list(&user, &TradeFilter::default())
+        .map_err(repository_error("Failed to get trades from trades table"))?
+        .into_iter()
+        .filter(|trade| !grouped.contains(&trade.id))
+        .collect();
+    ungrouped.sort_by_key(|trade| (trade.timestamp, trade.id));
+
+    let mut grouped_count = 0;
+    for trade in ungrouped {
+        let key = (trade.item_name, trade.account_id);
+        let flip_id = match (unclosed.get_mut(&key), trade.is_purchase) {
+            (Some((flip_id, bought, _)), true) => {
+                *bought += trade.quantity;
+                *flip_id
+            }
+            (Some((flip_id, _, sold)), false) => {
+                *sold += trade.quantity;
+                *flip_id
+            }
+            (None, true) => {
+                let item_id = db::get_or_insert_item_id(&tx, &audit, &key.0)
+                    .map_err(internal_error("Failed to get item from items table"))?;
+                tx.execute(
+                    "INSERT INTO flips (item_id, user_id) VALUES (?1, ?2)",
+                    params![item_id, user.id],
+                )
+                .map_err(internal_error("Failed to insert flip into flips table"))?;
+                let flip_id = tx.last_insert_rowid();
+                audit
+                    .record(&tx, Action::Insert, "flips", flip_id, None)
+                    .map_err(internal_error("Failed to write audit log"))?;
+                unclosed.insert(key.clone(), (flip_id, trade.quantity, 0));
+                flip_id
+            }
+            (None, false) => continue,
+        };
+
+        tx.execute(
+            "INSERT INTO flip_trades (flip_id, trade_id) VALUES (?1, ?2)",
+            params![flip_id, trade.id],
+        )
+        .map_err(internal_error(
+            "Failed to insert trade into flip_trades table",
+        ))?;
+        audit
+            .record(
+                &tx,
+                Action::Insert,
+                "flip_trades",
+                tx.last_insert_rowid(),
+                None,
+            )
+            .map_err(internal_error("Failed to write audit log"))?;
+        grouped_count += 1;
+
+        // Once everything bought has been sold the flip is closed and the next buy starts a new one
+        if let Some((_, bought, sold)) = unclosed.get(&key) {
+            if sold >= bought {
+                unclosed.remove(&key);
+            }
+        }
+    }
+
+    tx.commit()
+        .map_err(internal_error("Failed to commit transaction"))?;
+
+    info!(grouped_count, "Grouped trades into flips");
+
+    Ok(HttpResponse::Ok().json(grouped_count))
+}
+
+// Handle DELETE request for ungrouping a flip. The underlying trades are kept.
+#[utoipa::path(
+    tag = "flips",
+    summary = "Ungroup a flip",
+    responses(
+        (status = 200, description = "Flip ungrouped", body = String, content_type = "text/plain"),
+        (status = 404, description = "Flip not found"),
+    ),
+)]
+#[delete("/api/v1/flips/{id}")]
+pub async fn flips_delete(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    audit: Audit,
+    path: web::Path<i64>,
+) -> Result<impl Responder, Error> {
+    let id = path.into_inner();
+    debug!(flip_id = id, "Deleting flip");
+
+    let mut conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let tx = conn
+        .transaction()
+        .map_err(internal_error("Failed to start transaction"))?;
+
+    let owned: bool = tx
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM flips WHERE id = ?1 AND user_id = ?2)",
+            params![id, user.id],
+            |row| row.get(0),
+        )
+        .map_err(internal_error("Failed to get flip from flips table"))?;
+
+    if !owned {
+        return Err(ErrorNotFound("Flip not found"));
+    }
+
+    audit
+        .delete_rows(&tx, "flip_trades", "flip_id = ?1", &[&id])
+        .map_err(internal_error(
+            "Failed to delete trades from flip_trades table",
+        ))?;
+
+    audit
+        .delete_rows(&tx, "flips", "id = ?1", &[&id])
+        .map_err(internal_error("Failed to delete flip from flips table"))?;
+
+    tx.commit()
+        .map_err(internal_error("Failed to commit transaction"))?;
+
+    Ok(HttpResponse::Ok().body("Flip successfully ungrouped"))
+}
@@ -0,0 +1,278 @@
+use actix_web::error::{ErrorBadRequest, ErrorNotFound};
+use actix_web::{delete, get, post, web, Error, HttpResponse, Responder};
+use chrono::NaiveDateTime;
+use rusqlite::{params, Connection, Row};
+use shared::gp::{self, MAX_STACK};
+use tracing::debug;
+use utoipa::IntoParams;
+
+use crate::audit::{Action, Audit};
+use crate::auth::AuthenticatedUser;
+use crate::db::{self, datetime_from_unix, Database};
+use crate::ledger::{self, Ledger};
+use crate::models::{Goal, ItemData, NewGoal};
+use crate::repository::{TradeFilter, TradeRepository};
+use crate::routes::accounts::check_account_owner;
+use crate::routes::{internal_error, local_now, repository_error};
+
+// Goals can go past a stack of coins, since players keep more as platinum tokens, up to this
+const MAX_TARGET: i64 = 1_000 * MAX_STACK;
+
+const GOAL_SELECT: &str = "SELECT goals.id, goals.name, goals.target, goals.started_at, goals.deadline, items.name, goals.account_id
+    FROM goals LEFT JOIN items ON goals.item_id = items.id";
+
+// A goal as stored, before its progress is worked out
+struct StoredGoal {
+    id: i64,
+    name: String,
+    target: i64,
+    started_at: NaiveDateTime,
+    deadline: NaiveDateTime,
+    item_name: Option<String>,
+    account_id: Option<i64>,
+}
+
+fn stored_goal_from_row(row: &Row) -> rusqlite::Result<StoredGoal> {
+    Ok(StoredGoal {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        target: row.get(2)?,
+        started_at: datetime_from_unix(row.get(3)?).unwrap_or_default(),
+        deadline: datetime_from_unix(row.get(4)?).unwrap_or_default(),
+        item_name: row.get(5)?,
+        account_id: row.get(6)?,
+    })
+}
+
+// Work out how far along a goal is. Sales of the goal's item and by the goal's account count when
+// it has them.
+fn goal(goal: StoredGoal, ledger: &Ledger, now: NaiveDateTime) -> Goal {
+    let progress =
+        ledger::goal_progress(&ledger.sales, goal.target, goal.started_at, now, |sale| {
+            goal.item_name
+                .as_ref()
+                .is_none_or(|item_name| &sale.item_name == item_name)
+                && goal
+                    .account_id
+                    .is_none_or(|account_id| sale.account_id == Some(account_id))
+        });
+
+    Goal {
+        id: goal.id,
+        name: goal.name,
+        target: goal.target,
+        started_at: goal.started_at,
+        deadline: goal.deadline,
+        item_name: goal.item_name,
+        account_id: goal.account_id,
+        progress: progress.progress,
+        projected_completion: progress.projected_completion,
+    }
+}
+
+// Get the user's goals with their progress as of `now`, soonest deadline first, going by the
+// ledger of all their trades. It keeps accounts apart, so goals scoped to an account count the
+// same profit as that account's own trades make.
+pub(crate) fn query_goals(
+    conn: &Connection,
+    user: &AuthenticatedUser,
+    trades: &[ItemData],
+    now: NaiveDateTime,
+) -> Result<Vec<Goal>, Error> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} WHERE goals.user_id = ?1 ORDER BY goals.deadline, goals.id",
+            GOAL_SELECT
+        ))
+        .map_err(internal_error("Failed to prepare statement"))?;
+
+    let goals = stmt
+        .query_map([user.id], stored_goal_from_row)
+        .map_err(internal_error("Failed to query map"))?
+        .collect::<rusqlite::Result<Vec<StoredGoal>>>()
+        .map_err(internal_error("Failed to read goal row"))?;
+
+    let ledger = ledger::ledger(trades);
+    Ok(goals
+        .into_iter()
+        .map(|stored| goal(stored, &ledger, now))
+        .collect())
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GoalsQueryParams {
+    // The time it is where the trades are made, which progress is projected from.
+    // Defaults to the backend's local time.
+    #[param(example = "2024-01-31T13:45")]
+    now: Option<String>,
+}
+
+// Handle GET request for listing goals with their progress
+// Takes optional query parameter: now
+#[utoipa::path(
+    tag = "goals",
+    summary = "List goals",
+    params(GoalsQueryParams),
+    responses(
+        (status = 200, description = "Goals with their progress, soonest deadline first", body = Vec<Goal>),
+        (status = 400, description = "Invalid time"),
+    ),
+)]
+#[get("/api/v1/goals")]
+pub async fn goals_get(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    trades: web::Data<dyn TradeRepository>,
+    web::Query(query_params): web::Query<GoalsQueryParams>,
+) -> Result<impl Responder, Error> {
+    debug!(now = ?query_params.now, "Listing goals");
+
+    let now = local_now(query_params.now.as_deref())?;
+
+    let trades = trades
+        .list(&user, &TradeFilter::default())
+        .map_err(repository_error("Failed to list trades"))?;
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    Ok(HttpResponse::Ok().json(query_goals(&conn, &user, &trades, now)?))
+}
+
+// Handle POST request for adding a goal
+#[utoipa::path(
+    tag = "goals",
+    summary = "Add a goal",
+    request_body = NewGoal,
+    responses(
+        (status = 200, description = "The new goal with its progress so far", body = Goal),
+        (status = 400, description = "Empty name, target below 1 gp or above 1,000 stacks of coins, deadline before the start or unknown account"),
+    ),
+)]
+#[post("/api/v1/goals")]
+pub async fn goals_post(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    trades: web::Data<dyn TradeRepository>,
+    audit: Audit,
+    web::Json(new_goal): web::Json<NewGoal>,
+) -> Result<impl Responder, Error> {
+    debug!(?new_goal, "Adding goal");
+
+    let name = new_goal.name.trim();
+    if name.is_empty() {
+        return Err(ErrorBadRequest("Goal name must not be empty"));
+    }
+    if !(1..=MAX_TARGET).contains(&new_goal.target) {
+        return Err(ErrorBadRequest(format!(
+            "Target must be between 1 and {} gp",
+            gp::format(MAX_TARGET)
+        )));
+    }
+
+    let now = local_now(None)?;
+    let started_at = new_goal.started_at.unwrap_or(now);
+    if new_goal.deadline <= started_at {
+        return Err(ErrorBadRequest("Deadline must be after the goal starts"));
+    }
+
+    let item_name = new_goal
+        .item_name
+        .as_deref()
+        .map(str::trim)
+        .filter(|item_name| !item_name.is_empty());
+
+    let mut conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    check_account_owner(&conn, new_goal.account_id, &user)?;
+
+    let tx = conn
+        .transaction()
+        .map_err(internal_error("Failed to start transaction"))?;
+
+    let item_id = item_name
+        .map(|item_name| db::get_or_insert_item_id(&tx, &audit, item_name))
+        .transpose()
+        .map_err(internal_error("Failed to get item id"))?;
+
+    tx.execute(
+        "INSERT INTO goals (user_id, name, target, started_at, deadline, item_id, account_id)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            user.id,
+            name,
+            new_goal.target,
+            started_at.and_utc().timestamp(),
+            new_goal.deadline.and_utc().timestamp(),
+            item_id,
+            new_goal.account_id
+        ],
+    )
+    .map_err(internal_error("Failed to insert goal into goals table"))?;
+    let id = tx.last_insert_rowid();
+
+    audit
+        .record(&tx, Action::Insert, "goals", id, None)
+        .map_err(internal_error("Failed to write audit log"))?;
+
+    tx.commit()
+        .map_err(internal_error("Failed to commit transaction"))?;
+
+    let filter = TradeFilter {
+        account: new_goal.account_id,
+        ..TradeFilter::default()
+    };
+    let trades = trades
+        .list(&user, &filter)
+        .map_err(repository_error("Failed to list trades"))?;
+
+    let stored = StoredGoal {
+        id,
+        name: name.to_string(),
+        target: new_goal.target,
+        started_at,
+        deadline: new_goal.deadline,
+        item_name: item_name.map(str::to_string),
+        account_id: new_goal.account_id,
+    };
+
+    Ok(HttpResponse::Ok().json(goal(stored, &ledger::ledger(&trades), now.max(started_at))))
+}
+
+// Handle DELETE request for removing a goal
+#[utoipa::path(
+    tag = "goals",
+    summary = "Delete a goal",
+    responses(
+        (status = 200, description = "Goal deleted", body = String, content_type = "text/plain"),
+        (status = 404, description = "Goal not found"),
+    ),
+)]
+#[delete("/api/v1/goals/{id}")]
+pub async fn goals_delete(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    audit: Audit,
+    path: web::Path<i64>,
+) -> Result<impl Responder, Error> {
+    let id = path.into_inner();
+    debug!(goal_id = id, "Deleting goal");
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let deleted = audit
+        .delete_rows(&conn, "goals", "id = ?1 AND user_id = ?2", &[&id, &user.id])
+        .map_err(internal_error("Failed to delete goal from goals table"))?;
+
+    if deleted == 0 {
+        return Err(ErrorNotFound("Goal not found"));
+    }
+
+    Ok(HttpResponse::Ok().body("Goal successfully deleted"))
+}
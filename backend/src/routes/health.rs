@@ -0,0 +1,77 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use tracing::warn;
+
+use crate::db::{self, Database};
+use crate::models::{Readiness, ReadinessCheck};
+
+// Handle GET request for checking that the process is alive. It doesn't touch the database, so a
+// slow or locked database doesn't get the process restarted.
+#[utoipa::path(
+    tag = "health",
+    summary = "Check that the server is alive",
+    responses(
+        (status = 200, description = "The server is running", body = String, content_type = "text/plain"),
+    ),
+    security(()),
+)]
+#[get("/healthz")]
+pub async fn healthz_get() -> impl Responder {
+    HttpResponse::Ok().body("ok")
+}
+
+// Handle GET request for checking that the server can handle requests: the database can be
+// queried and every migration has been applied. There's no check of the price feed's freshness,
+// since ardy doesn't fetch prices yet and every price comes from the trades entered. A check
+// belongs here once a feed is added.
+#[utoipa::path(
+    tag = "health",
+    summary = "Check that the server is ready for requests",
+    description = "Checks that the database can be queried and every migration has been applied. \
+        Prices aren't fetched from a feed, so there's no price freshness check.",
+    responses(
+        (status = 200, description = "Every check passed", body = Readiness),
+        (status = 503, description = "Some checks failed", body = Readiness),
+    ),
+    security(()),
+)]
+#[get("/readyz")]
+pub async fn readyz_get(db: web::Data<Database>) -> impl Responder {
+    let conn = db.open();
+
+    let database = conn.as_ref().map_err(|e| e.to_string()).and_then(|conn| {
+        conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |_| Ok(()))
+            .map_err(|e| e.to_string())
+    });
+
+    let migrations = match &conn {
+        Ok(conn) => match db::pending_migrations(conn) {
+            Ok(0) => Ok(()),
+            Ok(pending) => Err(format!("{} migrations not applied", pending)),
+            Err(e) => Err(e.to_string()),
+        },
+        Err(_) => Err("Database unavailable".to_string()),
+    };
+
+    let checks: Vec<ReadinessCheck> = [("database", database), ("migrations", migrations)]
+        .into_iter()
+        .map(|(name, result)| {
+            if let Err(e) = &result {
+                warn!(check = name, error = %e, "Readiness check failed");
+            }
+            ReadinessCheck {
+                name: name.to_string(),
+                ok: result.is_ok(),
+                error: result.err(),
+            }
+        })
+        .collect();
+
+    let ready = checks.iter().all(|check| check.ok);
+    let readiness = Readiness { ready, checks };
+
+    if ready {
+        HttpResponse::Ok().json(readiness)
+    } else {
+        HttpResponse::ServiceUnavailable().json(readiness)
+    }
+}
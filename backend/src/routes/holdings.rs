@@ -0,0 +1,45 @@
+use actix_web::{get, web, Error, HttpResponse, Responder};
+use tracing::debug;
+use utoipa::IntoParams;
+
+use crate::auth::AuthenticatedUser;
+use crate::ledger;
+use crate::models::Holding;
+use crate::repository::{TradeFilter, TradeRepository};
+use crate::routes::repository_error;
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HoldingsQueryParams {
+    account: Option<i64>,
+}
+
+// Handle GET request for listing the items still held and what they cost
+// Takes optional query parameters: account
+#[utoipa::path(
+    tag = "trades",
+    summary = "List items held",
+    params(HoldingsQueryParams),
+    responses(
+        (status = 200, description = "Items held, by item name", body = Vec<Holding>),
+    ),
+)]
+#[get("/api/v1/holdings")]
+pub async fn holdings_get(
+    user: AuthenticatedUser,
+    trades: web::Data<dyn TradeRepository>,
+    web::Query(query_params): web::Query<HoldingsQueryParams>,
+) -> Result<impl Responder, Error> {
+    debug!("Listing holdings");
+
+    let filter = TradeFilter {
+        account: query_params.account,
+        ..TradeFilter::default()
+    };
+
+    let trades = trades
+        .list(&user, &filter)
+        .map_err(repository_error("Failed to list trades"))?;
+
+    Ok(HttpResponse::Ok().json(ledger::ledger(&trades).holdings))
+}
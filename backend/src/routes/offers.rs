@@ -0,0 +1,457 @@
+use std::sync::Mutex;
+
+use actix_web::error::{ErrorBadRequest, ErrorConflict, ErrorNotFound};
+use actix_web::{get, post, web, Error, HttpResponse, Responder};
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension, Row, TransactionBehavior};
+use shared::trade::TradeFields;
+use tracing::{debug, error};
+use utoipa::IntoParams;
+
+use crate::audit::{self, Action, Audit};
+use crate::auth::AuthenticatedUser;
+use crate::db::{self, Database};
+use crate::events::{Events, TradeEvent};
+use crate::models::{InvalidTrade, ItemData, NewOffer, Offer, OfferFill, OfferStatus};
+use crate::repository::{self, TradeRepository};
+use crate::routes::{internal_error, repository_error};
+
+// Number of Grand Exchange slots on a members account
+const GE_SLOTS: i64 = 8;
+
+// Held while an offer is filled
+static FILL_LOCK: Mutex<()> = Mutex::new(());
+
+// Selects offers along with the quantity filled so far, summed from the trades each fill created
+const OFFER_SELECT: &str = "SELECT offers.id, items.name, offers.slot, offers.is_purchase, offers.price, offers.quantity,
+    COALESCE((SELECT SUM(trades.quantity) FROM offer_fills INNER JOIN trades ON offer_fills.trade_id = trades.id WHERE offer_fills.offer_id = offers.id AND trades.deleted_at IS NULL), 0),
+    offers.status, offers.timestamp, offers.account_id
+    FROM offers INNER JOIN items ON offers.item_id = items.id";
+
+fn offer_from_row(row: &Row) -> rusqlite::Result<Offer> {
+    let status: String = row.get(7)?;
+
+    Ok(Offer {
+        id: row.get(0)?,
+        item_name: row.get(1)?,
+        slot: row.get(2)?,
+        is_purchase: row.get(3)?,
+        price: row.get(4)?,
+        quantity: row.get(5)?,
+        quantity_filled: row.get(6)?,
+        status: OfferStatus::parse(&status).ok_or_else(|| {
+            rusqlite::Error::InvalidColumnType(7, "status".to_string(), rusqlite::types::Type::Text)
+        })?,
+        timestamp: db::timestamp_from_row(row, 8)?,
+        account_id: row.get(9)?,
+    })
+}
+
+fn query_offer(conn: &Connection, id: i64, user: &AuthenticatedUser) -> Result<Offer, Error> {
+    conn.query_row(
+        &format!(
+            "{} WHERE offers.id = ?1 AND offers.user_id = ?2",
+            OFFER_SELECT
+        ),
+        params![id, user.id],
+        offer_from_row,
+    )
+    .optional()
+    .map_err(internal_error("Failed to get offer from offers table"))?
+    .ok_or_else(|| ErrorNotFound("Offer not found"))
+}
+
+fn set_offer_status(
+    conn: &Connection,
+    audit: &Audit,
+    id: i64,
+    status: OfferStatus,
+) -> Result<(), Error> {
+    let before = audit::snapshot(conn, "offers", id)
+        .map_err(internal_error("Failed to get offer from offers table"))?;
+
+    conn.execute(
+        "UPDATE offers SET status = ?1 WHERE id = ?2",
+        params![status.as_str(), id],
+    )
+    .map_err(internal_error("Failed to update offer status"))?;
+
+    audit
+        .record(conn, Action::Update, "offers", id, before)
+        .map_err(internal_error("Failed to write audit log"))?;
+
+    Ok(())
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OfferQueryParams {
+    #[param(value_type = Option<OfferStatus>)]
+    status: Option<String>,
+    account: Option<i64>,
+}
+
+// Handle GET request for listing offers
+// Takes optional query parameters: status (open, completed or cancelled), account
+#[utoipa::path(
+    tag = "offers",
+    summary = "List offers",
+    params(OfferQueryParams),
+    responses(
+        (status = 200, description = "Offers ordered by slot", body = Vec<Offer>),
+        (status = 400, description = "Invalid status"),
+    ),
+)]
+#[get("/api/v1/offer")]
+pub async fn offer_get(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    query_params: web::Query<OfferQueryParams>,
+) -> Result<impl Responder, Error> {
+    debug!("Listing offers");
+
+    let status = match &query_params.status {
+        Some(status) => Some(
+            OfferStatus::parse(status).ok_or_else(|| ErrorBadRequest("Invalid offer status"))?,
+        ),
+        None => None,
+    };
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} WHERE offers.user_id = ?3 AND (?1 IS NULL OR offers.status = ?1) AND (?2 IS NULL OR offers.account_id = ?2)
+            ORDER BY offers.slot, offers.id",
+            OFFER_SELECT
+        ))
+        .map_err(internal_error("Failed to prepare statement"))?;
+
+    let offers = stmt
+        .query_map(
+            params![
+                status.map(|status| status.as_str()),
+                query_params.account,
+                user.id
+            ],
+            offer_from_row,
+        )
+        .map_err(internal_error("Failed to query map"))?
+        .collect::<rusqlite::Result<Vec<Offer>>>()
+        .map_err(internal_error("Failed to read offer row"))?;
+
+    Ok(HttpResponse::Ok().json(offers))
+}
+
+// Handle POST request for placing a new offer in a GE slot
+// Each account has its own 8 slots
+#[utoipa::path(
+    tag = "offers",
+    summary = "Place an offer",
+    request_body = NewOffer,
+    responses(
+        (status = 200, description = "The new offer", body = Offer),
+        (status = 400, description = "Invalid offer, checked like a trade, or a plain text message for an invalid slot or account", body = InvalidTrade),
+        (status = 409, description = "Slot already has an open offer"),
+    ),
+)]
+#[post("/api/v1/offer")]
+pub async fn offer_post(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    audit: Audit,
+    web::Json(new_offer): web::Json<NewOffer>,
+) -> Result<impl Responder, Error> {
+    debug!(?new_offer, "Placing offer");
+
+    if !(1..=GE_SLOTS).contains(&new_offer.slot) {
+        return Err(ErrorBadRequest(format!(
+            "Slot must be between 1 and {}",
+            GE_SLOTS
+        )));
+    }
+
+    // Offers are in local time like trades, so their fills line up with trades added directly
+    let timestamp = new_offer
+        .timestamp
+        .unwrap_or_else(|| chrono::Local::now().naive_local());
+
+    // Fills become trades, so the offer has to make a valid trade when filled at once
+    repository::validate_fields(&TradeFields {
+        item_name: &new_offer.item_name,
+        quantity: new_offer.quantity,
+        price_each: new_offer.price,
+        timestamp,
+    })
+    .map_err(repository_error("Failed to validate offer"))?;
+
+    let mut conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(internal_error("Failed to start transaction"))?;
+
+    crate::routes::accounts::check_account_owner(&tx, new_offer.account_id, &user)?;
+
+    let slot_in_use: bool = tx
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM offers WHERE slot = ?1 AND status = ?2 AND account_id IS ?3 AND user_id = ?4)",
+            params![new_offer.slot, OfferStatus::Open.as_str(), new_offer.account_id, user.id],
+            |row| row.get(0),
+        )
+        .map_err(internal_error("Failed to check slot availability"))?;
+
+    let slot_taken = || ErrorConflict(format!("Slot {} already has an open offer", new_offer.slot));
+
+    if slot_in_use {
+        return Err(slot_taken());
+    }
+
+    let item_id = db::get_or_insert_item_id(&tx, &audit, &new_offer.item_name)
+        .map_err(internal_error("Failed to get item id from items table"))?;
+
+    // The open slot index backs up the check above, should another writer get in first anyway
+    tx.execute(
+        "INSERT INTO offers (item_id, slot, is_purchase, price, quantity, status, timestamp, account_id, user_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            item_id,
+            new_offer.slot,
+            new_offer.is_purchase,
+            new_offer.price,
+            new_offer.quantity,
+            OfferStatus::Open.as_str(),
+            timestamp.and_utc().timestamp(),
+            new_offer.account_id,
+            user.id,
+        ],
+    )
+    .map_err(|e| match e.sqlite_error_code() {
+        Some(ErrorCode::ConstraintViolation) => slot_taken(),
+        _ => internal_error("Failed to insert offer into offers table")(e),
+    })?;
+    let offer_id = tx.last_insert_rowid();
+
+    audit
+        .record(&tx, Action::Insert, "offers", offer_id, None)
+        .map_err(internal_error("Failed to write audit log"))?;
+
+    let offer = query_offer(&tx, offer_id, &user)?;
+
+    tx.commit()
+        .map_err(internal_error("Failed to commit transaction"))?;
+
+    Ok(HttpResponse::Ok().json(offer))
+}
+
+// Handle POST request for recording a (partial) fill of an open offer
+// Each fill creates the corresponding trade, and the offer completes once fully filled
+#[utoipa::path(
+    tag = "offers",
+    summary = "Fill an offer",
+    request_body = OfferFill,
+    responses(
+        (status = 200, description = "The offer after the fill", body = Offer),
+        (status = 400, description = "Invalid quantity, or a fill that makes an invalid trade", body = InvalidTrade),
+        (status = 404, description = "Offer not found"),
+        (status = 409, description = "Offer is not open"),
+    ),
+)]
+#[post("/api/v1/offer/{id}/fill")]
+pub async fn offer_fill_post(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    trades: web::Data<dyn TradeRepository>,
+    audit: Audit,
+    events: web::Data<Events>,
+    path: web::Path<i64>,
+    web::Json(fill): web::Json<OfferFill>,
+) -> Result<impl Responder, Error> {
+    let id = path.into_inner();
+    debug!(offer_id = id, ?fill, "Filling offer");
+
+    // Fills of any offer wait for each other, so two fills of the same offer can't both fill
+    // what's left. The trade is created through the repository on its own connection, so a
+    // database transaction can't cover both reading the offer and creating the trade.
+    let _fill = FILL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let offer = query_offer(&conn, id, &user)?;
+
+    if offer.status != OfferStatus::Open {
+        return Err(ErrorConflict("Offer is not open"));
+    }
+
+    let remaining = offer.quantity - offer.quantity_filled;
+    if fill.quantity <= 0 || fill.quantity > remaining {
+        return Err(ErrorBadRequest(format!(
+            "Fill quantity must be between 1 and {}",
+            remaining
+        )));
+    }
+
+    let trade = trades
+        .insert(
+            &audit,
+            &user,
+            &ItemData {
+                id: 0,
+                item_name: offer.item_name.clone(),
+                quantity: fill.quantity,
+                total_price: fill.price.unwrap_or(offer.price),
+                is_purchase: offer.is_purchase,
+                timestamp: fill
+                    .timestamp
+                    .unwrap_or_else(|| chrono::Local::now().naive_local()),
+                account_id: offer.account_id,
+                note: None,
+                session_id: None,
+                tags: Vec::new(),
+            },
+        )
+        .map_err(repository_error(
+            "Failed to insert trade data into trades table",
+        ))?;
+
+    // Take the trade back out if it can't be linked to the offer, so it isn't counted twice
+    // when the fill is retried
+    if let Err(e) = link_fill(&db, &audit, &user, id, trade.id, fill.quantity == remaining) {
+        if let Err(e) = trades.delete(&audit, &user, trade.id) {
+            error!(trade_id = trade.id, error = %e, "Failed to remove trade of a failed fill");
+        }
+        return Err(e);
+    }
+
+    events.send(user.id, TradeEvent::TradeCreated { trade });
+
+    let offer = query_offer(&conn, id, &user)?;
+
+    Ok(HttpResponse::Ok().json(offer))
+}
+
+// Record a trade as a fill of an offer, completing the offer if the fill is the last one
+fn link_fill(
+    db: &Database,
+    audit: &Audit,
+    user: &AuthenticatedUser,
+    offer_id: i64,
+    trade_id: i64,
+    completes: bool,
+) -> Result<(), Error> {
+    let mut conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(internal_error("Failed to start transaction"))?;
+
+    // The offer may have been cancelled or closed since the fill was checked
+    if query_offer(&tx, offer_id, user)?.status != OfferStatus::Open {
+        return Err(ErrorConflict("Offer is not open"));
+    }
+
+    tx.execute(
+        "INSERT INTO offer_fills (offer_id, trade_id) VALUES (?1, ?2)",
+        params![offer_id, trade_id],
+    )
+    .map_err(internal_error(
+        "Failed to insert fill into offer_fills table",
+    ))?;
+
+    audit
+        .record(
+            &tx,
+            Action::Insert,
+            "offer_fills",
+            tx.last_insert_rowid(),
+            None,
+        )
+        .map_err(internal_error("Failed to write audit log"))?;
+
+    if completes {
+        set_offer_status(&tx, audit, offer_id, OfferStatus::Completed)?;
+    }
+
+    tx.commit()
+        .map_err(internal_error("Failed to commit transaction"))
+}
+
+// Close an open offer with the given status, freeing its slot
+fn close_offer(
+    db: &Database,
+    id: i64,
+    status: OfferStatus,
+    user: &AuthenticatedUser,
+    audit: &Audit,
+) -> Result<Offer, Error> {
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let offer = query_offer(&conn, id, user)?;
+
+    if offer.status != OfferStatus::Open {
+        return Err(ErrorConflict("Offer is not open"));
+    }
+
+    set_offer_status(&conn, audit, id, status)?;
+
+    query_offer(&conn, id, user)
+}
+
+// Handle POST request for cancelling an open offer
+// Trades already created by fills are kept
+#[utoipa::path(
+    tag = "offers",
+    summary = "Cancel an offer",
+    responses(
+        (status = 200, description = "The cancelled offer", body = Offer),
+        (status = 404, description = "Offer not found"),
+        (status = 409, description = "Offer is not open"),
+    ),
+)]
+#[post("/api/v1/offer/{id}/cancel")]
+pub async fn offer_cancel_post(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    audit: Audit,
+    path: web::Path<i64>,
+) -> Result<impl Responder, Error> {
+    let id = path.into_inner();
+    debug!(offer_id = id, "Cancelling offer");
+
+    let offer = close_offer(&db, id, OfferStatus::Cancelled, &user, &audit)?;
+
+    Ok(HttpResponse::Ok().json(offer))
+}
+
+// Handle POST request for marking an open offer as completed, e.g. after collecting a partial fill
+#[utoipa::path(
+    tag = "offers",
+    summary = "Complete an offer",
+    responses(
+        (status = 200, description = "The completed offer", body = Offer),
+        (status = 404, description = "Offer not found"),
+        (status = 409, description = "Offer is not open"),
+    ),
+)]
+#[post("/api/v1/offer/{id}/complete")]
+pub async fn offer_complete_post(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    audit: Audit,
+    path: web::Path<i64>,
+) -> Result<impl Responder, Error> {
+    let id = path.into_inner();
+    debug!(offer_id = id, "Completing offer");
+
+    let offer = close_offer(&db, id, OfferStatus::Completed, &user, &audit)?;
+
+    Ok(HttpResponse::Ok().json(offer))
+}
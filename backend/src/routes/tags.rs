@@ -0,0 +1,444 @@
+use actix_web::error::{ErrorBadRequest, ErrorConflict, ErrorNotFound};
+use actix_web::{delete, get, post, put, web, Error, HttpResponse, Responder};
+use rusqlite::{params, Connection, ErrorCode};
+use tracing::debug;
+use utoipa::IntoParams;
+
+use crate::audit::{self, Action, Audit};
+use crate::auth::AuthenticatedUser;
+use crate::db::{self, Database};
+use crate::events::{Events, TradeEvent};
+use crate::models::{ItemData, NewTag, Tag, TagProfitLoss};
+use crate::repository::normalize_tags;
+use crate::routes::internal_error;
+
+// Map a failed insert or update of a tag name, reporting duplicate names as a conflict
+fn tag_name_error(e: rusqlite::Error) -> Error {
+    match e {
+        rusqlite::Error::SqliteFailure(ref err, _)
+            if err.code == ErrorCode::ConstraintViolation =>
+        {
+            ErrorConflict("A tag with that name already exists")
+        }
+        e => internal_error("Failed to save tag")(e),
+    }
+}
+
+fn validate_tag_name(name: &str) -> Result<String, Error> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ErrorBadRequest("Tag name must not be empty"));
+    }
+    Ok(name.to_string())
+}
+
+// Get the id of one of the user's tags by name, creating the tag if it doesn't exist yet
+fn get_or_insert_tag_id(
+    conn: &Connection,
+    audit: &Audit,
+    user: &AuthenticatedUser,
+    name: &str,
+) -> rusqlite::Result<i64> {
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO tags (user_id, name) VALUES (?1, ?2)",
+        params![user.id, name],
+    )?;
+    if inserted > 0 {
+        audit.record(conn, Action::Insert, "tags", conn.last_insert_rowid(), None)?;
+    }
+
+    conn.query_row(
+        "SELECT id FROM tags WHERE user_id = ?1 AND name = ?2",
+        params![user.id, name],
+        |row| row.get(0),
+    )
+}
+
+// Replace the tags of a trade with the given names, creating tags that don't exist yet.
+// Blank names are ignored. The caller must check that the trade belongs to the user.
+pub(crate) fn set_trade_tags(
+    conn: &Connection,
+    audit: &Audit,
+    user: &AuthenticatedUser,
+    trade_id: i64,
+    names: &[String],
+) -> rusqlite::Result<()> {
+    let names = normalize_tags(names);
+
+    let names_json = serde_json::to_string(&names).unwrap_or_default();
+
+    audit.delete_rows(
+        conn,
+        "trade_tags",
+        "trade_id = ?1 AND tag_id NOT IN (
+            SELECT tags.id FROM tags, json_each(?2) WHERE tags.user_id = ?3 AND tags.name = json_each.value
+        )",
+        &[&trade_id, &names_json, &user.id],
+    )?;
+
+    for name in &names {
+        let tag_id = get_or_insert_tag_id(conn, audit, user, name)?;
+
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO trade_tags (trade_id, tag_id) VALUES (?1, ?2)",
+            params![trade_id, tag_id],
+        )?;
+        if inserted > 0 {
+            audit.record(
+                conn,
+                Action::Insert,
+                "trade_tags",
+                conn.last_insert_rowid(),
+                None,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+// Get the trades outside the trash that have a tag, so renaming or deleting the tag can send
+// update events for them
+fn tagged_trades(conn: &Connection, tag_id: i64) -> rusqlite::Result<Vec<ItemData>> {
+    let mut stmt = conn.prepare(
+        "SELECT trade_tags.trade_id FROM trade_tags
+        INNER JOIN trades ON trade_tags.trade_id = trades.id
+        WHERE trade_tags.tag_id = ?1 AND trades.deleted_at IS NULL",
+    )?;
+    let trade_ids = stmt
+        .query_map([tag_id], |row| row.get::<_, i64>(0))?
+        .collect::<rusqlite::Result<Vec<i64>>>()?;
+
+    let mut trades = Vec::new();
+    for trade_id in trade_ids {
+        trades.extend(db::query_trade(conn, trade_id)?);
+    }
+    Ok(trades)
+}
+
+// Send update events for trades whose tags changed, given the trades as they were before
+fn send_trade_updates(
+    conn: &Connection,
+    events: &Events,
+    user: &AuthenticatedUser,
+    before: Vec<ItemData>,
+) -> rusqlite::Result<()> {
+    for before in before {
+        if let Some(trade) = db::query_trade(conn, before.id)? {
+            events.send(user.id, TradeEvent::TradeUpdated { before, trade });
+        }
+    }
+    Ok(())
+}
+
+// Handle GET request for listing tags along with how many trades have them
+#[utoipa::path(
+    tag = "tags",
+    summary = "List tags",
+    responses(
+        (status = 200, description = "Tags with their trade counts", body = Vec<Tag>),
+    ),
+)]
+#[get("/api/v1/tags")]
+pub async fn tags_get(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+) -> Result<impl Responder, Error> {
+    debug!("Listing tags");
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT tags.id, tags.name, COUNT(trades.id)
+            FROM tags
+            LEFT JOIN trade_tags ON trade_tags.tag_id = tags.id
+            LEFT JOIN trades ON trades.id = trade_tags.trade_id AND trades.deleted_at IS NULL
+            WHERE tags.user_id = ?1
+            GROUP BY tags.id
+            ORDER BY tags.name",
+        )
+        .map_err(internal_error("Failed to prepare statement"))?;
+
+    let tags = stmt
+        .query_map([user.id], |row| {
+            Ok(Tag {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                trade_count: row.get(2)?,
+            })
+        })
+        .map_err(internal_error("Failed to query map"))?
+        .collect::<rusqlite::Result<Vec<Tag>>>()
+        .map_err(internal_error("Failed to read tag row"))?;
+
+    Ok(HttpResponse::Ok().json(tags))
+}
+
+// Handle POST request for adding a tag
+#[utoipa::path(
+    tag = "tags",
+    summary = "Add a tag",
+    request_body = NewTag,
+    responses(
+        (status = 200, description = "The new tag", body = Tag),
+        (status = 400, description = "Empty name"),
+        (status = 409, description = "Name already taken"),
+    ),
+)]
+#[post("/api/v1/tags")]
+pub async fn tags_post(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    audit: Audit,
+    web::Json(new_tag): web::Json<NewTag>,
+) -> Result<impl Responder, Error> {
+    debug!(?new_tag, "Adding tag");
+
+    let name = validate_tag_name(&new_tag.name)?;
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    conn.execute(
+        "INSERT INTO tags (user_id, name) VALUES (?1, ?2)",
+        params![user.id, name],
+    )
+    .map_err(tag_name_error)?;
+    let id = conn.last_insert_rowid();
+
+    audit
+        .record(&conn, Action::Insert, "tags", id, None)
+        .map_err(internal_error("Failed to write audit log"))?;
+
+    Ok(HttpResponse::Ok().json(Tag {
+        id,
+        name,
+        trade_count: 0,
+    }))
+}
+
+// Handle PUT request for renaming a tag
+#[utoipa::path(
+    tag = "tags",
+    summary = "Rename a tag",
+    request_body = NewTag,
+    responses(
+        (status = 200, description = "Tag renamed", body = String, content_type = "text/plain"),
+        (status = 400, description = "Empty name"),
+        (status = 404, description = "Tag not found"),
+        (status = 409, description = "Name already taken"),
+    ),
+)]
+#[put("/api/v1/tags/{id}")]
+pub async fn tags_put(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    audit: Audit,
+    events: web::Data<Events>,
+    path: web::Path<i64>,
+    web::Json(new_tag): web::Json<NewTag>,
+) -> Result<impl Responder, Error> {
+    let id = path.into_inner();
+    debug!(tag_id = id, ?new_tag, "Renaming tag");
+
+    let name = validate_tag_name(&new_tag.name)?;
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let before = audit::snapshot(&conn, "tags", id)
+        .map_err(internal_error("Failed to get tag from tags table"))?;
+    let trades = tagged_trades(&conn, id).map_err(internal_error("Failed to get tagged trades"))?;
+
+    let updated = conn
+        .execute(
+            "UPDATE tags SET name = ?1 WHERE id = ?2 AND user_id = ?3",
+            params![name, id, user.id],
+        )
+        .map_err(tag_name_error)?;
+
+    if updated == 0 {
+        return Err(ErrorNotFound("Tag not found"));
+    }
+
+    audit
+        .record(&conn, Action::Update, "tags", id, before)
+        .map_err(internal_error("Failed to write audit log"))?;
+
+    send_trade_updates(&conn, &events, &user, trades)
+        .map_err(internal_error("Failed to get tagged trades"))?;
+
+    Ok(HttpResponse::Ok().body("Tag successfully renamed"))
+}
+
+// Handle DELETE request for removing a tag from all trades and deleting it
+#[utoipa::path(
+    tag = "tags",
+    summary = "Delete a tag",
+    responses(
+        (status = 200, description = "Tag deleted", body = String, content_type = "text/plain"),
+        (status = 404, description = "Tag not found"),
+    ),
+)]
+#[delete("/api/v1/tags/{id}")]
+pub async fn tags_delete(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    audit: Audit,
+    events: web::Data<Events>,
+    path: web::Path<i64>,
+) -> Result<impl Responder, Error> {
+    let id = path.into_inner();
+    debug!(tag_id = id, "Deleting tag");
+
+    let mut conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+    let tx = conn
+        .transaction()
+        .map_err(internal_error("Failed to start transaction"))?;
+
+    let trades = tagged_trades(&tx, id).map_err(internal_error("Failed to get tagged trades"))?;
+
+    audit
+        .delete_rows(
+            &tx,
+            "trade_tags",
+            "tag_id IN (SELECT id FROM tags WHERE id = ?1 AND user_id = ?2)",
+            &[&id, &user.id],
+        )
+        .map_err(internal_error("Failed to delete tag from trades"))?;
+
+    let deleted = audit
+        .delete_rows(&tx, "tags", "id = ?1 AND user_id = ?2", &[&id, &user.id])
+        .map_err(internal_error("Failed to delete tag from tags table"))?;
+
+    if deleted == 0 {
+        return Err(ErrorNotFound("Tag not found"));
+    }
+
+    tx.commit()
+        .map_err(internal_error("Failed to commit transaction"))?;
+
+    send_trade_updates(&conn, &events, &user, trades)
+        .map_err(internal_error("Failed to get tagged trades"))?;
+
+    Ok(HttpResponse::Ok().body("Tag successfully deleted"))
+}
+
+// Handle PUT request for replacing the tags of a trade
+// Takes a list of tag names, creating the ones that don't exist yet
+#[utoipa::path(
+    tag = "tags",
+    summary = "Replace the tags of a trade",
+    request_body = Vec<String>,
+    responses(
+        (status = 200, description = "Tags saved", body = String, content_type = "text/plain"),
+        (status = 404, description = "Trade not found"),
+    ),
+)]
+#[put("/api/v1/trade/{id}/tags")]
+pub async fn trade_tags_put(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    audit: Audit,
+    events: web::Data<Events>,
+    path: web::Path<i64>,
+    web::Json(names): web::Json<Vec<String>>,
+) -> Result<impl Responder, Error> {
+    let id = path.into_inner();
+    debug!(trade_id = id, ?names, "Setting trade tags");
+
+    let mut conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+    let tx = conn
+        .transaction()
+        .map_err(internal_error("Failed to start transaction"))?;
+
+    let owned: bool = tx
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM trades WHERE id = ?1 AND user_id = ?2 AND deleted_at IS NULL)",
+            params![id, user.id],
+            |row| row.get(0),
+        )
+        .map_err(internal_error("Failed to get trade from trades table"))?;
+
+    if !owned {
+        return Err(ErrorNotFound("Trade not found"));
+    }
+
+    let before = db::query_trade(&tx, id)
+        .map_err(internal_error("Failed to get trade from trades table"))?;
+
+    set_trade_tags(&tx, &audit, &user, id, &names)
+        .map_err(internal_error("Failed to set trade tags"))?;
+
+    tx.commit()
+        .map_err(internal_error("Failed to commit transaction"))?;
+
+    send_trade_updates(&conn, &events, &user, before.into_iter().collect())
+        .map_err(internal_error("Failed to get trade from trades table"))?;
+
+    Ok(HttpResponse::Ok().body("Trade tags successfully saved"))
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TagProfitLossQueryParams {
+    account: Option<i64>,
+}
+
+// Handle GET request for profit/loss broken down by tag
+// Takes optional query parameters: account
+#[utoipa::path(
+    tag = "tags",
+    summary = "Get profit/loss by tag",
+    params(TagProfitLossQueryParams),
+    responses(
+        (status = 200, description = "Profit/loss per tag, untagged trades last", body = Vec<TagProfitLoss>),
+    ),
+)]
+#[get("/api/v1/profit_loss/tags")]
+pub async fn profit_loss_tags_get(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    query_params: web::Query<TagProfitLossQueryParams>,
+) -> Result<impl Responder, Error> {
+    debug!("Calculating profit/loss by tag");
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    // Same sign convention as the total profit/loss: sales count positive, purchases negative
+    let mut stmt = conn
+        .prepare(
+            "SELECT tags.name, SUM(trades.quantity * trades.total_price * (CASE WHEN trades.is_purchase = 0 THEN 1 ELSE -1 END))
+            FROM trades
+            LEFT JOIN trade_tags ON trade_tags.trade_id = trades.id
+            LEFT JOIN tags ON tags.id = trade_tags.tag_id
+            WHERE trades.user_id = ?1 AND trades.deleted_at IS NULL AND (?2 IS NULL OR trades.account_id = ?2)
+            GROUP BY tags.name
+            ORDER BY tags.name IS NULL, tags.name",
+        )
+        .map_err(internal_error("Failed to prepare statement"))?;
+
+    let profit_loss = stmt
+        .query_map(params![user.id, query_params.account], |row| {
+            Ok(TagProfitLoss {
+                tag: row.get(0)?,
+                profit_loss: row.get(1)?,
+            })
+        })
+        .map_err(internal_error("Failed to query map"))?
+        .collect::<rusqlite::Result<Vec<TagProfitLoss>>>()
+        .map_err(internal_error("Failed to read profit/loss row"))?;
+
+    Ok(HttpResponse::Ok().json(profit_loss))
+}
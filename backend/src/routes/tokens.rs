@@ -0,0 +1,204 @@
+use actix_web::error::{ErrorBadRequest, ErrorNotFound};
+use actix_web::{delete, get, post, web, Error, HttpResponse, Responder};
+use chrono::TimeDelta;
+use rusqlite::{params, Row};
+use tracing::debug;
+
+use crate::audit::{Action, Audit};
+use crate::auth::{self, AuthenticatedUser};
+use crate::db::{datetime_from_unix, Database};
+use crate::models::{ApiToken, CreatedApiToken, NewApiToken, Scope};
+use crate::routes::internal_error;
+
+// Tokens expire within ten years at the most
+const MAX_EXPIRY_DAYS: i64 = 3650;
+
+const API_TOKEN_SELECT: &str =
+    "SELECT id, name, scopes, created_at, expires_at, last_used_at FROM api_tokens";
+
+fn api_token_from_row(row: &Row) -> rusqlite::Result<ApiToken> {
+    let scopes: String = row.get(2)?;
+
+    Ok(ApiToken {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        scopes: scopes.split(',').filter_map(Scope::parse).collect(),
+        created_at: datetime_from_unix(row.get(3)?).unwrap_or_default(),
+        expires_at: row.get::<_, Option<i64>>(4)?.and_then(datetime_from_unix),
+        last_used_at: row.get::<_, Option<i64>>(5)?.and_then(datetime_from_unix),
+    })
+}
+
+// Handle GET request for listing the user's API tokens
+#[utoipa::path(
+    tag = "tokens",
+    summary = "List API tokens",
+    responses(
+        (status = 200, description = "The user's API tokens", body = Vec<ApiToken>),
+    ),
+)]
+#[get("/api/v1/tokens")]
+pub async fn tokens_get(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+) -> Result<impl Responder, Error> {
+    debug!("Listing API tokens");
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} WHERE user_id = ?1 ORDER BY id",
+            API_TOKEN_SELECT
+        ))
+        .map_err(internal_error("Failed to prepare statement"))?;
+
+    let api_tokens = stmt
+        .query_map([user.id], api_token_from_row)
+        .map_err(internal_error("Failed to query map"))?
+        .collect::<rusqlite::Result<Vec<ApiToken>>>()
+        .map_err(internal_error("Failed to read API token row"))?;
+
+    Ok(HttpResponse::Ok().json(api_tokens))
+}
+
+// Handle POST request for creating an API token
+// The token is only returned here, only its hash is stored
+#[utoipa::path(
+    tag = "tokens",
+    summary = "Create an API token",
+    request_body = NewApiToken,
+    responses(
+        (status = 200, description = "The new token, only shown this once", body = CreatedApiToken),
+        (status = 400, description = "Empty name, invalid scopes or expiry outside 1 to 3650 days"),
+    ),
+)]
+#[post("/api/v1/tokens")]
+pub async fn tokens_post(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    audit: Audit,
+    web::Json(new_api_token): web::Json<NewApiToken>,
+) -> Result<impl Responder, Error> {
+    debug!(?new_api_token, "Creating API token");
+
+    let name = new_api_token.name.trim();
+    if name.is_empty() {
+        return Err(ErrorBadRequest("Token name must not be empty"));
+    }
+
+    let mut scopes = new_api_token.scopes;
+    scopes.sort_unstable();
+    scopes.dedup();
+    if scopes.is_empty() {
+        return Err(ErrorBadRequest("A token needs at least one scope"));
+    }
+
+    // A token can't be given more access than the request creating it has
+    if let Some(scope) = scopes.iter().find(|scope| !user.has_scope(**scope)) {
+        return Err(ErrorBadRequest(format!(
+            "Can't grant the {} scope",
+            scope.as_str()
+        )));
+    }
+
+    let now = chrono::Utc::now();
+    let expires_at = match new_api_token.expires_in_days {
+        Some(days) => Some(
+            Some(days)
+                .filter(|days| (1..=MAX_EXPIRY_DAYS).contains(days))
+                .and_then(TimeDelta::try_days)
+                .and_then(|expires_in| now.checked_add_signed(expires_in))
+                .ok_or_else(|| {
+                    ErrorBadRequest(format!(
+                        "Expiry must be between 1 and {} days",
+                        MAX_EXPIRY_DAYS
+                    ))
+                })?
+                .timestamp(),
+        ),
+        None => None,
+    };
+    let token = auth::generate_api_token();
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    conn.execute(
+        "INSERT INTO api_tokens (user_id, name, token_hash, scopes, created_at, expires_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            user.id,
+            name,
+            auth::hash_token(&token),
+            scopes
+                .iter()
+                .map(|scope| scope.as_str())
+                .collect::<Vec<&str>>()
+                .join(","),
+            now.timestamp(),
+            expires_at,
+        ],
+    )
+    .map_err(internal_error("Failed to insert API token into api_tokens table"))?;
+    let id = conn.last_insert_rowid();
+
+    audit
+        .record(&conn, Action::Insert, "api_tokens", id, None)
+        .map_err(internal_error("Failed to write audit log"))?;
+
+    let api_token = conn
+        .query_row(
+            &format!("{} WHERE id = ?1", API_TOKEN_SELECT),
+            [id],
+            api_token_from_row,
+        )
+        .map_err(internal_error(
+            "Failed to get API token from api_tokens table",
+        ))?;
+
+    Ok(HttpResponse::Ok().json(CreatedApiToken { token, api_token }))
+}
+
+// Handle DELETE request for revoking an API token
+#[utoipa::path(
+    tag = "tokens",
+    summary = "Revoke an API token",
+    responses(
+        (status = 200, description = "Token revoked", body = String, content_type = "text/plain"),
+        (status = 404, description = "Token not found"),
+    ),
+)]
+#[delete("/api/v1/tokens/{id}")]
+pub async fn tokens_delete(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    audit: Audit,
+    path: web::Path<i64>,
+) -> Result<impl Responder, Error> {
+    let id = path.into_inner();
+    debug!(token_id = id, "Revoking API token");
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let deleted = audit
+        .delete_rows(
+            &conn,
+            "api_tokens",
+            "id = ?1 AND user_id = ?2",
+            &[&id, &user.id],
+        )
+        .map_err(internal_error(
+            "Failed to delete API token from api_tokens table",
+        ))?;
+
+    if deleted == 0 {
+        return Err(ErrorNotFound("API token not found"));
+    }
+
+    Ok(HttpResponse::Ok().body("API token successfully revoked"))
+}
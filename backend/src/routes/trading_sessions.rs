@@ -0,0 +1,332 @@
+use actix_web::error::{ErrorBadRequest, ErrorConflict, ErrorNotFound};
+use actix_web::{get, post, web, Error, HttpResponse, Responder};
+use chrono::NaiveDateTime;
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension, Row};
+use tracing::{debug, info};
+use utoipa::IntoParams;
+
+use crate::audit::{self, Action, Audit};
+use crate::auth::AuthenticatedUser;
+use crate::db::{datetime_from_unix, Database};
+use crate::ledger::{self, Ledger};
+use crate::models::{ItemData, RecentSession, TradingSession};
+use crate::repository::{TradeFilter, TradeRepository};
+use crate::routes::dashboard::recent_session;
+use crate::routes::{internal_error, local_now, repository_error};
+
+const TRADING_SESSION_SELECT: &str = "SELECT id, started_at, ended_at FROM trading_sessions";
+
+// A trading session as stored, before its trades are totalled up
+struct StoredSession {
+    id: i64,
+    started_at: NaiveDateTime,
+    ended_at: Option<NaiveDateTime>,
+}
+
+fn stored_session_from_row(row: &Row) -> rusqlite::Result<StoredSession> {
+    Ok(StoredSession {
+        id: row.get(0)?,
+        started_at: datetime_from_unix(row.get(1)?).unwrap_or_default(),
+        ended_at: row.get::<_, Option<i64>>(2)?.and_then(datetime_from_unix),
+    })
+}
+
+// Get the id of the user's trading session in progress, which new trades join
+pub(crate) fn session_in_progress(
+    conn: &Connection,
+    user: &AuthenticatedUser,
+) -> rusqlite::Result<Option<i64>> {
+    conn.query_row(
+        "SELECT id FROM trading_sessions WHERE user_id = ?1 AND ended_at IS NULL",
+        [user.id],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+fn query_session(
+    conn: &Connection,
+    user: &AuthenticatedUser,
+    id: i64,
+) -> Result<StoredSession, Error> {
+    conn.query_row(
+        &format!("{} WHERE id = ?1 AND user_id = ?2", TRADING_SESSION_SELECT),
+        params![id, user.id],
+        stored_session_from_row,
+    )
+    .optional()
+    .map_err(internal_error(
+        "Failed to get trading session from trading_sessions table",
+    ))?
+    .ok_or_else(|| ErrorNotFound("Trading session not found"))
+}
+
+// Total up a session from the trades added while it was in progress, which runs until now if it
+// hasn't been stopped
+fn trading_session(
+    session: StoredSession,
+    trades: &[ItemData],
+    ledger: &Ledger,
+    now: NaiveDateTime,
+) -> TradingSession {
+    let totals = ledger::session_totals(
+        trades
+            .iter()
+            .filter(|trade| trade.session_id == Some(session.id)),
+        ledger,
+    );
+    let duration = session.ended_at.unwrap_or(now) - session.started_at;
+
+    TradingSession {
+        id: session.id,
+        started_at: session.started_at,
+        ended_at: session.ended_at,
+        trade_count: totals.trade_count,
+        profit: totals.profit,
+        capital_used: totals.capital_used,
+        gp_per_hour: ledger::gp_per_hour(totals.profit, duration),
+    }
+}
+
+fn list_trades(
+    trades: &dyn TradeRepository,
+    user: &AuthenticatedUser,
+    account: Option<i64>,
+) -> Result<Vec<ItemData>, Error> {
+    let filter = TradeFilter {
+        account,
+        ..TradeFilter::default()
+    };
+    trades
+        .list(user, &filter)
+        .map_err(repository_error("Failed to list trades"))
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TradingSessionsQueryParams {
+    account: Option<i64>,
+    // The time it is where the trades are made, which sessions in progress run until.
+    // Defaults to the backend's local time.
+    #[param(example = "2024-01-31T13:45")]
+    now: Option<String>,
+}
+
+// Handle GET request for listing trading sessions, the most recent first
+// Takes optional query parameters: account, now
+#[utoipa::path(
+    tag = "trading sessions",
+    summary = "List trading sessions",
+    params(TradingSessionsQueryParams),
+    responses(
+        (status = 200, description = "Sessions with the profit, trade count, capital used and GP/hour of their trades", body = Vec<TradingSession>),
+        (status = 400, description = "Invalid time"),
+    ),
+)]
+#[get("/api/v1/trading_sessions")]
+pub async fn trading_sessions_get(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    trades: web::Data<dyn TradeRepository>,
+    web::Query(query_params): web::Query<TradingSessionsQueryParams>,
+) -> Result<impl Responder, Error> {
+    debug!(account = ?query_params.account, "Listing trading sessions");
+
+    let now = local_now(query_params.now.as_deref())?;
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} WHERE user_id = ?1 ORDER BY started_at DESC, id DESC",
+            TRADING_SESSION_SELECT
+        ))
+        .map_err(internal_error("Failed to prepare statement"))?;
+
+    let sessions = stmt
+        .query_map([user.id], stored_session_from_row)
+        .map_err(internal_error("Failed to query map"))?
+        .collect::<rusqlite::Result<Vec<StoredSession>>>()
+        .map_err(internal_error("Failed to read trading session row"))?;
+
+    let trades = list_trades(trades.as_ref(), &user, query_params.account)?;
+    let ledger = ledger::ledger(&trades);
+
+    let sessions: Vec<TradingSession> = sessions
+        .into_iter()
+        .map(|session| trading_session(session, &trades, &ledger, now))
+        .collect();
+
+    Ok(HttpResponse::Ok().json(sessions))
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct InferredSessionsQueryParams {
+    account: Option<i64>,
+}
+
+// Handle GET request for sessions inferred from the gaps between trades, the most recent first.
+// Covers every trade, whether or not it was added during a trading session.
+// Takes optional query parameter: account
+#[utoipa::path(
+    tag = "trading sessions",
+    summary = "Infer trading sessions from the gaps between trades",
+    params(InferredSessionsQueryParams),
+    responses(
+        (status = 200, description = "Stretches of trades with no gap longer than an hour", body = Vec<RecentSession>),
+    ),
+)]
+#[get("/api/v1/trading_sessions/inferred")]
+pub async fn trading_sessions_inferred_get(
+    user: AuthenticatedUser,
+    trades: web::Data<dyn TradeRepository>,
+    web::Query(query_params): web::Query<InferredSessionsQueryParams>,
+) -> Result<impl Responder, Error> {
+    debug!(account = ?query_params.account, "Inferring trading sessions");
+
+    let trades = list_trades(trades.as_ref(), &user, query_params.account)?;
+    let ledger = ledger::ledger(&trades);
+
+    let sessions: Vec<RecentSession> = ledger::infer_sessions(&trades)
+        .iter()
+        .rev()
+        .map(|session| recent_session(session, &trades, &ledger))
+        .collect();
+
+    Ok(HttpResponse::Ok().json(sessions))
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NowQueryParams {
+    // The time it is where the trades are made. Defaults to the backend's local time.
+    #[param(example = "2024-01-31T13:45")]
+    now: Option<String>,
+}
+
+// Handle POST request for starting a trading session. Trades added until it's stopped belong to it.
+// Takes optional query parameter: now
+#[utoipa::path(
+    tag = "trading sessions",
+    summary = "Start a trading session",
+    params(NowQueryParams),
+    responses(
+        (status = 200, description = "The new session", body = TradingSession),
+        (status = 400, description = "Invalid time"),
+        (status = 409, description = "A session is already in progress"),
+    ),
+)]
+#[post("/api/v1/trading_sessions")]
+pub async fn trading_sessions_post(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    audit: Audit,
+    web::Query(query_params): web::Query<NowQueryParams>,
+) -> Result<impl Responder, Error> {
+    let started_at = local_now(query_params.now.as_deref())?;
+    debug!(%started_at, "Starting trading session");
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    // Only one session can be in progress, which the database enforces
+    conn.execute(
+        "INSERT INTO trading_sessions (user_id, started_at) VALUES (?1, ?2)",
+        params![user.id, started_at.and_utc().timestamp()],
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::SqliteFailure(ref err, _)
+            if err.code == ErrorCode::ConstraintViolation =>
+        {
+            ErrorConflict("A trading session is already in progress")
+        }
+        e => internal_error("Failed to insert trading session into trading_sessions table")(e),
+    })?;
+    let id = conn.last_insert_rowid();
+
+    audit
+        .record(&conn, Action::Insert, "trading_sessions", id, None)
+        .map_err(internal_error("Failed to write audit log"))?;
+
+    info!(session_id = id, "Trading session started");
+
+    Ok(HttpResponse::Ok().json(TradingSession {
+        id,
+        started_at,
+        ended_at: None,
+        trade_count: 0,
+        profit: 0,
+        capital_used: 0,
+        gp_per_hour: None,
+    }))
+}
+
+// Handle POST request for stopping a trading session in progress
+// Takes optional query parameter: now
+#[utoipa::path(
+    tag = "trading sessions",
+    summary = "Stop a trading session",
+    params(NowQueryParams),
+    responses(
+        (status = 200, description = "The stopped session with its totals", body = TradingSession),
+        (status = 400, description = "Invalid time, or earlier than the session started"),
+        (status = 404, description = "Trading session not found"),
+        (status = 409, description = "Trading session already stopped"),
+    ),
+)]
+#[post("/api/v1/trading_sessions/{id}/stop")]
+pub async fn trading_session_stop_post(
+    user: AuthenticatedUser,
+    db: web::Data<Database>,
+    trades: web::Data<dyn TradeRepository>,
+    audit: Audit,
+    path: web::Path<i64>,
+    web::Query(query_params): web::Query<NowQueryParams>,
+) -> Result<impl Responder, Error> {
+    let id = path.into_inner();
+    let ended_at = local_now(query_params.now.as_deref())?;
+    debug!(session_id = id, %ended_at, "Stopping trading session");
+
+    let conn = db
+        .open()
+        .map_err(internal_error("Failed to open database"))?;
+
+    let session = query_session(&conn, &user, id)?;
+    if session.ended_at.is_some() {
+        return Err(ErrorConflict("Trading session already stopped"));
+    }
+    if ended_at < session.started_at {
+        return Err(ErrorBadRequest(
+            "A trading session can't stop before it started",
+        ));
+    }
+
+    let before = audit::snapshot(&conn, "trading_sessions", id).map_err(internal_error(
+        "Failed to get trading session from trading_sessions table",
+    ))?;
+
+    conn.execute(
+        "UPDATE trading_sessions SET ended_at = ?1 WHERE id = ?2 AND user_id = ?3",
+        params![ended_at.and_utc().timestamp(), id, user.id],
+    )
+    .map_err(internal_error("Failed to update trading session"))?;
+
+    audit
+        .record(&conn, Action::Update, "trading_sessions", id, before)
+        .map_err(internal_error("Failed to write audit log"))?;
+
+    info!(session_id = id, "Trading session stopped");
+
+    let trades = list_trades(trades.as_ref(), &user, None)?;
+    let ledger = ledger::ledger(&trades);
+    let session = StoredSession {
+        ended_at: Some(ended_at),
+        ..session
+    };
+
+    Ok(HttpResponse::Ok().json(trading_session(session, &trades, &ledger, ended_at)))
+}
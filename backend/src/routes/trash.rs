@@ -0,0 +1,117 @@
+use actix_web::error::ErrorNotFound;
+use actix_web::{get, post, web, Error, HttpResponse, Responder};
+use tracing::debug;
+use utoipa::IntoParams;
+
+use crate::audit::Audit;
+use crate::auth::AuthenticatedUser;
+use crate::db::Database;
+use crate::events::{Events, TradeEvent};
+use crate::models::DeletedTrade;
+use crate::repository::TradeRepository;
+use crate::routes::repository_error;
+
+// How long deleted trades stay in the trash before they are purged
+pub const TRASH_RETENTION_DAYS: i64 = 30;
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TrashQueryParams {
+    account: Option<i64>,
+}
+
+// Handle GET request for listing the trades in the trash, most recently deleted first
+// Takes optional query parameters: account
+#[utoipa::path(
+    tag = "trades",
+    summary = "List trades in the trash",
+    params(TrashQueryParams),
+    responses(
+        (status = 200, description = "Deleted trades, most recent first", body = Vec<DeletedTrade>),
+    ),
+)]
+#[get("/api/v1/trade/trash")]
+pub async fn trash_get(
+    user: AuthenticatedUser,
+    trades: web::Data<dyn TradeRepository>,
+    query_params: web::Query<TrashQueryParams>,
+) -> Result<impl Responder, Error> {
+    debug!("Listing trash");
+
+    let deleted_trades = trades
+        .list_deleted(&user, query_params.account)
+        .map_err(repository_error("Failed to get trades from trades table"))?;
+
+    Ok(HttpResponse::Ok().json(deleted_trades))
+}
+
+// Handle POST request for restoring a trade from the trash
+#[utoipa::path(
+    tag = "trades",
+    summary = "Restore a trade from the trash",
+    responses(
+        (status = 200, description = "Trade restored", body = String, content_type = "text/plain"),
+        (status = 404, description = "Trade not found in trash"),
+    ),
+)]
+#[post("/api/v1/trade/{id}/restore")]
+pub async fn trade_restore_post(
+    user: AuthenticatedUser,
+    trades: web::Data<dyn TradeRepository>,
+    audit: Audit,
+    events: web::Data<Events>,
+    path: web::Path<i64>,
+) -> Result<impl Responder, Error> {
+    let id = path.into_inner();
+    debug!(trade_id = id, "Restoring trade from trash");
+
+    let trade = trades
+        .restore(&audit, &user, id)
+        .map_err(repository_error("Failed to restore trade"))?
+        .ok_or_else(|| ErrorNotFound("Trade not found in trash"))?;
+
+    events.send(user.id, TradeEvent::TradeCreated { trade });
+
+    Ok(HttpResponse::Ok().body("Trade successfully restored"))
+}
+
+// Permanently delete trades that have been in the trash longer than the retention period,
+// along with their links to flips, offer fills and tags, and the flips left without trades.
+// Returns the number of trades purged.
+pub fn purge_deleted_trades(db: &Database) -> rusqlite::Result<usize> {
+    let mut conn = db.open()?;
+    let cutoff = (chrono::Utc::now() - chrono::Duration::days(TRASH_RETENTION_DAYS)).timestamp();
+    let audit = Audit::system();
+
+    let tx = conn.transaction()?;
+
+    audit.delete_rows(
+        &tx,
+        "flip_trades",
+        "trade_id IN (SELECT id FROM trades WHERE deleted_at < ?1)",
+        &[&cutoff],
+    )?;
+    audit.delete_rows(
+        &tx,
+        "offer_fills",
+        "trade_id IN (SELECT id FROM trades WHERE deleted_at < ?1)",
+        &[&cutoff],
+    )?;
+    audit.delete_rows(
+        &tx,
+        "trade_tags",
+        "trade_id IN (SELECT id FROM trades WHERE deleted_at < ?1)",
+        &[&cutoff],
+    )?;
+    let purged = audit.delete_rows(&tx, "trades", "deleted_at < ?1", &[&cutoff])?;
+    audit.delete_rows(
+        &tx,
+        "flips",
+        "id NOT IN (SELECT flip_id FROM flip_trades)",
+        &[],
+    )?;
+
+    tx.commit()?;
+
+    Ok(purged)
+}
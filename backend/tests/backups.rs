@@ -0,0 +1,111 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use rusqlite::Connection;
+use serde_json::{json, Value};
+
+use common::{bearer, login, TestDatabase};
+
+// Every test in this file makes alice the only admin. Each test file runs in its own process, so
+// this doesn't leak into other tests.
+fn make_alice_admin() {
+    std::env::set_var("ARDY_ADMINS", "alice");
+}
+
+#[actix_web::test]
+async fn admins_can_back_up_and_list_backups() {
+    make_alice_admin();
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/admin/backups")
+        .insert_header(bearer(&token))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let created: Value = test::read_body_json(resp).await;
+    assert!(created["size_bytes"].as_u64().unwrap() > 0);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/backups")
+        .insert_header(bearer(&token))
+        .to_request();
+    let backups: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(backups, vec![created.clone()]);
+
+    // The backup is a copy of the database, with alice in it
+    let name = created["name"].as_str().unwrap();
+    let path = db.db.path().parent().unwrap().join("backups").join(name);
+    let users: i64 = Connection::open(path)
+        .unwrap()
+        .query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(users, 1);
+}
+
+#[actix_web::test]
+async fn snapshot_is_a_database_file() {
+    make_alice_admin();
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/snapshot")
+        .insert_header(bearer(&token))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get("Content-Type").unwrap(),
+        "application/vnd.sqlite3"
+    );
+
+    let body = test::read_body(resp).await;
+    assert!(body.starts_with(b"SQLite format 3\0"));
+
+    // Snapshots aren't kept as backups
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/backups")
+        .insert_header(bearer(&token))
+        .to_request();
+    let backups: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert!(backups.is_empty());
+}
+
+#[actix_web::test]
+async fn only_admins_manage_backups() {
+    make_alice_admin();
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let alice = login(&app, "alice").await;
+    let bob = login(&app, "bob").await;
+
+    for (method, uri) in [
+        (test::TestRequest::get(), "/api/v1/admin/backups"),
+        (test::TestRequest::post(), "/api/v1/admin/backups"),
+        (test::TestRequest::get(), "/api/v1/admin/snapshot"),
+    ] {
+        let req = method.uri(uri).insert_header(bearer(&bob)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN, "{}", uri);
+    }
+
+    // An admin's API token needs the admin scope too
+    let req = test::TestRequest::post()
+        .uri("/api/v1/tokens")
+        .insert_header(bearer(&alice))
+        .set_json(json!({ "name": "script", "scopes": ["write"] }))
+        .to_request();
+    let created: Value = test::call_and_read_body_json(&app, req).await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/backups")
+        .insert_header(bearer(created["token"].as_str().unwrap()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
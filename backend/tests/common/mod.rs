@@ -0,0 +1,94 @@
+// Each test file compiles this module separately and uses only some of the helpers
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use actix_http::Request;
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::{test, web, Error};
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+use backend::db::{self, Database};
+use backend::events::Events;
+use backend::repository::{SqliteTradeRepository, TradeRepository};
+
+// A database in a temporary directory, deleted when dropped
+pub struct TestDatabase {
+    pub db: web::Data<Database>,
+    _dir: TempDir,
+}
+
+impl TestDatabase {
+    pub fn new() -> Self {
+        let test_db = Self::uninitialized();
+        db::init_db(&test_db.db).expect("Failed to initialize database");
+        test_db
+    }
+
+    // A database whose tables haven't been created, like before the first start
+    pub fn uninitialized() -> Self {
+        let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+
+        TestDatabase {
+            db: web::Data::new(Database::new(dir.path().join("ardy.db"))),
+            _dir: dir,
+        }
+    }
+
+    pub async fn app(
+        &self,
+    ) -> impl Service<Request, Response = ServiceResponse<impl MessageBody>, Error = Error> {
+        let trades: Arc<dyn TradeRepository> =
+            Arc::new(SqliteTradeRepository::new(self.db.get_ref().clone()));
+        test::init_service(backend::app(
+            self.db.clone(),
+            web::Data::from(trades),
+            web::Data::new(Events::new()),
+        ))
+        .await
+    }
+}
+
+// Register a user and log in, returning the session token to send as a bearer token
+pub async fn login<S, B>(app: &S, username: &str) -> String
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    let credentials = json!({ "username": username, "password": "password1" });
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/auth/register")
+        .set_json(&credentials)
+        .to_request();
+    let resp = test::call_service(app, req).await;
+    assert!(
+        resp.status().is_success(),
+        "Failed to register {}",
+        username
+    );
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/auth/login")
+        .set_json(&credentials)
+        .to_request();
+    let login: Value = test::call_and_read_body_json(app, req).await;
+
+    login["token"].as_str().unwrap().to_string()
+}
+
+pub fn bearer(token: &str) -> (&'static str, String) {
+    ("Authorization", format!("Bearer {}", token))
+}
+
+pub fn trade(item_name: &str, quantity: i64, total_price: i64, is_purchase: bool) -> Value {
+    json!({
+        "item_name": item_name,
+        "quantity": quantity,
+        "total_price": total_price,
+        "is_purchase": is_purchase,
+        "timestamp": "2024-01-31T13:45",
+    })
+}
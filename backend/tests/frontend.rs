@@ -0,0 +1,43 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+
+use common::{bearer, login, TestDatabase};
+
+// The frontend directory is read from the environment, so this is the only test in its binary
+#[actix_web::test]
+async fn serves_the_frontend_for_any_path_outside_the_api() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("index.html"), "<html>ardy</html>").unwrap();
+    std::fs::write(dir.path().join("app.js"), "console.log('ardy')").unwrap();
+    std::env::set_var("ARDY_FRONTEND_DIR", dir.path());
+
+    let db = TestDatabase::new();
+    let app = db.app().await;
+
+    // Pages are routed in the browser, so they all get the app without logging in
+    for path in ["/", "/transactions?item=whip", "/items/Abyssal%20whip"] {
+        let req = test::TestRequest::get().uri(path).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK, "{}", path);
+        assert_eq!(test::read_body(resp).await, "<html>ardy</html>", "{}", path);
+    }
+
+    let req = test::TestRequest::get().uri("/app.js").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(test::read_body(resp).await, "console.log('ardy')");
+
+    // The API still needs a login, and unknown API routes aren't the app
+    let req = test::TestRequest::get().uri("/api/v1/trade").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let token = login(&app, "alice").await;
+    let req = test::TestRequest::get()
+        .uri("/api/v1/nothing")
+        .insert_header(bearer(&token))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
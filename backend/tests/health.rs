@@ -0,0 +1,57 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::{json, Value};
+
+use common::TestDatabase;
+
+#[actix_web::test]
+async fn healthz_needs_no_login() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+
+    let req = test::TestRequest::get().uri("/healthz").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(test::read_body(resp).await, "ok");
+}
+
+#[actix_web::test]
+async fn readyz_passes_once_migrated() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+
+    let req = test::TestRequest::get().uri("/readyz").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let readiness: Value = test::read_body_json(resp).await;
+    assert_eq!(readiness["ready"], true);
+    assert_eq!(
+        readiness["checks"],
+        json!([
+            { "name": "database", "ok": true, "error": null },
+            { "name": "migrations", "ok": true, "error": null },
+        ])
+    );
+}
+
+#[actix_web::test]
+async fn readyz_fails_before_migrations() {
+    let db = TestDatabase::uninitialized();
+    let app = db.app().await;
+
+    let req = test::TestRequest::get().uri("/readyz").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let readiness: Value = test::read_body_json(resp).await;
+    assert_eq!(readiness["ready"], false);
+    assert_eq!(readiness["checks"][0]["ok"], true);
+    assert_eq!(readiness["checks"][1]["ok"], false);
+    assert!(readiness["checks"][1]["error"]
+        .as_str()
+        .unwrap()
+        .ends_with("migrations not applied"));
+}
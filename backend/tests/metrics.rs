@@ -0,0 +1,108 @@
+#![cfg(feature = "metrics")]
+
+mod common;
+
+use actix_http::Request;
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::{test, Error};
+
+use common::{bearer, login, trade, TestDatabase};
+
+// Scrape the metrics, returning the value of each sample by its name and labels
+async fn scrape<S, B>(app: &S) -> Vec<(String, f64)>
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    let req = test::TestRequest::get().uri("/metrics").to_request();
+    let resp = test::call_service(app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let body = test::read_body(resp).await;
+    String::from_utf8(body.to_vec())
+        .unwrap()
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let (sample, value) = line.rsplit_once(' ')?;
+            Some((sample.to_string(), value.parse().ok()?))
+        })
+        .collect()
+}
+
+fn sample(samples: &[(String, f64)], name: &str) -> Option<f64> {
+    samples
+        .iter()
+        .find(|(sample, _)| sample == name)
+        .map(|(_, value)| *value)
+}
+
+#[actix_web::test]
+async fn metrics_count_requests_per_route() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+
+    for id in [1, 2] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/v1/offer/{}/cancel", id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    let samples = scrape(&app).await;
+
+    // Ids are folded into the route pattern
+    let requests = sample(
+        &samples,
+        r#"ardy_http_requests_total{method="POST",route="/api/v1/offer/{id}/cancel",status="401"}"#,
+    );
+    assert_eq!(requests, Some(2.0));
+    assert!(sample(
+        &samples,
+        r#"ardy_http_request_duration_seconds_count{method="POST",route="/api/v1/offer/{id}/cancel",status="401"}"#
+    )
+    .is_some());
+    assert!(samples.iter().any(|(sample, _)| sample
+        .starts_with(r#"ardy_db_query_duration_seconds_count{statement="select"}"#)));
+}
+
+#[actix_web::test]
+async fn metrics_report_trades_and_realized_profit() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    for new_trade in [
+        trade("Dragon bones", 100, 2_000, true),
+        trade("Dragon bones", 60, 2_300, false),
+        trade("Abyssal whip", 1, 1_500_000, true),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/trade")
+            .insert_header(bearer(&token))
+            .set_json(&new_trade)
+            .to_request();
+        test::call_service(&app, req).await;
+    }
+
+    // Sold without buying any, so there's no cost to realize profit against. Alice's bones
+    // aren't Bob's.
+    let other_token = login(&app, "bob").await;
+    let req = test::TestRequest::post()
+        .uri("/api/v1/trade")
+        .insert_header(bearer(&other_token))
+        .set_json(trade("Dragon bones", 40, 2_500, false))
+        .to_request();
+    test::call_service(&app, req).await;
+
+    let samples = scrape(&app).await;
+
+    assert_eq!(sample(&samples, "ardy_trades"), Some(4.0));
+    assert_eq!(sample(&samples, "ardy_users"), Some(2.0));
+    // 60 sold for 2,300 each, bought at 2,000 each, without grouping them into a flip
+    assert_eq!(sample(&samples, "ardy_realized_profit_gp"), Some(18_000.0));
+    assert!(sample(&samples, "ardy_db_size_bytes").unwrap() > 0.0);
+}
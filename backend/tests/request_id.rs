@@ -0,0 +1,58 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+
+use common::TestDatabase;
+
+#[actix_web::test]
+async fn responses_carry_a_generated_request_id() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+
+    let req = test::TestRequest::get().uri("/healthz").to_request();
+    let first = test::call_service(&app, req).await;
+    let req = test::TestRequest::get().uri("/healthz").to_request();
+    let second = test::call_service(&app, req).await;
+
+    let first_id = first.headers().get("X-Request-Id").unwrap();
+    let second_id = second.headers().get("X-Request-Id").unwrap();
+    assert_eq!(first_id.len(), 36);
+    assert_ne!(first_id, second_id);
+}
+
+#[actix_web::test]
+async fn request_id_from_the_client_is_echoed() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+
+    // Also on responses the auth middleware makes itself
+    let req = test::TestRequest::get()
+        .uri("/api/v1/trade")
+        .insert_header(("X-Request-Id", "import-batch_7.1"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        resp.headers().get("X-Request-Id").unwrap(),
+        "import-batch_7.1"
+    );
+}
+
+#[actix_web::test]
+async fn malformed_request_ids_are_replaced() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+
+    for request_id in ["", "has spaces", "quote\"d", &"a".repeat(65)] {
+        let req = test::TestRequest::get()
+            .uri("/healthz")
+            .insert_header(("X-Request-Id", request_id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        let echoed = resp.headers().get("X-Request-Id").unwrap();
+        assert_ne!(echoed, request_id);
+        assert_eq!(echoed.len(), 36);
+    }
+}
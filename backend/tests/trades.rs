@@ -0,0 +1,1173 @@
+mod common;
+
+use actix_http::Request;
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::{test, Error};
+use serde_json::{json, Value};
+
+use common::{bearer, login, trade, TestDatabase};
+
+#[actix_web::test]
+async fn trade_post_saves_trade() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    let mut new_trade = trade("Abyssal whip", 2, 1_500_000, true);
+    new_trade["note"] = json!("Bought the dip");
+    new_trade["tags"] = json!(["whips"]);
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/trade")
+        .insert_header(bearer(&token))
+        .set_json(&new_trade)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/trade")
+        .insert_header(bearer(&token))
+        .to_request();
+    let trades: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(trades.len(), 1);
+    assert_eq!(trades[0]["item_name"], "Abyssal whip");
+    assert_eq!(trades[0]["quantity"], 2);
+    assert_eq!(trades[0]["total_price"], 1_500_000);
+    assert_eq!(trades[0]["is_purchase"], true);
+    assert_eq!(trades[0]["timestamp"], "2024-01-31T13:45");
+    assert_eq!(trades[0]["note"], "Bought the dip");
+    assert_eq!(trades[0]["tags"], json!(["whips"]));
+}
+
+#[actix_web::test]
+async fn trade_post_requires_login() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/trade")
+        .set_json(trade("Abyssal whip", 1, 1_500_000, true))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/trade")
+        .insert_header(bearer("not-a-session"))
+        .set_json(trade("Abyssal whip", 1, 1_500_000, true))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn login_rejects_unknown_users_like_wrong_passwords() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    login(&app, "alice").await;
+
+    for (username, password) in [("alice", "password2"), ("mallory", "password1")] {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/auth/login")
+            .set_json(json!({ "username": username, "password": password }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let body = test::read_body(resp).await;
+        assert_eq!(body, "Invalid username or password");
+    }
+}
+
+#[actix_web::test]
+async fn tokens_post_rejects_expiry_out_of_range() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    let post_token = |expires_in_days: i64| {
+        test::TestRequest::post()
+            .uri("/api/v1/tokens")
+            .insert_header(bearer(&token))
+            .set_json(json!({
+                "name": "script",
+                "scopes": ["read"],
+                "expires_in_days": expires_in_days,
+            }))
+            .to_request()
+    };
+
+    for expires_in_days in [0, 3_651, 100_000_000, i64::MAX] {
+        let resp = test::call_service(&app, post_token(expires_in_days)).await;
+        assert_eq!(
+            resp.status(),
+            StatusCode::BAD_REQUEST,
+            "{}",
+            expires_in_days
+        );
+    }
+
+    let resp = test::call_service(&app, post_token(3_650)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn trade_post_rejects_malformed_timestamps() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    for timestamp in [
+        json!("2024-01-31"),
+        json!("2024-01-31 13:45"),
+        json!("2024-01-31T13:45:00Z"),
+        json!("2024-13-31T13:45"),
+        json!("yesterday"),
+        json!(""),
+        json!(1706708700),
+        Value::Null,
+    ] {
+        let mut new_trade = trade("Abyssal whip", 1, 1_500_000, true);
+        new_trade["timestamp"] = timestamp.clone();
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/trade")
+            .insert_header(bearer(&token))
+            .set_json(&new_trade)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.status(),
+            StatusCode::BAD_REQUEST,
+            "timestamp {} was accepted",
+            timestamp
+        );
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/trade")
+        .insert_header(bearer(&token))
+        .to_request();
+    let trades: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert!(trades.is_empty());
+}
+
+#[actix_web::test]
+async fn trade_post_rejects_malformed_bodies() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    let mut missing_quantity = trade("Abyssal whip", 1, 1_500_000, true);
+    missing_quantity.as_object_mut().unwrap().remove("quantity");
+
+    let mut text_price = trade("Abyssal whip", 1, 1_500_000, true);
+    text_price["total_price"] = json!("1.5m");
+
+    for body in [missing_quantity, text_price, json!([])] {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/trade")
+            .insert_header(bearer(&token))
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.status(),
+            StatusCode::BAD_REQUEST,
+            "{} was accepted",
+            body
+        );
+    }
+}
+
+#[actix_web::test]
+async fn trade_post_rejects_other_users_accounts() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let alice = login(&app, "alice").await;
+    let bob = login(&app, "bob").await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/account")
+        .insert_header(bearer(&bob))
+        .set_json(json!({ "name": "Bob's main" }))
+        .to_request();
+    let account: Value = test::call_and_read_body_json(&app, req).await;
+
+    let mut new_trade = trade("Abyssal whip", 1, 1_500_000, true);
+    new_trade["account_id"] = account["id"].clone();
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/trade")
+        .insert_header(bearer(&alice))
+        .set_json(&new_trade)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn trade_post_reports_invalid_fields() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    let mut invalid = trade("", -1, 0, true);
+    invalid["timestamp"] = json!("2999-01-01T00:00");
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/trade")
+        .insert_header(bearer(&token))
+        .set_json(&invalid)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let body: Value = test::read_body_json(resp).await;
+    let fields: Vec<&str> = body["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        fields,
+        vec!["item_name", "quantity", "total_price", "timestamp"]
+    );
+
+    // A trade coming to more than the max stack of coins is rejected too
+    let req = test::TestRequest::post()
+        .uri("/api/v1/trade")
+        .insert_header(bearer(&token))
+        .set_json(trade("Twisted bow", 2, 1_500_000_000, true))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/trade")
+        .insert_header(bearer(&token))
+        .to_request();
+    let trades: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert!(trades.is_empty());
+}
+
+// Add trades for a user, returning the list of their trades
+async fn add_trades<S, B>(app: &S, token: &str, trades: &[Value]) -> Vec<Value>
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    for new_trade in trades {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/trade")
+            .insert_header(bearer(token))
+            .set_json(new_trade)
+            .to_request();
+        let resp = test::call_service(app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/trade")
+        .insert_header(bearer(token))
+        .to_request();
+    test::call_and_read_body_json(app, req).await
+}
+
+fn item_names(trades: &[Value]) -> Vec<&str> {
+    trades
+        .iter()
+        .map(|trade| trade["item_name"].as_str().unwrap())
+        .collect()
+}
+
+#[actix_web::test]
+async fn trade_get_searches_by_item_name() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    add_trades(
+        &app,
+        &token,
+        &[
+            trade("Abyssal whip", 1, 1_500_000, true),
+            trade("Abyssal dagger", 1, 900_000, true),
+            trade("Dragon bones", 100, 2_000, true),
+        ],
+    )
+    .await;
+
+    for (search, expected) in [
+        ("Abyssal", vec!["Abyssal whip", "Abyssal dagger"]),
+        ("abyssal WHIP", vec!["Abyssal whip"]),
+        ("bones", vec!["Dragon bones"]),
+        ("Twisted bow", vec![]),
+    ] {
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/api/v1/trade?item_name={}",
+                search.replace(' ', "%20")
+            ))
+            .insert_header(bearer(&token))
+            .to_request();
+        let trades: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(item_names(&trades), expected, "searching for {}", search);
+    }
+}
+
+#[actix_web::test]
+async fn trade_get_filters_by_account_and_tag() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/account")
+        .insert_header(bearer(&token))
+        .set_json(json!({ "name": "Ironman" }))
+        .to_request();
+    let account: Value = test::call_and_read_body_json(&app, req).await;
+
+    let mut iron_trade = trade("Dragon bones", 100, 2_000, true);
+    iron_trade["account_id"] = account["id"].clone();
+    let mut tagged_trade = trade("Abyssal whip", 1, 1_500_000, true);
+    tagged_trade["tags"] = json!(["clan flip"]);
+
+    add_trades(&app, &token, &[iron_trade, tagged_trade]).await;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/v1/trade?account={}", account["id"]))
+        .insert_header(bearer(&token))
+        .to_request();
+    let trades: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(item_names(&trades), vec!["Dragon bones"]);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/trade?tag=clan%20flip")
+        .insert_header(bearer(&token))
+        .to_request();
+    let trades: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(item_names(&trades), vec!["Abyssal whip"]);
+}
+
+#[actix_web::test]
+async fn account_delete_refuses_while_trades_are_in_the_trash() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/account")
+        .insert_header(bearer(&token))
+        .set_json(json!({ "name": "Ironman" }))
+        .to_request();
+    let account: Value = test::call_and_read_body_json(&app, req).await;
+
+    let mut iron_trade = trade("Dragon bones", 100, 2_000, true);
+    iron_trade["account_id"] = account["id"].clone();
+    let trades = add_trades(&app, &token, &[iron_trade]).await;
+
+    let delete_account = || {
+        test::TestRequest::delete()
+            .uri(&format!("/api/v1/account/{}", account["id"]))
+            .insert_header(bearer(&token))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, delete_account()).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/v1/trade?id={}", trades[0]["id"]))
+        .insert_header(bearer(&token))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // The trade could still be restored to the account
+    let resp = test::call_service(&app, delete_account()).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    let body = test::read_body(resp).await;
+    assert!(String::from_utf8_lossy(&body).contains("trash"));
+}
+
+#[actix_web::test]
+async fn trade_get_only_lists_own_trades() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let alice = login(&app, "alice").await;
+    let bob = login(&app, "bob").await;
+
+    add_trades(&app, &alice, &[trade("Abyssal whip", 1, 1_500_000, true)]).await;
+    let bob_trades = add_trades(&app, &bob, &[trade("Dragon bones", 100, 2_000, true)]).await;
+
+    assert_eq!(item_names(&bob_trades), vec!["Dragon bones"]);
+}
+
+#[actix_web::test]
+async fn trade_get_rejects_malformed_queries() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/trade?account=main")
+        .insert_header(bearer(&token))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn trade_delete_moves_trade_to_trash() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    let trades = add_trades(
+        &app,
+        &token,
+        &[
+            trade("Abyssal whip", 1, 1_500_000, true),
+            trade("Dragon bones", 100, 2_000, true),
+        ],
+    )
+    .await;
+    let id = trades[0]["id"].as_i64().unwrap();
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/v1/trade?id={}", id))
+        .insert_header(bearer(&token))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/trade")
+        .insert_header(bearer(&token))
+        .to_request();
+    let trades: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(item_names(&trades), vec!["Dragon bones"]);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/trade/trash")
+        .insert_header(bearer(&token))
+        .to_request();
+    let trash: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0]["id"], id);
+
+    // A trade in the trash can't be deleted again
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/v1/trade?id={}", id))
+        .insert_header(bearer(&token))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn trade_delete_rejects_unknown_and_other_users_trades() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let alice = login(&app, "alice").await;
+    let bob = login(&app, "bob").await;
+
+    let trades = add_trades(&app, &alice, &[trade("Abyssal whip", 1, 1_500_000, true)]).await;
+    let id = trades[0]["id"].as_i64().unwrap();
+
+    for uri in [
+        format!("/api/v1/trade?id={}", id),
+        format!("/api/v1/trade?id={}", id + 1000),
+    ] {
+        let req = test::TestRequest::delete()
+            .uri(&uri)
+            .insert_header(bearer(&bob))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{}", uri);
+    }
+
+    for uri in ["/api/v1/trade", "/api/v1/trade?id=whip"] {
+        let req = test::TestRequest::delete()
+            .uri(uri)
+            .insert_header(bearer(&alice))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", uri);
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/trade")
+        .insert_header(bearer(&alice))
+        .to_request();
+    let trades: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(trades.len(), 1);
+}
+
+async fn profit_loss<S, B>(app: &S, token: &str, query: &str) -> i64
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/v1/profit_loss{}", query))
+        .insert_header(bearer(token))
+        .to_request();
+    test::call_and_read_body_json(app, req).await
+}
+
+#[actix_web::test]
+async fn profit_loss_get_sums_sales_minus_purchases() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    assert_eq!(profit_loss(&app, &token, "").await, 0);
+
+    let trades = add_trades(
+        &app,
+        &token,
+        &[
+            trade("Dragon bones", 100, 2_000, true),
+            trade("Dragon bones", 100, 2_300, false),
+            trade("Abyssal whip", 1, 1_500_000, true),
+        ],
+    )
+    .await;
+
+    assert_eq!(
+        profit_loss(&app, &token, "").await,
+        100 * 2_300 - 100 * 2_000 - 1_500_000
+    );
+
+    // Trades in the trash don't count
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/v1/trade?id={}", trades[2]["id"]))
+        .insert_header(bearer(&token))
+        .to_request();
+    test::call_service(&app, req).await;
+
+    assert_eq!(profit_loss(&app, &token, "").await, 30_000);
+}
+
+#[actix_web::test]
+async fn profit_loss_get_filters_by_account_and_user() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let alice = login(&app, "alice").await;
+    let bob = login(&app, "bob").await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/account")
+        .insert_header(bearer(&alice))
+        .set_json(json!({ "name": "Ironman" }))
+        .to_request();
+    let account: Value = test::call_and_read_body_json(&app, req).await;
+
+    let mut iron_sale = trade("Dragon bones", 10, 2_500, false);
+    iron_sale["account_id"] = account["id"].clone();
+
+    add_trades(
+        &app,
+        &alice,
+        &[iron_sale, trade("Abyssal whip", 1, 1_500_000, false)],
+    )
+    .await;
+    add_trades(&app, &bob, &[trade("Twisted bow", 1, 1_000_000_000, true)]).await;
+
+    assert_eq!(profit_loss(&app, &alice, "").await, 1_525_000);
+    assert_eq!(
+        profit_loss(&app, &alice, &format!("?account={}", account["id"])).await,
+        25_000
+    );
+    assert_eq!(profit_loss(&app, &bob, "").await, -1_000_000_000);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/profit_loss?account=ironman")
+        .insert_header(bearer(&alice))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn flips_get_counts_open_flips_up_to_now() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    add_trades(
+        &app,
+        &token,
+        &[
+            trade("Dragon bones", 100, 2_000, true),
+            trade("Dragon bones", 50, 2_400, false),
+        ],
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/flips/auto")
+        .insert_header(bearer(&token))
+        .to_request();
+    let grouped: i64 = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(grouped, 2);
+
+    // Trades are in local time, so two hours after 2024-01-31T13:45 where they were made
+    let req = test::TestRequest::get()
+        .uri("/api/v1/flips?now=2024-01-31T15:45")
+        .insert_header(bearer(&token))
+        .to_request();
+    let flips: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(flips.len(), 1);
+    assert_eq!(flips[0]["status"], "partially_closed");
+    assert_eq!(flips[0]["profit"], 20_000);
+    assert_eq!(flips[0]["duration_seconds"], 2 * 60 * 60);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/flips?now=soon")
+        .insert_header(bearer(&token))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+#[actix_web::test]
+async fn flips_post_rejects_trades_listed_twice() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    let trades = add_trades(&app, &token, &[trade("Dragon bones", 100, 2_000, true)]).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/flips")
+        .insert_header(bearer(&token))
+        .set_json(json!({ "trade_ids": [trades[0]["id"], trades[0]["id"]] }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn offer_post_refuses_a_slot_with_an_open_offer() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    let post_offer = || {
+        test::TestRequest::post()
+            .uri("/api/v1/offer")
+            .insert_header(bearer(&token))
+            .set_json(json!({
+                "item_name": "Dragon bones",
+                "slot": 1,
+                "is_purchase": true,
+                "price": 2_000,
+                "quantity": 100,
+            }))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, post_offer()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = test::call_service(&app, post_offer()).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+}
+
+#[actix_web::test]
+async fn offer_fills_are_added_like_trades() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    let post = |uri: String, body: Value| {
+        test::TestRequest::post()
+            .uri(&uri)
+            .insert_header(bearer(&token))
+            .set_json(body)
+            .to_request()
+    };
+    let offer = |price: i64, quantity: i64| {
+        json!({
+            "item_name": "Dragon bones",
+            "slot": 1,
+            "is_purchase": true,
+            "price": price,
+            "quantity": quantity,
+            "timestamp": "2024-01-31T13:45",
+        })
+    };
+
+    // Offers that couldn't be filled into a valid trade are rejected like trades
+    for invalid in [offer(0, 100), offer(1_500_000_000, 2)] {
+        let resp = test::call_service(&app, post("/api/v1/offer".to_string(), invalid)).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["errors"][0]["field"], "total_price");
+    }
+
+    let req = post("/api/v1/offer".to_string(), offer(2_000, 100));
+    let placed: Value = test::call_and_read_body_json(&app, req).await;
+    let fill_uri = format!("/api/v1/offer/{}/fill", placed["id"]);
+
+    // A fill at a price that makes an invalid trade leaves the offer as it was
+    let req = post(
+        fill_uri.clone(),
+        json!({ "quantity": 40, "price": 0, "timestamp": "2024-01-31T14:00" }),
+    );
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let req = post(
+        fill_uri,
+        json!({ "quantity": 40, "price": 1_990, "timestamp": "2024-01-31T14:00" }),
+    );
+    let filled: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(filled["quantity_filled"], 40);
+    assert_eq!(filled["status"], "open");
+
+    let trades = add_trades(&app, &token, &[]).await;
+    assert_eq!(trades.len(), 1);
+    assert_eq!(trades[0]["item_name"], "Dragon bones");
+    assert_eq!(trades[0]["quantity"], 40);
+    assert_eq!(trades[0]["total_price"], 1_990);
+    assert_eq!(trades[0]["timestamp"], "2024-01-31T14:00");
+}
+
+#[actix_web::test]
+async fn holdings_get_values_what_is_left_at_the_average_buy_price() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    add_trades(
+        &app,
+        &token,
+        &[
+            trade("Dragon bones", 100, 2_000, true),
+            trade("Dragon bones", 100, 2_300, true),
+            trade("Dragon bones", 50, 2_500, false),
+            trade("Abyssal whip", 1, 1_500_000, true),
+            trade("Abyssal whip", 1, 1_600_000, false),
+            // Sold without being bought, so there's nothing to hold
+            trade("Coal", 500, 150, false),
+        ],
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/holdings")
+        .insert_header(bearer(&token))
+        .to_request();
+    let holdings: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(
+        holdings,
+        vec![json!({
+            "item_name": "Dragon bones",
+            "quantity": 150,
+            "average_price": 2_150,
+            "cost": 322_500,
+        })]
+    );
+}
+
+#[actix_web::test]
+async fn dashboard_get_summarizes_realized_profit_sessions_and_buy_limits() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    let at = |timestamp: &str, mut trade: Value| {
+        trade["timestamp"] = json!(timestamp);
+        trade
+    };
+    add_trades(
+        &app,
+        &token,
+        &[
+            // Last week
+            at(
+                "2024-01-24T10:00",
+                trade("Abyssal whip", 1, 1_500_000, true),
+            ),
+            at(
+                "2024-01-24T10:30",
+                trade("Abyssal whip", 1, 1_400_000, false),
+            ),
+            // Monday of this week
+            at("2024-01-29T09:00", trade("Dragon bones", 100, 2_000, true)),
+            at("2024-01-29T09:30", trade("Dragon bones", 50, 2_400, false)),
+            // Today
+            at("2024-01-31T12:00", trade("Dragon bones", 50, 2_500, false)),
+            at(
+                "2024-01-31T12:30",
+                trade("Rune platebody", 10, 38_000, true),
+            ),
+        ],
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/dashboard?now=2024-01-31T13:45")
+        .insert_header(bearer(&token))
+        .to_request();
+    let dashboard: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(dashboard["profit_today"], 25_000);
+    assert_eq!(dashboard["profit_this_week"], 45_000);
+    assert_eq!(dashboard["profit_all_time"], -55_000);
+    assert_eq!(dashboard["capital_tied_up"], 380_000);
+    assert_eq!(dashboard["open_flips"], 0);
+
+    // Each day's trades are a session, the most recent first
+    let sessions = dashboard["recent_sessions"].as_array().unwrap();
+    assert_eq!(sessions.len(), 3);
+    assert_eq!(sessions[0]["start"], "2024-01-31T12:00");
+    assert_eq!(sessions[0]["profit"], 25_000);
+    assert_eq!(sessions[0]["gp_per_hour"], 50_000);
+    assert_eq!(sessions[0]["capital_used"], 380_000);
+    // 3 sessions of half an hour each
+    assert_eq!(dashboard["gp_per_hour"], -55_000 * 2 / 3);
+
+    assert_eq!(
+        dashboard["top_items"],
+        json!([{ "item_name": "Dragon bones", "profit": 45_000 }])
+    );
+    assert_eq!(
+        dashboard["worst_items"],
+        json!([{ "item_name": "Abyssal whip", "profit": -100_000 }])
+    );
+
+    assert_eq!(
+        dashboard["buy_limit_resets"],
+        json!([{
+            "item_name": "Rune platebody",
+            "account_id": null,
+            "quantity_bought": 10,
+            "resets_at": "2024-01-31T16:30",
+        }])
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/dashboard?now=yesterday")
+        .insert_header(bearer(&token))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn trading_sessions_total_up_the_trades_added_while_in_progress() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+    let other_token = login(&app, "bob").await;
+
+    let post = |uri: String, token: &str| {
+        test::TestRequest::post()
+            .uri(&uri)
+            .insert_header(bearer(token))
+            .to_request()
+    };
+
+    let req = post(
+        "/api/v1/trading_sessions?now=2024-01-31T12:00".to_string(),
+        &token,
+    );
+    let session: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(session["started_at"], "2024-01-31T12:00");
+    assert_eq!(session["ended_at"], Value::Null);
+    let id = session["id"].as_i64().unwrap();
+
+    let req = post("/api/v1/trading_sessions".to_string(), &token);
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+    let trades = add_trades(
+        &app,
+        &token,
+        &[
+            trade("Dragon bones", 100, 2_000, true),
+            trade("Dragon bones", 50, 2_400, false),
+        ],
+    )
+    .await;
+    assert!(trades.iter().all(|trade| trade["session_id"] == id));
+
+    let stop = |now: &str| format!("/api/v1/trading_sessions/{}/stop?now={}", id, now);
+
+    let req = post(stop("2024-01-31T14:00"), &other_token);
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let req = post(stop("2024-01-31T11:00"), &token);
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let req = post(stop("2024-01-31T14:00"), &token);
+    let session: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(session["ended_at"], "2024-01-31T14:00");
+    assert_eq!(session["trade_count"], 2);
+    assert_eq!(session["profit"], 20_000);
+    assert_eq!(session["capital_used"], 200_000);
+    assert_eq!(session["gp_per_hour"], 10_000);
+
+    let req = post(stop("2024-01-31T15:00"), &token);
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+    // Trades added once the session is over don't belong to it
+    let trades = add_trades(&app, &token, &[trade("Dragon bones", 50, 2_500, false)]).await;
+    assert_eq!(trades[2]["session_id"], Value::Null);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/trading_sessions")
+        .insert_header(bearer(&token))
+        .to_request();
+    let sessions: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0]["trade_count"], 2);
+    assert_eq!(sessions[0]["profit"], 20_000);
+
+    // All three trades were made at the same time, so they're one session that took no time
+    let req = test::TestRequest::get()
+        .uri("/api/v1/trading_sessions/inferred")
+        .insert_header(bearer(&token))
+        .to_request();
+    let inferred: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(inferred.len(), 1);
+    assert_eq!(inferred[0]["trade_count"], 3);
+    assert_eq!(inferred[0]["profit"], 45_000);
+    assert_eq!(inferred[0]["gp_per_hour"], Value::Null);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/trading_sessions")
+        .insert_header(bearer(&other_token))
+        .to_request();
+    let sessions: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert!(sessions.is_empty());
+}
+
+#[actix_web::test]
+async fn goals_track_realized_profit_since_they_started() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+    let other_token = login(&app, "bob").await;
+
+    // Made on 2024-01-31, 30k from bones and 100k from the whip
+    add_trades(
+        &app,
+        &token,
+        &[
+            trade("Dragon bones", 100, 2_000, true),
+            trade("Dragon bones", 100, 2_300, false),
+            trade("Abyssal whip", 1, 1_500_000, true),
+            trade("Abyssal whip", 1, 1_600_000, false),
+        ],
+    )
+    .await;
+
+    let post_goal = |goal: Value| {
+        test::TestRequest::post()
+            .uri("/api/v1/goals")
+            .insert_header(bearer(&token))
+            .set_json(goal)
+            .to_request()
+    };
+
+    let req = post_goal(json!({
+        "name": "Twisted bow fund",
+        "target": 1_040_000,
+        "started_at": "2024-01-30T13:45",
+        "deadline": "2024-02-29T23:59",
+    }));
+    let goal: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(goal["progress"], 130_000);
+    let fund_id = goal["id"].as_i64().unwrap();
+
+    let req = post_goal(json!({
+        "name": "Bones",
+        "target": 60_000,
+        "started_at": "2024-01-30T13:45",
+        "deadline": "2024-03-01T00:00",
+        "item_name": "Dragon bones",
+    }));
+    let goal: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(goal["progress"], 30_000);
+    let bones_id = goal["id"].as_i64().unwrap();
+
+    for invalid in [
+        json!({ "name": " ", "target": 1, "deadline": "2099-01-01T00:00" }),
+        json!({ "name": "Nothing", "target": 0, "deadline": "2099-01-01T00:00" }),
+        json!({ "name": "Everything", "target": i64::MAX, "deadline": "2099-01-01T00:00" }),
+        json!({ "name": "Late", "target": 1, "started_at": "2024-01-30T13:45", "deadline": "2024-01-30T13:45" }),
+        json!({ "name": "Theirs", "target": 1, "deadline": "2099-01-01T00:00", "account_id": 999 }),
+    ] {
+        let resp = test::call_service(&app, post_goal(invalid)).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    // Two days in, so the rest is projected at the rate of those two days
+    let req = test::TestRequest::get()
+        .uri("/api/v1/goals?now=2024-02-01T13:45")
+        .insert_header(bearer(&token))
+        .to_request();
+    let goals: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(goals.len(), 2);
+    assert_eq!(goals[0]["name"], "Twisted bow fund");
+    assert_eq!(goals[0]["projected_completion"], "2024-02-15T13:45");
+    assert_eq!(goals[1]["item_name"], "Dragon bones");
+    assert_eq!(goals[1]["projected_completion"], "2024-02-03T13:45");
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/dashboard?now=2024-02-01T13:45")
+        .insert_header(bearer(&token))
+        .to_request();
+    let dashboard: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(dashboard["goals"], json!(goals));
+
+    // Goals keep their own scope whichever account the dashboard is showing
+    let req = test::TestRequest::get()
+        .uri("/api/v1/dashboard?account=1&now=2024-02-01T13:45")
+        .insert_header(bearer(&token))
+        .to_request();
+    let dashboard: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(dashboard["goals"], json!(goals));
+
+    let delete_goal = |id: i64, token: &str| {
+        test::TestRequest::delete()
+            .uri(&format!("/api/v1/goals/{}", id))
+            .insert_header(bearer(token))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, delete_goal(fund_id, &other_token)).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let resp = test::call_service(&app, delete_goal(bones_id, &token)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = test::call_service(&app, delete_goal(bones_id, &token)).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/goals")
+        .insert_header(bearer(&token))
+        .to_request();
+    let goals: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(goals.len(), 1);
+}
+
+#[actix_web::test]
+async fn goals_too_far_off_have_no_projected_completion() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    // 1 gp profit is nowhere near enough to project a goal of a thousand stacks
+    add_trades(
+        &app,
+        &token,
+        &[
+            trade("Dragon bones", 1, 2_000, true),
+            trade("Dragon bones", 1, 2_001, false),
+        ],
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/goals")
+        .insert_header(bearer(&token))
+        .set_json(json!({
+            "name": "Max cash",
+            "target": 2_147_483_647_000_i64,
+            "started_at": "2024-01-30T13:45",
+            "deadline": "2099-01-01T00:00",
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    for uri in ["/api/v1/goals", "/api/v1/dashboard"] {
+        let req = test::TestRequest::get()
+            .uri(&format!("{}?now=2024-02-01T13:45", uri))
+            .insert_header(bearer(&token))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        let goal = if uri == "/api/v1/goals" {
+            &body[0]
+        } else {
+            &body["goals"][0]
+        };
+        assert_eq!(goal["progress"], 1, "{}", uri);
+        assert_eq!(goal["projected_completion"], Value::Null, "{}", uri);
+    }
+}
+
+#[actix_web::test]
+async fn goals_scoped_to_an_account_count_its_own_sales() {
+    let db = TestDatabase::new();
+    let app = db.app().await;
+    let token = login(&app, "alice").await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/account")
+        .insert_header(bearer(&token))
+        .set_json(json!({ "name": "Ironman" }))
+        .to_request();
+    let account: Value = test::call_and_read_body_json(&app, req).await;
+
+    // The bones bought without an account cost more, but the ironman sells its own
+    let on_account = |mut trade: Value| {
+        trade["account_id"] = account["id"].clone();
+        trade
+    };
+    add_trades(
+        &app,
+        &token,
+        &[
+            trade("Dragon bones", 100, 2_400, true),
+            on_account(trade("Dragon bones", 100, 2_000, true)),
+            on_account(trade("Dragon bones", 100, 2_300, false)),
+        ],
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/goals")
+        .insert_header(bearer(&token))
+        .set_json(json!({
+            "name": "Ironman bones",
+            "target": 60_000,
+            "started_at": "2024-01-30T13:45",
+            "deadline": "2024-03-01T00:00",
+            "account_id": account["id"],
+        }))
+        .to_request();
+    let goal: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(goal["progress"], 30_000);
+
+    for uri in [
+        "/api/v1/dashboard?now=2024-02-01T13:45".to_string(),
+        format!(
+            "/api/v1/dashboard?account={}&now=2024-02-01T13:45",
+            account["id"]
+        ),
+    ] {
+        let req = test::TestRequest::get()
+            .uri(&uri)
+            .insert_header(bearer(&token))
+            .to_request();
+        let dashboard: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(dashboard["goals"][0]["progress"], 30_000, "{}", uri);
+    }
+}
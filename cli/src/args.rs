@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use shared::gp;
+use shared::models::TradeFilter;
+pub use shared::trade::{parse_side, parse_timestamp};
+
+use crate::pnl::Grouping;
+use crate::Result;
+
+pub const USAGE: &str = "\
+Usage: ardy [options] <command>
+
+Commands:
+  add <item> <buy|sell> <quantity> @<price each>   Add a trade, e.g. add \"Dragon bones\" buy 500 @2.3k
+      [--account <id>] [--note <text>] [--at <YYYY-MM-DDTHH:MM>]
+  ls [--item <text>] [--account <id>] [--tag <tag>] [--since <7d|YYYY-MM-DD>]
+                                                   List trades
+  pnl [--by <day|week|month|item>] [--account <id>] [--tag <tag>] [--since <7d|YYYY-MM-DD>]
+                                                   Show sales minus purchases
+  rm <id>                                          Move a trade to the trash
+  import <file.csv>                                Add the trades in a CSV file, all or none of them
+
+Options:
+  --api <url>       Backend to talk to, default http://127.0.0.1:43211 (ARDY_API_URL)
+  --token <token>   API token to authenticate with (ARDY_TOKEN)
+  --db <path>       Work on this database file directly instead of the backend (ARDY_DB)
+  --user <name>     User to act as when working on the database directly (ARDY_USER)
+  --json            Print JSON instead of tables
+  -h, --help        Show this help
+
+CSV files for import have a header row naming the columns item, side (buy or sell), quantity and
+price (each) or total, and optionally timestamp, account and note. Amounts can be written like 2.3k.";
+
+const DEFAULT_API_URL: &str = "http://127.0.0.1:43211";
+
+// Options that are flags without a value
+const SWITCHES: &[&str] = &["json", "help"];
+
+// Where trades are read from and written to
+#[derive(Debug, PartialEq)]
+pub enum Source {
+    Api { url: String, token: Option<String> },
+    Database { path: PathBuf, username: String },
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub source: Source,
+    pub json: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct NewTrade {
+    pub item_name: String,
+    pub is_purchase: bool,
+    pub quantity: i64,
+    pub price_each: i64,
+    pub account: Option<i64>,
+    pub note: Option<String>,
+    pub timestamp: Option<NaiveDateTime>,
+}
+
+// Which trades to list or total. `since` is applied by the CLI, the rest by the backend.
+#[derive(Debug, Default, PartialEq)]
+pub struct Selection {
+    pub filter: TradeFilter,
+    pub since: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Add(NewTrade),
+    List(Selection),
+    ProfitLoss { by: Grouping, selection: Selection },
+    Remove { id: i64 },
+    Import { path: PathBuf },
+    Help,
+}
+
+// Arguments split into positional ones and options, in the form `--name value` or `--name=value`
+struct Parsed {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl Parsed {
+    fn new(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut positional = Vec::new();
+        let mut options = HashMap::new();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "-h" {
+                options.insert("help".to_string(), String::new());
+                continue;
+            }
+            let Some(name) = arg.strip_prefix("--") else {
+                positional.push(arg);
+                continue;
+            };
+
+            let (name, value) = match name.split_once('=') {
+                Some((name, value)) => (name.to_string(), value.to_string()),
+                None if SWITCHES.contains(&name) => (name.to_string(), String::new()),
+                None => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--{} needs a value", name))?;
+                    (name.to_string(), value)
+                }
+            };
+            options.insert(name, value);
+        }
+
+        Ok(Parsed {
+            positional,
+            options,
+        })
+    }
+
+    fn take(&mut self, name: &str) -> Option<String> {
+        self.options.remove(name)
+    }
+
+    fn take_id(&mut self, name: &str) -> Result<Option<i64>> {
+        self.take(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| format!("--{} must be a number, not \"{}\"", name, value))
+            })
+            .transpose()
+    }
+
+    fn take_selection(&mut self, now: NaiveDateTime) -> Result<Selection> {
+        Ok(Selection {
+            filter: TradeFilter {
+                item_name: self.take("item"),
+                account: self.take_id("account")?,
+                tag: self.take("tag"),
+            },
+            since: self
+                .take("since")
+                .map(|since| parse_since(&since, now))
+                .transpose()?,
+        })
+    }
+
+    // Fail on options the command doesn't know, so typos don't silently do nothing
+    fn finish(self, command: &str) -> Result<()> {
+        match self.options.keys().next() {
+            Some(name) => Err(format!("Unknown option --{} for {}", name, command)),
+            None => Ok(()),
+        }
+    }
+}
+
+// Parse the command line, falling back to environment variables for options that aren't given.
+// `now` is when relative times like --since 7d count back from.
+pub fn parse(
+    args: impl IntoIterator<Item = String>,
+    env: impl Fn(&str) -> Option<String>,
+    now: NaiveDateTime,
+) -> Result<(Options, Command)> {
+    let mut parsed = Parsed::new(args)?;
+
+    let json = parsed.take("json").is_some();
+    let api_url = parsed.take("api").or_else(|| env("ARDY_API_URL"));
+    let token = parsed.take("token").or_else(|| env("ARDY_TOKEN"));
+    let db = parsed.take("db").or_else(|| env("ARDY_DB"));
+    let user = parsed.take("user").or_else(|| env("ARDY_USER"));
+
+    let source = match db {
+        Some(path) => Source::Database {
+            path: PathBuf::from(path),
+            username: user.ok_or(
+                "Working on the database directly needs the user to act as, set with --user or ARDY_USER",
+            )?,
+        },
+        None => Source::Api {
+            url: api_url.unwrap_or_else(|| DEFAULT_API_URL.to_string()),
+            token,
+        },
+    };
+    let options = Options { source, json };
+
+    if parsed.take("help").is_some() {
+        return Ok((options, Command::Help));
+    }
+
+    let positional = std::mem::take(&mut parsed.positional);
+    let Some((command_name, arguments)) = positional.split_first() else {
+        return Ok((options, Command::Help));
+    };
+
+    let command = match (command_name.as_str(), arguments) {
+        ("add", [item_name, side, quantity, price]) => Command::Add(NewTrade {
+            item_name: item_name.trim().to_string(),
+            is_purchase: parse_side(side)?,
+            quantity: quantity
+                .parse()
+                .ok()
+                .filter(|quantity| *quantity > 0)
+                .ok_or_else(|| {
+                    format!("Quantity must be a positive number, not \"{}\"", quantity)
+                })?,
+            price_each: parse_gp(price.strip_prefix('@').unwrap_or(price))?,
+            account: parsed.take_id("account")?,
+            note: parsed.take("note"),
+            timestamp: parsed
+                .take("at")
+                .map(|at| parse_timestamp(&at))
+                .transpose()?,
+        }),
+        ("add", _) => {
+            return Err("Usage: ardy add <item> <buy|sell> <quantity> @<price each>".to_string())
+        }
+        ("ls", []) => Command::List(parsed.take_selection(now)?),
+        ("pnl", []) => Command::ProfitLoss {
+            by: parsed
+                .take("by")
+                .map(|by| Grouping::parse(&by))
+                .transpose()?
+                .unwrap_or(Grouping::Day),
+            selection: parsed.take_selection(now)?,
+        },
+        ("rm", [id]) => Command::Remove {
+            id: id
+                .parse()
+                .map_err(|_| format!("Trade id must be a number, not \"{}\"", id))?,
+        },
+        ("rm", _) => return Err("Usage: ardy rm <id>".to_string()),
+        ("import", [path]) => Command::Import {
+            path: PathBuf::from(path),
+        },
+        ("import", _) => return Err("Usage: ardy import <file.csv>".to_string()),
+        ("ls" | "pnl", _) => {
+            return Err(format!(
+                "{} takes no arguments besides options",
+                command_name
+            ))
+        }
+        (command, _) => return Err(format!("Unknown command {}", command)),
+    };
+
+    parsed.finish(command_name)?;
+
+    Ok((options, command))
+}
+
+// Amounts of gp are written the way players do, like 500, 2.3k or 1.5m
+pub fn parse_gp(amount: &str) -> Result<i64> {
+    gp::parse(amount).map_err(|e| format!("Invalid gp amount \"{}\": {}", amount.trim(), e))
+}
+
+// Parse a time to list trades from: a date, or how long ago like 12h, 7d or 2w
+fn parse_since(since: &str, now: NaiveDateTime) -> Result<NaiveDateTime> {
+    let invalid = || {
+        format!(
+            "Invalid --since \"{}\", expected e.g. 12h, 7d, 2w or 2024-01-31",
+            since
+        )
+    };
+
+    if let Ok(date) = NaiveDate::parse_from_str(since, "%Y-%m-%d") {
+        return Ok(date.and_time(chrono::NaiveTime::MIN));
+    }
+
+    let (unit_index, unit) = since.char_indices().last().ok_or_else(invalid)?;
+    let amount: i64 = since[..unit_index].parse().map_err(|_| invalid())?;
+    let ago = match unit {
+        'h' => Duration::hours(amount),
+        'd' => Duration::days(amount),
+        'w' => Duration::weeks(amount),
+        _ => return Err(invalid()),
+    };
+
+    Ok(now - ago)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> NaiveDateTime {
+        parse_timestamp("2024-01-31T13:45").unwrap()
+    }
+
+    fn parse_args(args: &str) -> Result<(Options, Command)> {
+        parse(args.split(' ').map(str::to_string), |_| None, now())
+    }
+
+    #[test]
+    fn parses_add_with_price_each() {
+        let (options, command) = parse_args("add Dragon_bones buy 500 @2.3k --note clan").unwrap();
+
+        assert_eq!(
+            options.source,
+            Source::Api {
+                url: DEFAULT_API_URL.to_string(),
+                token: None
+            }
+        );
+        assert_eq!(
+            command,
+            Command::Add(NewTrade {
+                item_name: "Dragon_bones".to_string(),
+                is_purchase: true,
+                quantity: 500,
+                price_each: 2_300,
+                account: None,
+                note: Some("clan".to_string()),
+                timestamp: None,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_ls_filters_and_since() {
+        let (options, command) = parse_args("ls --item rune --since 7d --json").unwrap();
+
+        assert!(options.json);
+        assert_eq!(
+            command,
+            Command::List(Selection {
+                filter: TradeFilter {
+                    item_name: Some("rune".to_string()),
+                    ..TradeFilter::default()
+                },
+                since: Some(parse_timestamp("2024-01-24T13:45").unwrap()),
+            })
+        );
+
+        let (_, command) = parse_args("ls --since=2024-01-01").unwrap();
+        let Command::List(selection) = command else {
+            panic!("Expected ls");
+        };
+        assert_eq!(
+            selection.since,
+            Some(parse_timestamp("2024-01-01T00:00").unwrap())
+        );
+    }
+
+    #[test]
+    fn database_source_needs_a_user() {
+        assert!(parse_args("--db ardy.db ls").is_err());
+
+        let (options, _) = parse(
+            ["ls".to_string()],
+            |name| match name {
+                "ARDY_DB" => Some("db/ardy.db".to_string()),
+                "ARDY_USER" => Some("alice".to_string()),
+                _ => None,
+            },
+            now(),
+        )
+        .unwrap();
+        assert_eq!(
+            options.source,
+            Source::Database {
+                path: PathBuf::from("db/ardy.db"),
+                username: "alice".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert!(parse_args("add Dragon_bones trade 500 @2.3k").is_err());
+        assert!(parse_args("add Dragon_bones buy -5 @2.3k").is_err());
+        assert!(parse_args("add Dragon_bones buy 500").is_err());
+        assert!(parse_args("ls --itme rune").is_err());
+        assert!(parse_args("ls --since 7y").is_err());
+        assert!(parse_args("pnl --by year").is_err());
+        assert!(parse_args("rm abc").is_err());
+        assert!(parse_args("frobnicate").is_err());
+        assert!(parse_args("ls --item").is_err());
+    }
+}
@@ -0,0 +1,27 @@
+use chrono::NaiveDateTime;
+use shared::csv;
+use shared::models::ItemData;
+
+use crate::Result;
+
+// Read the trades in an import file, in the format described in `shared::csv`. Trades without a
+// timestamp get `now`.
+pub fn trades_from_csv(text: &str, now: NaiveDateTime) -> Result<Vec<ItemData>> {
+    let trades = csv::trades_from_csv(text, now)?;
+
+    Ok(trades
+        .into_iter()
+        .map(|trade| ItemData {
+            id: 0,
+            item_name: trade.item_name,
+            quantity: trade.quantity,
+            total_price: trade.price_each,
+            is_purchase: trade.is_purchase,
+            timestamp: trade.timestamp,
+            account_id: trade.account_id,
+            note: trade.note,
+            session_id: None,
+            tags: Vec::new(),
+        })
+        .collect())
+}
@@ -0,0 +1,160 @@
+mod args;
+mod import;
+mod output;
+mod pnl;
+mod store;
+
+use std::io::Write;
+use std::process::ExitCode;
+
+use chrono::{Local, NaiveDateTime};
+use serde_json::json;
+use shared::models::ItemData;
+
+use args::{Command, Options, Selection, USAGE};
+use store::TradeStore;
+
+// Errors are shown to the user as they are, so they're kept as messages
+type Result<T> = std::result::Result<T, String>;
+
+fn main() -> ExitCode {
+    let now = local_now();
+
+    let (options, command) = match args::parse(
+        std::env::args().skip(1),
+        |name| std::env::var(name).ok(),
+        now,
+    ) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{}\n\nRun ardy --help for usage", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&options, command, now) {
+        Ok(output) => {
+            // Output piped into e.g. head is allowed to be cut off
+            let _ = writeln!(std::io::stdout(), "{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Trades without a time are at the current local time, like in the backend, since trade
+// timestamps are local
+fn local_now() -> NaiveDateTime {
+    Local::now().naive_local()
+}
+
+// Trades matching a selection, leaving out those before `since`
+fn select(store: &dyn TradeStore, selection: &Selection) -> Result<Vec<ItemData>> {
+    let mut trades = store.list(&selection.filter)?;
+    if let Some(since) = selection.since {
+        trades.retain(|trade| trade.timestamp >= since);
+    }
+    Ok(trades)
+}
+
+// Run a command, returning what to print
+fn run(options: &Options, command: Command, now: NaiveDateTime) -> Result<String> {
+    if command == Command::Help {
+        return Ok(USAGE.to_string());
+    }
+
+    let store = store::open(&options.source)?;
+
+    match command {
+        Command::Help => Ok(USAGE.to_string()),
+        Command::Add(new_trade) => {
+            // Despite its name, the backend stores the price of each item in total_price
+            let trade = ItemData {
+                id: 0,
+                total_price: new_trade.price_each,
+                item_name: new_trade.item_name,
+                quantity: new_trade.quantity,
+                is_purchase: new_trade.is_purchase,
+                timestamp: new_trade.timestamp.unwrap_or(now),
+                account_id: new_trade.account,
+                note: new_trade.note,
+                session_id: None,
+                tags: Vec::new(),
+            };
+            let id = store.add(&trade)?;
+
+            Ok(if options.json {
+                let mut added = json!(trade);
+                added["id"] = json!(id);
+                output::json(&added)
+            } else {
+                match id {
+                    Some(id) => format!("Added trade {}: {}", id, output::trade_summary(&trade)),
+                    None => format!("Added trade: {}", output::trade_summary(&trade)),
+                }
+            })
+        }
+        Command::List(selection) => {
+            let trades = select(store.as_ref(), &selection)?;
+
+            Ok(if options.json {
+                output::json(&trades)
+            } else {
+                output::trades(&trades)
+            })
+        }
+        Command::ProfitLoss { by, selection } => {
+            let trades = select(store.as_ref(), &selection)?;
+            let rows = pnl::profit_loss(&trades, by);
+
+            Ok(if options.json {
+                output::json(&rows)
+            } else {
+                output::profit_loss(&rows, by.as_str())
+            })
+        }
+        Command::Remove { id } => {
+            store.remove(id)?;
+
+            Ok(if options.json {
+                output::json(&json!({ "id": id, "trashed": true }))
+            } else {
+                format!("Moved trade {} to the trash", id)
+            })
+        }
+        Command::Import { path } => {
+            let text = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let trades = import::trades_from_csv(&text, now)?;
+            if trades.is_empty() {
+                return Err(format!("{} has no trades", path.display()));
+            }
+
+            let response = store.import(&trades)?;
+            if options.json {
+                return Ok(output::json(&response));
+            }
+
+            if response.applied {
+                Ok(format!("Imported {} trades", trades.len()))
+            } else {
+                // Results are in the order of the rows, which start on line 2
+                let failures: Vec<String> = response
+                    .results
+                    .iter()
+                    .filter_map(|result| {
+                        let error = result.error.as_ref()?;
+                        Some(format!("Line {}: {}", result.index + 2, error))
+                    })
+                    .collect();
+                Err(format!(
+                    "Nothing was imported because some trades failed:\n{}",
+                    failures.join("\n")
+                ))
+            }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use serde::Serialize;
+use shared::gp;
+use shared::models::ItemData;
+
+use crate::pnl::ProfitLossRow;
+
+fn side(trade: &ItemData) -> &'static str {
+    if trade.is_purchase {
+        "buy"
+    } else {
+        "sell"
+    }
+}
+
+// What a trade came to in all. The price stored with a trade is the price of each item.
+fn total(trade: &ItemData) -> i64 {
+    trade.total_price.saturating_mul(trade.quantity)
+}
+
+pub fn json(value: &impl Serialize) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+// Lay rows out in aligned columns under a header. Columns listed in `right` are right-aligned,
+// which suits numbers.
+fn table(header: &[&str], rows: &[Vec<String>], right: &[usize]) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|column| column.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let header: Vec<String> = header.iter().map(|column| column.to_string()).collect();
+    std::iter::once(&header)
+        .chain(rows)
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(index, (cell, width))| {
+                    if right.contains(&index) {
+                        format!("{:>width$}", cell, width = width)
+                    } else {
+                        format!("{:<width$}", cell, width = width)
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn trades(trades: &[ItemData]) -> String {
+    if trades.is_empty() {
+        return "No trades".to_string();
+    }
+
+    let rows: Vec<Vec<String>> = trades
+        .iter()
+        .map(|trade| {
+            vec![
+                trade.id.to_string(),
+                trade.timestamp.format("%Y-%m-%d %H:%M").to_string(),
+                side(trade).to_string(),
+                trade.quantity.to_string(),
+                trade.item_name.clone(),
+                gp::format(trade.total_price),
+                gp::format(total(trade)),
+                trade
+                    .account_id
+                    .map(|account| account.to_string())
+                    .unwrap_or_default(),
+                trade.tags.join(", "),
+            ]
+        })
+        .collect();
+
+    table(
+        &[
+            "ID", "TIME", "SIDE", "QTY", "ITEM", "EACH", "TOTAL", "ACCOUNT", "TAGS",
+        ],
+        &rows,
+        &[0, 3, 5, 6, 7],
+    )
+}
+
+// One line describing a trade, like "bought 500 Dragon bones for 1,150,000 gp (2,300 each)"
+pub fn trade_summary(trade: &ItemData) -> String {
+    format!(
+        "{} {} {} for {} gp ({} each)",
+        if trade.is_purchase { "bought" } else { "sold" },
+        trade.quantity,
+        trade.item_name,
+        gp::format(total(trade)),
+        gp::format(trade.total_price),
+    )
+}
+
+pub fn profit_loss(rows: &[ProfitLossRow], by: &str) -> String {
+    if rows.is_empty() {
+        return "No trades".to_string();
+    }
+
+    let mut cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            vec![
+                row.key.clone(),
+                row.trades.to_string(),
+                gp::format(row.bought),
+                gp::format(row.sold),
+                gp::format(row.profit_loss),
+            ]
+        })
+        .collect();
+
+    cells.push(vec![
+        "Total".to_string(),
+        rows.iter().map(|row| row.trades).sum::<usize>().to_string(),
+        gp::format(rows.iter().map(|row| row.bought).sum()),
+        gp::format(rows.iter().map(|row| row.sold).sum()),
+        gp::format(rows.iter().map(|row| row.profit_loss).sum()),
+    ]);
+
+    table(
+        &[
+            &by.to_uppercase(),
+            "TRADES",
+            "BOUGHT",
+            "SOLD",
+            "PROFIT/LOSS",
+        ],
+        &cells,
+        &[1, 2, 3, 4],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aligns_columns() {
+        let rows = vec![
+            vec!["1".to_string(), "Dragon bones".to_string()],
+            vec!["12".to_string(), "Rune".to_string()],
+        ];
+        assert_eq!(
+            table(&["ID", "ITEM"], &rows, &[0]),
+            "ID  ITEM\n 1  Dragon bones\n12  Rune"
+        );
+    }
+}
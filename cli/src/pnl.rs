@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+
+use chrono::Datelike;
+use serde::Serialize;
+use shared::models::ItemData;
+
+use crate::Result;
+
+// What profit/loss is totalled per
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Grouping {
+    Day,
+    Week,
+    Month,
+    Item,
+}
+
+impl Grouping {
+    pub fn parse(s: &str) -> Result<Grouping> {
+        match s {
+            "day" => Ok(Grouping::Day),
+            "week" => Ok(Grouping::Week),
+            "month" => Ok(Grouping::Month),
+            "item" => Ok(Grouping::Item),
+            _ => Err(format!(
+                "--by must be day, week, month or item, not \"{}\"",
+                s
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Grouping::Day => "day",
+            Grouping::Week => "week",
+            Grouping::Month => "month",
+            Grouping::Item => "item",
+        }
+    }
+
+    // Dates are written so they sort in order, weeks as ISO weeks like 2024-W05
+    fn key(&self, trade: &ItemData) -> String {
+        match self {
+            Grouping::Day => trade.timestamp.format("%Y-%m-%d").to_string(),
+            Grouping::Week => {
+                let week = trade.timestamp.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }
+            Grouping::Month => trade.timestamp.format("%Y-%m").to_string(),
+            Grouping::Item => trade.item_name.clone(),
+        }
+    }
+}
+
+// Sales minus purchases of the trades in one group, like the backend's profit/loss
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ProfitLossRow {
+    pub key: String,
+    pub trades: usize,
+    pub bought: i64,
+    pub sold: i64,
+    pub profit_loss: i64,
+}
+
+// Total the trades per group, in order of the group keys
+pub fn profit_loss(trades: &[ItemData], by: Grouping) -> Vec<ProfitLossRow> {
+    let mut rows: BTreeMap<String, ProfitLossRow> = BTreeMap::new();
+
+    for trade in trades {
+        let key = by.key(trade);
+        let row = rows.entry(key.clone()).or_insert_with(|| ProfitLossRow {
+            key,
+            trades: 0,
+            bought: 0,
+            sold: 0,
+            profit_loss: 0,
+        });
+
+        // The price stored with a trade is the price of each item
+        let total = trade.total_price.saturating_mul(trade.quantity);
+        row.trades += 1;
+        if trade.is_purchase {
+            row.bought += total;
+            row.profit_loss -= total;
+        } else {
+            row.sold += total;
+            row.profit_loss += total;
+        }
+    }
+
+    rows.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::parse_timestamp;
+
+    fn trade(
+        item_name: &str,
+        quantity: i64,
+        price_each: i64,
+        is_purchase: bool,
+        timestamp: &str,
+    ) -> ItemData {
+        ItemData {
+            id: 0,
+            item_name: item_name.to_string(),
+            quantity,
+            total_price: price_each,
+            is_purchase,
+            timestamp: parse_timestamp(timestamp).unwrap(),
+            account_id: None,
+            note: None,
+            session_id: None,
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn totals_sales_minus_purchases_per_group() {
+        let trades = [
+            trade("Dragon bones", 100, 2_000, true, "2024-01-29T10:00"),
+            trade("Dragon bones", 100, 2_300, false, "2024-01-31T13:45"),
+            trade("Abyssal whip", 1, 1_500_000, true, "2024-02-01T09:00"),
+        ];
+
+        let by_item = profit_loss(&trades, Grouping::Item);
+        assert_eq!(
+            by_item,
+            vec![
+                ProfitLossRow {
+                    key: "Abyssal whip".to_string(),
+                    trades: 1,
+                    bought: 1_500_000,
+                    sold: 0,
+                    profit_loss: -1_500_000,
+                },
+                ProfitLossRow {
+                    key: "Dragon bones".to_string(),
+                    trades: 2,
+                    bought: 200_000,
+                    sold: 230_000,
+                    profit_loss: 30_000,
+                },
+            ]
+        );
+
+        let keys = |by| -> Vec<String> {
+            profit_loss(&trades, by)
+                .into_iter()
+                .map(|row| row.key)
+                .collect()
+        };
+        assert_eq!(
+            keys(Grouping::Day),
+            ["2024-01-29", "2024-01-31", "2024-02-01"]
+        );
+        assert_eq!(keys(Grouping::Week), ["2024-W05"]);
+        assert_eq!(keys(Grouping::Month), ["2024-01", "2024-02"]);
+    }
+}
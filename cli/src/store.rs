@@ -0,0 +1,42 @@
+pub mod api;
+#[cfg(feature = "local")]
+pub mod local;
+
+use shared::models::{BulkTradeResponse, ItemData, TradeFilter};
+
+use crate::args::Source;
+use crate::Result;
+
+pub use api::ApiStore;
+#[cfg(feature = "local")]
+pub use local::LocalStore;
+
+// Where the CLI reads and writes trades: the backend's HTTP API, or the database file directly
+pub trait TradeStore {
+    // Add a trade, returning its id if the store tells it
+    fn add(&self, trade: &ItemData) -> Result<Option<i64>>;
+
+    // List the trades matching a filter, in the order they were added
+    fn list(&self, filter: &TradeFilter) -> Result<Vec<ItemData>>;
+
+    // Move a trade to the trash
+    fn remove(&self, id: i64) -> Result<()>;
+
+    // Add trades all together or not at all, returning the outcome of each
+    fn import(&self, trades: &[ItemData]) -> Result<BulkTradeResponse>;
+}
+
+pub fn open(source: &Source) -> Result<Box<dyn TradeStore>> {
+    Ok(match source {
+        Source::Api { url, token } => Box::new(ApiStore::new(url, token.clone())?),
+        #[cfg(feature = "local")]
+        Source::Database { path, username } => Box::new(LocalStore::open(path, username)?),
+        #[cfg(not(feature = "local"))]
+        Source::Database { .. } => {
+            return Err(
+                "This build of ardy can't open database files, leave out --db to use the API"
+                    .to_string(),
+            )
+        }
+    })
+}
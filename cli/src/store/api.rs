@@ -0,0 +1,174 @@
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use shared::models::{BulkTradeResponse, InvalidTrade, ItemData, TradeFilter};
+
+use crate::store::TradeStore;
+use crate::Result;
+
+// How long to wait for the backend before giving up
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+// Talks to a running backend over its HTTP API, authenticating with an API token
+pub struct ApiStore {
+    agent: ureq::Agent,
+    // Backend URL without a trailing slash
+    url: String,
+    token: Option<String>,
+}
+
+impl ApiStore {
+    pub fn new(url: &str, token: Option<String>) -> Result<Self> {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(format!(
+                "Backend URL must start with http:// or https://, not \"{}\"",
+                url
+            ));
+        }
+
+        Ok(ApiStore {
+            agent: ureq::AgentBuilder::new().timeout(TIMEOUT).build(),
+            url: url.trim_end_matches('/').to_string(),
+            token,
+        })
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        let request = self
+            .agent
+            .request(method, &format!("{}{}", self.url, path))
+            .set("Accept", "application/json");
+
+        match &self.token {
+            Some(token) => request.set("Authorization", &format!("Bearer {}", token)),
+            None => request,
+        }
+    }
+}
+
+struct Response {
+    status: u16,
+    body: String,
+}
+
+impl Response {
+    fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+// Read the response to a request whatever its status, failing only if the backend couldn't be
+// reached
+fn response(result: std::result::Result<ureq::Response, ureq::Error>) -> Result<Response> {
+    let response = match result {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+        Err(e) => return Err(format!("Failed to reach the backend: {}", e)),
+    };
+
+    let status = response.status();
+    let body = response
+        .into_string()
+        .map_err(|e| format!("Failed to read response from the backend: {}", e))?;
+    Ok(Response { status, body })
+}
+
+// Turn an error response into a message, pointing at the token for authentication failures
+fn error(response: Response) -> String {
+    if let Ok(invalid) = serde_json::from_str::<InvalidTrade>(&response.body) {
+        let messages: Vec<String> = invalid.errors.into_iter().map(|e| e.message).collect();
+        return format!("Invalid trade: {}", messages.join(", "));
+    }
+
+    match response.status {
+        401 => "Not logged in, create an API token in the frontend and set it with --token or ARDY_TOKEN"
+            .to_string(),
+        403 => format!("{}, the API token needs the write scope", response.body),
+        status => format!("Backend responded with {}: {}", status, response.body),
+    }
+}
+
+fn json<T: DeserializeOwned>(response: Response) -> Result<T> {
+    serde_json::from_str(&response.body)
+        .map_err(|e| format!("Invalid response from the backend: {}", e))
+}
+
+impl TradeStore for ApiStore {
+    // The API doesn't say which id the trade got
+    fn add(&self, trade: &ItemData) -> Result<Option<i64>> {
+        let response = response(self.request("POST", "/api/v1/trade").send_json(trade))?;
+
+        if response.is_success() {
+            Ok(None)
+        } else {
+            Err(error(response))
+        }
+    }
+
+    fn list(&self, filter: &TradeFilter) -> Result<Vec<ItemData>> {
+        let mut request = self.request("GET", "/api/v1/trade");
+        if let Some(item_name) = &filter.item_name {
+            request = request.query("item_name", item_name);
+        }
+        if let Some(account) = filter.account {
+            request = request.query("account", &account.to_string());
+        }
+        if let Some(tag) = &filter.tag {
+            request = request.query("tag", tag);
+        }
+
+        let response = response(request.call())?;
+
+        if response.is_success() {
+            json(response)
+        } else {
+            Err(error(response))
+        }
+    }
+
+    fn remove(&self, id: i64) -> Result<()> {
+        let response = response(
+            self.request("DELETE", "/api/v1/trade")
+                .query("id", &id.to_string())
+                .call(),
+        )?;
+
+        match response.status {
+            404 => Err(format!("Trade {} not found", id)),
+            _ if response.is_success() => Ok(()),
+            _ => Err(error(response)),
+        }
+    }
+
+    fn import(&self, trades: &[ItemData]) -> Result<BulkTradeResponse> {
+        let operations: Vec<_> = trades
+            .iter()
+            .map(|trade| json!({ "op": "create", "trade": trade }))
+            .collect();
+        let response = response(
+            self.request("POST", "/api/v1/trades/bulk")
+                .send_json(json!({ "operations": operations })),
+        )?;
+
+        // Failed operations are reported with 400 along with the outcome of each
+        match response.status {
+            200 => json(response),
+            400 => serde_json::from_str(&response.body).map_err(|_| error(response)),
+            _ => Err(error(response)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backend_urls_need_a_scheme() {
+        let store = ApiStore::new("https://example.com/ardy/", None).unwrap();
+        assert_eq!(store.url, "https://example.com/ardy");
+
+        assert!(ApiStore::new("http://localhost:43211", None).is_ok());
+        assert!(ApiStore::new("localhost:43211", None).is_err());
+    }
+}
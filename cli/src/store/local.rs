@@ -0,0 +1,154 @@
+use std::path::Path;
+
+use backend::audit::Audit;
+use backend::auth::{self, AuthenticatedUser};
+use backend::db::{self, Database};
+use backend::repository::{SqliteTradeRepository, TradeRepository};
+use backend::routes::bulk;
+use shared::models::{BulkTradeResponse, ItemData, TradeFilter, TradeOperation};
+
+use crate::store::TradeStore;
+use crate::Result;
+
+// Works on the database file directly, without a running backend. Changes are recorded in the
+// audit log as made by the user acting. A running backend doesn't notice them, so open pages
+// only show them after a reload.
+pub struct LocalStore {
+    trades: SqliteTradeRepository,
+    user: AuthenticatedUser,
+    audit: Audit,
+}
+
+impl LocalStore {
+    pub fn open(path: &Path, username: &str) -> Result<Self> {
+        // Opening a path that doesn't exist would create an empty database there
+        if !path.is_file() {
+            return Err(format!("No database at {}", path.display()));
+        }
+        let db = Database::new(path);
+
+        let conn = db.open().map_err(|e| e.to_string())?;
+        if db::pending_migrations(&conn).map_err(|e| e.to_string())? > 0 {
+            return Err(
+                "The database isn't fully migrated, start the backend once to migrate it"
+                    .to_string(),
+            );
+        }
+
+        let user = auth::find_local_user(&db, username)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("No user named {}", username))?;
+        let audit = Audit::system().for_user(user.id, &user.username);
+
+        Ok(LocalStore {
+            trades: SqliteTradeRepository::new(db),
+            user,
+            audit,
+        })
+    }
+}
+
+impl TradeStore for LocalStore {
+    fn add(&self, trade: &ItemData) -> Result<Option<i64>> {
+        let trade = self
+            .trades
+            .insert(&self.audit, &self.user, trade)
+            .map_err(|e| e.to_string())?;
+        Ok(Some(trade.id))
+    }
+
+    fn list(&self, filter: &TradeFilter) -> Result<Vec<ItemData>> {
+        self.trades
+            .list(&self.user, filter)
+            .map_err(|e| e.to_string())
+    }
+
+    fn remove(&self, id: i64) -> Result<()> {
+        self.trades
+            .delete(&self.audit, &self.user, id)
+            .map_err(|e| e.to_string())?
+            .map(|_| ())
+            .ok_or_else(|| format!("Trade {} not found", id))
+    }
+
+    fn import(&self, trades: &[ItemData]) -> Result<BulkTradeResponse> {
+        let operations: Vec<TradeOperation> = trades
+            .iter()
+            .map(|trade| TradeOperation::Create {
+                trade: trade.clone(),
+            })
+            .collect();
+
+        let (response, _) =
+            bulk::apply_operations(&self.trades, &self.audit, &self.user, &operations)
+                .map_err(|e| e.to_string())?;
+
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::parse_timestamp;
+
+    fn trade(item_name: &str, account_id: Option<i64>) -> ItemData {
+        ItemData {
+            id: 0,
+            item_name: item_name.to_string(),
+            quantity: 500,
+            total_price: 2_300,
+            is_purchase: true,
+            timestamp: parse_timestamp("2024-01-31T13:45").unwrap(),
+            account_id,
+            note: None,
+            session_id: None,
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn works_on_the_users_trades() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ardy.db");
+
+        // Not created by accident
+        assert!(LocalStore::open(&path, "alice").is_err());
+
+        let db = Database::new(&path);
+        db::init_db(&db).unwrap();
+        assert!(LocalStore::open(&path, "alice").is_err());
+
+        db.open()
+            .unwrap()
+            .execute(
+                "INSERT INTO users (username, password_hash) VALUES ('alice', '')",
+                [],
+            )
+            .unwrap();
+        let store = LocalStore::open(&path, "alice").unwrap();
+
+        let id = store.add(&trade("Dragon bones", None)).unwrap().unwrap();
+        assert_eq!(store.list(&TradeFilter::default()).unwrap().len(), 1);
+
+        // Imports apply all or nothing
+        let response = store
+            .import(&[
+                trade("Rune platebody", None),
+                trade("Abyssal whip", Some(99)),
+            ])
+            .unwrap();
+        assert!(!response.applied);
+        assert_eq!(store.list(&TradeFilter::default()).unwrap().len(), 1);
+
+        let response = store.import(&[trade("Rune platebody", None)]).unwrap();
+        assert!(response.applied);
+        assert_eq!(store.list(&TradeFilter::default()).unwrap().len(), 2);
+
+        store.remove(id).unwrap();
+        assert!(store.remove(id).is_err());
+        let trades = store.list(&TradeFilter::default()).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].item_name, "Rune platebody");
+    }
+}
@@ -0,0 +1,65 @@
+use reqwasm::http::{Request, RequestCredentials};
+
+// Where the backend runs, set with ARDY_API_URL when building the frontend
+const API_URL: &str = match option_env!("ARDY_API_URL") {
+    Some(url) => url,
+    None => "http://localhost:43211",
+};
+
+// The backend's URL for a path like /api/v1/trade
+pub fn url(path: &str) -> String {
+    format!("{}{}", API_URL, path)
+}
+
+// Requests to the backend include the session cookie, which identifies the logged in user.
+// They take the path on the backend, along with its query.
+pub fn get(path: &str) -> Request {
+    Request::get(&url(path)).credentials(RequestCredentials::Include)
+}
+
+pub fn post(path: &str) -> Request {
+    Request::post(&url(path)).credentials(RequestCredentials::Include)
+}
+
+pub fn put(path: &str) -> Request {
+    Request::put(&url(path)).credentials(RequestCredentials::Include)
+}
+
+pub fn delete(path: &str) -> Request {
+    Request::delete(&url(path)).credentials(RequestCredentials::Include)
+}
+
+// Send a request that modifies data on the backend, mapping non-success responses to their error body
+pub async fn send_request(request: Request) -> Result<(), String> {
+    match request.send().await {
+        Ok(resp) if resp.ok() => Ok(()),
+        Ok(resp) => Err(resp
+            .text()
+            .await
+            .unwrap_or_else(|_| format!("Request failed with status {}", resp.status()))),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// Append the query parameters that have a value to the given URL
+pub fn url_with_query(url: &str, params: &[(&str, Option<String>)]) -> String {
+    let query = params
+        .iter()
+        .filter_map(|(key, value)| {
+            value
+                .as_ref()
+                .map(|value| format!("{}={}", key, encode_query_value(value)))
+        })
+        .collect::<Vec<String>>()
+        .join("&");
+
+    if query.is_empty() {
+        url.to_string()
+    } else {
+        format!("{}?{}", url, query)
+    }
+}
+
+fn encode_query_value(value: &str) -> String {
+    String::from(web_sys::js_sys::encode_uri_component(value))
+}
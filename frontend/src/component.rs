@@ -1,5 +1,18 @@
+pub mod account_switcher;
 pub mod add_transaction_overlay;
+pub mod api_tokens;
 pub mod app;
+pub mod coins;
+pub mod detail_view;
+pub mod flip_list;
+pub mod goal_form;
+pub mod history;
+pub mod login;
+pub mod nav_bar;
 pub mod transaction_list;
+pub mod trash;
 pub mod profit_loss;
 pub mod search_bar;
+pub mod session_timer;
+pub mod slot_board;
+pub mod tag_input;
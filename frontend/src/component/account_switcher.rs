@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+use crate::api::{self, send_request};
+use crate::logging::error;
+
+pub struct AccountSwitcher {
+    accounts: Vec<Account>,
+    account_name: String,
+    error_message: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Account {
+    pub id: i64,
+    pub name: String,
+}
+
+#[derive(Serialize)]
+struct NewAccount {
+    name: String,
+}
+
+pub enum Msg {
+    GetAccounts,
+    GetAccountsComplete(Vec<Account>),
+    Select(Option<i64>),
+    UpdateAccountName(String),
+    AddAccount,
+    RenameAccount(i64),
+    DeleteAccount(i64),
+    AccountRequestComplete(Result<(), String>),
+}
+
+#[derive(PartialEq, Properties, Clone)]
+pub struct Props {
+    pub selected: Option<i64>,
+    pub on_select: Callback<Option<i64>>,
+}
+
+impl Component for AccountSwitcher {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Msg::GetAccounts);
+
+        Self {
+            accounts: Vec::new(),
+            account_name: String::new(),
+            error_message: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetAccounts => {
+                ctx.link().send_future(async {
+                    let resp = api::get("/api/v1/account")
+                        .send()
+                        .await;
+
+                    match resp {
+                        Ok(resp) => {
+                            let accounts = resp.json::<Vec<Account>>().await.unwrap_or_default();
+                            Msg::GetAccountsComplete(accounts)
+                        }
+                        Err(_) => Msg::GetAccountsComplete(Vec::new()),
+                    }
+                });
+                false
+            }
+            Msg::GetAccountsComplete(accounts) => {
+                // Fall back to all accounts if the selected one no longer exists
+                if let Some(selected) = ctx.props().selected {
+                    if !accounts.iter().any(|account| account.id == selected) {
+                        ctx.props().on_select.emit(None);
+                    }
+                }
+
+                self.accounts = accounts;
+                true
+            }
+            Msg::Select(account) => {
+                ctx.props().on_select.emit(account);
+                false
+            }
+            Msg::UpdateAccountName(name) => {
+                self.account_name = name;
+                false
+            }
+            Msg::AddAccount => {
+                let account_json = serde_json::to_string(&NewAccount {
+                    name: self.account_name.clone(),
+                })
+                .unwrap();
+
+                ctx.link().send_future(async {
+                    let request = api::post("/api/v1/account")
+                        .header("Content-Type", "application/json")
+                        .body(account_json);
+                    Msg::AccountRequestComplete(send_request(request).await)
+                });
+                false
+            }
+            Msg::RenameAccount(id) => {
+                let account_json = serde_json::to_string(&NewAccount {
+                    name: self.account_name.clone(),
+                })
+                .unwrap();
+
+                ctx.link().send_future(async move {
+                    let url = format!("/api/v1/account/{}", id);
+                    let request = api::put(&url)
+                        .header("Content-Type", "application/json")
+                        .body(account_json);
+                    Msg::AccountRequestComplete(send_request(request).await)
+                });
+                false
+            }
+            Msg::DeleteAccount(id) => {
+                ctx.link().send_future(async move {
+                    let url = format!("/api/v1/account/{}", id);
+                    Msg::AccountRequestComplete(send_request(api::delete(&url)).await)
+                });
+                false
+            }
+            Msg::AccountRequestComplete(result) => {
+                match result {
+                    Ok(()) => self.error_message = None,
+                    Err(e) => {
+                        error!("Account request failed: {}", e);
+                        self.error_message = Some(e);
+                    }
+                }
+
+                ctx.link().send_message(Msg::GetAccounts);
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let selected = ctx.props().selected;
+
+        html! {
+            <div class="account-switcher">
+                <select
+                    onchange={ctx.link().callback(|e: Event| {
+                        let select: HtmlSelectElement = e.target_unchecked_into();
+                        Msg::Select(select.value().parse::<i64>().ok())
+                    })}
+                >
+                    <option value="" selected={selected.is_none()}>{ "All accounts" }</option>
+                    { for self.accounts.iter().map(|account| html! {
+                        <option value={account.id.to_string()} selected={selected == Some(account.id)}>
+                            { &account.name }
+                        </option>
+                    }) }
+                </select>
+                <input
+                    type="text"
+                    placeholder="Account name"
+                    oninput={ctx.link().callback(|e: InputEvent| {
+                        let input: HtmlInputElement = e.target_unchecked_into();
+                        Msg::UpdateAccountName(input.value())
+                    })}
+                />
+                <button onclick={ctx.link().callback(|_| Msg::AddAccount)}>{ "Add" }</button>
+                if let Some(id) = selected {
+                    <button onclick={ctx.link().callback(move |_| Msg::RenameAccount(id))}>{ "Rename" }</button>
+                    <button class="material-icons" onclick={ctx.link().callback(move |_| Msg::DeleteAccount(id))}>
+                        { "delete" }
+                    </button>
+                }
+                if let Some(error_message) = &self.error_message {
+                    <p class="slot-error">{ error_message }</p>
+                }
+            </div>
+        }
+    }
+}
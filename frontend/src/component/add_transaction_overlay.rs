@@ -1,9 +1,18 @@
-use gloo::console::{error, info};
-use reqwasm::http::Request;
+use chrono::{Local, NaiveDateTime};
+use gloo::storage::{LocalStorage, Storage};
 use serde::{Deserialize, Serialize};
+use shared::gp;
+use shared::trade::{self, Field, FieldError};
 use web_sys::HtmlInputElement;
 use yew::prelude::*;
 
+use crate::api;
+use crate::component::tag_input::{self, Tag, TagInput};
+use crate::logging::{debug, error, info};
+
+// Local storage key remembering whether the form keeps its values after a trade is added
+const KEEP_VALUES_KEY: &str = "ardy_keep_trade_values";
+
 pub struct AddTransactionOverlay {
     state: State,
     trade: Trade,
@@ -13,12 +22,28 @@ struct State {
     show_overlay: bool,
     is_purchase_radio_button_checked: bool,
     submit_status: SubmitStatus,
+    tag_suggestions: Vec<String>,
+    // Amounts as typed, since they can be written like 1.5m
+    quantity_input: String,
+    price_input: String,
+    // Only shown once the user has tried to submit, and then kept up to date as they type
+    show_errors: bool,
+    errors: Vec<FieldError>,
+    // Whether the form keeps its values after a trade is added, for logging similar trades
+    keep_values: bool,
+    // Changed to reset the form, which recreates its inputs empty
+    form_key: u32,
 }
 
+// Where submitting a trade is up to. The status of the last submission stays until the next one
+// or until the overlay is toggled.
+#[derive(PartialEq)]
 enum SubmitStatus {
-    Success,
-    Failure,
-    None,
+    Idle,
+    Submitting,
+    Succeeded,
+    // With the message to show, from the backend if it gave one
+    Failed(String),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -29,22 +54,37 @@ pub struct Trade {
     total_price: i64,
     is_purchase: bool,
     timestamp: String,
+    account_id: Option<i64>,
+    note: Option<String>,
+    tags: Vec<String>,
+}
+
+// Body of the backend's response to an invalid trade
+#[derive(Deserialize)]
+struct InvalidTrade {
+    errors: Vec<FieldError>,
 }
 
 pub enum Msg {
     ToggleOverlay,
     SubmitTrade(SubmitEvent),
     SubmitTradeSuccess,
-    SubmitTradeFailure,
+    SubmitTradeFailure(String),
+    SubmitTradeInvalid(Vec<FieldError>),
+    UpdateKeepValues(bool),
     UpdateItemName(String),
-    UpdateQuantityTraded(i64),
-    UpdateTotalTradeValue(i64),
+    UpdateQuantityTraded(String),
+    UpdateTotalTradeValue(String),
     UpdateIsPurchase(bool),
     UpdateTimestamp(String),
+    UpdateNote(String),
+    UpdateTags(Vec<String>),
+    GetTagsComplete(Vec<Tag>),
 }
 
 #[derive(PartialEq, Properties, Clone)]
 pub struct Props {
+    pub account: Option<i64>,
     pub update: Callback<bool>,
 }
 
@@ -57,83 +97,145 @@ impl Component for AddTransactionOverlay {
             state: State {
                 show_overlay: false,
                 is_purchase_radio_button_checked: true,
-                submit_status: SubmitStatus::None,
-            },
-            trade: Trade {
-                id: 0,
-                item_name: String::new(),
-                quantity: 0,
-                total_price: 0,
-                is_purchase: true,
-                timestamp: String::new(),
+                submit_status: SubmitStatus::Idle,
+                tag_suggestions: Vec::new(),
+                quantity_input: String::new(),
+                price_input: String::new(),
+                show_errors: false,
+                errors: Vec::new(),
+                keep_values: LocalStorage::get(KEEP_VALUES_KEY).unwrap_or(false),
+                form_key: 0,
             },
+            trade: empty_trade(),
         }
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        // Errors from the backend are shown as they are, until the user changes something
+        let from_backend = matches!(msg, Msg::SubmitTradeInvalid(_));
+        let should_render = self.update_state(ctx, msg);
+
+        // Errors follow along with every change once they're shown
+        if self.state.show_errors && !from_backend {
+            self.state.errors = self.validate();
+        }
+
+        should_render
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        // This is the HTML that will be rendered by the component
+        self.construct_base_component_html(ctx)
+    }
+}
+
+fn empty_trade() -> Trade {
+    Trade {
+        id: 0,
+        item_name: String::new(),
+        quantity: 0,
+        total_price: 0,
+        is_purchase: true,
+        timestamp: String::new(),
+        account_id: None,
+        note: None,
+        tags: Vec::new(),
+    }
+}
+
+impl AddTransactionOverlay {
+    fn update_state(&mut self, ctx: &Context<Self>, msg: Msg) -> bool {
         // Match Msg enum to update the state of the component based on the desired action
         match msg {
             // Toggle the overlay and return true to re-render the component
             Msg::ToggleOverlay => {
                 self.state.show_overlay = !self.state.show_overlay;
                 // Log the value of self.show_overlay
-                info!(format!("self.show_overlay: {}", &self.state.show_overlay));
+                debug!("self.show_overlay: {}", &self.state.show_overlay);
+
+                // Forget how the last submission went, unless it's still going
+                if self.state.submit_status != SubmitStatus::Submitting {
+                    self.state.submit_status = SubmitStatus::Idle;
+                }
 
-                // Set submit_status to None
-                self.state.submit_status = SubmitStatus::None;
+                // Refresh the tag suggestions, since tags may have been added since the last time
+                if self.state.show_overlay {
+                    ctx.link().send_future(async { Msg::GetTagsComplete(tag_input::get_tags().await) });
+                }
             
                 true
             }
             // Update the trade struct and return false to prevent re-rendering the component
             Msg::UpdateItemName(name) => {
                 self.trade.item_name = name;
-                info!(format!("self.trade.item_name: {}", &self.trade.item_name));
+                debug!("self.trade.item_name: {}", &self.trade.item_name);
                 false
             }
             Msg::UpdateQuantityTraded(quantity) => {
-                self.trade.quantity = quantity;
-                info!(format!(
-                    "self.trade.quantity_traded: {}",
-                    &self.trade.quantity
-                ));
+                self.state.quantity_input = quantity;
+                debug!("self.state.quantity_input: {}", &self.state.quantity_input);
                 false
             }
             Msg::UpdateTotalTradeValue(value) => {
-                self.trade.total_price = value;
-                info!(format!(
-                    "self.trade.total_trade_value: {}",
-                    &self.trade.total_price
-                ));
+                self.state.price_input = value;
+                debug!("self.state.price_input: {}", &self.state.price_input);
                 false
             }
             Msg::UpdateIsPurchase(is_purchase) => {
                 self.trade.is_purchase = is_purchase;
                 self.state.is_purchase_radio_button_checked = is_purchase;
-                info!(format!(
-                    "self.trade.is_purchase: {}",
-                    &self.trade.is_purchase
-                ));
+                debug!("self.trade.is_purchase: {}", &self.trade.is_purchase);
                 false
             }
             Msg::UpdateTimestamp(timestamp) => {
                 self.trade.timestamp = timestamp;
-                info!(format!("self.trade.timestamp: {}", &self.trade.timestamp));
+                debug!("self.trade.timestamp: {}", &self.trade.timestamp);
+                false
+            }
+            Msg::UpdateNote(note) => {
+                // An empty note is stored as no note
+                self.trade.note = Some(note).filter(|note| !note.trim().is_empty());
                 false
             }
+            Msg::UpdateTags(tags) => {
+                self.trade.tags = tags;
+                true
+            }
+            Msg::GetTagsComplete(tags) => {
+                self.state.tag_suggestions = tags.into_iter().map(|tag| tag.name).collect();
+                true
+            }
             // Submit the trade struct to the backend and return true to re-render the component
             Msg::SubmitTrade(event) => {
                 // Prevent the default behavior of the event (i.e. prevent the form from submitting)
                 event.prevent_default();
 
-                self.state.submit_status = SubmitStatus::None;
+                // The button is disabled while submitting, but Enter still submits the form
+                if self.state.submit_status == SubmitStatus::Submitting {
+                    return false;
+                }
+                self.state.submit_status = SubmitStatus::Idle;
+
+                // Nothing is sent until every field is valid
+                self.state.show_errors = true;
+                self.state.errors = self.validate();
+                if !self.state.errors.is_empty() {
+                    return true;
+                }
+                self.trade.quantity = gp::parse(&self.state.quantity_input).unwrap_or_default();
+                self.trade.total_price = gp::parse(&self.state.price_input).unwrap_or_default();
+
+                // Log the trade against the account currently selected in the switcher
+                self.trade.account_id = ctx.props().account;
 
                 // Convert trade struct to JSON
                 let trade_json = serde_json::to_string(&self.trade).unwrap();
+                self.state.submit_status = SubmitStatus::Submitting;
 
                 // Send the trade to the backend
                 ctx.link().send_future(async {
-                    info!(format!("Sending trade: {}", trade_json));
-                    let resp = Request::post("http://localhost:43211/api/v1/trade")
+                    debug!("Sending trade: {}", trade_json);
+                    let resp = api::post("/api/v1/trade")
                         .header("Content-Type", "application/json")
                         .body(trade_json)
                         .send()
@@ -141,52 +243,140 @@ impl Component for AddTransactionOverlay {
 
                     // Match the response to determine whether the trade was submitted successfully
                     match resp {
-                        // If the trade was submitted successfully, log the response body and return Msg::SubmitTradeSuccess
-                        Ok(resp) => {
-                            info!(format!("Response: {:?}", resp.body()));
+                        // If the trade was saved, log the response body and return Msg::SubmitTradeSuccess
+                        Ok(resp) if resp.ok() => {
+                            debug!("Response: {:?}", resp.body());
                             Msg::SubmitTradeSuccess
                         }
-                        // If the trade submission failed, log the error and return Msg::SubmitTradeFailure
+                        // The backend checks the trade too, and says which fields it rejected
+                        Ok(resp) if resp.status() == 400 => match resp.json::<InvalidTrade>().await {
+                            Ok(invalid) => Msg::SubmitTradeInvalid(invalid.errors),
+                            Err(_) => Msg::SubmitTradeFailure(format!(
+                                "The backend responded with {}: {}",
+                                resp.status(),
+                                resp.text().await.unwrap_or_default()
+                            )),
+                        },
+                        // Any other status is a failure, with the body saying why
+                        Ok(resp) => {
+                            let status = resp.status();
+                            let body = resp.text().await.unwrap_or_default();
+                            error!("Trade submission failed with status {}: {}", status, body);
+                            Msg::SubmitTradeFailure(format!("The backend responded with {}: {}", status, body))
+                        }
+                        // If the request couldn't be sent, log the error and return Msg::SubmitTradeFailure
                         Err(e) => {
-                            error!(format!("Error: {:?}", e.to_string()));
-                            Msg::SubmitTradeFailure
+                            error!("Error: {:?}", e.to_string());
+                            Msg::SubmitTradeFailure(format!("Couldn't reach the backend: {}", e))
                         }
                     }
                 });
 
-                // Emit
-                ctx.props().update.emit(true);
-
                 true
             }
             Msg::SubmitTradeSuccess => {
                 info!("Trade submitted successfully");
-                self.state.submit_status = SubmitStatus::Success;
+                self.state.submit_status = SubmitStatus::Succeeded;
+
+                // Only now is there a new trade for the parents to show
+                ctx.props().update.emit(true);
+
+                if !self.state.keep_values {
+                    self.reset_form();
+                }
                 true
             }
-            Msg::SubmitTradeFailure => {
+            Msg::SubmitTradeFailure(message) => {
                 info!("Trade submission failed");
-                self.state.submit_status = SubmitStatus::Failure;
+                self.state.submit_status = SubmitStatus::Failed(message);
+                true
+            }
+            Msg::SubmitTradeInvalid(errors) => {
+                info!("Trade was rejected as invalid");
+                self.state.submit_status =
+                    SubmitStatus::Failed("The backend rejected some of the fields".to_string());
+                self.state.show_errors = true;
+                self.state.errors = errors;
+                true
+            }
+            Msg::UpdateKeepValues(keep_values) => {
+                self.state.keep_values = keep_values;
+                if let Err(e) = LocalStorage::set(KEEP_VALUES_KEY, keep_values) {
+                    error!("Failed to save the keep values setting: {}", e);
+                }
                 true
             }
         };
         true
     }
 
-    fn view(&self, ctx: &Context<Self>) -> Html {
-        // This is the HTML that will be rendered by the component
-        self.construct_base_component_html(&ctx)
+    // Empty every field for the next trade
+    fn reset_form(&mut self) {
+        self.trade = empty_trade();
+        self.state.is_purchase_radio_button_checked = true;
+        self.state.quantity_input.clear();
+        self.state.price_input.clear();
+        self.state.show_errors = false;
+        self.state.errors.clear();
+        self.state.form_key += 1;
+    }
+
+    // Check every field with the same rules the backend uses. Timestamps are in local time, so
+    // here they can be compared with the local time exactly.
+    fn validate(&self) -> Vec<FieldError> {
+        let amount = |field: Field, input: &str| {
+            gp::parse(input).map_err(|e| FieldError::new(field, e.to_string()))
+        };
+        let quantity = amount(Field::Quantity, &self.state.quantity_input);
+        let price = amount(Field::Price, &self.state.price_input);
+
+        [
+            trade::validate_item_name(&self.trade.item_name),
+            quantity.clone().and_then(trade::validate_quantity),
+            price.and_then(|price| trade::validate_price(price, quantity.unwrap_or(1))),
+            NaiveDateTime::parse_from_str(&self.trade.timestamp, "%Y-%m-%dT%H:%M")
+                .map_err(|_| FieldError::new(Field::Timestamp, "Enter a date and time"))
+                .and_then(|timestamp| {
+                    trade::validate_timestamp(timestamp, Local::now().naive_local())
+                }),
+        ]
+        .into_iter()
+        .filter_map(Result::err)
+        .collect()
+    }
+
+    fn error_for(&self, field: Field) -> Option<&str> {
+        self.state
+            .errors
+            .iter()
+            .find(|error| error.field == field)
+            .map(|error| error.message.as_str())
+    }
+
+    // Class of an input, marking it when its value is invalid
+    fn input_class(&self, field: Field) -> &'static str {
+        if self.error_for(field).is_some() {
+            "invalid"
+        } else {
+            ""
+        }
+    }
+
+    // The error for a field, shown under its input
+    fn construct_field_error_html(&self, field: Field) -> Html {
+        match self.error_for(field) {
+            Some(message) => html! { <p class="field-error">{ message }</p> },
+            None => html! {},
+        }
     }
-}
 
-impl AddTransactionOverlay {
     fn construct_base_component_html(&self, ctx: &Context<Self>) -> Html {
         html! {
             <>
                 <button id="add-transaction-button" onclick={ctx.link().callback(|_| Msg::ToggleOverlay)}>
                     {"Add Transaction"}
                 </button> {
-                    self.construct_overlay_html(&ctx)
+                    self.construct_overlay_html(ctx)
                 }
             </>
         }
@@ -208,12 +398,12 @@ impl AddTransactionOverlay {
         html! {
             <div class={overlay_container_class}>
                 <div class={overlay_class}> {
-                    self.construct_overlay_header_html(&ctx)
+                    self.construct_overlay_header_html(ctx)
                 } {
-                    self.construct_overlay_body_html(&ctx)
+                    self.construct_overlay_body_html(ctx)
                 } 
-                if let SubmitStatus::Success = self.state.submit_status {
-                    { self.construct_success_fail_msg_html(&ctx) }
+                if matches!(self.state.submit_status, SubmitStatus::Succeeded | SubmitStatus::Failed(_)) {
+                    { self.construct_success_fail_msg_html(ctx) }
                 }
                 </div>
             </div>
@@ -231,36 +421,42 @@ impl AddTransactionOverlay {
     fn construct_overlay_body_html(&self, ctx: &Context<Self>) -> Html {
         html! {
             <div class="overlay-body"> {
-                self.construct_form_html(&ctx)
+                self.construct_form_html(ctx)
             } {
-                self.construct_close_button_html(&ctx)
+                self.construct_close_button_html(ctx)
             }
             </div>
         }
     }
 
     fn construct_success_fail_msg_html(&self, _ctx: &Context<Self>) -> Html {
+        let (class, icon, title, message) = match &self.state.submit_status {
+            SubmitStatus::Failed(message) => ("alert-card failure", "error", "Failed", message.as_str()),
+            _ => ("alert-card", "check_circle", "Success", "Trade submitted successfully"),
+        };
+
         html! {
-            <div class="alert-card">
+            <div class={class}>
                 <div class="bar"></div>
-                <span class="material-icons">{"check_circle"}</span>
+                <span class="material-icons">{icon}</span>
                 <div class="content">
-                    <h1>{"Success"}</h1>
-                    <p>{"Trade submitted successfully"}</p>
+                    <h1>{title}</h1>
+                    <p>{message}</p>
                 </div>
             </div>
         }
-           
     }
 
     fn construct_form_html(&self, ctx: &Context<Self>) -> Html {
         html! {
-            <form id="add-transaction-form" onsubmit={ctx.link().callback(|event| Msg::SubmitTrade(event))}>
+            <form key={self.state.form_key} id="add-transaction-form" novalidate=true onsubmit={ctx.link().callback(Msg::SubmitTrade)}>
                 <div class="label-input-container">
+                    { self.construct_field_error_html(Field::ItemName) }
                     <input
                         type = "text"
                         id = "item-name"
                         name = "item-name"
+                        class = {self.input_class(Field::ItemName)}
                         required = true
                         oninput = {
                             ctx.link().callback(|e: InputEvent| {
@@ -273,31 +469,30 @@ impl AddTransactionOverlay {
                 </div>
 
                 <div class="label-input-container">
+                    { self.construct_field_error_html(Field::Quantity) }
                     <input
-                        type="number"
+                        type="text"
+                        inputmode="decimal"
                         id="quantity-traded"
                         name="quantity-traded"
+                        class={self.input_class(Field::Quantity)}
                         required=true
-                        pattern="\\d+"
                         oninput={ctx.link().callback(|e: InputEvent| {
-                            if let Some(input) = e.target_dyn_into::<HtmlInputElement>() {
-                                match input.value().parse::<i64>() {
-                                    Ok(quantity) => Msg::UpdateQuantityTraded(quantity),
-                                    Err(_) => Msg::UpdateQuantityTraded(0),
-                                }
-                            } else {
-                                Msg::UpdateQuantityTraded(0)
-                            }
+                            let input: HtmlInputElement = e.target_unchecked_into();
+                            Msg::UpdateQuantityTraded(input.value())
                         })}
                     />
                     <label for="quantity-traded">{"Quantity Traded"}</label>
                 </div>
 
                 <div class="label-input-container">
-                    <input type="number" id="total-trade-value" name="total-trade-value" required=true
+                    { self.construct_field_error_html(Field::Price) }
+                    <input type="text" inputmode="decimal" id="total-trade-value" name="total-trade-value" required=true
+                        class={self.input_class(Field::Price)}
+                        placeholder="e.g. 250k or 1.5m"
                         oninput={ctx.link().callback(|e: InputEvent| {
                             let input: HtmlInputElement = e.target_unchecked_into();
-                            Msg::UpdateTotalTradeValue(input.value().parse::<i64>().unwrap())
+                            Msg::UpdateTotalTradeValue(input.value())
                         })}
                     />
                     <label for="total-trade-value">{"Price Per Item"}</label>
@@ -320,7 +515,9 @@ impl AddTransactionOverlay {
                 </div>
 
                 <div class="label-input-container">
+                    { self.construct_field_error_html(Field::Timestamp) }
                     <input type="datetime-local" id="timestamp" name="timestamp" required=true
+                        class={self.input_class(Field::Timestamp)}
                         oninput={ctx.link().callback(|e: InputEvent| {
                             let input: HtmlInputElement = e.target_unchecked_into();
                             Msg::UpdateTimestamp(input.value())
@@ -329,7 +526,40 @@ impl AddTransactionOverlay {
                     <label for="timestamp">{"Timestamp"}</label>
                 </div>
 
-                <button type="submit">{"Submit"}</button>
+                <div class="label-input-container">
+                    <input type="text" id="note" name="note"
+                        oninput={ctx.link().callback(|e: InputEvent| {
+                            let input: HtmlInputElement = e.target_unchecked_into();
+                            Msg::UpdateNote(input.value())
+                        })}
+                    />
+                    <label for="note">{"Note"}</label>
+                </div>
+
+                <div class="label-input-container">
+                    <TagInput
+                        id="overlay-tag-suggestions"
+                        tags={self.trade.tags.clone()}
+                        suggestions={self.state.tag_suggestions.clone()}
+                        on_change={ctx.link().callback(Msg::UpdateTags)} />
+                    <label>{"Tags"}</label>
+                </div>
+
+                <div class="radio-button-container">
+                    <input type="checkbox" id="keep-values" checked={self.state.keep_values}
+                        onclick={ctx.link().callback(|e: MouseEvent| {
+                            let input: HtmlInputElement = e.target_unchecked_into();
+                            Msg::UpdateKeepValues(input.checked())
+                        })}
+                    />
+                    <label for="keep-values">{"Keep values after submitting"}</label>
+                </div>
+
+                if self.state.submit_status == SubmitStatus::Submitting {
+                    <button type="submit" disabled=true>{"Submitting..."}</button>
+                } else {
+                    <button type="submit">{"Submit"}</button>
+                }
 
             </form>
         }
@@ -0,0 +1,271 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::api::{self, send_request};
+use crate::logging::error;
+
+const SCOPES: [&str; 3] = ["read", "write", "admin"];
+
+pub struct ApiTokens {
+    api_tokens: Vec<ApiToken>,
+    name: String,
+    scopes: BTreeSet<String>,
+    expires_in_days: String,
+    created_token: Option<String>,
+    error_message: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ApiToken {
+    pub id: i64,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub created_at: String,
+    pub expires_at: Option<String>,
+    pub last_used_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CreatedApiToken {
+    token: String,
+}
+
+#[derive(Serialize)]
+struct NewApiToken {
+    name: String,
+    scopes: Vec<String>,
+    expires_in_days: Option<i64>,
+}
+
+pub enum Msg {
+    GetApiTokens,
+    GetApiTokensComplete(Vec<ApiToken>),
+    UpdateName(String),
+    ToggleScope(String),
+    UpdateExpiresInDays(String),
+    CreateApiToken,
+    CreateApiTokenComplete(Result<String, String>),
+    RevokeApiToken(i64),
+    ApiTokenRequestComplete(Result<(), String>),
+}
+
+impl Component for ApiTokens {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Msg::GetApiTokens);
+
+        Self {
+            api_tokens: Vec::new(),
+            name: String::new(),
+            scopes: BTreeSet::from(["read".to_string()]),
+            expires_in_days: String::new(),
+            created_token: None,
+            error_message: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetApiTokens => {
+                ctx.link().send_future(async {
+                    let resp = api::get("/api/v1/tokens")
+                        .send()
+                        .await;
+
+                    match resp {
+                        Ok(resp) => {
+                            let api_tokens = resp.json::<Vec<ApiToken>>().await.unwrap_or_default();
+                            Msg::GetApiTokensComplete(api_tokens)
+                        }
+                        Err(_) => Msg::GetApiTokensComplete(Vec::new()),
+                    }
+                });
+                false
+            }
+            Msg::GetApiTokensComplete(api_tokens) => {
+                self.api_tokens = api_tokens;
+                true
+            }
+            Msg::UpdateName(name) => {
+                self.name = name;
+                false
+            }
+            Msg::ToggleScope(scope) => {
+                if !self.scopes.remove(&scope) {
+                    self.scopes.insert(scope);
+                }
+                true
+            }
+            Msg::UpdateExpiresInDays(expires_in_days) => {
+                self.expires_in_days = expires_in_days;
+                false
+            }
+            Msg::CreateApiToken => {
+                // An empty expiry means the token never expires
+                let expires_in_days = match self.expires_in_days.trim() {
+                    "" => None,
+                    days => match days.parse::<i64>() {
+                        Ok(days) => Some(days),
+                        Err(_) => {
+                            self.error_message = Some("Expiry must be a number of days".to_string());
+                            return true;
+                        }
+                    },
+                };
+
+                let api_token_json = serde_json::to_string(&NewApiToken {
+                    name: self.name.clone(),
+                    scopes: self.scopes.iter().cloned().collect(),
+                    expires_in_days,
+                })
+                .unwrap();
+
+                ctx.link().send_future(async {
+                    let resp = api::post("/api/v1/tokens")
+                        .header("Content-Type", "application/json")
+                        .body(api_token_json)
+                        .send()
+                        .await;
+
+                    let result = match resp {
+                        Ok(resp) if resp.ok() => resp
+                            .json::<CreatedApiToken>()
+                            .await
+                            .map(|created| created.token)
+                            .map_err(|e| e.to_string()),
+                        Ok(resp) => Err(resp
+                            .text()
+                            .await
+                            .unwrap_or_else(|_| format!("Request failed with status {}", resp.status()))),
+                        Err(e) => Err(e.to_string()),
+                    };
+                    Msg::CreateApiTokenComplete(result)
+                });
+                false
+            }
+            Msg::CreateApiTokenComplete(result) => {
+                match result {
+                    Ok(token) => {
+                        self.created_token = Some(token);
+                        self.error_message = None;
+                    }
+                    Err(e) => {
+                        error!("Creating API token failed: {}", e);
+                        self.error_message = Some(e);
+                    }
+                }
+
+                ctx.link().send_message(Msg::GetApiTokens);
+                true
+            }
+            Msg::RevokeApiToken(id) => {
+                ctx.link().send_future(async move {
+                    let url = format!("/api/v1/tokens/{}", id);
+                    Msg::ApiTokenRequestComplete(send_request(api::delete(&url)).await)
+                });
+                false
+            }
+            Msg::ApiTokenRequestComplete(result) => {
+                match result {
+                    Ok(()) => self.error_message = None,
+                    Err(e) => {
+                        error!("API token request failed: {}", e);
+                        self.error_message = Some(e);
+                    }
+                }
+
+                ctx.link().send_message(Msg::GetApiTokens);
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <div class="transaction-panel">
+                <h2>{ "API tokens" }</h2>
+                <p>
+                    { "See the " }
+                    <a href={api::url("/api/v1/docs")} target="_blank">{ "API docs" }</a>
+                    { " for what scripts can do with a token." }
+                </p>
+                <div class="flip-toolbar">
+                    <input
+                        type="text"
+                        placeholder="Token name"
+                        oninput={ctx.link().callback(|e: InputEvent| {
+                            let input: HtmlInputElement = e.target_unchecked_into();
+                            Msg::UpdateName(input.value())
+                        })}
+                    />
+                    { for SCOPES.iter().map(|scope| html! {
+                        <label>
+                            <input
+                                type="checkbox"
+                                checked={self.scopes.contains(*scope)}
+                                onclick={ctx.link().callback(move |_| Msg::ToggleScope(scope.to_string()))}
+                            />
+                            { scope }
+                        </label>
+                    }) }
+                    <input
+                        type="text"
+                        placeholder="Expires in days (blank for never)"
+                        oninput={ctx.link().callback(|e: InputEvent| {
+                            let input: HtmlInputElement = e.target_unchecked_into();
+                            Msg::UpdateExpiresInDays(input.value())
+                        })}
+                    />
+                    <button onclick={ctx.link().callback(|_| Msg::CreateApiToken)}>{ "Create" }</button>
+                </div>
+                if let Some(token) = &self.created_token {
+                    <p class="created-token">
+                        { "Copy this token now, it won't be shown again: " }
+                        <code>{ token }</code>
+                    </p>
+                }
+                if let Some(error_message) = &self.error_message {
+                    <p class="slot-error">{ error_message }</p>
+                }
+                <div class="transaction-list-container">
+                    <table class="transaction-list-table">
+                        <thead>
+                            <tr>
+                                <th style="border-top-left-radius:8px">{ "Name" }</th>
+                                <th>{ "Scopes" }</th>
+                                <th>{ "Created" }</th>
+                                <th>{ "Expires" }</th>
+                                <th>{ "Last used" }</th>
+                                <th style="border-top-right-radius:8px">{ "Actions" }</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            { for self.api_tokens.iter().map(|api_token| {
+                                let id = api_token.id;
+                                html! {
+                                    <tr>
+                                        <td>{ &api_token.name }</td>
+                                        <td>{ api_token.scopes.join(", ") }</td>
+                                        <td>{ &api_token.created_at }</td>
+                                        <td>{ api_token.expires_at.as_deref().unwrap_or("Never") }</td>
+                                        <td>{ api_token.last_used_at.as_deref().unwrap_or("Never") }</td>
+                                        <td>
+                                            <button class="material-icons" onclick={ctx.link().callback(move |_| Msg::RevokeApiToken(id))}>
+                                                { "delete" }
+                                            </button>
+                                        </td>
+                                    </tr>
+                                }
+                            }) }
+                        </tbody>
+                    </table>
+                </div>
+            </div>
+        }
+    }
+}
@@ -1,59 +1,98 @@
+use crate::api;
 use crate::component::*;
-use gloo::console::log;
+use crate::component::login::User;
+use crate::logging::{debug, error};
+use crate::page;
+use crate::route::Route;
 use yew::prelude::*;
+use yew_router::prelude::*;
 
 pub struct App {
-    profit_loss_update_counter: i64,
     transaction_list_update_counter: i64,
-    search_string: String,
+    account: Option<i64>,
+    user: Option<User>,
+    session_checked: bool,
 }
 
 pub enum Msg {
-    UpdateProfitLoss(bool),
     UpdateTransactionList(bool),
-    UpdateTransactionListSearch(String),
+    SelectAccount(Option<i64>),
+    SessionChecked(Option<User>),
+    LoggedIn(User),
+    Logout,
+    LoggedOut,
 }
 
 impl Component for App {
     type Message = Msg;
     type Properties = ();
 
-    fn create(_ctx: &Context<Self>) -> Self {
+    fn create(ctx: &Context<Self>) -> Self {
+        // Check for an existing session before showing either the login screen or the app
+        ctx.link().send_future(async {
+            let resp = api::get("/api/v1/auth/me")
+                .send()
+                .await;
+
+            match resp {
+                Ok(resp) if resp.ok() => Msg::SessionChecked(resp.json::<User>().await.ok()),
+                _ => Msg::SessionChecked(None),
+            }
+        });
+
         Self {
-            profit_loss_update_counter: 0,
             transaction_list_update_counter: 0,
-            search_string: String::new(),
+            account: None,
+            user: None,
+            session_checked: false,
         }
     }
 
-    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
-            Msg::UpdateProfitLoss(should_update) => {
-                log!(format!("should_update in fn update(): {}", should_update));
+            Msg::UpdateTransactionList(should_update) => {
+                debug!("should_update in fn update(): {}", should_update);
 
-                // Update profit loss
+                // Update transaction list
                 if should_update {
-                    self.profit_loss_update_counter += 1;
+                    self.transaction_list_update_counter += 1;
                 }
 
                 true
             },
-            Msg::UpdateTransactionList(should_update) => {
-                log!(format!("should_update in fn update(): {}", should_update));
+            Msg::SelectAccount(account) => {
+                debug!("account in fn update(): {:?}", account);
 
-                // Update transaction list
-                if should_update {
-                    self.transaction_list_update_counter += 1;
-                    self.profit_loss_update_counter += 1;
-                }
+                // Scope every component to the selected account
+                self.account = account;
+
+                true
+            },
+            Msg::SessionChecked(user) => {
+                self.user = user;
+                self.session_checked = true;
+
+                true
+            },
+            Msg::LoggedIn(user) => {
+                self.user = Some(user);
 
                 true
             },
-            Msg::UpdateTransactionListSearch(item_name) => {
-                log!(format!("item_name in fn update(): {}", item_name));
+            Msg::Logout => {
+                ctx.link().send_future(async {
+                    if let Err(e) = api::send_request(api::post("/api/v1/auth/logout")).await {
+                        error!("Logout failed: {}", e);
+                    }
+                    Msg::LoggedOut
+                });
 
-                // Update search string
-                self.search_string = item_name;
+                false
+            },
+            Msg::LoggedOut => {
+                // Don't carry the selected account over to the next user
+                self.user = None;
+                self.account = None;
 
                 true
             }
@@ -61,40 +100,96 @@ impl Component for App {
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
+        if !self.session_checked {
+            return html! {};
+        }
+
+        let Some(user) = &self.user else {
+            return html! {
+                <>
+                    <div id="app" class="pattern">
+                    </div>
+                    <div class="content">
+                        <main>
+                            <login::Login on_login={ctx.link().callback(Msg::LoggedIn)} />
+                        </main>
+                    </div>
+                </>
+            };
+        };
+
+        // The user bar, account switcher, session timer and add transaction overlay stay put while
+        // the page below them changes, so the selected account and a half-filled trade survive
+        // navigation
         html! {
-            <>
+            <BrowserRouter>
                 <div id="app" class="pattern">
                 </div>
                 <div class="content">
                     <main>
-                        // Search bar
-                        <search_bar::SearchBar
-                            on_search={
-                                ctx.link().callback(|search_string| Msg::UpdateTransactionListSearch(search_string))
-                            }/>
-
-                        // Account for transaction list props
-                        <transaction_list::TransactionList should_update={
-                            {
-                                log!(format!("should_update_transaction_list in fn view(): {}", self.transaction_list_update_counter));
-                            }
-                            self.transaction_list_update_counter
-                        } search_string={
-                            {
-                                log!(format!("search_string in fn view(): {}", self.search_string));
-                            }
-                            self.search_string.clone()
-                        } update={ctx.link().callback(|should_update| Msg::UpdateProfitLoss(should_update))} />
-                        <profit_loss::ProfitLoss update_counter={
-                            {
-                                log!(format!("should_update_profit_loss in fn view(): {}", self.profit_loss_update_counter));
-                            }
-                            self.profit_loss_update_counter
-                        }/>
-                        <add_transaction_overlay::AddTransactionOverlay update={ctx.link().callback(|should_update| Msg::UpdateTransactionList(should_update))} />
+                        <div class="user-bar">
+                            <span>{ format!("Logged in as {}", user.username) }</span>
+                            <button onclick={ctx.link().callback(|_| Msg::Logout)}>{ "Log out" }</button>
+                        </div>
+
+                        <nav_bar::NavBar />
+
+                        // Account switcher
+                        <account_switcher::AccountSwitcher selected={self.account} on_select={ctx.link().callback(Msg::SelectAccount)} />
+
+                        <session_timer::SessionTimer account={self.account} />
+
+                        <Switch<Route> render={self.construct_page_callback(ctx)} />
+
+                        <add_transaction_overlay::AddTransactionOverlay account={self.account} update={ctx.link().callback(Msg::UpdateTransactionList)} />
                     </main>
                 </div>
-            </>
+            </BrowserRouter>
         }
     }
 }
+
+impl App {
+    // Render the page for a route, scoped to the selected account
+    fn construct_page_callback(&self, ctx: &Context<Self>) -> Callback<Route, Html> {
+        let account = self.account;
+        let update_counter = self.transaction_list_update_counter;
+        let update = ctx.link().callback(Msg::UpdateTransactionList);
+
+        Callback::from(move |route: Route| match route {
+            Route::Dashboard => html! {
+                <>
+                    <page::dashboard::Dashboard account={account} />
+                    <slot_board::SlotBoard account={account} update={update.clone()} />
+                </>
+            },
+            Route::Transactions => html! {
+                <page::transactions::Transactions account={account} />
+            },
+            Route::Item { name } => html! {
+                <page::item::Item name={name} account={account} />
+            },
+            Route::Holdings => html! {
+                <page::holdings::Holdings account={account} />
+            },
+            Route::Reports => html! {
+                <>
+                    <profit_loss::ProfitLoss account={account} />
+                    <flip_list::FlipList update_counter={update_counter} account={account} />
+                </>
+            },
+            Route::ImportExport => html! {
+                <page::import_export::ImportExport account={account} />
+            },
+            Route::Settings => html! {
+                <api_tokens::ApiTokens />
+            },
+            Route::NotFound => html! {
+                <div class="transaction-panel">
+                    <p>{ "There's no page here." }</p>
+                    <Link<Route> to={Route::Dashboard}>{ "Go to the dashboard" }</Link<Route>>
+                </div>
+            },
+        })
+    }
+}
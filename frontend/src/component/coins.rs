@@ -0,0 +1,34 @@
+use shared::gp;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+// An amount of gp labelled the way the game labels a stack of coins, with the full amount shown
+// on hover
+pub fn coins(amount: i64) -> Html {
+    let stack = gp::stack(amount);
+
+    html! {
+        <span
+            class="coins"
+            style={format!("color: {};", stack.colour.hex())}
+            title={format!("{} gp", gp::format(amount))}
+        >
+            { stack.text }
+        </span>
+    }
+}
+
+// Read an amount typed like 1.5m, 250k or 1,234,567 from an input. Anything else marks the input
+// as invalid, which stops its form from being submitted, and reads as 0.
+pub fn read_amount(input: &HtmlInputElement) -> i64 {
+    match gp::parse(&input.value()) {
+        Ok(amount) => {
+            input.set_custom_validity("");
+            amount
+        }
+        Err(e) => {
+            input.set_custom_validity(&e.to_string());
+            0
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use shared::gp;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::component::history;
+use crate::component::transaction_list::Transaction;
+use crate::route::Route;
+
+// What a detail view shows: a single trade, or an item and all of its trades
+#[derive(Clone, Debug, PartialEq)]
+pub enum Detail {
+    Trade(Transaction),
+    Item(String),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Tab {
+    Details,
+    History,
+}
+
+pub struct DetailView {
+    tab: Tab,
+}
+
+pub enum Msg {
+    SelectTab(Tab),
+}
+
+#[derive(PartialEq, Properties, Clone)]
+pub struct Props {
+    pub detail: Detail,
+    // Trades of the item shown in an item detail view
+    #[prop_or_default]
+    pub transactions: Vec<Transaction>,
+    pub on_close: Callback<()>,
+}
+
+impl Component for DetailView {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self { tab: Tab::Details }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, old_props: &Self::Properties) -> bool {
+        if ctx.props().detail != old_props.detail {
+            self.tab = Tab::Details;
+        }
+        true
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::SelectTab(tab) => {
+                self.tab = tab;
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let title = match &ctx.props().detail {
+            Detail::Trade(transaction) => format!("Trade {}", transaction.id),
+            Detail::Item(item_name) => item_name.clone(),
+        };
+
+        let tab_class = |tab: Tab| if self.tab == tab { "detail-tab selected" } else { "detail-tab" };
+
+        html! {
+            <div class="overlay-container">
+                <div class="overlay detail-view">
+                    <h2>{ title }</h2>
+                    <div class="detail-tabs">
+                        <button class={tab_class(Tab::Details)} onclick={ctx.link().callback(|_| Msg::SelectTab(Tab::Details))}>
+                            { "Details" }
+                        </button>
+                        <button class={tab_class(Tab::History)} onclick={ctx.link().callback(|_| Msg::SelectTab(Tab::History))}>
+                            { "History" }
+                        </button>
+                    </div>
+                    {
+                        match self.tab {
+                            Tab::Details => self.view_details(ctx),
+                            Tab::History => self.view_history(ctx),
+                        }
+                    }
+                    <button class="close-button" onclick={ctx.props().on_close.reform(|_| ())}>{ "Close" }</button>
+                </div>
+            </div>
+        }
+    }
+}
+
+impl DetailView {
+    fn view_details(&self, ctx: &Context<Self>) -> Html {
+        match &ctx.props().detail {
+            Detail::Trade(transaction) => html! {
+                <dl class="detail-fields">
+                    <dt>{ "Item" }</dt>
+                    <dd>{ &transaction.item_name }</dd>
+                    <dt>{ "Quantity" }</dt>
+                    <dd>{ transaction.quantity }</dd>
+                    <dt>{ "Price" }</dt>
+                    <dd>{ format!("{} gp", gp::format(transaction.total_price)) }</dd>
+                    <dt>{ "Sale or Purchase" }</dt>
+                    <dd>{ if transaction.is_purchase { "Purchase" } else { "Sale" } }</dd>
+                    <dt>{ "Date" }</dt>
+                    <dd>{ &transaction.timestamp }</dd>
+                    <dt>{ "Tags" }</dt>
+                    <dd>
+                        { for transaction.tags.iter().map(|tag| html! { <span class="tag-chip">{ tag }</span> }) }
+                    </dd>
+                    <dt>{ "Note" }</dt>
+                    <dd>{ transaction.note.as_deref().unwrap_or("") }</dd>
+                </dl>
+            },
+            Detail::Item(item_name) => {
+                let trades = ctx.props().transactions.iter().filter(|transaction| &transaction.item_name == item_name);
+                let (bought, sold) = trades.fold((0, 0), |(bought, sold), transaction| {
+                    if transaction.is_purchase {
+                        (bought + transaction.quantity, sold)
+                    } else {
+                        (bought, sold + transaction.quantity)
+                    }
+                });
+
+                html! {
+                    <dl class="detail-fields">
+                        <dt>{ "Bought" }</dt>
+                        <dd>{ bought }</dd>
+                        <dt>{ "Sold" }</dt>
+                        <dd>{ sold }</dd>
+                        <dt></dt>
+                        <dd>
+                            <Link<Route> to={Route::Item { name: item_name.clone() }}>{ "Open item page" }</Link<Route>>
+                        </dd>
+                    </dl>
+                }
+            }
+        }
+    }
+
+    fn view_history(&self, ctx: &Context<Self>) -> Html {
+        match &ctx.props().detail {
+            Detail::Trade(transaction) => html! {
+                <history::History table_name={"trades".to_string()} record_id={transaction.id} />
+            },
+            Detail::Item(item_name) => html! {
+                <history::History item_name={item_name.clone()} />
+            },
+        }
+    }
+}
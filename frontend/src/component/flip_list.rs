@@ -0,0 +1,267 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use shared::gp;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::api::{self, send_request, url_with_query};
+use crate::component::coins::coins;
+use crate::component::transaction_list::Transaction;
+use crate::logging::error;
+
+pub struct FlipList {
+    flips: Vec<Flip>,
+    expanded: HashSet<i64>,
+    trade_ids: String,
+    error_message: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Flip {
+    pub id: i64,
+    pub item_name: String,
+    pub status: String,
+    pub quantity_bought: i64,
+    pub quantity_sold: i64,
+    pub profit: i64,
+    pub roi: f64,
+    pub duration_seconds: i64,
+    pub trades: Vec<Transaction>,
+}
+
+#[derive(Serialize)]
+struct NewFlip {
+    trade_ids: Vec<i64>,
+}
+
+pub enum Msg {
+    GetFlips,
+    GetFlipsComplete(Vec<Flip>),
+    ToggleExpanded(i64),
+    AutoGroup,
+    UpdateTradeIds(String),
+    GroupTrades,
+    Ungroup(i64),
+    FlipRequestComplete(Result<(), String>),
+}
+
+#[derive(PartialEq, Properties, Clone)]
+pub struct Props {
+    pub update_counter: i64,
+    pub account: Option<i64>,
+}
+
+// Format a duration in seconds as e.g. "2d 4h" or "3h 15m"
+fn format_duration(seconds: i64) -> String {
+    let minutes = seconds / 60;
+    let hours = minutes / 60;
+    let days = hours / 24;
+
+    if days > 0 {
+        format!("{}d {}h", days, hours % 24)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes % 60)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+impl Component for FlipList {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Msg::GetFlips);
+
+        Self {
+            flips: Vec::new(),
+            expanded: HashSet::new(),
+            trade_ids: String::new(),
+            error_message: None,
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        ctx.link().send_message(Msg::GetFlips);
+        false
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetFlips => {
+                let url = url_with_query(
+                    "/api/v1/flips",
+                    &[("account", ctx.props().account.map(|account| account.to_string()))],
+                );
+
+                ctx.link().send_future(async move {
+                    let resp = api::get(&url)
+                        .send()
+                        .await;
+
+                    match resp {
+                        Ok(resp) => {
+                            let flips = resp.json::<Vec<Flip>>().await.unwrap_or_default();
+                            Msg::GetFlipsComplete(flips)
+                        }
+                        Err(_) => Msg::GetFlipsComplete(Vec::new()),
+                    }
+                });
+                false
+            }
+            Msg::GetFlipsComplete(flips) => {
+                self.flips = flips;
+                true
+            }
+            Msg::ToggleExpanded(id) => {
+                if !self.expanded.remove(&id) {
+                    self.expanded.insert(id);
+                }
+                true
+            }
+            Msg::AutoGroup => {
+                ctx.link().send_future(async {
+                    let request = api::post("/api/v1/flips/auto");
+                    Msg::FlipRequestComplete(send_request(request).await)
+                });
+                false
+            }
+            Msg::UpdateTradeIds(trade_ids) => {
+                self.trade_ids = trade_ids;
+                false
+            }
+            Msg::GroupTrades => {
+                let trade_ids = self
+                    .trade_ids
+                    .split(',')
+                    .map(|id| id.trim().parse::<i64>())
+                    .collect::<Result<Vec<i64>, _>>();
+
+                let trade_ids = match trade_ids {
+                    Ok(trade_ids) => trade_ids,
+                    Err(_) => {
+                        self.error_message =
+                            Some("Trade IDs must be a comma separated list of numbers".to_string());
+                        return true;
+                    }
+                };
+
+                let flip_json = serde_json::to_string(&NewFlip { trade_ids }).unwrap();
+
+                ctx.link().send_future(async {
+                    let request = api::post("/api/v1/flips")
+                        .header("Content-Type", "application/json")
+                        .body(flip_json);
+                    Msg::FlipRequestComplete(send_request(request).await)
+                });
+                false
+            }
+            Msg::Ungroup(id) => {
+                ctx.link().send_future(async move {
+                    let url = format!("/api/v1/flips/{}", id);
+                    Msg::FlipRequestComplete(send_request(api::delete(&url)).await)
+                });
+                false
+            }
+            Msg::FlipRequestComplete(result) => {
+                match result {
+                    Ok(()) => self.error_message = None,
+                    Err(e) => {
+                        error!("Flip request failed: {}", e);
+                        self.error_message = Some(e);
+                    }
+                }
+
+                ctx.link().send_message(Msg::GetFlips);
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <div class="transaction-panel">
+                <div class="flip-toolbar">
+                    <button onclick={ctx.link().callback(|_| Msg::AutoGroup)}>{ "Auto-group trades" }</button>
+                    <input
+                        type="text"
+                        placeholder="Trade IDs, e.g. 1, 2, 5"
+                        oninput={ctx.link().callback(|e: InputEvent| {
+                            let input: HtmlInputElement = e.target_unchecked_into();
+                            Msg::UpdateTradeIds(input.value())
+                        })}
+                    />
+                    <button onclick={ctx.link().callback(|_| Msg::GroupTrades)}>{ "Group" }</button>
+                </div>
+                if let Some(error_message) = &self.error_message {
+                    <p class="slot-error">{ error_message }</p>
+                }
+                <div class="transaction-list-container">
+                    <table class="transaction-list-table">
+                        <thead>
+                            <tr>
+                                <th style="border-top-left-radius:8px"></th>
+                                <th>{ "Item" }</th>
+                                <th>{ "Status" }</th>
+                                <th>{ "Bought / Sold" }</th>
+                                <th>{ "Profit" }</th>
+                                <th>{ "ROI" }</th>
+                                <th>{ "Duration" }</th>
+                                <th style="border-top-right-radius:8px">{ "Actions" }</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            { for self.flips.iter().map(|flip| self.render_flip(ctx, flip)) }
+                        </tbody>
+                    </table>
+                </div>
+            </div>
+        }
+    }
+}
+
+impl FlipList {
+    fn render_flip(&self, ctx: &Context<Self>, flip: &Flip) -> Html {
+        let id = flip.id;
+        let is_expanded = self.expanded.contains(&id);
+        let profit_class = if flip.profit >= 0 { "profit" } else { "loss" };
+
+        html! {
+            <>
+                <tr>
+                    <td>
+                        <button class="material-icons" onclick={ctx.link().callback(move |_| Msg::ToggleExpanded(id))}>
+                            { if is_expanded { "expand_less" } else { "expand_more" } }
+                        </button>
+                    </td>
+                    <td>{ &flip.item_name }</td>
+                    <td>{ flip.status.replace('_', " ") }</td>
+                    <td>{ format!("{} / {}", flip.quantity_bought, flip.quantity_sold) }</td>
+                    <td class={profit_class}>{ format!("{} gp", gp::format(flip.profit)) }</td>
+                    <td class={profit_class}>{ format!("{:.1}%", flip.roi * 100.0) }</td>
+                    <td>{ format_duration(flip.duration_seconds) }</td>
+                    <td>
+                        <button class="material-icons" onclick={ctx.link().callback(move |_| Msg::Ungroup(id))}>
+                            { "link_off" }
+                        </button>
+                    </td>
+                </tr>
+                if is_expanded {
+                    { for flip.trades.iter().map(|trade| html! {
+                        <tr class="flip-trade">
+                            <td>{ trade.id }</td>
+                            <td>{ &trade.item_name }</td>
+                            <td>{ if trade.is_purchase { "Purchase" } else { "Sale" } }</td>
+                            <td>{ trade.quantity }</td>
+                            <td>{ coins(trade.total_price) }</td>
+                            <td></td>
+                            <td>{ &trade.timestamp }</td>
+                            <td></td>
+                        </tr>
+                    }) }
+                }
+            </>
+        }
+    }
+}
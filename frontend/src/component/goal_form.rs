@@ -0,0 +1,199 @@
+use serde::Serialize;
+use shared::gp;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::api;
+use crate::logging::error;
+
+// Adds a goal of an amount of gp to make by a deadline, optionally only from one item or from the
+// selected account
+pub struct GoalForm {
+    name: String,
+    target: String,
+    // As picked in a date input, like 2024-02-29
+    deadline: String,
+    item_name: String,
+    only_account: bool,
+    sending: bool,
+    error_message: Option<String>,
+}
+
+#[derive(Serialize)]
+struct NewGoal {
+    name: String,
+    target: i64,
+    deadline: String,
+    item_name: Option<String>,
+    account_id: Option<i64>,
+}
+
+pub enum Msg {
+    UpdateName(String),
+    UpdateTarget(String),
+    UpdateDeadline(String),
+    UpdateItemName(String),
+    ToggleOnlyAccount,
+    AddGoal,
+    AddGoalComplete(Result<(), String>),
+}
+
+#[derive(PartialEq, Properties, Clone)]
+pub struct Props {
+    pub account: Option<i64>,
+    pub on_added: Callback<()>,
+}
+
+impl Component for GoalForm {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            name: String::new(),
+            target: String::new(),
+            deadline: String::new(),
+            item_name: String::new(),
+            only_account: false,
+            sending: false,
+            error_message: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::UpdateName(name) => {
+                self.name = name;
+                false
+            },
+            Msg::UpdateTarget(target) => {
+                self.target = target;
+                false
+            },
+            Msg::UpdateDeadline(deadline) => {
+                self.deadline = deadline;
+                false
+            },
+            Msg::UpdateItemName(item_name) => {
+                self.item_name = item_name;
+                false
+            },
+            Msg::ToggleOnlyAccount => {
+                self.only_account = !self.only_account;
+                true
+            },
+            Msg::AddGoal => {
+                if self.sending {
+                    return false;
+                }
+
+                let target = match gp::parse(&self.target) {
+                    Ok(target) => target,
+                    Err(e) => {
+                        self.error_message = Some(format!("Target: {}", e));
+                        return true;
+                    },
+                };
+                if self.deadline.is_empty() {
+                    self.error_message = Some("Pick a deadline".to_string());
+                    return true;
+                }
+
+                // The goal runs until the end of the day it's due
+                let goal_json = serde_json::to_string(&NewGoal {
+                    name: self.name.clone(),
+                    target,
+                    deadline: format!("{}T23:59", self.deadline),
+                    item_name: Some(self.item_name.trim().to_string()).filter(|item_name| !item_name.is_empty()),
+                    account_id: ctx.props().account.filter(|_| self.only_account),
+                })
+                .unwrap();
+
+                self.sending = true;
+                ctx.link().send_future(async {
+                    let request = api::post("/api/v1/goals")
+                        .header("Content-Type", "application/json")
+                        .body(goal_json);
+                    Msg::AddGoalComplete(api::send_request(request).await)
+                });
+                true
+            },
+            Msg::AddGoalComplete(result) => {
+                self.sending = false;
+                match result {
+                    Ok(()) => {
+                        self.name.clear();
+                        self.target.clear();
+                        self.deadline.clear();
+                        self.item_name.clear();
+                        self.error_message = None;
+                        ctx.props().on_added.emit(());
+                    },
+                    Err(e) => {
+                        error!("Adding goal failed: {}", e);
+                        self.error_message = Some(e);
+                    },
+                }
+                true
+            },
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <>
+                <div class="flip-toolbar goal-form">
+                    <input
+                        type="text"
+                        placeholder="Goal, like Twisted bow fund"
+                        value={self.name.clone()}
+                        oninput={ctx.link().callback(|e: InputEvent| {
+                            let input: HtmlInputElement = e.target_unchecked_into();
+                            Msg::UpdateName(input.value())
+                        })}
+                    />
+                    <input
+                        type="text"
+                        placeholder="Target, like 100m"
+                        value={self.target.clone()}
+                        oninput={ctx.link().callback(|e: InputEvent| {
+                            let input: HtmlInputElement = e.target_unchecked_into();
+                            Msg::UpdateTarget(input.value())
+                        })}
+                    />
+                    <input
+                        type="date"
+                        value={self.deadline.clone()}
+                        oninput={ctx.link().callback(|e: InputEvent| {
+                            let input: HtmlInputElement = e.target_unchecked_into();
+                            Msg::UpdateDeadline(input.value())
+                        })}
+                    />
+                    <input
+                        type="text"
+                        placeholder="Only from item (optional)"
+                        value={self.item_name.clone()}
+                        oninput={ctx.link().callback(|e: InputEvent| {
+                            let input: HtmlInputElement = e.target_unchecked_into();
+                            Msg::UpdateItemName(input.value())
+                        })}
+                    />
+                    if ctx.props().account.is_some() {
+                        <label>
+                            <input
+                                type="checkbox"
+                                checked={self.only_account}
+                                onclick={ctx.link().callback(|_| Msg::ToggleOnlyAccount)}
+                            />
+                            { "Only this account" }
+                        </label>
+                    }
+                    <button disabled={self.sending} onclick={ctx.link().callback(|_| Msg::AddGoal)}>{ "Add goal" }</button>
+                </div>
+                if let Some(error_message) = &self.error_message {
+                    <p class="slot-error">{ error_message }</p>
+                }
+            </>
+        }
+    }
+}
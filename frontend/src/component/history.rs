@@ -0,0 +1,145 @@
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use yew::prelude::*;
+
+use crate::api::{self, url_with_query};
+
+pub struct History {
+    entries: Vec<AuditEntry>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub actor: String,
+    pub timestamp: String,
+    pub request_id: Option<String>,
+    pub action: String,
+    pub table_name: String,
+    pub record_id: i64,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+pub enum Msg {
+    GetHistory,
+    GetHistoryComplete(Vec<AuditEntry>),
+}
+
+// Which changes to show: those to a single row, or those to an item and everything referring to it
+#[derive(PartialEq, Properties, Clone)]
+pub struct Props {
+    #[prop_or_default]
+    pub table_name: Option<String>,
+    #[prop_or_default]
+    pub record_id: Option<i64>,
+    #[prop_or_default]
+    pub item_name: Option<String>,
+}
+
+impl Component for History {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Msg::GetHistory);
+
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        ctx.link().send_message(Msg::GetHistory);
+        false
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetHistory => {
+                let props = ctx.props();
+                let url = url_with_query(
+                    "/api/v1/audit",
+                    &[
+                        ("table_name", props.table_name.clone()),
+                        ("record_id", props.record_id.map(|record_id| record_id.to_string())),
+                        ("item_name", props.item_name.clone()),
+                    ],
+                );
+
+                ctx.link().send_future(async move {
+                    let resp = api::get(&url)
+                        .send()
+                        .await;
+
+                    match resp {
+                        Ok(resp) => {
+                            let entries = resp.json::<Vec<AuditEntry>>().await.unwrap_or_default();
+                            Msg::GetHistoryComplete(entries)
+                        }
+                        Err(_) => Msg::GetHistoryComplete(Vec::new()),
+                    }
+                });
+                false
+            }
+            Msg::GetHistoryComplete(entries) => {
+                self.entries = entries;
+                true
+            }
+        }
+    }
+
+    fn view(&self, _ctx: &Context<Self>) -> Html {
+        if self.entries.is_empty() {
+            return html! { <p>{ "No changes recorded." }</p> };
+        }
+
+        html! {
+            <div class="transaction-list-container">
+                <table class="transaction-list-table">
+                    <thead>
+                        <tr>
+                            <th style="border-top-left-radius:8px">{ "Time" }</th>
+                            <th>{ "Actor" }</th>
+                            <th>{ "Action" }</th>
+                            <th style="border-top-right-radius:8px">{ "Changes" }</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        { for self.entries.iter().map(|entry| html! {
+                            <tr key={entry.id}>
+                                <td title={entry.request_id.clone()}>{ &entry.timestamp }</td>
+                                <td>{ &entry.actor }</td>
+                                <td>{ format!("{} {} {}", entry.action, entry.table_name, entry.record_id) }</td>
+                                <td class="history-changes">{ changed_fields(entry) }</td>
+                            </tr>
+                        }) }
+                    </tbody>
+                </table>
+            </div>
+        }
+    }
+}
+
+// Describe the columns that differ between the before and after snapshots of a change
+fn changed_fields(entry: &AuditEntry) -> String {
+    let empty = Map::new();
+    let before = entry.before.as_ref().and_then(Value::as_object).unwrap_or(&empty);
+    let after = entry.after.as_ref().and_then(Value::as_object).unwrap_or(&empty);
+
+    let mut columns: Vec<&String> = before.keys().chain(after.keys()).collect();
+    columns.sort();
+    columns.dedup();
+
+    columns
+        .into_iter()
+        .filter(|column| before.get(*column) != after.get(*column))
+        .map(|column| match (before.get(column), after.get(column)) {
+            (Some(old), Some(new)) => format!("{}: {} → {}", column, old, new),
+            (None, Some(new)) => format!("{}: {}", column, new),
+            (Some(old), None) => format!("{}: {} → removed", column, old),
+            (None, None) => unreachable!(),
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::api::{self, send_request};
+use crate::logging::error;
+
+pub struct Login {
+    username: String,
+    password: String,
+    is_registering: bool,
+    error_message: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct User {
+    pub id: i64,
+    pub username: String,
+}
+
+#[derive(Deserialize)]
+struct LoginResponse {
+    user: User,
+}
+
+#[derive(Serialize)]
+struct Credentials {
+    username: String,
+    password: String,
+}
+
+pub enum Msg {
+    UpdateUsername(String),
+    UpdatePassword(String),
+    ToggleRegistering,
+    Submit,
+    SubmitComplete(Result<User, String>),
+}
+
+#[derive(PartialEq, Properties, Clone)]
+pub struct Props {
+    pub on_login: Callback<User>,
+}
+
+// Log in with the given credentials, which sets the session cookie
+async fn login(credentials_json: String) -> Result<User, String> {
+    let resp = api::post("/api/v1/auth/login")
+        .header("Content-Type", "application/json")
+        .body(credentials_json)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if !resp.ok() {
+        return Err(resp.text().await.unwrap_or_else(|_| "Login failed".to_string()));
+    }
+
+    resp.json::<LoginResponse>()
+        .await
+        .map(|login_response| login_response.user)
+        .map_err(|e| e.to_string())
+}
+
+impl Component for Login {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            username: String::new(),
+            password: String::new(),
+            is_registering: false,
+            error_message: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::UpdateUsername(username) => {
+                self.username = username;
+                false
+            }
+            Msg::UpdatePassword(password) => {
+                self.password = password;
+                false
+            }
+            Msg::ToggleRegistering => {
+                self.is_registering = !self.is_registering;
+                self.error_message = None;
+                true
+            }
+            Msg::Submit => {
+                let credentials_json = serde_json::to_string(&Credentials {
+                    username: self.username.clone(),
+                    password: self.password.clone(),
+                })
+                .unwrap();
+                let is_registering = self.is_registering;
+
+                // Registering doesn't log in, so a new user is logged in right after
+                ctx.link().send_future(async move {
+                    if is_registering {
+                        let request = api::post("/api/v1/auth/register")
+                            .header("Content-Type", "application/json")
+                            .body(credentials_json.clone());
+
+                        if let Err(e) = send_request(request).await {
+                            return Msg::SubmitComplete(Err(e));
+                        }
+                    }
+
+                    Msg::SubmitComplete(login(credentials_json).await)
+                });
+                false
+            }
+            Msg::SubmitComplete(result) => {
+                match result {
+                    Ok(user) => {
+                        self.error_message = None;
+                        ctx.props().on_login.emit(user);
+                    }
+                    Err(e) => {
+                        error!("Login failed: {}", e);
+                        self.error_message = Some(e);
+                    }
+                }
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <form
+                class="login"
+                onsubmit={ctx.link().callback(|e: SubmitEvent| {
+                    e.prevent_default();
+                    Msg::Submit
+                })}
+            >
+                <h2>{ if self.is_registering { "Create an account" } else { "Log in" } }</h2>
+                <input
+                    type="text"
+                    placeholder="Username"
+                    autocomplete="username"
+                    oninput={ctx.link().callback(|e: InputEvent| {
+                        let input: HtmlInputElement = e.target_unchecked_into();
+                        Msg::UpdateUsername(input.value())
+                    })}
+                />
+                <input
+                    type="password"
+                    placeholder="Password"
+                    autocomplete={if self.is_registering { "new-password" } else { "current-password" }}
+                    oninput={ctx.link().callback(|e: InputEvent| {
+                        let input: HtmlInputElement = e.target_unchecked_into();
+                        Msg::UpdatePassword(input.value())
+                    })}
+                />
+                <button type="submit">{ if self.is_registering { "Register" } else { "Log in" } }</button>
+                <button type="button" onclick={ctx.link().callback(|_| Msg::ToggleRegistering)}>
+                    { if self.is_registering { "I already have an account" } else { "Create an account" } }
+                </button>
+                if let Some(error_message) = &self.error_message {
+                    <p class="slot-error">{ error_message }</p>
+                }
+            </form>
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::route::Route;
+
+// Links to the pages of the app, marking the one that's open
+pub struct NavBar {
+    _location_listener: Option<LocationHandle>,
+}
+
+pub enum Msg {
+    LocationChanged,
+}
+
+impl Component for NavBar {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            _location_listener: ctx.link().add_location_listener(ctx.link().callback(|_| Msg::LocationChanged)),
+        }
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::LocationChanged => true,
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let current = ctx.link().route::<Route>();
+
+        html! {
+            <nav class="nav-bar">
+                { for Route::NAVIGATION.into_iter().map(|(route, title)| {
+                    let selected = current.as_ref() == Some(&route);
+                    let classes = if selected { classes!("nav-link", "selected") } else { classes!("nav-link") };
+
+                    html! {
+                        <Link<Route> to={route} classes={classes}>{ title }</Link<Route>>
+                    }
+                }) }
+            </nav>
+        }
+    }
+}
@@ -1,9 +1,15 @@
-use gloo::console::log;
-use reqwasm::http::Request;
+use shared::gp;
 use yew::prelude::*;
 
+use crate::api::{self, url_with_query};
+use crate::component::transaction_list::Transaction;
+use crate::events::{self, EventSubscription, TradeEvent};
+use crate::logging::debug;
+
 pub struct ProfitLoss {
     state: State,
+    // Keeps the total up to date with trades changed anywhere
+    _events: Option<EventSubscription>,
 }
 
 pub struct State {
@@ -14,11 +20,22 @@ pub struct State {
 pub enum Msg {
     GetProfitLossComplete(i64),
     UpdateProfitLoss,
+    TradeEvent(Box<TradeEvent>),
 }
 
 #[derive(PartialEq, Properties, Clone)]
 pub struct Props {
-    pub update_counter: i64,
+    pub account: Option<i64>,
+}
+
+// What a trade adds to the profit/loss of the account shown, matching the backend's sum
+fn contribution(trade: &Transaction, account: Option<i64>) -> i64 {
+    if account.is_some_and(|account| trade.account_id != Some(account)) {
+        return 0;
+    }
+
+    let value = trade.quantity * trade.total_price;
+    if trade.is_purchase { -value } else { value }
 }
 
 impl Component for ProfitLoss {
@@ -26,10 +43,13 @@ impl Component for ProfitLoss {
     type Properties = Props;
 
     fn create(ctx: &Context<Self>) -> Self {
-        log!(format!("ctx.props().update_counter: {}", ctx.props().update_counter));
+        let url = url_with_query(
+            "/api/v1/profit_loss",
+            &[("account", ctx.props().account.map(|account| account.to_string()))],
+        );
 
-        ctx.link().send_future(async {
-            let resp = Request::get("http://localhost:43211/api/v1/profit_loss")
+        ctx.link().send_future(async move {
+            let resp = api::get(&url)
                 .send()
                 .await;
 
@@ -50,12 +70,15 @@ impl Component for ProfitLoss {
                 component_ready: false,
                 profit_loss: 0,
             },
+            _events: events::subscribe(ctx.link().callback(|event| Msg::TradeEvent(Box::new(event)))),
         }
     }
 
-    fn changed(&mut self, ctx: &Context<Self>, props: &Self::Properties) -> bool {
-        log!(format!("props.update: {}", props.update_counter));
-        ctx.link().send_message(Msg::UpdateProfitLoss);
+    fn changed(&mut self, ctx: &Context<Self>, old_props: &Self::Properties) -> bool {
+        // Changes to trades arrive as events, so only a different account needs a new total
+        if ctx.props().account != old_props.account {
+            ctx.link().send_message(Msg::UpdateProfitLoss);
+        }
         true
     }
 
@@ -67,12 +90,17 @@ impl Component for ProfitLoss {
                 true
             },
             Msg::UpdateProfitLoss => {
-                log!("Waiting 50ms before updating profit/loss");
-                log!("Updating profit/loss");
+                debug!("Waiting 50ms before updating profit/loss");
+                debug!("Updating profit/loss");
                 self.state.component_ready = false;
 
-                ctx.link().send_future(async {
-                    let resp = Request::get("http://localhost:43211/api/v1/profit_loss")
+                let url = url_with_query(
+                    "/api/v1/profit_loss",
+                    &[("account", ctx.props().account.map(|account| account.to_string()))],
+                );
+
+                ctx.link().send_future(async move {
+                    let resp = api::get(&url)
                         .send()
                         .await;
 
@@ -89,35 +117,32 @@ impl Component for ProfitLoss {
                 });
                 true
             },
+            Msg::TradeEvent(event) => {
+                let account = ctx.props().account;
+                match *event {
+                    TradeEvent::TradeCreated { trade } => {
+                        self.state.profit_loss += contribution(&trade, account);
+                    },
+                    TradeEvent::TradeUpdated { before, trade } => {
+                        self.state.profit_loss += contribution(&trade, account) - contribution(&before, account);
+                    },
+                    TradeEvent::TradeDeleted { trade } => {
+                        self.state.profit_loss -= contribution(&trade, account);
+                    },
+                    TradeEvent::Resync => ctx.link().send_message(Msg::UpdateProfitLoss),
+                }
+                true
+            },
         }
     }
 
     fn view(&self, _ctx: &Context<Self>) -> Html {
-        // Helper function to format the number with commas.
-        fn format_with_commas(n: i64) -> String {
-            let is_negative = n < 0;
-            let mut s = n.abs().to_string();
-            let mut pos = s.len() as isize - 3;
-            
-            while pos > 0 {
-                s.insert(pos as usize, ',');
-                pos -= 3;
-            }
-            
-            if is_negative {
-                s = format!("({})", s);
-            }
-            
-            s
-        }
-        
         // Conditionally render the profit/loss amount based on whether it's ready or not.
         let formatted_profit_loss = {
             // Assuming self.state.profit_loss is i64 or similar type.
             let color = if self.state.profit_loss >= 0 { "#a6da95" } else { "#ed8796" };
             
-            // Using the helper function to format the number with commas.
-            let formatted_number = format_with_commas(self.state.profit_loss);
+            let formatted_number = gp::format(self.state.profit_loss);
             
             let formatted_string = format!("{} gp", formatted_number);
             
@@ -13,6 +13,9 @@ pub enum Msg {
 
 #[derive(PartialEq, Properties, Clone)]
 pub struct Props {
+    // The search to start with, and to change to when it's changed from outside, like going back
+    #[prop_or_default]
+    pub value: String,
     pub on_search: Callback<String>,
 }
 
@@ -20,13 +23,23 @@ impl Component for SearchBar {
     type Message = Msg;
     type Properties = Props;
 
-    fn create(_ctx: &Context<Self>) -> Self {
+    fn create(ctx: &Context<Self>) -> Self {
         Self {
-            item_name: String::new(),
+            item_name: ctx.props().value.clone(),
             timeout_id: None,
         }
     }
 
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        // The value comes back from a search of what was typed, which mustn't undo any typing since
+        if ctx.props().value == self.item_name.trim() || self.timeout_id.is_some() {
+            return false;
+        }
+
+        self.item_name = ctx.props().value.clone();
+        true
+    }
+
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             // Update the item name used for searching, but wait 750ms before searching after no more
@@ -62,6 +75,7 @@ impl Component for SearchBar {
                 true
             },
             Msg::Search => {
+                self.timeout_id = None;
                 let item_name = self.item_name.clone();
                 ctx.props().on_search.emit(item_name);
                 true
@@ -0,0 +1,258 @@
+use chrono::Local;
+use gloo::timers::callback::Interval;
+use serde::Deserialize;
+use shared::gp;
+use shared::trade::{parse_timestamp, TIMESTAMP_FORMAT};
+use yew::prelude::*;
+
+use crate::api::{self, url_with_query};
+use crate::events::{self, EventSubscription, TradeEvent};
+
+// Starts and stops trading sessions, counting up while one is in progress. Trades added in the
+// meantime join the session on the backend, so its totals are fetched again whenever trades change.
+pub struct SessionTimer {
+    // The session in progress, if there is one
+    session: Option<TradingSession>,
+    // The session stopped most recently, to show how it went
+    last_session: Option<TradingSession>,
+    sending: bool,
+    error: Option<String>,
+    // Redraws the time spent every second while a session is in progress
+    _tick: Option<Interval>,
+    _events: Option<EventSubscription>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TradingSession {
+    pub id: i64,
+    pub started_at: String,
+    pub ended_at: Option<String>,
+    pub trade_count: i64,
+    pub profit: i64,
+    pub capital_used: i64,
+    pub gp_per_hour: Option<i64>,
+}
+
+pub enum Msg {
+    GetSessions,
+    GetSessionsComplete(Vec<TradingSession>),
+    Start,
+    Stop,
+    SendComplete(Result<TradingSession, String>),
+    Tick,
+    TradeChanged,
+}
+
+#[derive(PartialEq, Properties, Clone)]
+pub struct Props {
+    pub account: Option<i64>,
+}
+
+fn now() -> String {
+    Local::now().format(TIMESTAMP_FORMAT).to_string()
+}
+
+// Time since a session started, like "1:05:09"
+fn elapsed(started_at: &str) -> String {
+    let Ok(started_at) = parse_timestamp(started_at) else {
+        return String::new();
+    };
+
+    let seconds = (Local::now().naive_local() - started_at).num_seconds().max(0);
+    format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+// Start or stop a session, returning it as the backend has it afterwards
+async fn send(url: String) -> Result<TradingSession, String> {
+    let resp = api::post(&url)
+        .send()
+        .await
+        .map_err(|e| format!("Couldn't reach the backend: {}", e))?;
+
+    if resp.ok() {
+        resp.json::<TradingSession>().await.map_err(|e| e.to_string())
+    } else {
+        Err(resp
+            .text()
+            .await
+            .unwrap_or_else(|_| format!("The backend responded with {}", resp.status())))
+    }
+}
+
+impl Component for SessionTimer {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Msg::GetSessions);
+
+        Self {
+            session: None,
+            last_session: None,
+            sending: false,
+            error: None,
+            _tick: None,
+            _events: events::subscribe(ctx.link().callback(|_: TradeEvent| Msg::TradeChanged)),
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        ctx.link().send_message(Msg::GetSessions);
+        false
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetSessions => {
+                let url = url_with_query(
+                    "/api/v1/trading_sessions",
+                    &[
+                        ("account", ctx.props().account.map(|account| account.to_string())),
+                        ("now", Some(now())),
+                    ],
+                );
+
+                ctx.link().send_future(async move {
+                    let resp = api::get(&url)
+                        .send()
+                        .await;
+
+                    match resp {
+                        Ok(resp) if resp.ok() => Msg::GetSessionsComplete(resp.json::<Vec<TradingSession>>().await.unwrap_or_default()),
+                        _ => Msg::GetSessionsComplete(Vec::new()),
+                    }
+                });
+
+                false
+            },
+            Msg::GetSessionsComplete(sessions) => {
+                // The most recent session comes first, and only that one can be in progress
+                let mut sessions = sessions.into_iter();
+                match sessions.next() {
+                    Some(session) if session.ended_at.is_none() => {
+                        self.session = Some(session);
+                        self.last_session = sessions.next();
+                    },
+                    last_session => {
+                        self.session = None;
+                        self.last_session = last_session;
+                    },
+                }
+                self.update_tick(ctx);
+
+                true
+            },
+            Msg::Start => {
+                if self.sending {
+                    return false;
+                }
+                self.sending = true;
+                self.error = None;
+
+                let url = url_with_query("/api/v1/trading_sessions", &[("now", Some(now()))]);
+                ctx.link().send_future(async move { Msg::SendComplete(send(url).await) });
+
+                true
+            },
+            Msg::Stop => {
+                let Some(session) = &self.session else {
+                    return false;
+                };
+                if self.sending {
+                    return false;
+                }
+                self.sending = true;
+                self.error = None;
+
+                let url = url_with_query(
+                    &format!("/api/v1/trading_sessions/{}/stop", session.id),
+                    &[("now", Some(now()))],
+                );
+                ctx.link().send_future(async move { Msg::SendComplete(send(url).await) });
+
+                true
+            },
+            Msg::SendComplete(result) => {
+                self.sending = false;
+                match result {
+                    Ok(session) if session.ended_at.is_none() => self.session = Some(session),
+                    Ok(session) => {
+                        self.session = None;
+                        self.last_session = Some(session);
+                        // Stopping totals up every account, so fetch it again for the selected one
+                        ctx.link().send_message(Msg::GetSessions);
+                    },
+                    Err(e) => {
+                        self.error = Some(e);
+                        // Another tab may have started or stopped a session in the meantime
+                        ctx.link().send_message(Msg::GetSessions);
+                    },
+                }
+                self.update_tick(ctx);
+
+                true
+            },
+            Msg::Tick => true,
+            Msg::TradeChanged => {
+                ctx.link().send_message(Msg::GetSessions);
+
+                false
+            },
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <div class="session-timer">
+                {
+                    match &self.session {
+                        Some(session) => html! {
+                            <>
+                                <span class="session-elapsed">{ elapsed(&session.started_at) }</span>
+                                { Self::construct_totals_html(session) }
+                                <button disabled={self.sending} onclick={ctx.link().callback(|_| Msg::Stop)}>{ "Stop session" }</button>
+                            </>
+                        },
+                        None => html! {
+                            <>
+                                <button disabled={self.sending} onclick={ctx.link().callback(|_| Msg::Start)}>{ "Start session" }</button>
+                                if let Some(last_session) = &self.last_session {
+                                    <span class="session-last">{ format!("Last session {} to {}:", last_session.started_at, last_session.ended_at.clone().unwrap_or_default()) }</span>
+                                    { Self::construct_totals_html(last_session) }
+                                }
+                            </>
+                        },
+                    }
+                }
+                if let Some(error) = &self.error {
+                    <span class="slot-error">{ error }</span>
+                }
+            </div>
+        }
+    }
+}
+
+impl SessionTimer {
+    // Tick while a session is in progress, and stop ticking once there's none
+    fn update_tick(&mut self, ctx: &Context<Self>) {
+        if self.session.is_none() {
+            self._tick = None;
+        } else if self._tick.is_none() {
+            let link = ctx.link().clone();
+            self._tick = Some(Interval::new(1_000, move || link.send_message(Msg::Tick)));
+        }
+    }
+
+    fn construct_totals_html(session: &TradingSession) -> Html {
+        let gp_per_hour = session.gp_per_hour.map_or_else(|| "-".to_string(), |gp_per_hour| format!("{}/h", gp::format_compact(gp_per_hour)));
+
+        html! {
+            <span class="session-totals">
+                <span>{ format!("{} trades", session.trade_count) }</span>
+                <span class={if session.profit >= 0 { "profit" } else { "loss" }}>{ format!("{} gp", gp::format(session.profit)) }</span>
+                <span>{ gp_per_hour }</span>
+                <span title="Spent on purchases">{ format!("{} gp used", gp::format(session.capital_used)) }</span>
+            </span>
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::api::{self, send_request, url_with_query};
+use crate::component::coins::{coins, read_amount};
+use crate::logging::{debug, error};
+
+// Number of Grand Exchange slots on a members account
+const GE_SLOTS: i64 = 8;
+
+pub struct SlotBoard {
+    offers: Vec<Offer>,
+    selected_slot: Option<i64>,
+    new_offer: NewOffer,
+    fill_quantities: HashMap<i64, i64>,
+    error_message: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Offer {
+    pub id: i64,
+    pub item_name: String,
+    pub slot: i64,
+    pub is_purchase: bool,
+    pub price: i64,
+    pub quantity: i64,
+    pub quantity_filled: i64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct NewOffer {
+    item_name: String,
+    slot: i64,
+    is_purchase: bool,
+    price: i64,
+    quantity: i64,
+    account_id: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct OfferFill {
+    quantity: i64,
+}
+
+pub enum Msg {
+    GetOffers,
+    GetOffersComplete(Vec<Offer>),
+    SelectSlot(Option<i64>),
+    UpdateItemName(String),
+    UpdatePrice(i64),
+    UpdateQuantity(i64),
+    UpdateIsPurchase(bool),
+    SubmitOffer(SubmitEvent),
+    UpdateFillQuantity(i64, i64),
+    FillOffer(i64),
+    CancelOffer(i64),
+    CompleteOffer(i64),
+    // Result of a request that changed an offer, true if trades were created
+    OfferRequestComplete(Result<bool, String>),
+}
+
+#[derive(PartialEq, Properties, Clone)]
+pub struct Props {
+    pub account: Option<i64>,
+    pub update: Callback<bool>,
+}
+
+impl Component for SlotBoard {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Msg::GetOffers);
+
+        Self {
+            offers: Vec::new(),
+            selected_slot: None,
+            new_offer: NewOffer {
+                item_name: String::new(),
+                slot: 1,
+                is_purchase: true,
+                price: 0,
+                quantity: 0,
+                account_id: None,
+            },
+            fill_quantities: HashMap::new(),
+            error_message: None,
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        ctx.link().send_message(Msg::GetOffers);
+        false
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetOffers => {
+                let url = url_with_query(
+                    "/api/v1/offer",
+                    &[
+                        ("status", Some("open".to_string())),
+                        ("account", ctx.props().account.map(|account| account.to_string())),
+                    ],
+                );
+
+                ctx.link().send_future(async move {
+                    let resp = api::get(&url)
+                        .send()
+                        .await;
+
+                    match resp {
+                        Ok(resp) => {
+                            let offers = resp.json::<Vec<Offer>>().await.unwrap_or_default();
+                            Msg::GetOffersComplete(offers)
+                        }
+                        Err(_) => Msg::GetOffersComplete(Vec::new()),
+                    }
+                });
+                false
+            }
+            Msg::GetOffersComplete(offers) => {
+                self.offers = offers;
+                true
+            }
+            Msg::SelectSlot(slot) => {
+                self.selected_slot = slot;
+                if let Some(slot) = slot {
+                    self.new_offer.slot = slot;
+                }
+                self.error_message = None;
+                true
+            }
+            Msg::UpdateItemName(name) => {
+                self.new_offer.item_name = name;
+                false
+            }
+            Msg::UpdatePrice(price) => {
+                self.new_offer.price = price;
+                false
+            }
+            Msg::UpdateQuantity(quantity) => {
+                self.new_offer.quantity = quantity;
+                false
+            }
+            Msg::UpdateIsPurchase(is_purchase) => {
+                self.new_offer.is_purchase = is_purchase;
+                true
+            }
+            Msg::SubmitOffer(event) => {
+                event.prevent_default();
+
+                self.new_offer.account_id = ctx.props().account;
+                let offer_json = serde_json::to_string(&self.new_offer).unwrap();
+
+                ctx.link().send_future(async {
+                    debug!("Placing offer: {}", offer_json);
+                    let request = api::post("/api/v1/offer")
+                        .header("Content-Type", "application/json")
+                        .body(offer_json);
+
+                    Msg::OfferRequestComplete(send_request(request).await.map(|_| false))
+                });
+                false
+            }
+            Msg::UpdateFillQuantity(id, quantity) => {
+                self.fill_quantities.insert(id, quantity);
+                false
+            }
+            Msg::FillOffer(id) => {
+                let fill = OfferFill {
+                    quantity: self.fill_quantities.get(&id).copied().unwrap_or(0),
+                };
+                let fill_json = serde_json::to_string(&fill).unwrap();
+
+                ctx.link().send_future(async move {
+                    let url = format!("/api/v1/offer/{}/fill", id);
+                    let request = api::post(&url)
+                        .header("Content-Type", "application/json")
+                        .body(fill_json);
+
+                    Msg::OfferRequestComplete(send_request(request).await.map(|_| true))
+                });
+                false
+            }
+            Msg::CancelOffer(id) => {
+                ctx.link().send_future(async move {
+                    let url = format!("/api/v1/offer/{}/cancel", id);
+                    Msg::OfferRequestComplete(
+                        send_request(api::post(&url)).await.map(|_| false),
+                    )
+                });
+                false
+            }
+            Msg::CompleteOffer(id) => {
+                ctx.link().send_future(async move {
+                    let url = format!("/api/v1/offer/{}/complete", id);
+                    Msg::OfferRequestComplete(
+                        send_request(api::post(&url)).await.map(|_| false),
+                    )
+                });
+                false
+            }
+            Msg::OfferRequestComplete(result) => {
+                match result {
+                    Ok(trades_changed) => {
+                        self.error_message = None;
+                        self.selected_slot = None;
+
+                        // Fills create trades, so the transaction list and profit need refreshing
+                        if trades_changed {
+                            ctx.props().update.emit(true);
+                        }
+                    }
+                    Err(e) => {
+                        error!("Offer request failed: {}", e);
+                        self.error_message = Some(e);
+                    }
+                }
+
+                ctx.link().send_message(Msg::GetOffers);
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <div class="slot-board">
+                <div class="slot-grid">
+                    { for (1..=GE_SLOTS).map(|slot| self.render_slot(ctx, slot)) }
+                </div>
+                if let Some(error_message) = &self.error_message {
+                    <p class="slot-error">{ error_message }</p>
+                }
+                if let Some(slot) = self.selected_slot {
+                    { self.render_offer_form(ctx, slot) }
+                }
+            </div>
+        }
+    }
+}
+
+impl SlotBoard {
+    fn render_slot(&self, ctx: &Context<Self>, slot: i64) -> Html {
+        let offer = match self.offers.iter().find(|offer| offer.slot == slot) {
+            Some(offer) => offer,
+            None => {
+                return html! {
+                    <div class="slot empty">
+                        <span class="slot-number">{ format!("Slot {}", slot) }</span>
+                        <button onclick={ctx.link().callback(move |_| Msg::SelectSlot(Some(slot)))}>
+                            { "New offer" }
+                        </button>
+                    </div>
+                };
+            }
+        };
+
+        let id = offer.id;
+        let progress = offer.quantity_filled * 100 / offer.quantity.max(1);
+        let side_class = if offer.is_purchase { "slot buy" } else { "slot sell" };
+
+        html! {
+            <div class={side_class}>
+                <span class="slot-number">{ format!("Slot {}", slot) }</span>
+                <span class="slot-item">{ &offer.item_name }</span>
+                <span>{ format!("{} @ ", if offer.is_purchase { "Buy" } else { "Sell" }) }{ coins(offer.price) }</span>
+                <div class="slot-progress">
+                    <div class="slot-progress-fill" style={format!("width: {}%;", progress)}></div>
+                </div>
+                <span>{ format!("{} / {}", offer.quantity_filled, offer.quantity) }</span>
+                <input
+                    type="text"
+                    inputmode="decimal"
+                    placeholder="Qty"
+                    oninput={ctx.link().callback(move |e: InputEvent| {
+                        let input: HtmlInputElement = e.target_unchecked_into();
+                        Msg::UpdateFillQuantity(id, read_amount(&input))
+                    })}
+                />
+                <div class="slot-actions">
+                    <button onclick={ctx.link().callback(move |_| Msg::FillOffer(id))}>{ "Fill" }</button>
+                    <button onclick={ctx.link().callback(move |_| Msg::CompleteOffer(id))}>{ "Collect" }</button>
+                    <button onclick={ctx.link().callback(move |_| Msg::CancelOffer(id))}>{ "Cancel" }</button>
+                </div>
+            </div>
+        }
+    }
+
+    fn render_offer_form(&self, ctx: &Context<Self>, slot: i64) -> Html {
+        html! {
+            <form class="slot-offer-form" onsubmit={ctx.link().callback(Msg::SubmitOffer)}>
+                <h3>{ format!("New offer in slot {}", slot) }</h3>
+                <input
+                    type="text"
+                    placeholder="Item name"
+                    required=true
+                    oninput={ctx.link().callback(|e: InputEvent| {
+                        let input: HtmlInputElement = e.target_unchecked_into();
+                        Msg::UpdateItemName(input.value())
+                    })}
+                />
+                <input
+                    type="text"
+                    inputmode="decimal"
+                    placeholder="Price per item, e.g. 1.5m"
+                    required=true
+                    oninput={ctx.link().callback(|e: InputEvent| {
+                        let input: HtmlInputElement = e.target_unchecked_into();
+                        Msg::UpdatePrice(read_amount(&input))
+                    })}
+                />
+                <input
+                    type="text"
+                    inputmode="decimal"
+                    placeholder="Quantity"
+                    required=true
+                    oninput={ctx.link().callback(|e: InputEvent| {
+                        let input: HtmlInputElement = e.target_unchecked_into();
+                        Msg::UpdateQuantity(read_amount(&input))
+                    })}
+                />
+                <div class="slot-actions">
+                    <button
+                        type="button"
+                        class={if self.new_offer.is_purchase { "selected" } else { "" }}
+                        onclick={ctx.link().callback(|_| Msg::UpdateIsPurchase(true))}
+                    >
+                        { "Buy" }
+                    </button>
+                    <button
+                        type="button"
+                        class={if self.new_offer.is_purchase { "" } else { "selected" }}
+                        onclick={ctx.link().callback(|_| Msg::UpdateIsPurchase(false))}
+                    >
+                        { "Sell" }
+                    </button>
+                </div>
+                <div class="slot-actions">
+                    <button type="submit">{ "Place offer" }</button>
+                    <button type="button" onclick={ctx.link().callback(|_| Msg::SelectSlot(None))}>{ "Close" }</button>
+                </div>
+            </form>
+        }
+    }
+}
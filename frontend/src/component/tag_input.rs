@@ -0,0 +1,132 @@
+use serde::Deserialize;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::api;
+
+// Tags are shown as chips with a remove button. Typing a name and pressing Enter or a comma adds
+// it, with the user's existing tags offered as suggestions.
+pub struct TagInput {
+    input: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+    pub trade_count: i64,
+}
+
+pub enum Msg {
+    UpdateInput(String),
+    AddTag,
+    RemoveTag(String),
+}
+
+#[derive(PartialEq, Properties, Clone)]
+pub struct Props {
+    // Id of the suggestion list, which must be unique on the page
+    pub id: AttrValue,
+    pub tags: Vec<String>,
+    // Names of the user's existing tags, offered for autocomplete
+    pub suggestions: Vec<String>,
+    pub on_change: Callback<Vec<String>>,
+}
+
+// Get the user's tags, for autocomplete and filtering
+pub async fn get_tags() -> Vec<Tag> {
+    match api::get("/api/v1/tags").send().await {
+        Ok(resp) => resp.json::<Vec<Tag>>().await.unwrap_or_default(),
+        Err(_) => Vec::new(),
+    }
+}
+
+impl Component for TagInput {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            input: String::new(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::UpdateInput(input) => {
+                // Typing a comma finishes the tag, like pressing Enter
+                match input.strip_suffix(',') {
+                    Some(input) => {
+                        self.input = input.to_string();
+                        ctx.link().send_message(Msg::AddTag);
+                    }
+                    None => self.input = input,
+                }
+                true
+            }
+            Msg::AddTag => {
+                let name = self.input.trim().to_string();
+                self.input.clear();
+
+                if !name.is_empty() && !ctx.props().tags.contains(&name) {
+                    let mut tags = ctx.props().tags.clone();
+                    tags.push(name);
+                    ctx.props().on_change.emit(tags);
+                }
+                true
+            }
+            Msg::RemoveTag(name) => {
+                let tags = ctx.props().tags.iter().filter(|tag| **tag != name).cloned().collect();
+                ctx.props().on_change.emit(tags);
+                false
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let suggestions: Vec<&String> = ctx
+            .props()
+            .suggestions
+            .iter()
+            .filter(|suggestion| !ctx.props().tags.contains(suggestion))
+            .collect();
+
+        html! {
+            <div class="tag-input">
+                { for ctx.props().tags.iter().map(|tag| {
+                    let name = tag.clone();
+                    html! {
+                        <span class="tag-chip">
+                            { tag }
+                            <button type="button" onclick={ctx.link().callback(move |_| Msg::RemoveTag(name.clone()))}>
+                                { "×" }
+                            </button>
+                        </span>
+                    }
+                }) }
+                <input
+                    type="text"
+                    placeholder="Add tag"
+                    list={ctx.props().id.clone()}
+                    value={self.input.clone()}
+                    oninput={ctx.link().callback(|e: InputEvent| {
+                        let input: HtmlInputElement = e.target_unchecked_into();
+                        Msg::UpdateInput(input.value())
+                    })}
+                    onkeydown={ctx.link().batch_callback(|e: KeyboardEvent| {
+                        if e.key() == "Enter" {
+                            // Don't submit the form the input is in
+                            e.prevent_default();
+                            Some(Msg::AddTag)
+                        } else {
+                            None
+                        }
+                    })}
+                />
+                <datalist id={ctx.props().id.clone()}>
+                    { for suggestions.iter().map(|suggestion| html! { <option value={(*suggestion).clone()} /> }) }
+                </datalist>
+            </div>
+        }
+    }
+}
@@ -1,20 +1,46 @@
-use gloo::console::info;
-use gloo::console::log;
-use reqwasm::http::Request;
+use std::collections::BTreeSet;
+
+use gloo::timers::callback::Timeout;
 use serde::{Serialize, Deserialize};
-use web_sys::HtmlInputElement;
+use shared::gp;
+use web_sys::HtmlSelectElement;
 use yew::prelude::*;
 use yew::virtual_dom::VNode;
 
-use self::_Props::should_update;
+use crate::api::{self, send_request, url_with_query};
+use crate::component::account_switcher::Account;
+use crate::component::detail_view::{self, Detail};
+use crate::component::tag_input::{self, Tag, TagInput};
+use crate::component::trash;
+use crate::events::{self, EventSubscription, TradeEvent};
+use crate::logging::{debug, error};
+
+// How long the undo toast stays up after deleting a trade
+const UNDO_TIMEOUT_MS: u32 = 5000;
 
 pub struct TransactionList {
     transactions: Vec<Transaction>,
     item_name: String,
-    should_update: bool,
+    // The last deleted trade and the timer hiding its undo toast
+    undo: Option<(i64, Timeout)>,
+    show_trash: bool,
+    trash_update_counter: i64,
+    // The trade or item open in the detail view
+    detail: Option<Detail>,
+    // Ids of the checked trades that bulk operations apply to
+    selected: BTreeSet<i64>,
+    accounts: Vec<Account>,
+    bulk_error: Option<String>,
+    tags: Vec<Tag>,
+    // Only trades with this tag are listed, from the tag prop
+    tag_filter: Option<String>,
+    // Tags to give the selected trades, replacing their current ones
+    retag: Vec<String>,
+    // Keeps the list up to date with changes made elsewhere
+    _events: Option<EventSubscription>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Transaction {
     pub id: i64,
     pub item_name: String,
@@ -22,20 +48,80 @@ pub struct Transaction {
     pub total_price: i64,
     pub is_purchase: bool,
     pub timestamp: String,
+    #[serde(default)]
+    pub account_id: Option<i64>,
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+// An operation sent to the bulk trade endpoint
+#[derive(Serialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum TradeOperation {
+    Update { id: i64, changes: TradeChanges },
+    Delete { id: i64 },
+}
+
+// Fields left out of the changes keep their current value
+#[derive(Serialize, Default)]
+struct TradeChanges {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    account_id: Option<Option<i64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+struct BulkTradeRequest {
+    operations: Vec<TradeOperation>,
+}
+
+#[derive(Deserialize)]
+struct BulkOperationResult {
+    index: usize,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BulkTradeResponse {
+    results: Vec<BulkOperationResult>,
 }
 
 pub enum Msg {
-    UpdateItemName(String),
     Search,
     GetTransactionsComplete(Vec<Transaction>),
     DeleteTransaction(i64),
+    DeleteTransactionComplete(i64, Result<(), String>),
+    HideUndo(i64),
+    Restore(i64),
+    RestoreComplete(Result<(), String>),
+    ToggleTrash,
+    ShowDetail(Detail),
+    HideDetail,
+    GetAccountsComplete(Vec<Account>),
+    ToggleSelected(i64),
+    ToggleSelectAll,
+    BulkDelete,
+    // None moves the selected trades out of any account
+    BulkAssignAccount(Option<i64>),
+    BulkRetag,
+    BulkComplete(Result<(), String>),
+    GetTagsComplete(Vec<Tag>),
+    FilterTag(Option<String>),
+    UpdateRetag(Vec<String>),
+    TradeEvent(TradeEvent),
 }
 
 #[derive(PartialEq, Properties, Clone)]
 pub struct Props {
     pub search_string: String,
-    pub should_update: i64,
-    pub update: Callback<bool>,
+    // Only trades with this tag are listed
+    pub tag: Option<String>,
+    // Picking a tag to filter by asks for it to be passed back in as `tag`
+    pub on_filter_tag: Callback<Option<String>>,
+    pub account: Option<i64>,
 }
 
 impl Component for TransactionList {
@@ -43,54 +129,59 @@ impl Component for TransactionList {
     type Properties = Props;
 
     fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Msg::Search);
+        ctx.link().send_future(async { Msg::GetTagsComplete(tag_input::get_tags().await) });
+
         ctx.link().send_future(async {
-            let resp = Request::get("http://localhost:43211/api/v1/trade")
+            let resp = api::get("/api/v1/account")
                 .send()
                 .await;
 
             match resp {
-                Ok(resp) => {
-                    let transactions = resp.json::<Vec<Transaction>>().await.unwrap();
-                    Msg::GetTransactionsComplete(transactions)
-                },
-                Err(_) => {
-                    Msg::GetTransactionsComplete(Vec::new())
-                },
+                Ok(resp) => Msg::GetAccountsComplete(resp.json::<Vec<Account>>().await.unwrap_or_default()),
+                Err(_) => Msg::GetAccountsComplete(Vec::new()),
             }
         });
 
         Self { 
             transactions: Vec::new(),
-            item_name: String::new(),
-            should_update: false,
+            item_name: ctx.props().search_string.clone(),
+            undo: None,
+            show_trash: false,
+            trash_update_counter: 0,
+            detail: None,
+            selected: BTreeSet::new(),
+            accounts: Vec::new(),
+            bulk_error: None,
+            tags: Vec::new(),
+            tag_filter: ctx.props().tag.clone(),
+            retag: Vec::new(),
+            _events: events::subscribe(ctx.link().callback(Msg::TradeEvent)),
         }
     }
 
-    fn changed(&mut self, ctx: &Context<Self>, old_props: &Self::Properties) -> bool {
-        log!(format!("props.update: {}", old_props.should_update));
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        debug!("props.search_string: {}", ctx.props().search_string);
         self.item_name = ctx.props().search_string.clone();
+        self.tag_filter = ctx.props().tag.clone();
         ctx.link().send_message(Msg::Search);
-        if ctx.props().should_update != old_props.should_update {
-            self.should_update = true;
-        }
         true
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
-            Msg::UpdateItemName(name) => {
-                self.item_name = name;
-                false
-            },
             Msg::Search => {
-                let item_name = self.item_name.clone();
+                let url = url_with_query(
+                    "/api/v1/trade",
+                    &[
+                        ("item_name", Some(self.item_name.clone()).filter(|item_name| !item_name.is_empty())),
+                        ("account", ctx.props().account.map(|account| account.to_string())),
+                        ("tag", self.tag_filter.clone()),
+                    ],
+                );
                 // Send request to backend
                 ctx.link().send_future(async move {
-                    let mut url = String::from("http://localhost:43211/api/v1/trade");
-                    if !item_name.is_empty() {
-                        url.push_str(&format!("?item_name={}", item_name));
-                    }
-                    let resp = Request::get(&url)
+                    let resp = api::get(&url)
                         .send()
                         .await;
 
@@ -110,34 +201,195 @@ impl Component for TransactionList {
             Msg::GetTransactionsComplete(transactions) => {
                 self.transactions = transactions;
 
-                if self.should_update {
-                    ctx.props().update.emit(self.should_update);
-                }
-
-                self.should_update  = false;
+                // Keep only the selected trades that still match the search
+                let ids = self.transactions.iter().map(|transaction| transaction.id).collect::<BTreeSet<i64>>();
+                self.selected.retain(|id| ids.contains(id));
 
                 true
             },
             Msg::DeleteTransaction(id) => {
-                // Send DELETE request to backend
+                // Send DELETE request to backend, which moves the trade to the trash
                 ctx.link().send_future(async move {
-                    let url = format!("http://localhost:43211/api/v1/trade?id={}", id);
-                    let resp = Request::delete(&url)
-                        .send()
-                        .await;
+                    let url = format!("/api/v1/trade?id={}", id);
+                    Msg::DeleteTransactionComplete(id, send_request(api::delete(&url)).await)
+                });
 
-                    match resp {
-                        Ok(_) => {
-                            Msg::Search
-                        },
-                        Err(_) => {
-                            Msg::Search
-                        },
-                    }
+                true
+            },
+            Msg::DeleteTransactionComplete(id, result) => {
+                match result {
+                    Ok(()) => {
+                        let link = ctx.link().clone();
+                        let timeout = Timeout::new(UNDO_TIMEOUT_MS, move || link.send_message(Msg::HideUndo(id)));
+                        self.undo = Some((id, timeout));
+                        self.trash_update_counter += 1;
+                    },
+                    Err(e) => error!("Deleting trade failed: {}", e),
+                }
+
+                true
+            },
+            Msg::HideUndo(id) => {
+                // A newer delete replaces the toast, so only hide it if it's still for this trade
+                if self.undo.as_ref().is_some_and(|(undo_id, _)| *undo_id == id) {
+                    self.undo = None;
+                }
+
+                true
+            },
+            Msg::Restore(id) => {
+                self.undo = None;
+
+                ctx.link().send_future(async move {
+                    let url = format!("/api/v1/trade/{}/restore", id);
+                    Msg::RestoreComplete(send_request(api::post(&url)).await)
                 });
 
-                self.should_update = true;
-                
+                true
+            },
+            Msg::RestoreComplete(result) => {
+                if let Err(e) = result {
+                    error!("Restoring trade failed: {}", e);
+                }
+
+                self.trash_update_counter += 1;
+
+                true
+            },
+            Msg::ToggleTrash => {
+                self.show_trash = !self.show_trash;
+
+                true
+            },
+            Msg::ShowDetail(detail) => {
+                self.detail = Some(detail);
+
+                true
+            },
+            Msg::HideDetail => {
+                self.detail = None;
+
+                true
+            },
+            Msg::GetAccountsComplete(accounts) => {
+                self.accounts = accounts;
+
+                true
+            },
+            Msg::GetTagsComplete(tags) => {
+                self.tags = tags;
+
+                true
+            },
+            Msg::FilterTag(tag) => {
+                ctx.props().on_filter_tag.emit(tag);
+
+                false
+            },
+            Msg::UpdateRetag(tags) => {
+                self.retag = tags;
+
+                true
+            },
+            Msg::ToggleSelected(id) => {
+                if !self.selected.remove(&id) {
+                    self.selected.insert(id);
+                }
+
+                true
+            },
+            Msg::ToggleSelectAll => {
+                // Select every trade matching the current search, or clear the selection if they all are
+                if self.selected.len() == self.transactions.len() {
+                    self.selected.clear();
+                } else {
+                    self.selected = self.transactions.iter().map(|transaction| transaction.id).collect();
+                }
+
+                true
+            },
+            Msg::BulkDelete => {
+                let operations = self.selected.iter().map(|&id| TradeOperation::Delete { id }).collect();
+                self.send_bulk(ctx, operations);
+
+                true
+            },
+            Msg::BulkAssignAccount(account_id) => {
+                let operations = self.selected.iter().map(|&id| TradeOperation::Update {
+                    id,
+                    changes: TradeChanges {
+                        account_id: Some(account_id),
+                        ..TradeChanges::default()
+                    },
+                }).collect();
+                self.send_bulk(ctx, operations);
+
+                true
+            },
+            Msg::BulkRetag => {
+                let operations = self.selected.iter().map(|&id| TradeOperation::Update {
+                    id,
+                    changes: TradeChanges {
+                        tags: Some(self.retag.clone()),
+                        ..TradeChanges::default()
+                    },
+                }).collect();
+                self.send_bulk(ctx, operations);
+
+                true
+            },
+            Msg::BulkComplete(result) => {
+                match result {
+                    Ok(()) => {
+                        self.selected.clear();
+                        self.retag.clear();
+                        self.bulk_error = None;
+                        self.trash_update_counter += 1;
+                    },
+                    Err(e) => {
+                        error!("Bulk trade operation failed: {}", e);
+                        self.bulk_error = Some(e);
+                    },
+                }
+
+                true
+            },
+            Msg::TradeEvent(event) => {
+                debug!("Trade event: {:?}", event);
+
+                let (before, trade) = match event {
+                    TradeEvent::TradeCreated { trade } => (None, Some(trade)),
+                    TradeEvent::TradeUpdated { before, trade } => (Some(before), Some(trade)),
+                    TradeEvent::TradeDeleted { trade } => (Some(trade), None),
+                    TradeEvent::Resync => {
+                        ctx.link().send_message(Msg::Search);
+                        ctx.link().send_future(async { Msg::GetTagsComplete(tag_input::get_tags().await) });
+                        return true;
+                    },
+                };
+
+                // Tag counts only change when the trade has tags before or after
+                if before.iter().chain(trade.iter()).any(|transaction| !transaction.tags.is_empty()) {
+                    ctx.link().send_future(async { Msg::GetTagsComplete(tag_input::get_tags().await) });
+                }
+
+                if let Some(before) = before {
+                    self.transactions.retain(|transaction| transaction.id != before.id);
+                }
+
+                match trade {
+                    Some(trade) if self.matches_search(ctx, &trade) => {
+                        // The list is in id order, like the backend returns it
+                        let index = self.transactions.partition_point(|transaction| transaction.id < trade.id);
+                        self.transactions.insert(index, trade);
+                    },
+                    _ => {},
+                }
+
+                // Deleted trades can no longer be selected
+                let ids = self.transactions.iter().map(|transaction| transaction.id).collect::<BTreeSet<i64>>();
+                self.selected.retain(|id| ids.contains(id));
+
                 true
             },
         };
@@ -149,16 +401,80 @@ impl Component for TransactionList {
         html! {
             <>
                 <div class="transaction-panel">
+                    <div class="flip-toolbar">
+                        <button onclick={ctx.link().callback(|_| Msg::ToggleTrash)}>
+                            { if self.show_trash { "Hide trash" } else { "Show trash" } }
+                        </button>
+                        <select
+                            onchange={ctx.link().callback(|e: Event| {
+                                let select: HtmlSelectElement = e.target_unchecked_into();
+                                Msg::FilterTag(Some(select.value()).filter(|tag| !tag.is_empty()))
+                            })}
+                        >
+                            <option value="" selected={self.tag_filter.is_none()}>{ "All tags" }</option>
+                            { for self.tags.iter().map(|tag| html! {
+                                <option value={tag.name.clone()} selected={self.tag_filter.as_ref() == Some(&tag.name)}>
+                                    { format!("{} ({})", tag.name, tag.trade_count) }
+                                </option>
+                            }) }
+                        </select>
+                        if !self.selected.is_empty() {
+                            <span>{ format!("{} selected", self.selected.len()) }</span>
+                            <button onclick={ctx.link().callback(|_| Msg::BulkDelete)}>{ "Delete selected" }</button>
+                            <select
+                                onchange={ctx.link().batch_callback(|e: Event| {
+                                    let select: HtmlSelectElement = e.target_unchecked_into();
+                                    let value = select.value();
+                                    select.set_value("");
+                                    match value.as_str() {
+                                        "" => None,
+                                        "none" => Some(Msg::BulkAssignAccount(None)),
+                                        id => id.parse::<i64>().ok().map(|id| Msg::BulkAssignAccount(Some(id))),
+                                    }
+                                })}
+                            >
+                                <option value="" selected=true>{ "Move to account..." }</option>
+                                <option value="none">{ "No account" }</option>
+                                { for self.accounts.iter().map(|account| html! {
+                                    <option value={account.id.to_string()}>{ &account.name }</option>
+                                }) }
+                            </select>
+                            <TagInput
+                                id="retag-suggestions"
+                                tags={self.retag.clone()}
+                                suggestions={self.tags.iter().map(|tag| tag.name.clone()).collect::<Vec<String>>()}
+                                on_change={ctx.link().callback(Msg::UpdateRetag)} />
+                            <button onclick={ctx.link().callback(|_| Msg::BulkRetag)}>{ "Set tags" }</button>
+                        }
+                    </div>
+                    if let Some(bulk_error) = &self.bulk_error {
+                        <p class="slot-error">{ bulk_error }</p>
+                    }
+                    if self.show_trash {
+                        <trash::Trash
+                            update_counter={self.trash_update_counter}
+                            account={ctx.props().account}
+                            on_restore={ctx.link().callback(Msg::Restore)} />
+                    }
                     <div class="transaction-list-container">
                         <table class="transaction-list-table">
                             <thead>
                                 <tr>
-                                    <th style="border-top-left-radius:8px">{ "ID" }</th>
+                                    <th style="border-top-left-radius:8px">
+                                        <input
+                                            type="checkbox"
+                                            title="Select all"
+                                            checked={!self.transactions.is_empty() && self.selected.len() == self.transactions.len()}
+                                            onclick={ctx.link().callback(|_| Msg::ToggleSelectAll)}
+                                        />
+                                    </th>
+                                    <th>{ "ID" }</th>
                                     <th>{ "Name" }</th>
                                     <th>{ "Quantity" }</th>
                                     <th>{ "Price" }</th>
                                     <th>{ "Sale or Purchase" }</th>
                                     <th>{ "Date" }</th>
+                                    <th>{ "Tags" }</th>
                                     <th style="border-top-right-radius:8px">{ "Actions" }</th>
                                 </tr>
                             </thead>
@@ -168,12 +484,62 @@ impl Component for TransactionList {
                         </table>
                     </div>
                 </div>
+                if let Some(detail) = &self.detail {
+                    <detail_view::DetailView
+                        detail={detail.clone()}
+                        transactions={self.transactions.clone()}
+                        on_close={ctx.link().callback(|_| Msg::HideDetail)} />
+                }
+                if let Some((id, _)) = &self.undo {
+                    <div class="undo-toast">
+                        <span>{ format!("Trade {} moved to trash", id) }</span>
+                        <button onclick={ctx.link().callback({
+                            let id = *id;
+                            move |_| Msg::Restore(id)
+                        })}>{ "Undo" }</button>
+                    </div>
+                }
             </>
         }
     }
 }
 
 impl TransactionList {
+    // Whether a trade belongs in the list, using the same filters as the search request
+    fn matches_search(&self, ctx: &Context<Self>, transaction: &Transaction) -> bool {
+        ctx.props().account.is_none_or(|account| transaction.account_id == Some(account))
+            && transaction.item_name.to_lowercase().contains(&self.item_name.to_lowercase())
+            && self.tag_filter.as_ref().is_none_or(|tag| transaction.tags.contains(tag))
+    }
+
+    // Apply operations to the selected trades in one request. The backend applies all of them or none.
+    fn send_bulk(&self, ctx: &Context<Self>, operations: Vec<TradeOperation>) {
+        let bulk_json = serde_json::to_string(&BulkTradeRequest { operations }).unwrap();
+
+        ctx.link().send_future(async {
+            let resp = api::post("/api/v1/trades/bulk")
+                .header("Content-Type", "application/json")
+                .body(bulk_json)
+                .send()
+                .await;
+
+            let result = match resp {
+                Ok(resp) if resp.ok() => Ok(()),
+                Ok(resp) => match resp.json::<BulkTradeResponse>().await {
+                    Ok(bulk) => Err(bulk
+                        .results
+                        .into_iter()
+                        .filter_map(|result| result.error.map(|error| format!("Operation {}: {}", result.index + 1, error)))
+                        .collect::<Vec<String>>()
+                        .join(", ")),
+                    Err(_) => Err(format!("Request failed with status {}", resp.status())),
+                },
+                Err(e) => Err(e.to_string()),
+            };
+            Msg::BulkComplete(result)
+        });
+    }
+
     fn render_row(&self, ctx: &Context<Self>, index: usize, transaction: &Transaction) -> Html {
         let last_row_style = if index == self.transactions.len() - 1 {
             "border-bottom:0px"
@@ -181,31 +547,27 @@ impl TransactionList {
             ""
         };
 
-        let id = transaction.id.clone();
-
-        fn format_with_commas(n: i64) -> String {
-            let is_negative = n < 0;
-            let mut s = n.abs().to_string();
-            let mut pos = s.len() as isize - 3;
-            
-            while pos > 0 {
-                s.insert(pos as usize, ',');
-                pos -= 3;
-            }
-            
-            if is_negative {
-                s = format!("({})", s);
-            }
-            
-            s
-        }
+        let id = transaction.id;
+        let trade_detail = Detail::Trade(transaction.clone());
+        let item_detail = Detail::Item(transaction.item_name.clone());
 
-        let formatted_total_price = format_with_commas(transaction.total_price);
+        let formatted_total_price = gp::format(transaction.total_price);
     
         html! {
             <tr>
-                <td style={ last_row_style }> { &transaction.id }</td>
-                <td style={ last_row_style }> { &transaction.item_name }</td>
+                <td style={ last_row_style }>
+                    <input
+                        type="checkbox"
+                        checked={self.selected.contains(&id)}
+                        onclick={ctx.link().callback(move |_| Msg::ToggleSelected(id))}
+                    />
+                </td>
+                <td style={ last_row_style }>
+                    <a class="detail-link" onclick={ctx.link().callback(move |_| Msg::ShowDetail(trade_detail.clone()))}>{ &transaction.id }</a>
+                </td>
+                <td style={ last_row_style }>
+                    <a class="detail-link" onclick={ctx.link().callback(move |_| Msg::ShowDetail(item_detail.clone()))}>{ &transaction.item_name }</a>
+                </td>
                 <td style={ last_row_style }> { transaction.quantity }</td>
                 <td style={ last_row_style }> { formatted_total_price }</td>
                 <td style={ last_row_style }> {
@@ -216,6 +578,9 @@ impl TransactionList {
                     }
                 }</td>
                 <td style={ last_row_style }> { &transaction.timestamp }</td>
+                <td style={ last_row_style }>
+                    { for transaction.tags.iter().map(|tag| html! { <span class="tag-chip">{ tag }</span> }) }
+                </td>
                 <td style={ last_row_style }> { 
                     html! {
                         <>
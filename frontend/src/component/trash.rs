@@ -0,0 +1,126 @@
+use serde::Deserialize;
+use shared::gp;
+use yew::prelude::*;
+
+use crate::api::{self, url_with_query};
+
+// Matches how long the backend keeps deleted trades before purging them
+const TRASH_RETENTION_DAYS: i64 = 30;
+
+pub struct Trash {
+    deleted_trades: Vec<DeletedTrade>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct DeletedTrade {
+    pub id: i64,
+    pub item_name: String,
+    pub quantity: i64,
+    pub total_price: i64,
+    pub is_purchase: bool,
+    pub timestamp: String,
+    pub deleted_at: String,
+}
+
+pub enum Msg {
+    GetDeletedTrades,
+    GetDeletedTradesComplete(Vec<DeletedTrade>),
+}
+
+#[derive(PartialEq, Properties, Clone)]
+pub struct Props {
+    pub update_counter: i64,
+    pub account: Option<i64>,
+    pub on_restore: Callback<i64>,
+}
+
+impl Component for Trash {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Msg::GetDeletedTrades);
+
+        Self {
+            deleted_trades: Vec::new(),
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        ctx.link().send_message(Msg::GetDeletedTrades);
+        false
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetDeletedTrades => {
+                let url = url_with_query(
+                    "/api/v1/trade/trash",
+                    &[("account", ctx.props().account.map(|account| account.to_string()))],
+                );
+
+                ctx.link().send_future(async move {
+                    let resp = api::get(&url)
+                        .send()
+                        .await;
+
+                    match resp {
+                        Ok(resp) => {
+                            let deleted_trades = resp.json::<Vec<DeletedTrade>>().await.unwrap_or_default();
+                            Msg::GetDeletedTradesComplete(deleted_trades)
+                        }
+                        Err(_) => Msg::GetDeletedTradesComplete(Vec::new()),
+                    }
+                });
+                false
+            }
+            Msg::GetDeletedTradesComplete(deleted_trades) => {
+                self.deleted_trades = deleted_trades;
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <div class="transaction-list-container">
+                <p>{ format!("Deleted trades are permanently removed after {} days.", TRASH_RETENTION_DAYS) }</p>
+                <table class="transaction-list-table">
+                    <thead>
+                        <tr>
+                            <th style="border-top-left-radius:8px">{ "ID" }</th>
+                            <th>{ "Name" }</th>
+                            <th>{ "Quantity" }</th>
+                            <th>{ "Price" }</th>
+                            <th>{ "Sale or Purchase" }</th>
+                            <th>{ "Date" }</th>
+                            <th>{ "Deleted" }</th>
+                            <th style="border-top-right-radius:8px">{ "Actions" }</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        { for self.deleted_trades.iter().map(|trade| {
+                            let id = trade.id;
+                            html! {
+                                <tr>
+                                    <td>{ trade.id }</td>
+                                    <td>{ &trade.item_name }</td>
+                                    <td>{ trade.quantity }</td>
+                                    <td>{ gp::format(trade.total_price) }</td>
+                                    <td>{ if trade.is_purchase { "Purchase" } else { "Sale" } }</td>
+                                    <td>{ &trade.timestamp }</td>
+                                    <td>{ &trade.deleted_at }</td>
+                                    <td>
+                                        <button class="material-icons" onclick={ctx.props().on_restore.reform(move |_| id)}>
+                                            { "restore_from_trash" }
+                                        </button>
+                                    </td>
+                                </tr>
+                            }
+                        }) }
+                    </tbody>
+                </table>
+            </div>
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use serde::Deserialize;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use web_sys::{EventSource, EventSourceInit, MessageEvent};
+use yew::Callback;
+
+use crate::api;
+use crate::component::transaction_list::Transaction;
+use crate::logging::error;
+
+// A change to one of the user's trades, made in this or any other tab, device or script.
+// Updates and deletes carry the trade as it was before the change.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TradeEvent {
+    TradeCreated { trade: Transaction },
+    TradeUpdated { before: Transaction, trade: Transaction },
+    TradeDeleted { trade: Transaction },
+    // Events may have been missed, so data should be reloaded
+    Resync,
+}
+
+// An open connection to the backend's event stream, closed when dropped
+pub struct EventSubscription {
+    source: EventSource,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_open: Closure<dyn FnMut()>,
+    _on_error: Closure<dyn FnMut()>,
+}
+
+impl Drop for EventSubscription {
+    fn drop(&mut self) {
+        self.source.close();
+    }
+}
+
+// Subscribe to the trade events of the logged in user. The browser reconnects by itself after
+// the connection drops, and a resync is sent once it's back since events may have been missed.
+pub fn subscribe(on_event: Callback<TradeEvent>) -> Option<EventSubscription> {
+    let init = EventSourceInit::new();
+    // Send the session cookie, which identifies the user
+    init.set_with_credentials(true);
+
+    let source = match EventSource::new_with_event_source_init_dict(&api::url("/api/v1/events"), &init) {
+        Ok(source) => source,
+        Err(e) => {
+            error!("Failed to open event stream: {:?}", e);
+            return None;
+        }
+    };
+
+    let on_message = Closure::<dyn FnMut(MessageEvent)>::new({
+        let on_event = on_event.clone();
+        move |message: MessageEvent| {
+            let Some(data) = message.data().as_string() else {
+                return;
+            };
+            match serde_json::from_str::<TradeEvent>(&data) {
+                Ok(event) => on_event.emit(event),
+                Err(e) => error!("Failed to parse event: {}", e),
+            }
+        }
+    });
+
+    let disconnected = Rc::new(Cell::new(false));
+
+    let on_open = Closure::<dyn FnMut()>::new({
+        let disconnected = disconnected.clone();
+        move || {
+            if disconnected.replace(false) {
+                on_event.emit(TradeEvent::Resync);
+            }
+        }
+    });
+
+    let on_error = Closure::<dyn FnMut()>::new(move || disconnected.set(true));
+
+    source.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    source.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+    source.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+
+    Some(EventSubscription {
+        source,
+        _on_message: on_message,
+        _on_open: on_open,
+        _on_error: on_error,
+    })
+}
@@ -0,0 +1,68 @@
+use gloo::storage::{LocalStorage, Storage};
+
+// Local storage key holding the log level, so it can be changed at runtime from the browser
+// console with e.g. `localStorage.setItem("ardy_log_level", "debug")`
+const LOG_LEVEL_KEY: &str = "ardy_log_level";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    fn parse(level: &str) -> Option<Self> {
+        match level.trim().to_lowercase().as_str() {
+            "error" => Some(LogLevel::Error),
+            "warn" => Some(LogLevel::Warn),
+            "info" => Some(LogLevel::Info),
+            "debug" => Some(LogLevel::Debug),
+            _ => None,
+        }
+    }
+}
+
+// The most detailed level logged, read on every call so a change applies without a reload.
+// Info unless set otherwise.
+pub fn level() -> LogLevel {
+    LocalStorage::raw()
+        .get_item(LOG_LEVEL_KEY)
+        .ok()
+        .flatten()
+        .and_then(|level| LogLevel::parse(&level))
+        .unwrap_or(LogLevel::Info)
+}
+
+pub fn enabled(level: LogLevel) -> bool {
+    level <= self::level()
+}
+
+// Log a formatted message to the browser console if its level is enabled
+
+macro_rules! error {
+    ($($arg:tt)*) => {
+        if $crate::logging::enabled($crate::logging::LogLevel::Error) {
+            gloo::console::error!(format!($($arg)*));
+        }
+    };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if $crate::logging::enabled($crate::logging::LogLevel::Info) {
+            gloo::console::info!(format!($($arg)*));
+        }
+    };
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::logging::enabled($crate::logging::LogLevel::Debug) {
+            gloo::console::debug!(format!($($arg)*));
+        }
+    };
+}
+
+pub(crate) use {debug, error, info};
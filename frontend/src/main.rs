@@ -1,6 +1,11 @@
 use component::app::App;
 
+mod api;
 mod component;
+mod events;
+mod logging;
+mod page;
+mod route;
 
 fn main() {
     yew::Renderer::<App>::new().render();
@@ -0,0 +1,5 @@
+pub mod dashboard;
+pub mod holdings;
+pub mod import_export;
+pub mod item;
+pub mod transactions;
@@ -0,0 +1,346 @@
+use chrono::Local;
+use serde::Deserialize;
+use shared::gp;
+use shared::trade::{parse_timestamp, TIMESTAMP_FORMAT};
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::api::{self, url_with_query};
+use crate::component::coins::coins;
+use crate::component::goal_form::GoalForm;
+use crate::events::{self, EventSubscription, TradeEvent};
+use crate::logging::error;
+use crate::route::Route;
+
+// The key numbers at a glance, from one request to the backend
+pub struct Dashboard {
+    summary: Option<Summary>,
+    // Keeps the numbers up to date with trades changed anywhere
+    _events: Option<EventSubscription>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Summary {
+    pub profit_today: i64,
+    pub profit_this_week: i64,
+    pub profit_all_time: i64,
+    pub gp_per_hour: Option<i64>,
+    pub recent_sessions: Vec<RecentSession>,
+    pub top_items: Vec<ItemProfit>,
+    pub worst_items: Vec<ItemProfit>,
+    pub capital_tied_up: i64,
+    pub open_flips: i64,
+    pub buy_limit_resets: Vec<BuyLimitReset>,
+    pub goals: Vec<Goal>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RecentSession {
+    pub start: String,
+    pub end: String,
+    pub trade_count: i64,
+    pub profit: i64,
+    pub gp_per_hour: Option<i64>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ItemProfit {
+    pub item_name: String,
+    pub profit: i64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct BuyLimitReset {
+    pub item_name: String,
+    pub account_id: Option<i64>,
+    pub quantity_bought: i64,
+    pub resets_at: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Goal {
+    pub id: i64,
+    pub name: String,
+    pub target: i64,
+    pub started_at: String,
+    pub deadline: String,
+    pub item_name: Option<String>,
+    pub account_id: Option<i64>,
+    pub progress: i64,
+    pub projected_completion: Option<String>,
+}
+
+pub enum Msg {
+    GetSummary,
+    GetSummaryComplete(Option<Summary>),
+    DeleteGoal(i64),
+    TradeChanged,
+}
+
+#[derive(PartialEq, Properties, Clone)]
+pub struct Props {
+    pub account: Option<i64>,
+}
+
+fn profit_class(profit: i64) -> &'static str {
+    if profit >= 0 { "profit" } else { "loss" }
+}
+
+fn gp_per_hour(gp_per_hour: Option<i64>) -> String {
+    gp_per_hour.map_or_else(|| "-".to_string(), |gp_per_hour| format!("{}/h", gp::format_compact(gp_per_hour)))
+}
+
+// How far along a goal is, as a percentage of its target to fill a progress bar with
+fn goal_percentage(goal: &Goal) -> i64 {
+    (goal.progress.max(0) * 100 / goal.target.max(1)).min(100)
+}
+
+// How long until a buy limit resets, like "1h 20m"
+fn time_until(resets_at: &str) -> String {
+    let Ok(resets_at) = parse_timestamp(resets_at) else {
+        return resets_at.to_string();
+    };
+
+    let minutes = (resets_at - Local::now().naive_local()).num_minutes().max(0);
+    if minutes >= 60 {
+        format!("{}h {}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+impl Component for Dashboard {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Msg::GetSummary);
+
+        Self {
+            summary: None,
+            _events: events::subscribe(ctx.link().callback(|_: TradeEvent| Msg::TradeChanged)),
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        ctx.link().send_message(Msg::GetSummary);
+        false
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetSummary => {
+                // Trades are in local time, so today and this week are too
+                let url = url_with_query(
+                    "/api/v1/dashboard",
+                    &[
+                        ("account", ctx.props().account.map(|account| account.to_string())),
+                        ("now", Some(Local::now().format(TIMESTAMP_FORMAT).to_string())),
+                    ],
+                );
+
+                ctx.link().send_future(async move {
+                    let resp = api::get(&url)
+                        .send()
+                        .await;
+
+                    match resp {
+                        Ok(resp) if resp.ok() => Msg::GetSummaryComplete(resp.json::<Summary>().await.ok()),
+                        _ => Msg::GetSummaryComplete(None),
+                    }
+                });
+
+                false
+            },
+            Msg::GetSummaryComplete(summary) => {
+                self.summary = summary;
+
+                true
+            },
+            Msg::DeleteGoal(id) => {
+                ctx.link().send_future(async move {
+                    let request = api::delete(&format!("/api/v1/goals/{}", id));
+                    if let Err(e) = api::send_request(request).await {
+                        error!("Deleting goal failed: {}", e);
+                    }
+                    Msg::GetSummary
+                });
+
+                false
+            },
+            Msg::TradeChanged => {
+                ctx.link().send_message(Msg::GetSummary);
+
+                false
+            },
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let Some(summary) = &self.summary else {
+            return html! {};
+        };
+
+        html! {
+            <div class="transaction-panel dashboard">
+                { self.construct_stats_html(summary) }
+                { self.construct_goals_html(ctx, summary) }
+                <div class="dashboard-tables">
+                    { self.construct_items_html("Top items", &summary.top_items) }
+                    { self.construct_items_html("Worst items", &summary.worst_items) }
+                </div>
+                { self.construct_sessions_html(summary) }
+                { self.construct_buy_limits_html(summary) }
+            </div>
+        }
+    }
+}
+
+impl Dashboard {
+    fn construct_stats_html(&self, summary: &Summary) -> Html {
+        let stat = |label: &str, value: Html| html! {
+            <div class="dashboard-stat">
+                <span class="dashboard-stat-label">{ label }</span>
+                <span class="dashboard-stat-value">{ value }</span>
+            </div>
+        };
+        let profit = |profit: i64| html! {
+            <span class={profit_class(profit)}>{ format!("{} gp", gp::format(profit)) }</span>
+        };
+
+        html! {
+            <div class="dashboard-stats">
+                { stat("Today", profit(summary.profit_today)) }
+                { stat("This week", profit(summary.profit_this_week)) }
+                { stat("All time", profit(summary.profit_all_time)) }
+                { stat("GP/hour", html! { gp_per_hour(summary.gp_per_hour) }) }
+                { stat("Capital tied up", coins(summary.capital_tied_up)) }
+                { stat("Open flips", html! { summary.open_flips }) }
+            </div>
+        }
+    }
+
+    fn construct_goals_html(&self, ctx: &Context<Self>, summary: &Summary) -> Html {
+        html! {
+            <>
+                <h3>{ "Goals" }</h3>
+                { for summary.goals.iter().map(|goal| {
+                    let id = goal.id;
+                    let scope = match (&goal.item_name, goal.account_id) {
+                        (Some(item_name), Some(account_id)) => format!("{} on account {}", item_name, account_id),
+                        (Some(item_name), None) => item_name.clone(),
+                        (None, Some(account_id)) => format!("Account {}", account_id),
+                        (None, None) => "All trades".to_string(),
+                    };
+                    let projection = if goal.progress >= goal.target {
+                        "Reached".to_string()
+                    } else {
+                        match &goal.projected_completion {
+                            Some(projected_completion) => format!("On track for {}", projected_completion),
+                            None => "No recent profit to project from".to_string(),
+                        }
+                    };
+
+                    html! {
+                        <div class="goal">
+                            <div class="goal-header">
+                                <span class="goal-name">{ &goal.name }</span>
+                                <span class="goal-scope">{ scope }</span>
+                                <button class="material-icons" title="Delete goal" onclick={ctx.link().callback(move |_| Msg::DeleteGoal(id))}>
+                                    { "delete" }
+                                </button>
+                            </div>
+                            <div class="goal-progress" title={format!("{}%", goal_percentage(goal))}>
+                                <div class="goal-progress-fill" style={format!("width:{}%", goal_percentage(goal))}></div>
+                            </div>
+                            <div class="goal-details">
+                                <span>{ format!("{} / {} gp", gp::format(goal.progress), gp::format(goal.target)) }</span>
+                                <span>{ format!("{} to {}", goal.started_at, goal.deadline) }</span>
+                                <span>{ projection }</span>
+                            </div>
+                        </div>
+                    }
+                }) }
+                <GoalForm account={ctx.props().account} on_added={ctx.link().callback(|_| Msg::GetSummary)} />
+            </>
+        }
+    }
+
+    fn construct_items_html(&self, title: &str, items: &[ItemProfit]) -> Html {
+        html! {
+            <div>
+                <h3>{ title }</h3>
+                <table class="transaction-list-table">
+                    <tbody>
+                        { for items.iter().map(|item| html! {
+                            <tr>
+                                <td>
+                                    <Link<Route> to={Route::Item { name: item.item_name.clone() }}>{ &item.item_name }</Link<Route>>
+                                </td>
+                                <td class={profit_class(item.profit)}>{ format!("{} gp", gp::format(item.profit)) }</td>
+                            </tr>
+                        }) }
+                    </tbody>
+                </table>
+            </div>
+        }
+    }
+
+    fn construct_sessions_html(&self, summary: &Summary) -> Html {
+        html! {
+            <>
+                <h3>{ "Recent sessions" }</h3>
+                <table class="transaction-list-table">
+                    <thead>
+                        <tr>
+                            <th style="border-top-left-radius:8px">{ "Start" }</th>
+                            <th>{ "End" }</th>
+                            <th>{ "Trades" }</th>
+                            <th>{ "Profit" }</th>
+                            <th style="border-top-right-radius:8px">{ "GP/hour" }</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        { for summary.recent_sessions.iter().map(|session| html! {
+                            <tr>
+                                <td>{ &session.start }</td>
+                                <td>{ &session.end }</td>
+                                <td>{ session.trade_count }</td>
+                                <td class={profit_class(session.profit)}>{ format!("{} gp", gp::format(session.profit)) }</td>
+                                <td>{ gp_per_hour(session.gp_per_hour) }</td>
+                            </tr>
+                        }) }
+                    </tbody>
+                </table>
+            </>
+        }
+    }
+
+    fn construct_buy_limits_html(&self, summary: &Summary) -> Html {
+        html! {
+            <>
+                <h3>{ "Buy limits resetting" }</h3>
+                <table class="transaction-list-table">
+                    <thead>
+                        <tr>
+                            <th style="border-top-left-radius:8px">{ "Item" }</th>
+                            <th>{ "Account" }</th>
+                            <th>{ "Bought" }</th>
+                            <th style="border-top-right-radius:8px">{ "Resets in" }</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        { for summary.buy_limit_resets.iter().map(|reset| html! {
+                            <tr>
+                                <td>{ &reset.item_name }</td>
+                                <td>{ reset.account_id.map(|account_id| account_id.to_string()).unwrap_or_default() }</td>
+                                <td>{ gp::format(reset.quantity_bought) }</td>
+                                <td title={reset.resets_at.clone()}>{ time_until(&reset.resets_at) }</td>
+                            </tr>
+                        }) }
+                    </tbody>
+                </table>
+            </>
+        }
+    }
+}
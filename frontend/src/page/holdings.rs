@@ -0,0 +1,123 @@
+use serde::Deserialize;
+use shared::gp;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::api::{self, url_with_query};
+use crate::component::coins::coins;
+use crate::events::{self, EventSubscription, TradeEvent};
+use crate::route::Route;
+
+// The items still held after the sales so far, and what they cost
+pub struct Holdings {
+    holdings: Vec<Holding>,
+    // Keeps the holdings up to date with trades changed anywhere
+    _events: Option<EventSubscription>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Holding {
+    pub item_name: String,
+    pub quantity: i64,
+    pub average_price: i64,
+    pub cost: i64,
+}
+
+pub enum Msg {
+    GetHoldings,
+    GetHoldingsComplete(Vec<Holding>),
+    // Any trade can change what's held, and averaging depends on every earlier trade
+    TradeChanged,
+}
+
+#[derive(PartialEq, Properties, Clone)]
+pub struct Props {
+    pub account: Option<i64>,
+}
+
+impl Component for Holdings {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Msg::GetHoldings);
+
+        Self {
+            holdings: Vec::new(),
+            _events: events::subscribe(ctx.link().callback(|_: TradeEvent| Msg::TradeChanged)),
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        ctx.link().send_message(Msg::GetHoldings);
+        false
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetHoldings => {
+                let url = url_with_query(
+                    "/api/v1/holdings",
+                    &[("account", ctx.props().account.map(|account| account.to_string()))],
+                );
+
+                ctx.link().send_future(async move {
+                    let resp = api::get(&url)
+                        .send()
+                        .await;
+
+                    match resp {
+                        Ok(resp) => Msg::GetHoldingsComplete(resp.json::<Vec<Holding>>().await.unwrap_or_default()),
+                        Err(_) => Msg::GetHoldingsComplete(Vec::new()),
+                    }
+                });
+
+                false
+            },
+            Msg::GetHoldingsComplete(holdings) => {
+                self.holdings = holdings;
+
+                true
+            },
+            Msg::TradeChanged => {
+                ctx.link().send_message(Msg::GetHoldings);
+
+                false
+            },
+        }
+    }
+
+    fn view(&self, _ctx: &Context<Self>) -> Html {
+        let total_cost: i64 = self.holdings.iter().map(|holding| holding.cost).sum();
+
+        html! {
+            <div class="transaction-panel">
+                <p>{ format!("{} gp tied up in {} items", gp::format(total_cost), self.holdings.len()) }</p>
+                <div class="transaction-list-container">
+                    <table class="transaction-list-table">
+                        <thead>
+                            <tr>
+                                <th style="border-top-left-radius:8px">{ "Item" }</th>
+                                <th>{ "Quantity" }</th>
+                                <th>{ "Average Price" }</th>
+                                <th style="border-top-right-radius:8px">{ "Cost" }</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            { for self.holdings.iter().map(|holding| html! {
+                                <tr>
+                                    <td>
+                                        <Link<Route> to={Route::Item { name: holding.item_name.clone() }}>{ &holding.item_name }</Link<Route>>
+                                    </td>
+                                    <td>{ gp::format(holding.quantity) }</td>
+                                    <td>{ coins(holding.average_price) }</td>
+                                    <td>{ coins(holding.cost) }</td>
+                                </tr>
+                            }) }
+                        </tbody>
+                    </table>
+                </div>
+            </div>
+        }
+    }
+}
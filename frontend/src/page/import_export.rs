@@ -0,0 +1,287 @@
+use chrono::Local;
+use gloo::file::callbacks::FileReader;
+use gloo::file::{Blob, File, ObjectUrl};
+use serde::{Deserialize, Serialize};
+use shared::csv::{self, CsvTrade};
+use shared::trade::{parse_timestamp, TIMESTAMP_FORMAT};
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlElement, HtmlInputElement};
+use yew::prelude::*;
+
+use crate::api::{self, url_with_query};
+use crate::component::transaction_list::Transaction;
+
+const EXPORT_FILE_NAME: &str = "ardy-trades.csv";
+
+// Trades as CSV files in the same format `ardy import` reads: exported from the selected account,
+// and imported all together or not at all
+pub struct ImportExport {
+    export_error: Option<String>,
+    // The last export, kept so the download isn't revoked while the browser starts it
+    _export_url: Option<ObjectUrl>,
+    file_name: Option<String>,
+    trades: Vec<CsvTrade>,
+    // The outcome of reading or importing the file, as a message
+    status: Option<Result<String, String>>,
+    importing: bool,
+    _reader: Option<FileReader>,
+}
+
+// Trades are created with the bulk endpoint, which applies all of them or none
+#[derive(Serialize)]
+struct NewTrade {
+    item_name: String,
+    quantity: i64,
+    total_price: i64,
+    is_purchase: bool,
+    timestamp: String,
+    account_id: Option<i64>,
+    note: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum TradeOperation {
+    Create { trade: NewTrade },
+}
+
+#[derive(Serialize)]
+struct BulkTradeRequest {
+    operations: Vec<TradeOperation>,
+}
+
+#[derive(Deserialize)]
+struct BulkOperationResult {
+    index: usize,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BulkTradeResponse {
+    results: Vec<BulkOperationResult>,
+}
+
+pub enum Msg {
+    Export,
+    ExportComplete(Result<Vec<Transaction>, String>),
+    SelectFile(Option<File>),
+    FileRead(String, Result<String, String>),
+    Import,
+    ImportComplete(Result<usize, String>),
+}
+
+#[derive(PartialEq, Properties, Clone)]
+pub struct Props {
+    pub account: Option<i64>,
+}
+
+fn csv_trade(transaction: Transaction) -> Result<CsvTrade, String> {
+    Ok(CsvTrade {
+        timestamp: parse_timestamp(&transaction.timestamp)?,
+        item_name: transaction.item_name,
+        is_purchase: transaction.is_purchase,
+        quantity: transaction.quantity,
+        price_each: transaction.total_price,
+        account_id: transaction.account_id,
+        note: transaction.note,
+    })
+}
+
+// Have the browser save a file, as if a link to it had been clicked
+fn download(file_name: &str, contents: &str) -> ObjectUrl {
+    let url = ObjectUrl::from(Blob::new_with_options(contents, Some("text/csv")));
+
+    let link: HtmlElement = gloo::utils::document().create_element("a").unwrap().unchecked_into();
+    link.set_attribute("href", &url).unwrap();
+    link.set_attribute("download", file_name).unwrap();
+    link.click();
+
+    url
+}
+
+// Import the trades, counting the ones imported or describing every row that was rejected
+async fn import(trades: Vec<NewTrade>) -> Result<usize, String> {
+    let count = trades.len();
+    let operations = trades.into_iter().map(|trade| TradeOperation::Create { trade }).collect();
+    let bulk_json = serde_json::to_string(&BulkTradeRequest { operations }).unwrap();
+
+    let resp = api::post("/api/v1/trades/bulk")
+        .header("Content-Type", "application/json")
+        .body(bulk_json)
+        .send()
+        .await
+        .map_err(|e| format!("Couldn't reach the backend: {}", e))?;
+
+    if resp.ok() {
+        return Ok(count);
+    }
+
+    match resp.json::<BulkTradeResponse>().await {
+        // Results are in the order of the rows, which start on line 2
+        Ok(bulk) => Err(bulk
+            .results
+            .into_iter()
+            .filter_map(|result| result.error.map(|error| format!("Line {}: {}", result.index + 2, error)))
+            .collect::<Vec<String>>()
+            .join(", ")),
+        Err(_) => Err(format!("The backend responded with {}", resp.status())),
+    }
+}
+
+impl Component for ImportExport {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            export_error: None,
+            _export_url: None,
+            file_name: None,
+            trades: Vec::new(),
+            status: None,
+            importing: false,
+            _reader: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Export => {
+                let url = url_with_query(
+                    "/api/v1/trade",
+                    &[("account", ctx.props().account.map(|account| account.to_string()))],
+                );
+
+                ctx.link().send_future(async move {
+                    let resp = api::get(&url)
+                        .send()
+                        .await;
+
+                    let result = match resp {
+                        Ok(resp) if resp.ok() => resp.json::<Vec<Transaction>>().await.map_err(|e| e.to_string()),
+                        Ok(resp) => Err(format!("The backend responded with {}", resp.status())),
+                        Err(e) => Err(format!("Couldn't reach the backend: {}", e)),
+                    };
+                    Msg::ExportComplete(result)
+                });
+
+                false
+            },
+            Msg::ExportComplete(result) => {
+                let trades = result.and_then(|transactions| transactions.into_iter().map(csv_trade).collect::<Result<Vec<CsvTrade>, String>>());
+
+                match trades {
+                    Ok(trades) => {
+                        self._export_url = Some(download(EXPORT_FILE_NAME, &csv::trades_to_csv(&trades)));
+                        self.export_error = None;
+                    },
+                    Err(e) => self.export_error = Some(format!("Exporting trades failed: {}", e)),
+                }
+
+                true
+            },
+            Msg::SelectFile(file) => {
+                self.trades.clear();
+                self.status = None;
+                self.file_name = file.as_ref().map(File::name);
+
+                self._reader = file.map(|file| {
+                    let link = ctx.link().clone();
+                    let name = file.name();
+                    gloo::file::callbacks::read_as_text(&file, move |result| {
+                        link.send_message(Msg::FileRead(name, result.map_err(|e| e.to_string())))
+                    })
+                });
+
+                true
+            },
+            Msg::FileRead(name, result) => {
+                // A different file may have been picked while this one was read
+                if self.file_name.as_ref() != Some(&name) {
+                    return false;
+                }
+                self._reader = None;
+
+                let trades = result.and_then(|text| csv::trades_from_csv(&text, Local::now().naive_local()));
+                self.status = match trades {
+                    Ok(trades) if trades.is_empty() => Some(Err(format!("{} has no trades", name))),
+                    Ok(trades) => {
+                        self.trades = trades;
+                        None
+                    },
+                    Err(e) => Some(Err(e)),
+                };
+
+                true
+            },
+            Msg::Import => {
+                if self.importing || self.trades.is_empty() {
+                    return false;
+                }
+                self.importing = true;
+                self.status = None;
+
+                let trades = self.trades.iter().map(|trade| NewTrade {
+                    item_name: trade.item_name.clone(),
+                    quantity: trade.quantity,
+                    total_price: trade.price_each,
+                    is_purchase: trade.is_purchase,
+                    timestamp: trade.timestamp.format(TIMESTAMP_FORMAT).to_string(),
+                    account_id: trade.account_id,
+                    note: trade.note.clone(),
+                }).collect();
+                ctx.link().send_future(async move { Msg::ImportComplete(import(trades).await) });
+
+                true
+            },
+            Msg::ImportComplete(result) => {
+                self.importing = false;
+                if result.is_ok() {
+                    self.trades.clear();
+                }
+                self.status = Some(result.map(|count| format!("Imported {} trades", count)));
+
+                true
+            },
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <div class="transaction-panel import-export">
+                <h2>{ "Export" }</h2>
+                <p>{ "Download the trades of the selected account as a CSV file, which can be imported again here or with ardy import." }</p>
+                <div class="flip-toolbar">
+                    <button onclick={ctx.link().callback(|_| Msg::Export)}>{ "Export trades" }</button>
+                </div>
+                if let Some(export_error) = &self.export_error {
+                    <p class="slot-error">{ export_error }</p>
+                }
+                <h2>{ "Import" }</h2>
+                <p>{ "CSV files need item, side, quantity and price or total columns, and can have timestamp, account and note columns." }</p>
+                <div class="flip-toolbar">
+                    <input
+                        type="file"
+                        accept=".csv,text/csv"
+                        onchange={ctx.link().callback(|e: Event| {
+                            let input: HtmlInputElement = e.target_unchecked_into();
+                            Msg::SelectFile(input.files().and_then(|files| files.get(0)).map(File::from))
+                        })}
+                    />
+                    if !self.trades.is_empty() {
+                        <button disabled={self.importing} onclick={ctx.link().callback(|_| Msg::Import)}>
+                            { if self.importing { "Importing...".to_string() } else { format!("Import {} trades", self.trades.len()) } }
+                        </button>
+                    }
+                </div>
+                {
+                    match &self.status {
+                        Some(Ok(message)) => html! { <p class="import-status">{ message }</p> },
+                        Some(Err(error)) => html! { <p class="slot-error">{ error }</p> },
+                        None => html! {},
+                    }
+                }
+            </div>
+        }
+    }
+}
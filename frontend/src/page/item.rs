@@ -0,0 +1,177 @@
+use shared::gp;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::api::{self, url_with_query};
+use crate::component::coins::coins;
+use crate::component::history;
+use crate::component::transaction_list::Transaction;
+use crate::events::{self, EventSubscription, TradeEvent};
+use crate::route::{Route, TransactionsQuery};
+
+// Everything about one item: its totals, its trades and the history of changes to them
+pub struct Item {
+    transactions: Vec<Transaction>,
+    // Keeps the trades up to date with changes made elsewhere
+    _events: Option<EventSubscription>,
+}
+
+pub enum Msg {
+    GetTransactions,
+    GetTransactionsComplete(Vec<Transaction>),
+    TradeEvent(Box<TradeEvent>),
+}
+
+#[derive(PartialEq, Properties, Clone)]
+pub struct Props {
+    pub name: String,
+    pub account: Option<i64>,
+}
+
+impl Component for Item {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Msg::GetTransactions);
+
+        Self {
+            transactions: Vec::new(),
+            _events: events::subscribe(ctx.link().callback(|event| Msg::TradeEvent(Box::new(event)))),
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        ctx.link().send_message(Msg::GetTransactions);
+        false
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetTransactions => {
+                let url = url_with_query(
+                    "/api/v1/trade",
+                    &[
+                        ("item_name", Some(ctx.props().name.clone())),
+                        ("account", ctx.props().account.map(|account| account.to_string())),
+                    ],
+                );
+
+                ctx.link().send_future(async move {
+                    let resp = api::get(&url)
+                        .send()
+                        .await;
+
+                    match resp {
+                        Ok(resp) => Msg::GetTransactionsComplete(resp.json::<Vec<Transaction>>().await.unwrap_or_default()),
+                        Err(_) => Msg::GetTransactionsComplete(Vec::new()),
+                    }
+                });
+
+                false
+            },
+            Msg::GetTransactionsComplete(transactions) => {
+                // The search matches part of the name, so leave out other items containing this one
+                let name = &ctx.props().name;
+                self.transactions = transactions.into_iter().filter(|transaction| &transaction.item_name == name).collect();
+
+                true
+            },
+            Msg::TradeEvent(event) => {
+                let name = &ctx.props().name;
+                let affects_item = match event.as_ref() {
+                    TradeEvent::TradeCreated { trade } | TradeEvent::TradeDeleted { trade } => &trade.item_name == name,
+                    TradeEvent::TradeUpdated { before, trade } => &before.item_name == name || &trade.item_name == name,
+                    TradeEvent::Resync => true,
+                };
+
+                if affects_item {
+                    ctx.link().send_message(Msg::GetTransactions);
+                }
+
+                false
+            },
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let name = &ctx.props().name;
+        let search = TransactionsQuery {
+            item: Some(name.clone()),
+            ..TransactionsQuery::default()
+        };
+
+        html! {
+            <div class="transaction-panel item-page">
+                <h2>{ name }</h2>
+                { self.construct_totals_html() }
+                <Link<Route, TransactionsQuery> to={Route::Transactions} query={Some(search)}>
+                    { "Search transactions for this item" }
+                </Link<Route, TransactionsQuery>>
+                { self.construct_trades_html() }
+                <h3>{ "History" }</h3>
+                <history::History item_name={name.clone()} />
+            </div>
+        }
+    }
+}
+
+impl Item {
+    fn construct_totals_html(&self) -> Html {
+        let total = |is_purchase: bool| {
+            self.transactions
+                .iter()
+                .filter(|transaction| transaction.is_purchase == is_purchase)
+                .fold((0, 0), |(quantity, value), transaction| {
+                    (quantity + transaction.quantity, value + transaction.quantity * transaction.total_price)
+                })
+        };
+        let (bought, spent) = total(true);
+        let (sold, received) = total(false);
+        let profit_loss = received - spent;
+
+        html! {
+            <dl class="detail-fields">
+                <dt>{ "Bought" }</dt>
+                <dd>{ gp::format(bought) }</dd>
+                <dt>{ "Sold" }</dt>
+                <dd>{ gp::format(sold) }</dd>
+                <dt>{ "Spent" }</dt>
+                <dd>{ format!("{} gp", gp::format(spent)) }</dd>
+                <dt>{ "Received" }</dt>
+                <dd>{ format!("{} gp", gp::format(received)) }</dd>
+                <dt>{ "Profit/Loss" }</dt>
+                <dd class={if profit_loss >= 0 { "profit" } else { "loss" }}>{ format!("{} gp", gp::format(profit_loss)) }</dd>
+            </dl>
+        }
+    }
+
+    fn construct_trades_html(&self) -> Html {
+        html! {
+            <div class="transaction-list-container">
+                <table class="transaction-list-table">
+                    <thead>
+                        <tr>
+                            <th style="border-top-left-radius:8px">{ "ID" }</th>
+                            <th>{ "Sale or Purchase" }</th>
+                            <th>{ "Quantity" }</th>
+                            <th>{ "Price" }</th>
+                            <th style="border-top-right-radius:8px">{ "Date" }</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        { for self.transactions.iter().map(|transaction| html! {
+                            <tr>
+                                <td>{ transaction.id }</td>
+                                <td>{ if transaction.is_purchase { "Purchase" } else { "Sale" } }</td>
+                                <td>{ transaction.quantity }</td>
+                                <td>{ coins(transaction.total_price) }</td>
+                                <td>{ &transaction.timestamp }</td>
+                            </tr>
+                        }) }
+                    </tbody>
+                </table>
+            </div>
+        }
+    }
+}
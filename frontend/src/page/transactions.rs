@@ -0,0 +1,98 @@
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::component::{search_bar, transaction_list};
+use crate::logging::error;
+use crate::route::{Route, TransactionsQuery};
+
+// The list of trades, searched by the filters in the query string so a search can be linked to
+// and comes back with the back button
+pub struct Transactions {
+    query: TransactionsQuery,
+    _location_listener: Option<LocationHandle>,
+}
+
+pub enum Msg {
+    LocationChanged(Location),
+    Search(String),
+    FilterTag(Option<String>),
+}
+
+#[derive(PartialEq, Properties, Clone)]
+pub struct Props {
+    pub account: Option<i64>,
+}
+
+// The filters in a location's query string, or none if it doesn't have any
+fn query_of(location: &Location) -> TransactionsQuery {
+    location.query::<TransactionsQuery>().unwrap_or_default()
+}
+
+impl Component for Transactions {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            query: ctx.link().location().map(|location| query_of(&location)).unwrap_or_default(),
+            _location_listener: ctx.link().add_location_listener(ctx.link().callback(Msg::LocationChanged)),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        let Some(navigator) = ctx.link().navigator() else {
+            return false;
+        };
+
+        let result = match msg {
+            Msg::LocationChanged(location) => {
+                let query = query_of(&location);
+                if query == self.query {
+                    return false;
+                }
+                self.query = query;
+
+                return true;
+            },
+            Msg::Search(item_name) => {
+                let query = TransactionsQuery {
+                    item: Some(item_name).filter(|item_name| !item_name.is_empty()),
+                    ..self.query.clone()
+                };
+
+                // Searching as the user types would otherwise fill the history with every pause
+                navigator.replace_with_query(&Route::Transactions, &query)
+            },
+            Msg::FilterTag(tag) => {
+                let query = TransactionsQuery {
+                    tag,
+                    ..self.query.clone()
+                };
+
+                navigator.push_with_query(&Route::Transactions, &query)
+            },
+        };
+
+        if let Err(e) = result {
+            error!("Updating the transactions query failed: {}", e);
+        }
+
+        // The location listener picks up the new query
+        false
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <>
+                <search_bar::SearchBar
+                    value={self.query.item.clone().unwrap_or_default()}
+                    on_search={ctx.link().callback(Msg::Search)} />
+                <transaction_list::TransactionList
+                    search_string={self.query.item.clone().unwrap_or_default()}
+                    tag={self.query.tag.clone()}
+                    on_filter_tag={ctx.link().callback(Msg::FilterTag)}
+                    account={ctx.props().account} />
+            </>
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use yew_router::prelude::*;
+
+// The pages of the app. The backend serves the app for every path outside the API, so any of
+// them can be linked to or reloaded.
+#[derive(Clone, Debug, PartialEq, Routable)]
+pub enum Route {
+    #[at("/")]
+    Dashboard,
+    #[at("/transactions")]
+    Transactions,
+    #[at("/items/:name")]
+    Item { name: String },
+    #[at("/holdings")]
+    Holdings,
+    #[at("/reports")]
+    Reports,
+    #[at("/import-export")]
+    ImportExport,
+    #[at("/settings")]
+    Settings,
+    #[not_found]
+    #[at("/404")]
+    NotFound,
+}
+
+impl Route {
+    // The pages in the navigation bar, with their titles
+    pub const NAVIGATION: [(Route, &'static str); 6] = [
+        (Route::Dashboard, "Dashboard"),
+        (Route::Transactions, "Transactions"),
+        (Route::Holdings, "Holdings"),
+        (Route::Reports, "Reports"),
+        (Route::ImportExport, "Import/Export"),
+        (Route::Settings, "Settings"),
+    ];
+}
+
+// Filters of the transactions page, kept in its query string so searches can be linked to
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TransactionsQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
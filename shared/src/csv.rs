@@ -0,0 +1,313 @@
+use chrono::NaiveDateTime;
+
+use crate::gp;
+use crate::trade::{parse_side, parse_timestamp, TIMESTAMP_FORMAT};
+
+// Trades as CSV files, the way `ardy import` and the frontend's import read them and the
+// frontend's export writes them. A header row names the columns, in any order:
+//
+// item, side (buy or sell), quantity, price (each) or total, and optionally timestamp, account
+// and note
+
+// The columns written on export, which read back in on import
+pub const HEADER: [&str; 7] = [
+    "item",
+    "side",
+    "quantity",
+    "price",
+    "timestamp",
+    "account",
+    "note",
+];
+
+// A trade read from or written to a CSV file
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvTrade {
+    pub item_name: String,
+    pub is_purchase: bool,
+    pub quantity: i64,
+    pub price_each: i64,
+    pub timestamp: NaiveDateTime,
+    pub account_id: Option<i64>,
+    pub note: Option<String>,
+}
+
+// Split CSV text into rows of fields. Fields can be quoted to contain commas, newlines and quotes,
+// which are doubled inside quotes.
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if field.is_empty() => in_quotes = true,
+            (',', false) => row.push(std::mem::take(&mut field)),
+            ('\r', false) if chars.peek() == Some(&'\n') => {}
+            ('\n', false) => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (c, _) => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err("CSV file ends inside a quoted field".to_string());
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    // Skip blank lines
+    rows.retain(|row| !(row.len() == 1 && row[0].trim().is_empty()));
+
+    Ok(rows)
+}
+
+fn parse_amount(amount: &str) -> Result<i64, String> {
+    gp::parse(amount).map_err(|e| format!("Invalid gp amount \"{}\": {}", amount.trim(), e))
+}
+
+// Columns of an import file, by their position in the header row
+struct Columns {
+    item: usize,
+    side: usize,
+    quantity: usize,
+    price: Option<usize>,
+    total: Option<usize>,
+    timestamp: Option<usize>,
+    account: Option<usize>,
+    note: Option<usize>,
+}
+
+impl Columns {
+    fn new(header: &[String]) -> Result<Self, String> {
+        let find = |name: &str| {
+            header
+                .iter()
+                .position(|column| column.trim().eq_ignore_ascii_case(name))
+        };
+        let require =
+            |name: &str| find(name).ok_or_else(|| format!("CSV file has no {} column", name));
+
+        let columns = Columns {
+            item: require("item")?,
+            side: require("side")?,
+            quantity: require("quantity")?,
+            price: find("price"),
+            total: find("total"),
+            timestamp: find("timestamp"),
+            account: find("account"),
+            note: find("note"),
+        };
+        if columns.price.is_none() && columns.total.is_none() {
+            return Err("CSV file needs a price (each) or total column".to_string());
+        }
+
+        Ok(columns)
+    }
+
+    fn trade(&self, row: &[String], now: NaiveDateTime) -> Result<CsvTrade, String> {
+        // Missing trailing fields count as empty
+        let field = |index: usize| row.get(index).map_or("", |field| field.trim());
+        let optional = |index: Option<usize>| index.map(field).filter(|field| !field.is_empty());
+
+        let item_name = field(self.item);
+        if item_name.is_empty() {
+            return Err("Item is empty".to_string());
+        }
+
+        let quantity: i64 = field(self.quantity)
+            .replace(',', "")
+            .parse()
+            .ok()
+            .filter(|quantity| *quantity > 0)
+            .ok_or_else(|| format!("Invalid quantity \"{}\"", field(self.quantity)))?;
+
+        // Trades are stored with the price of each item. A total wins over the price each, since
+        // it's what was actually paid, as long as it divides evenly between the items.
+        let price_each = match (optional(self.total), optional(self.price)) {
+            (Some(total), _) => {
+                let total = parse_amount(total)?;
+                if total % quantity != 0 {
+                    return Err(format!(
+                        "Total {} gp isn't a whole number of gp each for {} items",
+                        gp::format(total),
+                        quantity
+                    ));
+                }
+                total / quantity
+            }
+            (None, Some(price)) => parse_amount(price)?,
+            (None, None) => return Err("Price and total are both empty".to_string()),
+        };
+
+        Ok(CsvTrade {
+            item_name: item_name.to_string(),
+            is_purchase: parse_side(field(self.side))?,
+            quantity,
+            price_each,
+            timestamp: optional(self.timestamp)
+                .map(parse_timestamp)
+                .transpose()?
+                .unwrap_or(now),
+            account_id: optional(self.account)
+                .map(|account| {
+                    account
+                        .parse()
+                        .map_err(|_| format!("Invalid account id \"{}\"", account))
+                })
+                .transpose()?,
+            note: optional(self.note).map(str::to_string),
+        })
+    }
+}
+
+// Read the trades in an import file. Trades without a timestamp get `now`.
+// Errors name the line they're on, counting the header as line 1.
+pub fn trades_from_csv(text: &str, now: NaiveDateTime) -> Result<Vec<CsvTrade>, String> {
+    let rows = parse_csv(text)?;
+    let Some((header, rows)) = rows.split_first() else {
+        return Err("CSV file is empty".to_string());
+    };
+    let columns = Columns::new(header)?;
+
+    rows.iter()
+        .enumerate()
+        .map(|(index, row)| {
+            columns
+                .trade(row, now)
+                .map_err(|e| format!("Line {}: {}", index + 2, e))
+        })
+        .collect()
+}
+
+// Quote a field if it has anything in it that would otherwise end it
+fn write_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Write trades with the header row, in the format `trades_from_csv` reads
+pub fn trades_to_csv(trades: &[CsvTrade]) -> String {
+    let mut csv = HEADER.join(",");
+    csv.push_str("\r\n");
+
+    for trade in trades {
+        let fields = [
+            trade.item_name.clone(),
+            if trade.is_purchase { "buy" } else { "sell" }.to_string(),
+            trade.quantity.to_string(),
+            trade.price_each.to_string(),
+            trade.timestamp.format(TIMESTAMP_FORMAT).to_string(),
+            trade
+                .account_id
+                .map(|account_id| account_id.to_string())
+                .unwrap_or_default(),
+            trade.note.clone().unwrap_or_default(),
+        ];
+
+        let fields: Vec<String> = fields.iter().map(|field| write_field(field)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> NaiveDateTime {
+        parse_timestamp("2024-01-31T13:45").unwrap()
+    }
+
+    #[test]
+    fn reads_trades_with_price_each_or_total() {
+        let text = "Item,Side,Quantity,Price,Total,Timestamp,Note\r\n\
+            Dragon bones,buy,500,2.3k,,2024-01-30T10:00,\r\n\
+            \"Rune platebody\",sell,2,,\"77,000\",,\"said \"\"gf\"\", left\"\r\n\
+            \r\n";
+
+        let trades = trades_from_csv(text, now()).unwrap();
+        assert_eq!(trades.len(), 2);
+
+        assert_eq!(trades[0].item_name, "Dragon bones");
+        assert!(trades[0].is_purchase);
+        assert_eq!(trades[0].price_each, 2_300);
+        assert_eq!(
+            trades[0].timestamp,
+            parse_timestamp("2024-01-30T10:00").unwrap()
+        );
+        assert_eq!(trades[0].note, None);
+
+        assert_eq!(trades[1].item_name, "Rune platebody");
+        assert!(!trades[1].is_purchase);
+        assert_eq!(trades[1].price_each, 38_500);
+        assert_eq!(trades[1].timestamp, now());
+        assert_eq!(trades[1].note.as_deref(), Some("said \"gf\", left"));
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let text =
+            "item,side,quantity,price\nDragon bones,buy,500,2.3k\nDragon bones,swap,500,2.3k\n";
+        assert_eq!(
+            trades_from_csv(text, now()).unwrap_err(),
+            "Line 3: Side must be buy or sell, not \"swap\""
+        );
+
+        assert_eq!(
+            trades_from_csv(
+                "item,side,quantity,total\nRune platebody,sell,3,77k\n",
+                now()
+            )
+            .unwrap_err(),
+            "Line 2: Total 77,000 gp isn't a whole number of gp each for 3 items"
+        );
+        assert!(trades_from_csv("item,side,quantity\n", now()).is_err());
+        assert!(trades_from_csv("", now()).is_err());
+        assert!(trades_from_csv("item,side,quantity,price\n\"Dragon bones", now()).is_err());
+    }
+
+    #[test]
+    fn exported_trades_read_back_in() {
+        let trades = vec![
+            CsvTrade {
+                item_name: "Dragon bones".to_string(),
+                is_purchase: true,
+                quantity: 500,
+                price_each: 2_300,
+                timestamp: parse_timestamp("2024-01-30T10:00").unwrap(),
+                account_id: Some(2),
+                note: None,
+            },
+            CsvTrade {
+                item_name: "Rune platebody".to_string(),
+                is_purchase: false,
+                quantity: 2,
+                price_each: 38_500,
+                timestamp: now(),
+                account_id: None,
+                note: Some("said \"gf\",\nleft".to_string()),
+            },
+        ];
+
+        let csv = trades_to_csv(&trades);
+        assert!(csv.starts_with("item,side,quantity,price,timestamp,account,note\r\n"));
+        assert_eq!(trades_from_csv(&csv, now()), Ok(trades));
+    }
+}
@@ -0,0 +1,320 @@
+use std::fmt;
+
+// Amounts of gp, written and shown the way players do: 1.5m, 250k, 2b or 1,234,567
+
+// The most coins, or of any other item, that fit in one stack in game
+pub const MAX_STACK: i64 = 2_147_483_647;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    Empty,
+    Invalid,
+    Negative,
+    NotWhole,
+    TooLarge,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "Enter an amount"),
+            ParseError::Invalid => {
+                write!(f, "Expected an amount like 500, 250k, 1.5m or 1,234,567")
+            }
+            ParseError::Negative => write!(f, "Amounts can't be negative"),
+            ParseError::NotWhole => write!(f, "Amounts have to be whole gp"),
+            ParseError::TooLarge => write!(f, "Amount is too large"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Parse an amount written in full, with thousands separators or with a k, m or b suffix.
+// Fractions have to come out as whole gp, so 2.3k is fine but 2.3 isn't.
+pub fn parse(s: &str) -> Result<i64, ParseError> {
+    let cleaned: String = s
+        .trim()
+        .chars()
+        .filter(|c| *c != ',' && *c != '_')
+        .collect::<String>()
+        .to_lowercase();
+
+    if cleaned.is_empty() {
+        return Err(ParseError::Empty);
+    }
+    if cleaned.starts_with('-') {
+        return Err(ParseError::Negative);
+    }
+
+    let (number, multiplier) = match cleaned.char_indices().last() {
+        Some((i, 'k')) => (&cleaned[..i], 1_000),
+        Some((i, 'm')) => (&cleaned[..i], 1_000_000),
+        Some((i, 'b')) => (&cleaned[..i], 1_000_000_000),
+        _ => (cleaned.as_str(), 1),
+    };
+
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return Err(ParseError::Invalid);
+    }
+    if !whole
+        .chars()
+        .chain(fraction.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+        return Err(ParseError::Invalid);
+    }
+
+    let whole: i64 = if whole.is_empty() {
+        0
+    } else {
+        // Only digits are left, so the number can only fail to parse by being too long
+        whole.parse().map_err(|_| ParseError::TooLarge)?
+    };
+
+    let mut fraction_gp = 0;
+    let mut scale = multiplier;
+    for digit in fraction.chars() {
+        let digit = i64::from(digit.to_digit(10).unwrap_or(0));
+        if scale % 10 != 0 {
+            // Trailing zeros past the last whole gp don't change the amount
+            if digit != 0 {
+                return Err(ParseError::NotWhole);
+            }
+            continue;
+        }
+        scale /= 10;
+        fraction_gp += digit * scale;
+    }
+
+    whole
+        .checked_mul(multiplier)
+        .and_then(|gp| gp.checked_add(fraction_gp))
+        .ok_or(ParseError::TooLarge)
+}
+
+// Format an amount in full with thousands separators, like 1,234,567
+pub fn format(gp: i64) -> String {
+    let digits = gp.unsigned_abs().to_string();
+
+    let mut formatted = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+
+    if gp < 0 {
+        format!("-{}", formatted)
+    } else {
+        formatted
+    }
+}
+
+// Format an amount in at most one decimal place with a K, M or B suffix, like 1.2M. Amounts are
+// rounded down, so 999,999 is 999.9K rather than 1000K.
+pub fn format_compact(gp: i64) -> String {
+    let amount = gp.unsigned_abs();
+    let (unit, suffix) = match amount {
+        0..=999 => (1, ""),
+        1_000..=999_999 => (1_000, "K"),
+        1_000_000..=999_999_999 => (1_000_000, "M"),
+        _ => (1_000_000_000, "B"),
+    };
+
+    let whole = amount / unit;
+    let tenths = amount % unit * 10 / unit;
+    let sign = if gp < 0 { "-" } else { "" };
+
+    if tenths == 0 {
+        format!("{}{}{}", sign, whole, suffix)
+    } else {
+        format!("{}{}.{}{}", sign, whole, tenths, suffix)
+    }
+}
+
+// Colours of coin stacks in game, which change as the stack grows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackColour {
+    Yellow,
+    White,
+    Green,
+}
+
+impl StackColour {
+    pub fn hex(self) -> &'static str {
+        match self {
+            StackColour::Yellow => "#ffff00",
+            StackColour::White => "#ffffff",
+            StackColour::Green => "#00ff80",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stack {
+    pub text: String,
+    pub colour: StackColour,
+}
+
+// Format an amount the way the game labels a stack of coins: in full and yellow below 100K,
+// in whole thousands and white below 10M, and in whole millions and green from then on.
+// Negative amounts are coloured by their size.
+pub fn stack(gp: i64) -> Stack {
+    let amount = gp.unsigned_abs();
+    let sign = if gp < 0 { "-" } else { "" };
+
+    let (text, colour) = match amount {
+        0..=99_999 => (amount.to_string(), StackColour::Yellow),
+        100_000..=9_999_999 => (format!("{}K", amount / 1_000), StackColour::White),
+        _ => (format!("{}M", amount / 1_000_000), StackColour::Green),
+    };
+
+    Stack {
+        text: format!("{}{}", sign, text),
+        colour,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_plain_and_separated_amounts() {
+        assert_eq!(parse("0"), Ok(0));
+        assert_eq!(parse("500"), Ok(500));
+        assert_eq!(parse("1,234,567"), Ok(1_234_567));
+        assert_eq!(parse("1_234_567"), Ok(1_234_567));
+        assert_eq!(parse(" 12000 "), Ok(12_000));
+        assert_eq!(parse("007"), Ok(7));
+    }
+
+    #[test]
+    fn parses_suffixes_in_either_case() {
+        assert_eq!(parse("250k"), Ok(250_000));
+        assert_eq!(parse("250K"), Ok(250_000));
+        assert_eq!(parse("1.5m"), Ok(1_500_000));
+        assert_eq!(parse("1.5M"), Ok(1_500_000));
+        assert_eq!(parse("2b"), Ok(2_000_000_000));
+        assert_eq!(parse("2B"), Ok(2_000_000_000));
+        assert_eq!(parse("1,500k"), Ok(1_500_000));
+    }
+
+    #[test]
+    fn parses_fractions_that_come_out_whole() {
+        assert_eq!(parse("2.3k"), Ok(2_300));
+        assert_eq!(parse("2.345k"), Ok(2_345));
+        assert_eq!(parse(".5m"), Ok(500_000));
+        assert_eq!(parse("1.15m"), Ok(1_150_000));
+        assert_eq!(parse("1.234567m"), Ok(1_234_567));
+        assert_eq!(parse("2.147483647b"), Ok(2_147_483_647));
+        assert_eq!(parse("5."), Ok(5));
+        assert_eq!(parse("5.0"), Ok(5));
+        assert_eq!(parse("2.3000k"), Ok(2_300));
+    }
+
+    #[test]
+    fn rejects_fractions_of_gp() {
+        assert_eq!(parse("2.5"), Err(ParseError::NotWhole));
+        assert_eq!(parse("1.50"), Err(ParseError::NotWhole));
+        assert_eq!(parse("2.3456k"), Err(ParseError::NotWhole));
+        assert_eq!(parse("1.0000001m"), Err(ParseError::NotWhole));
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert_eq!(parse(""), Err(ParseError::Empty));
+        assert_eq!(parse("   "), Err(ParseError::Empty));
+        assert_eq!(parse(","), Err(ParseError::Empty));
+        assert_eq!(parse("k"), Err(ParseError::Invalid));
+        assert_eq!(parse("."), Err(ParseError::Invalid));
+        assert_eq!(parse("1.2.3k"), Err(ParseError::Invalid));
+        assert_eq!(parse("1 000"), Err(ParseError::Invalid));
+        assert_eq!(parse("12kk"), Err(ParseError::Invalid));
+        assert_eq!(parse("1e6"), Err(ParseError::Invalid));
+        assert_eq!(parse("+5"), Err(ParseError::Invalid));
+        assert_eq!(parse("5gp"), Err(ParseError::Invalid));
+        assert_eq!(parse("-5"), Err(ParseError::Negative));
+        assert_eq!(parse("-1.5m"), Err(ParseError::Negative));
+    }
+
+    #[test]
+    fn rejects_amounts_that_overflow() {
+        assert_eq!(parse("9223372036854775807"), Ok(i64::MAX));
+        assert_eq!(parse("9223372036854775808"), Err(ParseError::TooLarge));
+        assert_eq!(parse("99999999999b"), Err(ParseError::TooLarge));
+        assert_eq!(parse("9223372036.854775808b"), Err(ParseError::TooLarge));
+    }
+
+    #[test]
+    fn formats_in_full() {
+        assert_eq!(format(0), "0");
+        assert_eq!(format(999), "999");
+        assert_eq!(format(1_000), "1,000");
+        assert_eq!(format(123_456), "123,456");
+        assert_eq!(format(1_234_567), "1,234,567");
+        assert_eq!(format(-25_000), "-25,000");
+        assert_eq!(format(i64::MAX), "9,223,372,036,854,775,807");
+        assert_eq!(format(i64::MIN), "-9,223,372,036,854,775,808");
+    }
+
+    #[test]
+    fn formats_compactly() {
+        assert_eq!(format_compact(0), "0");
+        assert_eq!(format_compact(999), "999");
+        assert_eq!(format_compact(1_000), "1K");
+        assert_eq!(format_compact(1_250), "1.2K");
+        assert_eq!(format_compact(250_000), "250K");
+        assert_eq!(format_compact(999_999), "999.9K");
+        assert_eq!(format_compact(1_234_567), "1.2M");
+        assert_eq!(format_compact(1_500_000), "1.5M");
+        assert_eq!(format_compact(2_000_000_000), "2B");
+        assert_eq!(format_compact(2_147_483_647), "2.1B");
+        assert_eq!(format_compact(-1_234_567), "-1.2M");
+        assert_eq!(format_compact(i64::MIN), "-9223372036.8B");
+    }
+
+    #[test]
+    fn formats_stacks_like_the_game() {
+        let stack_of = |gp| {
+            let stack = stack(gp);
+            (stack.text, stack.colour)
+        };
+
+        assert_eq!(stack_of(0), ("0".to_string(), StackColour::Yellow));
+        assert_eq!(stack_of(99_999), ("99999".to_string(), StackColour::Yellow));
+        assert_eq!(stack_of(100_000), ("100K".to_string(), StackColour::White));
+        assert_eq!(
+            stack_of(1_234_567),
+            ("1234K".to_string(), StackColour::White)
+        );
+        assert_eq!(
+            stack_of(9_999_999),
+            ("9999K".to_string(), StackColour::White)
+        );
+        assert_eq!(
+            stack_of(10_000_000),
+            ("10M".to_string(), StackColour::Green)
+        );
+        assert_eq!(
+            stack_of(2_147_483_647),
+            ("2147M".to_string(), StackColour::Green)
+        );
+        assert_eq!(
+            stack_of(-250_000),
+            ("-250K".to_string(), StackColour::White)
+        );
+    }
+
+    #[test]
+    fn full_format_parses_back() {
+        for gp in [0, 7, 1_000, 1_234_567, 2_147_483_647, i64::MAX] {
+            assert_eq!(parse(&format(gp)), Ok(gp));
+        }
+        assert_eq!(parse(&format_compact(1_500_000)), Ok(1_500_000));
+        assert_eq!(parse(&stack(250_000).text), Ok(250_000));
+    }
+}
@@ -0,0 +1,4 @@
+pub mod csv;
+pub mod gp;
+pub mod models;
+pub mod trade;
@@ -0,0 +1,168 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::trade::{FieldError, TIMESTAMP_FORMAT};
+
+pub fn deserialize_datetime<'de, D>(deserializer: D) -> Result<NaiveDateTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    NaiveDateTime::parse_from_str(&s, TIMESTAMP_FORMAT).map_err(serde::de::Error::custom)
+}
+
+pub fn deserialize_optional_datetime<'de, D>(
+    deserializer: D,
+) -> Result<Option<NaiveDateTime>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_datetime(deserializer).map(Some)
+}
+
+// Distinguishes a field set to null from a missing one, which `#[serde(default)]` leaves as None
+pub fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+pub fn serialize_datetime<S>(datetime: &NaiveDateTime, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    let s = format!("{}", datetime.format(TIMESTAMP_FORMAT));
+    serializer.serialize_str(&s)
+}
+
+pub fn serialize_optional_datetime<S>(
+    datetime: &Option<NaiveDateTime>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match datetime {
+        Some(datetime) => serialize_datetime(datetime, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct ItemData {
+    // Ignored when creating a trade
+    #[serde(default)]
+    pub id: i64,
+    pub item_name: String,
+    pub quantity: i64,
+    pub total_price: i64,
+    pub is_purchase: bool,
+    #[serde(deserialize_with = "deserialize_datetime")]
+    #[serde(serialize_with = "serialize_datetime")]
+    #[cfg_attr(feature = "utoipa", schema(value_type = String, example = "2024-01-31T13:45"))]
+    pub timestamp: NaiveDateTime,
+    #[serde(default)]
+    pub account_id: Option<i64>,
+    #[serde(default)]
+    pub note: Option<String>,
+    // The trading session in progress when the trade was added. Ignored when creating a trade.
+    #[serde(default)]
+    pub session_id: Option<i64>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+// Why a trade was rejected, with an error for each invalid field
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct InvalidTrade {
+    #[cfg_attr(
+        feature = "utoipa",
+        schema(
+            value_type = Vec<Object>,
+            example = json!([{ "field": "quantity", "message": "Quantity must be at least 1" }])
+        )
+    )]
+    pub errors: Vec<FieldError>,
+}
+
+// One operation of a bulk trade request. Deletes move the trade to the trash like a single delete.
+#[derive(Deserialize, Debug)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum TradeOperation {
+    Create { trade: ItemData },
+    Update { id: i64, changes: TradeChanges },
+    Delete { id: i64 },
+}
+
+// Fields of a trade to change in a bulk update. Fields left out keep their current value,
+// `account_id: null` removes the trade from its account and `tags` replaces all of its tags.
+#[derive(Deserialize, Debug, Default)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct TradeChanges {
+    pub item_name: Option<String>,
+    pub quantity: Option<i64>,
+    pub total_price: Option<i64>,
+    pub is_purchase: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_optional_datetime")]
+    #[cfg_attr(feature = "utoipa", schema(value_type = Option<String>, example = "2024-01-31T13:45"))]
+    pub timestamp: Option<NaiveDateTime>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    #[cfg_attr(feature = "utoipa", schema(value_type = Option<i64>))]
+    pub account_id: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    #[cfg_attr(feature = "utoipa", schema(value_type = Option<String>))]
+    pub note: Option<Option<String>>,
+    pub tags: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Debug)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct BulkTradeRequest {
+    pub operations: Vec<TradeOperation>,
+}
+
+// Outcome of one bulk operation. `id` is the created, updated or deleted trade.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct BulkOperationResult {
+    pub index: usize,
+    pub ok: bool,
+    pub id: Option<i64>,
+    pub error: Option<String>,
+}
+
+// Operations are applied all together or not at all, so `applied` is false if any of them failed
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct BulkTradeResponse {
+    pub applied: bool,
+    pub results: Vec<BulkOperationResult>,
+}
+
+// Which of a user's trades to list or total. Trades in the trash are always left out.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TradeFilter {
+    // Case-insensitive part of the item name
+    pub item_name: Option<String>,
+    pub account: Option<i64>,
+    pub tag: Option<String>,
+}
+
+impl TradeFilter {
+    pub fn matches(&self, trade: &ItemData) -> bool {
+        self.item_name.as_ref().is_none_or(|item_name| {
+            trade
+                .item_name
+                .to_lowercase()
+                .contains(&item_name.to_lowercase())
+        }) && self
+            .account
+            .is_none_or(|account| trade.account_id == Some(account))
+            && self.tag.as_ref().is_none_or(|tag| trade.tags.contains(tag))
+    }
+}
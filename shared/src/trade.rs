@@ -0,0 +1,264 @@
+use chrono::{Duration, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::gp::{self, MAX_STACK};
+
+// Item names are shown in full in tables, and no item in game has a name anywhere near this long
+pub const MAX_ITEM_NAME_LENGTH: usize = 100;
+
+// Timestamps are written without a time zone, so the backend only knows a trade is in the future
+// once it's later than it is anywhere yet, at UTC+14
+pub const MAX_UTC_OFFSET: Duration = Duration::hours(14);
+
+// How timestamps are written, in the API and everywhere else, like 2024-01-31T13:45
+pub const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+// The fields of a trade that are checked, named the way the API names them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    ItemName,
+    Quantity,
+    // The price of each item, which the API calls total_price
+    #[serde(rename = "total_price")]
+    Price,
+    Timestamp,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: Field,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: Field, message: impl Into<String>) -> Self {
+        FieldError {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+// A trade as far as validation is concerned, borrowed from however the trade is stored
+#[derive(Debug, Clone, Copy)]
+pub struct TradeFields<'a> {
+    pub item_name: &'a str,
+    pub quantity: i64,
+    pub price_each: i64,
+    pub timestamp: NaiveDateTime,
+}
+
+impl TradeFields<'_> {
+    // Check every field, returning an error for each one that's invalid. Timestamps after
+    // `latest` are in the future.
+    pub fn validate(&self, latest: NaiveDateTime) -> Vec<FieldError> {
+        [
+            validate_item_name(self.item_name),
+            validate_quantity(self.quantity),
+            validate_price(self.price_each, self.quantity),
+            validate_timestamp(self.timestamp, latest),
+        ]
+        .into_iter()
+        .filter_map(Result::err)
+        .collect()
+    }
+}
+
+// Names are checked as they'll be stored, trimmed. Any name is accepted otherwise, since there's
+// no catalogue of items to check it against.
+pub fn validate_item_name(item_name: &str) -> Result<(), FieldError> {
+    let error = |message| Err(FieldError::new(Field::ItemName, message));
+
+    let item_name = item_name.trim();
+    if item_name.is_empty() {
+        return error("Enter an item name".to_string());
+    }
+    if item_name.chars().count() > MAX_ITEM_NAME_LENGTH {
+        return error(format!(
+            "Item names are at most {} characters",
+            MAX_ITEM_NAME_LENGTH
+        ));
+    }
+    if item_name.chars().any(char::is_control) {
+        return error("Item names can't contain control characters".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_quantity(quantity: i64) -> Result<(), FieldError> {
+    if quantity < 1 {
+        return Err(FieldError::new(
+            Field::Quantity,
+            "Quantity must be at least 1",
+        ));
+    }
+    if quantity > MAX_STACK {
+        return Err(FieldError::new(
+            Field::Quantity,
+            format!("Quantity can be at most {}", gp::format(MAX_STACK)),
+        ));
+    }
+
+    Ok(())
+}
+
+// The Grand Exchange doesn't trade anything for less than 1 gp, and the whole trade has to fit in
+// one stack of coins. The total isn't checked for quantities that are invalid themselves.
+pub fn validate_price(price_each: i64, quantity: i64) -> Result<(), FieldError> {
+    if price_each < 1 {
+        return Err(FieldError::new(Field::Price, "Price must be at least 1 gp"));
+    }
+
+    let total = price_each.checked_mul(quantity.max(1));
+    if total.is_none_or(|total| total > MAX_STACK) {
+        return Err(FieldError::new(
+            Field::Price,
+            format!(
+                "The trade comes to more than the max stack of {} gp",
+                gp::format(MAX_STACK)
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn validate_timestamp(
+    timestamp: NaiveDateTime,
+    latest: NaiveDateTime,
+) -> Result<(), FieldError> {
+    if timestamp > latest {
+        return Err(FieldError::new(
+            Field::Timestamp,
+            "Timestamp can't be in the future",
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn parse_side(side: &str) -> Result<bool, String> {
+    match side.trim().to_lowercase().as_str() {
+        "buy" | "bought" | "b" => Ok(true),
+        "sell" | "sold" | "s" => Ok(false),
+        _ => Err(format!("Side must be buy or sell, not \"{}\"", side)),
+    }
+}
+
+pub fn parse_timestamp(timestamp: &str) -> Result<NaiveDateTime, String> {
+    NaiveDateTime::parse_from_str(timestamp.trim(), TIMESTAMP_FORMAT).map_err(|_| {
+        format!(
+            "Invalid time \"{}\", expected e.g. 2024-01-31T13:45",
+            timestamp
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn at(day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    fn trade(item_name: &str, quantity: i64, price_each: i64) -> TradeFields<'_> {
+        TradeFields {
+            item_name,
+            quantity,
+            price_each,
+            timestamp: at(30, 12),
+        }
+    }
+
+    fn fields(errors: &[FieldError]) -> Vec<Field> {
+        errors.iter().map(|error| error.field).collect()
+    }
+
+    #[test]
+    fn accepts_valid_trades() {
+        assert!(trade("Dragon bones", 500, 2_300)
+            .validate(at(31, 0))
+            .is_empty());
+        assert!(trade("Coins", MAX_STACK, 1).validate(at(31, 0)).is_empty());
+        assert!(trade("Twisted bow", 1, MAX_STACK)
+            .validate(at(31, 0))
+            .is_empty());
+    }
+
+    #[test]
+    fn reports_every_invalid_field() {
+        let mut invalid = trade(" ", 0, 0);
+        invalid.timestamp = at(31, 1);
+
+        assert_eq!(
+            fields(&invalid.validate(at(31, 0))),
+            vec![
+                Field::ItemName,
+                Field::Quantity,
+                Field::Price,
+                Field::Timestamp
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_item_names() {
+        assert!(validate_item_name("Dragon bones").is_ok());
+        assert!(validate_item_name("  Dragon bones  ").is_ok());
+        assert!(validate_item_name("").is_err());
+        assert!(validate_item_name(" \t ").is_err());
+        assert!(validate_item_name("Dragon\nbones").is_err());
+        assert!(validate_item_name(&"a".repeat(MAX_ITEM_NAME_LENGTH)).is_ok());
+        assert!(validate_item_name(&"a".repeat(MAX_ITEM_NAME_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn checks_quantities() {
+        assert!(validate_quantity(1).is_ok());
+        assert!(validate_quantity(MAX_STACK).is_ok());
+        assert!(validate_quantity(0).is_err());
+        assert!(validate_quantity(-5).is_err());
+        assert!(validate_quantity(MAX_STACK + 1).is_err());
+    }
+
+    #[test]
+    fn checks_prices_and_totals() {
+        assert!(validate_price(1, 1).is_ok());
+        assert!(validate_price(0, 1).is_err());
+        assert!(validate_price(-1, 1).is_err());
+        assert!(validate_price(2_147_483, 1_000).is_ok());
+        assert!(validate_price(2_147_484, 1_000).is_err());
+        assert!(validate_price(i64::MAX, 2).is_err());
+
+        // An invalid quantity is reported on its own, not as part of the price
+        assert!(validate_price(2_300, 0).is_ok());
+        assert!(validate_price(2_300, -5).is_ok());
+    }
+
+    #[test]
+    fn checks_timestamps_are_not_in_the_future() {
+        assert!(validate_timestamp(at(30, 12), at(30, 12)).is_ok());
+        assert!(validate_timestamp(at(1, 0), at(30, 12)).is_ok());
+        assert!(validate_timestamp(at(30, 13), at(30, 12)).is_err());
+    }
+
+    #[test]
+    fn fields_are_named_like_the_api() {
+        assert_eq!(
+            serde_json::to_string(&FieldError::new(Field::Price, "Too low")).unwrap(),
+            r#"{"field":"total_price","message":"Too low"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Field::ItemName).unwrap(),
+            r#""item_name""#
+        );
+    }
+}